@@ -0,0 +1,157 @@
+//! The agent instructions (`--include-agent-rules`): the files in which a
+//! repository tells coding assistants how to work on it, such as `AGENTS.md`,
+//! hoisted into a section at the top of the output.
+//!
+//! A model reads them before the code they apply to, and under a heading that
+//! sets them apart from the files of the project, instead of finding them
+//! somewhere in the middle of the join.
+
+use crate::decommenter::LanguageDB;
+use crate::format::{OutputFormat, fence};
+use crate::options::JoinOptions;
+use ignore::WalkBuilder;
+use std::path::PathBuf;
+
+/// The instruction files that apply to the folder they are in, and are found
+/// at any depth.
+pub(crate) const NESTED: [&str; 2] = ["AGENTS.md", "CLAUDE.md"];
+
+/// The instruction files that are read at the root of the input folder only.
+pub(crate) const ROOT: [&str; 2] = [".cursorrules", ".github/copilot-instructions.md"];
+
+/// Finds the instruction files of the input folder of `options`: those at its
+/// root first, then the nested ones in path order. Files that are ignored,
+/// excluded or never included are left out.
+pub fn discover(options: &JoinOptions) -> anyhow::Result<Vec<PathBuf>> {
+    let root = &options.input_folder;
+    let allowed = crate::walker::allowed_outside_walk(options)?;
+
+    let mut files: Vec<PathBuf> = NESTED
+        .iter()
+        .chain(&ROOT)
+        .map(|name| root.join(name))
+        .filter(|path| path.is_file() && allowed(path))
+        .collect();
+    let mut nested: Vec<PathBuf> = WalkBuilder::new(root)
+        .max_depth(options.max_depth)
+        .build()
+        .flatten()
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.parent() != Some(root.as_path())
+                && path
+                    .file_name()
+                    .is_some_and(|name| NESTED.iter().any(|n| name == *n))
+                && path.is_file()
+                && !options.is_output(path)
+                && allowed(path)
+        })
+        .collect();
+    nested.sort();
+    files.extend(nested);
+    Ok(files)
+}
+
+/// Renders the instruction files in `format`, given as their path in the output
+/// and their content, as the section that opens the output.
+pub fn render(files: &[(PathBuf, Vec<u8>)], format: OutputFormat) -> String {
+    let languages = LanguageDB::builtin();
+    let mut text = String::new();
+    if format == OutputFormat::Markdown {
+        text.push_str(
+            "## Agent Instructions\n\n\
+             The repository gives these instructions to coding assistants.\n\n",
+        );
+    }
+    for (path, contents) in files {
+        let contents = String::from_utf8_lossy(contents);
+        let newline = if contents.is_empty() || contents.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        match format {
+            OutputFormat::Text => text.push_str(&format!(
+                "// AGENT INSTRUCTIONS: {}\n{contents}{newline}\n",
+                path.display()
+            )),
+            OutputFormat::Markdown => {
+                let fence = fence(contents.as_bytes());
+                let tag = languages
+                    .for_path(path)
+                    .map_or("", |language| language.fence.as_str());
+                text.push_str(&format!(
+                    "### {}\n\n{fence}{tag}\n{contents}{newline}{fence}\n\n",
+                    path.display()
+                ));
+            }
+        }
+    }
+    text
+}
+
+// --- Unit Tests for Agent Instructions ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that instruction files are found at the root and below it,
+    /// root files first, and that excluded folders and other files are not.
+    #[test]
+    fn test_discover_agent_rules() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        for path in [
+            "AGENTS.md",
+            ".cursorrules",
+            ".github/copilot-instructions.md",
+            "api/CLAUDE.md",
+            "api/README.md",
+            "vendor/AGENTS.md",
+            "web/.cursorrules",
+        ] {
+            dir.child(path).write_str("Be brief.\n")?;
+        }
+        let mut options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        options.exclude = Some(vec!["vendor/".to_string()]);
+
+        let found: Vec<PathBuf> = discover(&options)?
+            .iter()
+            .map(|path| path.strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "AGENTS.md",
+                ".cursorrules",
+                ".github/copilot-instructions.md",
+                "api/CLAUDE.md"
+            ]
+            .map(PathBuf::from)
+        );
+        Ok(())
+    }
+
+    /// Verifies that each instruction file is labeled with its path, in both
+    /// formats.
+    #[test]
+    fn test_render_agent_rules() {
+        let files = vec![
+            (PathBuf::from("AGENTS.md"), b"Run `cargo test`.".to_vec()),
+            (PathBuf::from(".cursorrules"), b"No unsafe.\n".to_vec()),
+        ];
+        assert_eq!(
+            render(&files, OutputFormat::Text),
+            "// AGENT INSTRUCTIONS: AGENTS.md\nRun `cargo test`.\n\n\
+             // AGENT INSTRUCTIONS: .cursorrules\nNo unsafe.\n\n"
+        );
+        assert_eq!(
+            render(&files, OutputFormat::Markdown),
+            "## Agent Instructions\n\n\
+             The repository gives these instructions to coding assistants.\n\n\
+             ### AGENTS.md\n\n```markdown\nRun `cargo test`.\n```\n\n\
+             ### .cursorrules\n\n```\nNo unsafe.\n```\n\n"
+        );
+    }
+}
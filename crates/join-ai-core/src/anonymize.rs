@@ -0,0 +1,183 @@
+//! Path anonymization (`--anonymize-paths`): the paths in file headers are
+//! replaced with pseudonyms such as `dir_01/file_07.rs`, which keep the shape of
+//! the tree and the file extensions but none of the names, so that code can be
+//! shared for analysis without the product or customer names paths often carry.
+//!
+//! The real path behind each pseudonym is kept in a private mapping file. It is
+//! read before each join and extended, so a path keeps its pseudonym from one
+//! join to the next, and answers about `dir_01/file_07.rs` can be traced back.
+
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The pseudonyms of the files and folders of an input folder.
+#[derive(Debug, Default)]
+pub struct PathMap {
+    /// Real paths, relative to the input folder, and their pseudonyms.
+    pseudonyms: BTreeMap<PathBuf, PathBuf>,
+    /// The highest folder and file numbers given so far.
+    dirs: usize,
+    files: usize,
+}
+
+impl PathMap {
+    /// Reads the mapping file at `path`, or starts an empty map if there is none.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        let entries: BTreeMap<String, String> = serde_json::from_str(&text).with_context(|| {
+            format!(
+                "{} is not a mapping file written by `--anonymize-paths`",
+                path.display()
+            )
+        })?;
+        let mut map = Self::default();
+        for (pseudonym, real) in entries {
+            let pseudonym = PathBuf::from(pseudonym);
+            let name = pseudonym
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if let Some(number) = number(&name, "dir_") {
+                map.dirs = map.dirs.max(number);
+            } else if let Some(number) = number(&name, "file_") {
+                map.files = map.files.max(number);
+            }
+            map.pseudonyms.insert(PathBuf::from(real), pseudonym);
+        }
+        Ok(map)
+    }
+
+    /// Writes the map to `path`, readable only by its owner where permissions
+    /// allow it.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let entries: BTreeMap<String, String> = self
+            .pseudonyms
+            .iter()
+            .map(|(real, pseudonym)| {
+                (
+                    pseudonym.to_string_lossy().into_owned(),
+                    real.to_string_lossy().into_owned(),
+                )
+            })
+            .collect();
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string_pretty(&entries)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Returns the pseudonym of the file at `relative`, a path relative to the
+    /// input folder, giving it one if it has none yet.
+    pub fn file(&mut self, relative: &Path) -> PathBuf {
+        if let Some(pseudonym) = self.pseudonyms.get(relative) {
+            return pseudonym.clone();
+        }
+        let folder = self.parent(relative);
+        self.files += 1;
+        let mut name = format!("file_{:02}", self.files);
+        if let Some(extension) = relative.extension() {
+            name.push('.');
+            name.push_str(&extension.to_string_lossy());
+        }
+        let pseudonym = folder.join(name);
+        self.pseudonyms
+            .insert(relative.to_path_buf(), pseudonym.clone());
+        pseudonym
+    }
+
+    /// Returns the pseudonym of the folder at `relative`, giving it and the
+    /// folders above it one if they have none yet.
+    pub fn dir(&mut self, relative: &Path) -> PathBuf {
+        if let Some(pseudonym) = self.pseudonyms.get(relative) {
+            return pseudonym.clone();
+        }
+        let folder = self.parent(relative);
+        self.dirs += 1;
+        let pseudonym = folder.join(format!("dir_{:02}", self.dirs));
+        self.pseudonyms
+            .insert(relative.to_path_buf(), pseudonym.clone());
+        pseudonym
+    }
+
+    /// Returns the real path behind `pseudonym`, relative to the input folder.
+    pub fn real(&self, pseudonym: &Path) -> Option<&Path> {
+        self.pseudonyms
+            .iter()
+            .find(|(_, candidate)| candidate.as_path() == pseudonym)
+            .map(|(real, _)| real.as_path())
+    }
+
+    /// The pseudonym of the folder holding `relative`; empty at the top.
+    fn parent(&mut self, relative: &Path) -> PathBuf {
+        match relative.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => self.dir(parent),
+            _ => PathBuf::new(),
+        }
+    }
+}
+
+/// Reads the number of a pseudonym such as `file_07.rs`.
+fn number(name: &str, prefix: &str) -> Option<usize> {
+    let rest = name.strip_prefix(prefix)?;
+    let digits = rest.split('.').next()?;
+    digits.parse().ok()
+}
+
+// --- Unit Tests for Path Anonymization ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+
+    /// Verifies that pseudonyms keep the tree and the extensions, and that a
+    /// saved map keeps them and continues their numbering.
+    #[test]
+    fn test_path_map() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let file = dir.path().join("paths.json");
+        let mut map = PathMap::load(&file)?;
+        assert_eq!(
+            map.file(Path::new("acme/billing/invoice.rs")),
+            Path::new("dir_01/dir_02/file_01.rs")
+        );
+        assert_eq!(
+            map.file(Path::new("acme/README")),
+            Path::new("dir_01/file_02")
+        );
+        assert_eq!(map.file(Path::new("main.py")), Path::new("file_03.py"));
+        assert_eq!(
+            map.file(Path::new("acme/billing/invoice.rs")),
+            Path::new("dir_01/dir_02/file_01.rs")
+        );
+        map.save(&file)?;
+
+        let mut map = PathMap::load(&file)?;
+        assert_eq!(
+            map.real(Path::new("dir_01/dir_02/file_01.rs")),
+            Some(Path::new("acme/billing/invoice.rs"))
+        );
+        assert_eq!(
+            map.file(Path::new("acme/tax.rs")),
+            Path::new("dir_01/file_04.rs")
+        );
+        assert_eq!(map.dir(Path::new("vendor")), Path::new("dir_03"));
+
+        fs::write(&file, "[]")?;
+        assert!(PathMap::load(&file).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+//! A content cache for incremental joins.
+//!
+//! For every file, the cache remembers its size, modification time and content
+//! hash, together with its rendered section (the content after all transforms).
+//! On the next run, files whose size and modification time are unchanged are not
+//! read at all, and files whose content hash is unchanged are not transformed
+//! again. The cache lives in `.join-ai/cache` inside the input folder, and is
+//! discarded whenever the set of transforms changes.
+
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+/// The folder, relative to the input folder, where the cache is stored.
+pub const CACHE_DIR: &str = ".join-ai/cache";
+
+/// The first line of the index file. Bump it whenever the format changes.
+const INDEX_VERSION: &str = "join-ai-cache v1";
+
+/// The size and modification time of a file, used to detect changes cheaply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    size: u64,
+    modified: u128,
+}
+
+impl Stamp {
+    /// Reads the stamp of a file from its metadata.
+    pub fn of(metadata: &Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_nanos());
+        Self {
+            size: metadata.len(),
+            modified,
+        }
+    }
+}
+
+/// A rendered section, as stored in the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Section {
+    /// The file is binary and is left out of the output.
+    Binary,
+    /// The content of the file after all transforms.
+    Text(Vec<u8>),
+}
+
+/// What the cache knows about a single file.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    stamp: Stamp,
+    hash: u64,
+    binary: bool,
+}
+
+/// The cache of an input folder, shared by the worker threads of a join.
+pub struct Cache {
+    dir: PathBuf,
+    root: PathBuf,
+    /// A description of the transforms the cached sections were rendered with.
+    key: String,
+    /// The entries loaded from the previous run.
+    previous: HashMap<String, Entry>,
+    /// The entries of the files seen during this run.
+    current: Mutex<HashMap<String, Entry>>,
+    hits: AtomicUsize,
+}
+
+impl Cache {
+    /// Opens the cache of `root` for sections rendered with the transforms
+    /// described by `key`. A missing, unreadable or outdated cache is treated as
+    /// empty.
+    pub fn open(root: &Path, key: &str) -> Self {
+        let dir = root.join(CACHE_DIR);
+        let previous = fs::read_to_string(dir.join("index"))
+            .map(|index| parse_index(&index, key))
+            .unwrap_or_default();
+        Self {
+            dir,
+            root: root.to_path_buf(),
+            key: key.to_string(),
+            previous,
+            current: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the cached section of `path` if the file is unchanged: either its
+    /// stamp matches, or, when `hash` is given, its content hash matches.
+    pub fn lookup(&self, path: &Path, stamp: Stamp, hash: Option<u64>) -> Option<Section> {
+        let name = self.name(path);
+        let entry = *self.previous.get(&name)?;
+        if entry.stamp != stamp && hash != Some(entry.hash) {
+            return None;
+        }
+        let section = if entry.binary {
+            Section::Binary
+        } else {
+            Section::Text(fs::read(self.section_path(&name)).ok()?)
+        };
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        self.record(name, Entry { stamp, ..entry });
+        Some(section)
+    }
+
+    /// Stores the rendered section of `path`, whose raw content hashes to `hash`.
+    pub fn insert(&self, path: &Path, stamp: Stamp, hash: u64, section: &Section) {
+        let name = self.name(path);
+        let binary = match section {
+            Section::Binary => true,
+            Section::Text(content) => {
+                let written = fs::create_dir_all(self.dir.join("sections"))
+                    .and_then(|_| fs::write(self.section_path(&name), content));
+                if written.is_err() {
+                    return;
+                }
+                false
+            }
+        };
+        self.record(
+            name,
+            Entry {
+                stamp,
+                hash,
+                binary,
+            },
+        );
+    }
+
+    /// Returns how many files were served from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Writes the index of the files seen during this run, and removes the
+    /// sections of files that no longer exist.
+    pub fn save(self) -> anyhow::Result<()> {
+        let current = self.current.into_inner().expect("a worker panicked");
+        fs::create_dir_all(&self.dir)?;
+
+        let mut index = format!("{INDEX_VERSION}\n{}\n", self.key);
+        for (name, entry) in &current {
+            index.push_str(&format!(
+                "{}\t{}\t{:016x}\t{}\t{name}\n",
+                entry.stamp.size, entry.stamp.modified, entry.hash, entry.binary as u8
+            ));
+        }
+        fs::write(self.dir.join("index"), index)?;
+
+        if let Ok(sections) = fs::read_dir(self.dir.join("sections")) {
+            let live: Vec<String> = current.keys().map(|name| section_name(name)).collect();
+            for section in sections.flatten() {
+                if !live.contains(&section.file_name().to_string_lossy().into_owned()) {
+                    let _ = fs::remove_file(section.path());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The name of `path` in the index: its path relative to the input folder.
+    fn name(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        relative.to_string_lossy().replace('\\', "/")
+    }
+
+    fn section_path(&self, name: &str) -> PathBuf {
+        self.dir.join("sections").join(section_name(name))
+    }
+
+    fn record(&self, name: String, entry: Entry) {
+        self.current
+            .lock()
+            .expect("a worker panicked")
+            .insert(name, entry);
+    }
+}
+
+/// Hashes the raw content of a file.
+pub fn hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The file name under which the section of the file called `name` is stored.
+fn section_name(name: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Parses an index file, returning no entries if it was written by another
+/// version or for another set of transforms.
+fn parse_index(index: &str, key: &str) -> HashMap<String, Entry> {
+    let mut lines = index.lines();
+    if lines.next() != Some(INDEX_VERSION) || lines.next() != Some(key) {
+        return HashMap::new();
+    }
+    lines
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\t');
+            let size = fields.next()?.parse().ok()?;
+            let modified = fields.next()?.parse().ok()?;
+            let hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+            let binary = fields.next()? == "1";
+            let name = fields.next()?.to_string();
+            let stamp = Stamp { size, modified };
+            Some((
+                name,
+                Entry {
+                    stamp,
+                    hash,
+                    binary,
+                },
+            ))
+        })
+        .collect()
+}
+
+// --- Unit Tests for the Content Cache ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that sections survive a save, are found by stamp or by hash, and
+    /// that a different transform key discards them.
+    #[test]
+    fn test_cache_round_trip() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("a.rs").write_str("fn a() {}")?;
+        let path = dir.path().join("a.rs");
+        let stamp = Stamp::of(&fs::metadata(&path)?);
+        let section = Section::Text(b"FN A() {}".to_vec());
+
+        let cache = Cache::open(dir.path(), "shout");
+        assert_eq!(cache.lookup(&path, stamp, None), None);
+        cache.insert(&path, stamp, hash(b"fn a() {}"), &section);
+        cache.save()?;
+
+        let cache = Cache::open(dir.path(), "shout");
+        assert_eq!(cache.lookup(&path, stamp, None), Some(section.clone()));
+        let touched = Stamp {
+            modified: 0,
+            ..stamp
+        };
+        assert_eq!(
+            cache.lookup(&path, touched, Some(hash(b"fn a() {}"))),
+            Some(section)
+        );
+        assert_eq!(cache.lookup(&path, touched, Some(hash(b"changed"))), None);
+        assert_eq!(cache.hits(), 2);
+
+        let cache = Cache::open(dir.path(), "normalize");
+        assert_eq!(cache.lookup(&path, stamp, None), None);
+
+        Ok(())
+    }
+}
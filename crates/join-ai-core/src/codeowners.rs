@@ -0,0 +1,177 @@
+//! Code ownership: the `CODEOWNERS` file of the repository, which assigns
+//! each path to the users and teams that own it. Joins can be limited to the
+//! files of some owners (`--owner`) and name the owners of each file in its
+//! header (`--show-owners`).
+//!
+//! As on GitHub and GitLab, patterns use the gitignore syntax and the last
+//! pattern that matches a file decides its owners.
+
+use anyhow::Context;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the file is looked for, in each folder, in the order GitHub reads
+/// them.
+pub const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// The owners of the paths of a repository.
+#[derive(Debug)]
+pub struct CodeOwners {
+    /// The `CODEOWNERS` file the rules were read from.
+    pub path: PathBuf,
+    /// The input folder as the walker sees it, and as an absolute path.
+    input_folder: PathBuf,
+    absolute: PathBuf,
+    /// The folder the patterns are relative to: the root of the repository.
+    root: PathBuf,
+    /// Each pattern, and the owners it assigns, in the order of the file.
+    rules: Vec<(Gitignore, Vec<String>)>,
+}
+
+impl CodeOwners {
+    /// Reads the `CODEOWNERS` file of the repository of `input_folder`, looked
+    /// for in the input folder and then in the folders above it. Returns
+    /// `None` when there is none.
+    pub fn discover(input_folder: &Path) -> anyhow::Result<Option<Self>> {
+        let absolute = input_folder
+            .canonicalize()
+            .or_else(|_| std::path::absolute(input_folder))?;
+        for folder in absolute.ancestors() {
+            for location in LOCATIONS {
+                let path = folder.join(location);
+                let Ok(text) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let rules = parse(folder, &text)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                return Ok(Some(Self {
+                    path,
+                    input_folder: input_folder.to_path_buf(),
+                    absolute: absolute.clone(),
+                    root: folder.to_path_buf(),
+                    rules,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the owners of the file `path`, a path under the input folder.
+    /// A file that no rule matches, or whose last matching rule lists no one,
+    /// has no owners.
+    pub fn owners(&self, path: &Path) -> &[String] {
+        let relative = path.strip_prefix(&self.input_folder).unwrap_or(path);
+        let absolute = self.absolute.join(relative);
+        let Ok(relative) = absolute.strip_prefix(&self.root) else {
+            return &[];
+        };
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| {
+                pattern
+                    .matched_path_or_any_parents(relative, false)
+                    .is_ignore()
+            })
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+
+    /// Whether the file `path` is owned by one of `wanted`.
+    pub fn is_owned_by(&self, path: &Path, wanted: &[String]) -> bool {
+        self.owners(path)
+            .iter()
+            .any(|owner| wanted.iter().any(|wanted| same_owner(owner, wanted)))
+    }
+}
+
+/// Parses the rules of a `CODEOWNERS` file whose patterns are relative to
+/// `root`. The section headers of GitLab (`[Backend]`) are skipped.
+fn parse(root: &Path, text: &str) -> anyhow::Result<Vec<(Gitignore, Vec<String>)>> {
+    let mut rules = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with('[')
+            || line.starts_with("^[")
+        {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let Some(pattern) = parts.next() else {
+            continue;
+        };
+        let owners = parts
+            .take_while(|part| !part.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        let mut builder = GitignoreBuilder::new(root);
+        builder
+            .add_line(None, pattern)
+            .with_context(|| format!("Invalid pattern '{pattern}' on line {}", number + 1))?;
+        rules.push((builder.build()?, owners));
+    }
+    Ok(rules)
+}
+
+/// Whether `owner`, as written in `CODEOWNERS`, is `wanted`. Names are
+/// compared without case and with or without their `@`, and a team may be
+/// given without its organization (`@backend` for `@acme/backend`).
+fn same_owner(owner: &str, wanted: &str) -> bool {
+    let owner = owner.trim_start_matches('@').to_lowercase();
+    let wanted = wanted.trim_start_matches('@').to_lowercase();
+    owner == wanted
+        || (!wanted.contains('/')
+            && owner
+                .split_once('/')
+                .is_some_and(|(_, team)| team == wanted))
+}
+
+// --- Unit Tests for Code Owners ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that the last matching rule decides the owners of a file,
+    /// that rules without owners unassign files, and that teams are matched
+    /// with or without their organization.
+    #[test]
+    fn test_code_owners() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child(".github/CODEOWNERS").write_str(
+            "# Default owners\n\
+             *       @acme/core\n\
+             \n\
+             [Backend]\n\
+             /api/   @acme/backend @ana # API team\n\
+             *.md    docs@acme.com\n\
+             /api/generated/\n",
+        )?;
+        let owners = CodeOwners::discover(dir.path())?.expect("CODEOWNERS exists");
+        let file = |path: &str| dir.path().join(path);
+
+        assert_eq!(owners.owners(&file("main.rs")), ["@acme/core"]);
+        assert_eq!(
+            owners.owners(&file("api/v1/users.rs")),
+            ["@acme/backend", "@ana"]
+        );
+        assert_eq!(owners.owners(&file("api/README.md")), ["docs@acme.com"]);
+        assert!(owners.owners(&file("api/generated/client.rs")).is_empty());
+
+        let wanted = |owner: &str| vec![owner.to_string()];
+        assert!(owners.is_owned_by(&file("api/users.rs"), &wanted("@Backend")));
+        assert!(owners.is_owned_by(&file("api/users.rs"), &wanted("ana")));
+        assert!(!owners.is_owned_by(&file("main.rs"), &wanted("@acme/backend")));
+
+        // The rules are found from a subfolder, relative to the repository.
+        let owners = CodeOwners::discover(&dir.path().join("api"))?.expect("CODEOWNERS exists");
+        assert_eq!(
+            owners.owners(&dir.path().join("api").join("users.rs")),
+            ["@acme/backend", "@ana"]
+        );
+        Ok(())
+    }
+}
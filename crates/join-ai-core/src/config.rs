@@ -0,0 +1,266 @@
+//! Repository configuration: `.join-ai.toml` files in the input folder and in
+//! the folders above it, which apply to every join of the files below them.
+//!
+//! `never_include` lists paths, in gitignore syntax (`secrets/`, `*.pem`,
+//! `customers.csv`), that are never joined. Unlike `--exclude`, no flag brings
+//! them back: they are left out even with `--hidden`, and a pattern, entry
+//! file or input folder that names one of them is an error. It is a safeguard
+//! for sensitive repositories against a mistyped command.
+
+use anyhow::Context;
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::options::JoinOptions;
+
+/// The name of the configuration file.
+pub const CONFIG_FILE: &str = ".join-ai.toml";
+
+/// The paths that no join may include, from the `never_include` lists of the
+/// configuration files above the input folder.
+#[derive(Debug, Default)]
+pub struct NeverInclude {
+    /// The input folder as the walker sees it, and as an absolute path.
+    input_folder: PathBuf,
+    absolute: PathBuf,
+    /// Each configuration file, and the matcher of its list.
+    lists: Vec<(PathBuf, Gitignore)>,
+}
+
+impl NeverInclude {
+    /// Reads the configuration files in `input_folder` and the folders above
+    /// it. A file that cannot be parsed is an error, rather than no protection.
+    pub fn discover(input_folder: &Path) -> anyhow::Result<Self> {
+        let absolute = input_folder
+            .canonicalize()
+            .or_else(|_| std::path::absolute(input_folder))?;
+        let mut lists = Vec::new();
+        for folder in absolute.ancestors() {
+            let path = folder.join(CONFIG_FILE);
+            let Ok(text) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let patterns =
+                parse(&text).with_context(|| format!("Failed to read {}", path.display()))?;
+            if patterns.is_empty() {
+                continue;
+            }
+            let mut builder = GitignoreBuilder::new(folder);
+            for pattern in &patterns {
+                builder
+                    .add_line(Some(path.clone()), pattern)
+                    .with_context(|| {
+                        format!("Invalid pattern '{pattern}' in {}", path.display())
+                    })?;
+            }
+            lists.push((path, builder.build()?));
+        }
+        Ok(Self {
+            input_folder: input_folder.to_path_buf(),
+            absolute,
+            lists,
+        })
+    }
+
+    /// Returns the configuration file and the pattern that forbid `path`, a
+    /// path under the input folder, or one of its parent folders.
+    pub fn forbids(&self, path: &Path, is_dir: bool) -> Option<(&Path, String)> {
+        let relative = path.strip_prefix(&self.input_folder).unwrap_or(path);
+        let absolute = self.absolute.join(relative);
+        self.lists.iter().find_map(|(config, list)| {
+            let relative = absolute.strip_prefix(list.path()).ok()?;
+            if relative.as_os_str().is_empty() {
+                return None;
+            }
+            match list.matched_path_or_any_parents(relative, is_dir) {
+                Match::Ignore(glob) => Some((config.as_path(), glob.original().to_string())),
+                _ => None,
+            }
+        })
+    }
+
+    /// Fails when the input folder, the entry file or an include pattern of
+    /// `options` names a path that is never included.
+    pub fn check_requests(&self, options: &JoinOptions) -> anyhow::Result<()> {
+        let mut requests: Vec<(String, PathBuf, bool)> = vec![(
+            format!("The input folder {}", options.input_folder.display()),
+            options.input_folder.clone(),
+            true,
+        )];
+        if let Some(entry) = options.entry_path() {
+            requests.push((format!("The entry file {}", entry.display()), entry, false));
+        }
+        for pattern in options.patterns.iter().flatten() {
+            // A pattern read as a path names what it selects: `secrets/*` is in
+            // `secrets/`, and `*.pem` matches `*.pem`.
+            let path = pattern.trim_start_matches("./").trim_start_matches('/');
+            requests.push((
+                format!("The pattern '{pattern}'"),
+                options.input_folder.join(path),
+                false,
+            ));
+        }
+        for (request, path, is_dir) in requests {
+            if let Some((config, forbidden)) = self.forbids(&path, is_dir) {
+                anyhow::bail!(
+                    "{request} asks for files that '{forbidden}' in {} never allows in a join. \
+                     Remove it from the command; `never_include` cannot be overridden.",
+                    config.display()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A context file kept in the repository, and how it is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextFile {
+    /// The path of the file.
+    pub output: PathBuf,
+    /// The arguments of the `join` command that writes it, without the output.
+    pub args: Vec<String>,
+}
+
+/// Reads the `[[context]]` tables of the configuration file at `path`. There
+/// are none when the file does not exist.
+pub fn contexts(path: &Path) -> anyhow::Result<Vec<ContextFile>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    parse_contexts(&text).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn parse_contexts(text: &str) -> anyhow::Result<Vec<ContextFile>> {
+    let table: toml::Table = text.parse()?;
+    let Some(contexts) = table.get("context") else {
+        return Ok(Vec::new());
+    };
+    let strings = |value: &toml::Value| -> Option<Vec<String>> {
+        value
+            .as_array()?
+            .iter()
+            .map(|arg| arg.as_str().map(String::from))
+            .collect()
+    };
+    contexts
+        .as_array()
+        .and_then(|contexts| {
+            contexts
+                .iter()
+                .map(|context| {
+                    Some(ContextFile {
+                        output: PathBuf::from(context.get("output")?.as_str()?),
+                        args: context.get("args").map_or(Some(Vec::new()), strings)?,
+                    })
+                })
+                .collect()
+        })
+        .context(
+            "Each `[[context]]` needs an `output` path, and `args` must be a list of arguments",
+        )
+}
+
+/// Reads the `never_include` list of a configuration file.
+fn parse(text: &str) -> anyhow::Result<Vec<String>> {
+    let table: toml::Table = text.parse()?;
+    let Some(list) = table.get("never_include") else {
+        return Ok(Vec::new());
+    };
+    list.as_array()
+        .and_then(|list| {
+            list.iter()
+                .map(|pattern| pattern.as_str().map(String::from))
+                .collect()
+        })
+        .context("`never_include` must be a list of path patterns")
+}
+
+// --- Unit Tests for Repository Configuration ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that lists apply below their folder, including the lists of
+    /// folders above the input folder, and that explicit requests fail.
+    #[test]
+    fn test_never_include() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child(CONFIG_FILE)
+            .write_str("never_include = [\"secrets/\", \"*.pem\"]\n")?;
+        dir.child("app/.join-ai.toml")
+            .write_str("never_include = [\"customers.csv\"]\n")?;
+        let input = dir.path().join("app");
+        let never = NeverInclude::discover(&input)?;
+
+        assert!(
+            never
+                .forbids(&input.join("secrets/db.txt"), false)
+                .is_some()
+        );
+        assert!(
+            never
+                .forbids(&input.join("deploy/key.pem"), false)
+                .is_some()
+        );
+        let (config, pattern) = never
+            .forbids(&input.join("data/customers.csv"), false)
+            .unwrap();
+        assert_eq!(
+            (config, pattern.as_str()),
+            (input.join(CONFIG_FILE).as_path(), "customers.csv")
+        );
+        assert!(never.forbids(&input.join("src/main.rs"), false).is_none());
+
+        let mut options = JoinOptions::new(&input, dir.path().join("out.txt"));
+        assert!(never.check_requests(&options).is_ok());
+        options.patterns = Some(vec!["src/*.rs".to_string(), "secrets/*".to_string()]);
+        let error = never.check_requests(&options).unwrap_err().to_string();
+        assert!(error.starts_with("The pattern 'secrets/*' asks for files that 'secrets/'"));
+        options.patterns = Some(vec!["*.pem".to_string()]);
+        assert!(never.check_requests(&options).is_err());
+
+        let error = NeverInclude::discover(&input.join("secrets")).and_then(|never| {
+            never.check_requests(&JoinOptions::new(input.join("secrets"), "out.txt"))
+        });
+        assert!(error.is_err());
+
+        dir.child("bad/.join-ai.toml")
+            .write_str("never_include = \"*.pem\"\n")?;
+        assert!(NeverInclude::discover(&dir.path().join("bad")).is_err());
+        Ok(())
+    }
+
+    /// Verifies that context files are read with their arguments, and that a
+    /// context without an output is an error.
+    #[test]
+    fn test_contexts() -> anyhow::Result<()> {
+        let contexts = parse_contexts(
+            "never_include = [\"secrets/\"]\n\n\
+             [[context]]\noutput = \"CONTEXT.md\"\nargs = [\"src\", \"-p\", \"*.rs\"]\n\n\
+             [[context]]\noutput = \"all.txt\"\n",
+        )?;
+        assert_eq!(
+            contexts,
+            [
+                ContextFile {
+                    output: PathBuf::from("CONTEXT.md"),
+                    args: vec!["src".into(), "-p".into(), "*.rs".into()],
+                },
+                ContextFile {
+                    output: PathBuf::from("all.txt"),
+                    args: Vec::new(),
+                },
+            ]
+        );
+        assert!(parse_contexts("[[context]]\nargs = [\".\"]\n").is_err());
+        assert!(super::contexts(Path::new("missing/.join-ai.toml"))?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+//! Test coverage (`--coverage`): the share of the lines of each file that the
+//! tests run, read from an lcov (`lcov.info`) or Cobertura XML report. Each
+//! file header states the coverage of its file, and `SortOrder::Coverage`
+//! puts the least tested files first, so that a request to write tests keeps
+//! them under a token budget.
+
+use anyhow::Context;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+/// How much of a file the tests run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// The number of lines run at least once.
+    pub covered: u64,
+    /// The number of lines that can be run.
+    pub total: u64,
+}
+
+impl FileCoverage {
+    /// The covered share of the lines, in hundredths of a percent, for
+    /// ordering. A file without lines to run counts as fully covered.
+    pub fn basis_points(&self) -> u64 {
+        match self.total {
+            0 => 10_000,
+            total => self.covered * 10_000 / total,
+        }
+    }
+}
+
+impl fmt::Display for FileCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}% ({} of {} lines)",
+            self.basis_points() as f64 / 100.0,
+            self.covered,
+            self.total
+        )
+    }
+}
+
+/// The coverage of the files of a report.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// The coverage of each file, by its path in the report.
+    files: HashMap<PathBuf, FileCoverage>,
+    /// The input folder as the walker sees it, and as an absolute path.
+    input_folder: PathBuf,
+    absolute: PathBuf,
+}
+
+/// The lines of a file found in a report, by number, and whether each is run.
+type Lines = BTreeMap<u64, bool>;
+
+impl Coverage {
+    /// Reads the report at `report`, in the lcov or the Cobertura format, for
+    /// the files of `input_folder`.
+    pub fn load(report: &Path, input_folder: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(report)
+            .with_context(|| format!("Failed to read the coverage report {}", report.display()))?;
+        let files = match text.trim_start().starts_with('<') {
+            true => parse_cobertura(&text),
+            false => parse_lcov(&text),
+        };
+        if files.is_empty() {
+            anyhow::bail!(
+                "No file coverage was found in {}. Expected an lcov (lcov.info) or a Cobertura XML report.",
+                report.display()
+            );
+        }
+        Ok(Self {
+            files,
+            input_folder: input_folder.to_path_buf(),
+            absolute: input_folder
+                .canonicalize()
+                .or_else(|_| std::path::absolute(input_folder))?,
+        })
+    }
+
+    /// Returns the coverage of the file `path`, a path under the input folder.
+    ///
+    /// Reports name files by absolute path or relative to where the tests ran,
+    /// which may be above the input folder, so a file is looked up by its
+    /// absolute path, then by its path relative to the input folder, then as
+    /// the only file of the report whose path ends with it.
+    pub fn of(&self, path: &Path) -> Option<FileCoverage> {
+        let relative = path.strip_prefix(&self.input_folder).unwrap_or(path);
+        let relative: PathBuf = relative
+            .components()
+            .filter(|component| *component != Component::CurDir)
+            .collect();
+        if let Some(coverage) = self
+            .files
+            .get(&self.absolute.join(&relative))
+            .or_else(|| self.files.get(&relative))
+        {
+            return Some(*coverage);
+        }
+        let mut matches = self
+            .files
+            .iter()
+            .filter(|(file, _)| file.ends_with(&relative));
+        match (matches.next(), matches.next()) {
+            (Some((_, coverage)), None) => Some(*coverage),
+            _ => None,
+        }
+    }
+}
+
+/// Sums up the lines of each file.
+fn summarize(files: HashMap<PathBuf, Lines>) -> HashMap<PathBuf, FileCoverage> {
+    files
+        .into_iter()
+        .map(|(path, lines)| {
+            let covered = lines.values().filter(|&&hit| hit).count() as u64;
+            let total = lines.len() as u64;
+            (path, FileCoverage { covered, total })
+        })
+        .collect()
+}
+
+/// Parses an lcov tracefile: `SF:` starts the record of a file and each
+/// `DA:line,hits` gives a line. Records of the same file, as written by
+/// several test runs, are merged.
+fn parse_lcov(text: &str) -> HashMap<PathBuf, FileCoverage> {
+    let mut files: HashMap<PathBuf, Lines> = HashMap::new();
+    let mut current: Option<PathBuf> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix("SF:") {
+            let path = PathBuf::from(path);
+            files.entry(path.clone()).or_default();
+            current = Some(path);
+        } else if let Some(data) = line.strip_prefix("DA:")
+            && let Some(path) = &current
+        {
+            let mut fields = data.split(',');
+            let (Some(Ok(number)), Some(Ok(hits))) = (
+                fields.next().map(str::parse::<u64>),
+                fields.next().map(str::parse::<u64>),
+            ) else {
+                continue;
+            };
+            let hit = files
+                .entry(path.clone())
+                .or_default()
+                .entry(number)
+                .or_default();
+            *hit |= hits > 0;
+        } else if line == "end_of_record" {
+            current = None;
+        }
+    }
+    summarize(files)
+}
+
+/// Parses a Cobertura XML report: each `<class>` names a file, relative to
+/// the `<source>` folder, and its `<line>` elements give its lines. A line
+/// listed both under a method and under its class is counted once.
+fn parse_cobertura(text: &str) -> HashMap<PathBuf, FileCoverage> {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    static SOURCE: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| {
+        Regex::new(r"<(/?)(class|line)\b([^>]*)>").expect("built-in XML pattern is valid")
+    });
+    let attribute = ATTRIBUTE.get_or_init(|| {
+        Regex::new(r#"([\w-]+)\s*=\s*"([^"]*)""#).expect("built-in XML pattern is valid")
+    });
+    let source = SOURCE.get_or_init(|| {
+        Regex::new(r"<source>\s*([^<]*?)\s*</source>").expect("built-in XML pattern is valid")
+    });
+
+    // With several sources, a file could be in any of them; its path in the
+    // report is then matched by suffix.
+    let sources: Vec<&str> = source
+        .captures_iter(text)
+        .map(|captures| captures.get(1).map_or("", |m| m.as_str()))
+        .collect();
+    let root = match sources.as_slice() {
+        [source] => PathBuf::from(unescape(source)),
+        _ => PathBuf::new(),
+    };
+    let mut files: HashMap<PathBuf, Lines> = HashMap::new();
+    let mut current: Option<PathBuf> = None;
+    for captures in tag.captures_iter(text) {
+        let closing = &captures[1] == "/";
+        let attributes: HashMap<&str, &str> = attribute
+            .captures_iter(&captures[3])
+            .filter_map(|pair| Some((pair.get(1)?.as_str(), pair.get(2)?.as_str())))
+            .collect();
+        match (&captures[2], closing) {
+            ("class", true) => current = None,
+            ("class", false) => {
+                current = attributes
+                    .get("filename")
+                    .map(|filename| root.join(unescape(filename)));
+                if let Some(path) = &current {
+                    files.entry(path.clone()).or_default();
+                }
+            }
+            ("line", false) => {
+                let (Some(path), Some(Ok(number)), Some(Ok(hits))) = (
+                    &current,
+                    attributes.get("number").map(|n| n.parse::<u64>()),
+                    attributes.get("hits").map(|h| h.parse::<u64>()),
+                ) else {
+                    continue;
+                };
+                let hit = files
+                    .entry(path.clone())
+                    .or_default()
+                    .entry(number)
+                    .or_default();
+                *hit |= hits > 0;
+            }
+            _ => {}
+        }
+    }
+    summarize(files)
+}
+
+/// Replaces the predefined entities of XML in `text`.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// --- Unit Tests for Coverage Reports ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that lcov records are merged per file and looked up by
+    /// absolute path, by relative path and by suffix.
+    #[test]
+    fn test_lcov_report() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let absolute = dir.path().canonicalize()?.join("src/lib.rs");
+        dir.child("lcov.info").write_str(&format!(
+            "TN:\nSF:{}\nDA:1,1\nDA:2,0\nDA:3,0\nDA:4,0\nLF:4\nLH:1\nend_of_record\n\
+             SF:{}\nDA:2,3\nend_of_record\n\
+             SF:src/main.rs\nDA:1,0\nend_of_record\n\
+             SF:crates/app/src/util.rs\nDA:1,1\nend_of_record\n",
+            absolute.display(),
+            absolute.display()
+        ))?;
+        let coverage = Coverage::load(&dir.path().join("lcov.info"), dir.path())?;
+
+        let lib = coverage.of(&dir.path().join("src/lib.rs")).unwrap();
+        assert_eq!((lib.covered, lib.total), (2, 4));
+        assert_eq!(lib.to_string(), "50.0% (2 of 4 lines)");
+        assert_eq!(
+            coverage
+                .of(&dir.path().join("src/main.rs"))
+                .unwrap()
+                .covered,
+            0
+        );
+        assert_eq!(
+            coverage
+                .of(&dir.path().join("src/util.rs"))
+                .unwrap()
+                .covered,
+            1
+        );
+        assert_eq!(coverage.of(&dir.path().join("README.md")), None);
+        Ok(())
+    }
+
+    /// Verifies that the lines of each class are read from a Cobertura report,
+    /// once each, relative to its source folder.
+    #[test]
+    fn test_cobertura_report() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("coverage.xml").write_str(
+            r#"<?xml version="1.0" ?>
+<coverage line-rate="0.5">
+  <sources><source>/work/app</source></sources>
+  <packages><package name="app"><classes>
+    <class name="db.py" filename="app/db.py" line-rate="0.33">
+      <methods><method name="connect"><lines><line number="2" hits="1"/></lines></method></methods>
+      <lines>
+        <line number="1" hits="1"/>
+        <line number="2" hits="1"/>
+        <line number="3" hits="0" branch="true" condition-coverage="0% (0/2)"/>
+      </lines>
+    </class>
+  </classes></package></packages>
+</coverage>
+"#,
+        )?;
+        let coverage = Coverage::load(&dir.path().join("coverage.xml"), dir.path())?;
+        let db = coverage.of(&dir.path().join("app/db.py")).unwrap();
+        assert_eq!((db.covered, db.total), (2, 3));
+
+        dir.child("empty.info").write_str("TN:\n")?;
+        assert!(Coverage::load(&dir.path().join("empty.info"), dir.path()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! JSX elements inside JavaScript and TypeScript: their text is copied
+//! verbatim (an apostrophe or `//` in it is not code), the code of their `{…}`
+//! expressions is stripped, and `{/* … */}` comment expressions are removed.
+
+use super::{Scanner, block_comment_end};
+
+/// The bytes after which a `<` starts an expression, and thus may open an
+/// element, rather than being a comparison.
+const EXPRESSION_STARTS: &[u8] = b"(,=:?&|{};[!>";
+
+impl Scanner<'_> {
+    /// Whether a JSX element starts at the current position.
+    pub(super) fn jsx_starts(&self) -> bool {
+        let rest = &self.bytes[self.i..];
+        if rest[0] != b'<' {
+            return false;
+        }
+        let name_len = rest[1..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-' | b':'))
+            .count();
+        let after_name = &rest[1 + name_len..];
+        let is_fragment = name_len == 0 && after_name.first() == Some(&b'>');
+        let is_tag = name_len > 0
+            && rest[1].is_ascii_alphabetic()
+            // `<T,>` and `<T extends U>` are type parameters in TSX.
+            && !after_name.starts_with(b",")
+            && !after_name.starts_with(b" extends ");
+        if !is_fragment && !is_tag {
+            return false;
+        }
+        let before = self.out.trim_ascii_end();
+        before.is_empty()
+            || before.ends_with(b"return")
+            || EXPRESSION_STARTS.contains(&before[before.len() - 1])
+    }
+
+    /// Copies the JSX element (or fragment) starting at the current position.
+    pub(super) fn jsx_element(&mut self) {
+        self.copy_to(self.i + 1);
+        // The tag name and attributes.
+        while self.i < self.bytes.len() {
+            match self.bytes[self.i] {
+                b'/' if self.bytes.get(self.i + 1) == Some(&b'>') => {
+                    self.copy_to(self.i + 2);
+                    return;
+                }
+                b'>' => {
+                    self.copy_to(self.i + 1);
+                    break;
+                }
+                quote @ (b'"' | b'\'') => {
+                    let end = self.bytes[self.i + 1..]
+                        .iter()
+                        .position(|&b| b == quote)
+                        .map_or(self.bytes.len(), |n| self.i + n + 2);
+                    self.copy_to(end);
+                }
+                b'{' => self.jsx_expression(),
+                b'\n' => self.newline(),
+                _ => self.copy_to(self.i + 1),
+            }
+        }
+        self.jsx_children();
+    }
+
+    /// Copies the children of an element, up to and including its closing tag.
+    fn jsx_children(&mut self) {
+        while self.i < self.bytes.len() {
+            let rest = &self.bytes[self.i..];
+            if rest.starts_with(b"</") {
+                let end = rest
+                    .iter()
+                    .position(|&b| b == b'>')
+                    .map_or(self.bytes.len(), |n| self.i + n + 1);
+                self.copy_to(end);
+                return;
+            }
+            match rest[0] {
+                b'<' => self.jsx_element(),
+                b'{' => self.jsx_expression(),
+                b'\n' => self.newline(),
+                _ => self.copy_to(self.i + 1),
+            }
+        }
+    }
+
+    /// Handles the `{…}` expression starting at the current position: removes
+    /// it if it holds only comments that are not kept, and otherwise strips
+    /// its code.
+    fn jsx_expression(&mut self) {
+        if let Some(end) = self.comment_expression_end() {
+            if self.options.keeps_marker(&self.bytes[self.i + 1..end]) {
+                self.copy_to(end);
+            } else {
+                self.skip_to(end);
+            }
+            return;
+        }
+        self.copy_to(self.i + 1);
+        self.scan(true);
+        if self.i < self.bytes.len() {
+            self.copy_to(self.i + 1);
+        }
+    }
+
+    /// Returns the end of the `{…}` expression starting at the current
+    /// position if it holds nothing but comments, such as `{/* note */}`.
+    fn comment_expression_end(&self) -> Option<usize> {
+        let language = self.language;
+        let mut i = self.i + 1;
+        let mut comments = 0;
+        loop {
+            i += self.bytes[i..]
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+            let rest = &self.bytes[i..];
+            if rest.first() == Some(&b'}') {
+                return (comments > 0).then_some(i + 1);
+            }
+            if let Some((open, close)) = language
+                .block_comments
+                .iter()
+                .find(|(open, _)| rest.starts_with(open.as_bytes()))
+            {
+                i = block_comment_end(self.bytes, i + open.len(), open, close, false);
+            } else if language
+                .line_comments
+                .iter()
+                .any(|marker| rest.starts_with(marker.as_bytes()))
+            {
+                i += rest.iter().position(|&b| b == b'\n')?;
+            } else {
+                return None;
+            }
+            comments += 1;
+        }
+    }
+}
+
+// --- Unit Tests for JSX ---
+#[cfg(test)]
+mod tests {
+    use crate::decommenter::{LanguageDB, remove_comments};
+
+    /// Verifies that JSX text is copied verbatim, comment expressions removed,
+    /// and comments in the surrounding code and in expressions stripped.
+    #[test]
+    fn test_jsx_comments() {
+        let languages = LanguageDB::builtin();
+        let tsx = languages.find_by_extension("tsx").unwrap();
+        let code = "\
+const id = <T,>(x: T) => x; // generic
+function App() {
+  // Render.
+  return (
+    <div title=\"a // b\" onClick={() => go(/* now */)}>
+      {/* Greeting. */}
+      <p>Don't visit http://example.com</p>
+      {items.map((i) => <Item key={i} />)} {/* TODO: paginate */}
+    </div>
+  );
+}
+";
+        assert_eq!(
+            remove_comments(code, tsx),
+            "\
+const id = <T,>(x: T) => x;
+function App() {
+  return (
+    <div title=\"a // b\" onClick={() => go()}>
+      <p>Don't visit http://example.com</p>
+      {items.map((i) => <Item key={i} />)}
+    </div>
+  );
+}
+"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Markup (HTML, XML and friends): CDATA sections are copied verbatim, and
+//! the code of embedded `<script>` and `<style>` elements is stripped with the
+//! comment syntax of JavaScript, TypeScript or CSS.
+
+use super::{Language, Scanner, builtin_languages, remove_comments_with};
+use aho_corasick::AhoCorasick;
+
+/// Opens a CDATA section, whose text is never a comment.
+const CDATA_OPEN: &[u8] = b"<![CDATA[";
+const CDATA_CLOSE: &[u8] = b"]]>";
+
+/// Elements whose content is code in another language.
+const EMBEDDING_TAGS: [&str; 2] = ["script", "style"];
+
+impl Scanner<'_> {
+    /// Handles the CDATA section or embedding element starting at the current
+    /// position, if there is one. Returns whether the input was consumed.
+    pub(super) fn markup(&mut self) -> bool {
+        let rest = &self.bytes[self.i..];
+        if rest.starts_with(CDATA_OPEN) {
+            let end = memchr::memmem::find(rest, CDATA_CLOSE)
+                .map_or(self.bytes.len(), |n| self.i + n + CDATA_CLOSE.len());
+            self.copy_to(end);
+            return true;
+        }
+        let Some(tag) = EMBEDDING_TAGS
+            .into_iter()
+            .find(|tag| starts_with_tag(rest, tag))
+        else {
+            return false;
+        };
+        let Some(tag_len) = rest.iter().position(|&b| b == b'>').map(|n| n + 1) else {
+            return false;
+        };
+        let open_tag = &rest[..tag_len];
+        self.copy_to(self.i + tag_len);
+        if open_tag.ends_with(b"/>") {
+            return true;
+        }
+
+        let close = format!("</{tag}");
+        let body_end = find_ignore_case(&self.bytes[self.i..], close.as_bytes())
+            .map_or(self.bytes.len(), |n| self.i + n);
+        let body = &self.bytes[self.i..body_end];
+        let stripped = embedded_language(tag, open_tag)
+            .zip(std::str::from_utf8(body).ok())
+            .map(|(language, body)| remove_comments_with(body, language, self.options));
+        match stripped {
+            Some(stripped) => {
+                self.out.extend_from_slice(stripped.as_bytes());
+                self.i = body_end;
+            }
+            None => self.copy_to(body_end),
+        }
+        // Lines inside the element were finished by the embedded language.
+        if let Some(newline) = self.out[self.line_start..]
+            .iter()
+            .rposition(|&b| b == b'\n')
+        {
+            self.line_start += newline + 1;
+            self.line_had_comment = false;
+        }
+        true
+    }
+}
+
+/// Returns the language of the content of an embedding element, from its
+/// `lang` or `type` attribute. Content that is data (e.g., JSON) or a template
+/// has no language and is copied verbatim.
+fn embedded_language(tag: &str, open_tag: &[u8]) -> Option<&'static Language> {
+    let open_tag = String::from_utf8_lossy(open_tag).to_ascii_lowercase();
+    let attribute = |name: &str| {
+        let start = open_tag.find(&format!(" {name}="))? + name.len() + 2;
+        let value = open_tag[start..].trim_start_matches(['"', '\'']);
+        let end = value.find(['"', '\'', ' ', '>']).unwrap_or(value.len());
+        Some(value[..end].to_string())
+    };
+    let languages = builtin_languages();
+    if tag == "style" {
+        return match attribute("lang").as_deref() {
+            Some("scss" | "sass" | "less") => languages.find("scss"),
+            _ => languages.find("css"),
+        };
+    }
+    if let Some(kind) = attribute("type")
+        && (kind.contains("json") || kind.contains("template") || kind.contains("html"))
+    {
+        return None;
+    }
+    match attribute("lang").as_deref() {
+        Some("ts" | "tsx" | "typescript") => languages.find("typescript"),
+        _ => languages.find("javascript"),
+    }
+}
+
+/// Whether `rest` starts with the opening tag `<tag`, ignoring case.
+fn starts_with_tag(rest: &[u8], tag: &str) -> bool {
+    rest.first() == Some(&b'<')
+        && rest
+            .get(1..=tag.len())
+            .is_some_and(|name| name.eq_ignore_ascii_case(tag.as_bytes()))
+        && matches!(
+            rest.get(tag.len() + 1),
+            Some(b' ' | b'\t' | b'\n' | b'\r' | b'>' | b'/')
+        )
+}
+
+/// Returns the position of `needle` in `haystack`, ignoring ASCII case.
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let searcher = AhoCorasick::builder()
+        .ascii_case_insensitive(true)
+        .build([needle])
+        .ok()?;
+    searcher.find(haystack).map(|found| found.start())
+}
+
+// --- Unit Tests for Markup ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decommenter::remove_comments;
+
+    /// Verifies that HTML comments are removed, CDATA kept, and embedded
+    /// scripts and styles stripped in their own syntax.
+    #[test]
+    fn test_markup_regions() {
+        let html = builtin_languages().find("html").unwrap();
+        let page = "\
+<!-- Header -->
+<p>Don't // stop</p>
+<script type=\"module\">
+  // Setup.
+  const url = 'http://x'; /* inline */
+</script>
+<STYLE>
+  p { color: red; } /* red */
+</STYLE>
+<script type=\"text/x-template\">// kept</script>
+<script lang=\"ts\">let x: number = 1; // one</script>
+";
+        assert_eq!(
+            remove_comments(page, html),
+            "\
+<p>Don't // stop</p>
+<script type=\"module\">
+  const url = 'http://x';
+</script>
+<STYLE>
+  p { color: red; }
+</STYLE>
+<script type=\"text/x-template\">// kept</script>
+<script lang=\"ts\">let x: number = 1;</script>
+"
+        );
+
+        let xml = builtin_languages().find("xml").unwrap();
+        let doc = "<a><![CDATA[<!-- data -->]]></a> <!-- note -->\n";
+        assert_eq!(
+            remove_comments(doc, xml),
+            "<a><![CDATA[<!-- data -->]]></a>\n"
+        );
+    }
+}
@@ -0,0 +1,1293 @@
+//! Comment removal: strips comments from source files so the output spends
+//! its tokens on code.
+//!
+//! The comment and string syntax of each language is described in
+//! `languages.toml`, which is embedded in the crate. The scanner only knows
+//! about comments and strings; it does not parse the language, so a comment
+//! marker inside a string is kept while everything else is removed.
+
+mod jsx;
+mod markup;
+mod preprocessor;
+
+use crate::transform::Transform;
+use aho_corasick::{AhoCorasick, Input, MatchKind};
+use anyhow::Context;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The built-in language definitions.
+const LANGUAGES: &str = include_str!("languages.toml");
+
+/// The comment and string syntax of a language.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Language {
+    /// The display name, such as `Rust`.
+    pub name: String,
+    /// The tag of Markdown code fences, such as `rust`.
+    pub fence: String,
+    /// File extensions, without the dot and lowercase.
+    pub extensions: Vec<String>,
+    /// Glob patterns matched against the file name, for files without a
+    /// telling extension (e.g., `Makefile`, `Dockerfile.*`).
+    pub filenames: Vec<String>,
+    /// Interpreters named in a shebang line (e.g., `python` for
+    /// `#!/usr/bin/env python3`). Version suffixes are ignored.
+    pub interpreters: Vec<String>,
+    /// Markers that start a comment running to the end of the line.
+    pub line_comments: Vec<String>,
+    /// Whether line comment markers only count at the start of a word: after
+    /// whitespace or a shell operator (`;|&()<>`), as `#` in shell, where
+    /// `${var#prefix}` and `$#` are not comments.
+    pub requires_boundary: bool,
+    /// Open and close delimiters of block comments.
+    pub block_comments: Vec<(String, String)>,
+    /// Whether block comments nest.
+    pub nested_comments: bool,
+    /// Open and close delimiters of strings with backslash escapes.
+    pub strings: Vec<(String, String)>,
+    /// Open and close delimiters of strings without escapes.
+    pub literal_strings: Vec<(String, String)>,
+    /// Whether Rust raw strings (`r#"…"#`) are recognized.
+    pub raw_strings: bool,
+    /// Whether backticks start JavaScript template literals, whose `${…}`
+    /// interpolations hold code.
+    pub template_literals: bool,
+    /// Whether shell heredocs (`<<EOF … EOF`) are recognized.
+    pub heredocs: bool,
+    /// Line comment markers of documentation comments (e.g., `///`).
+    pub doc_comments: Vec<String>,
+    /// Block comment openings of documentation comments (e.g., `/**`).
+    pub doc_block_comments: Vec<String>,
+    /// Whether triple-quoted strings on their own line are docstrings, which
+    /// are removed with the comments.
+    pub docstrings: bool,
+    /// Whether the language has a C preprocessor, whose disabled blocks
+    /// (`#if 0 … #endif`) can be removed.
+    pub preprocessor: bool,
+    /// Whether the language is markup (HTML, XML), with CDATA sections and
+    /// embedded `<script>` and `<style>` elements.
+    pub markup: bool,
+    /// Whether JSX elements may appear in expressions.
+    pub jsx: bool,
+    /// The automaton that finds the delimiters of the language, built once.
+    candidates: Candidates,
+}
+
+impl Language {
+    /// The automaton that finds the next position where something other than
+    /// plain code may start, built on first use if it was not when the
+    /// language was loaded.
+    fn candidates(&self) -> &AhoCorasick {
+        self.candidates.0.get_or_init(|| candidates(self))
+    }
+}
+
+/// The automaton of [`candidates`] of a language. It is derived from the
+/// definition, so it plays no part in comparing languages.
+#[derive(Debug, Clone, Default)]
+struct Candidates(OnceLock<AhoCorasick>);
+
+impl PartialEq for Candidates {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Candidates {}
+
+/// Which comments are removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum StripMode {
+    /// Remove every comment, including documentation.
+    #[default]
+    All,
+    /// Keep documentation comments (`///`, `//!`, `/** … */`, docstrings) and
+    /// remove the others.
+    KeepDoc,
+}
+
+/// The markers of comments that join-ai keeps by default while stripping,
+/// as they record intent and known problems.
+pub const DEFAULT_KEEP_MARKERS: [&str; 5] = ["TODO", "FIXME", "SAFETY", "HACK", "NOTE"];
+
+/// When the first comment block of a file is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum HeaderMode {
+    /// Keep it if it looks like a license or copyright notice.
+    License,
+    /// Always keep it.
+    Always,
+}
+
+/// Words that mark a comment block as a license or copyright notice.
+const LICENSE_WORDS: [&str; 6] = [
+    "copyright",
+    "license",
+    "licence",
+    "spdx-license-identifier",
+    "all rights reserved",
+    "©",
+];
+
+/// Settings of comment removal beyond the language syntax.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StripOptions {
+    /// Whether documentation comments and docstrings are kept.
+    pub keep_doc: bool,
+    /// Comments whose first word is one of these markers (e.g., `TODO` in
+    /// `// TODO: retry`) are kept. Matching is case-sensitive.
+    pub keep_markers: Vec<String>,
+    /// Whether preprocessor blocks that are never compiled (`#if 0 … #endif`)
+    /// are removed, in languages with a C preprocessor.
+    pub strip_disabled: bool,
+    /// Whether removed lines are left blank instead, so the output has the
+    /// same line numbers as the input.
+    pub preserve_lines: bool,
+    /// When the first comment block of the file (e.g., a license header) is
+    /// kept. `None` removes it like any other comment.
+    pub keep_header: Option<HeaderMode>,
+}
+
+impl From<StripMode> for StripOptions {
+    fn from(mode: StripMode) -> Self {
+        Self {
+            keep_doc: mode == StripMode::KeepDoc,
+            keep_markers: Vec::new(),
+            strip_disabled: false,
+            preserve_lines: false,
+            keep_header: None,
+        }
+    }
+}
+
+impl StripOptions {
+    /// Whether the comment `text`, delimiters included, starts with one of
+    /// the kept markers.
+    fn keeps_marker(&self, text: &[u8]) -> bool {
+        if self.keep_markers.is_empty() {
+            return false;
+        }
+        let body = text
+            .iter()
+            .position(u8::is_ascii_alphanumeric)
+            .map_or(&[][..], |start| &text[start..]);
+        let word_len = body
+            .iter()
+            .take_while(|&&b| b.is_ascii_alphanumeric() || b == b'_')
+            .count();
+        let word = &body[..word_len];
+        self.keep_markers
+            .iter()
+            .any(|marker| marker.as_bytes() == word)
+    }
+}
+
+/// A set of language definitions, looked up by file name, extension or shebang.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageDB {
+    languages: Vec<Language>,
+    /// The compiled `filenames` patterns of each language.
+    filenames: Vec<GlobSet>,
+}
+
+impl LanguageDB {
+    /// Returns the built-in language definitions.
+    pub fn builtin() -> Self {
+        builtin_languages().clone()
+    }
+
+    /// Parses language definitions from TOML, in the format of the built-in
+    /// `languages.toml`: an array of `[[language]]` tables.
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        let table: toml::Table = text
+            .parse()
+            .context("Failed to parse the language definitions")?;
+        let Some(entries) = table.get("language").and_then(|value| value.as_array()) else {
+            return Ok(Self::default());
+        };
+        let languages = entries
+            .iter()
+            .map(|entry| {
+                let entry = entry.as_table().context("Each language must be a table")?;
+                parse_language(entry)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let filenames = languages
+            .iter()
+            .map(|language| {
+                let mut builder = GlobSetBuilder::new();
+                for pattern in &language.filenames {
+                    let glob = Glob::new(pattern).with_context(|| {
+                        format!("Invalid file name pattern '{pattern}' of {}", language.name)
+                    })?;
+                    builder.add(glob);
+                }
+                Ok(builder.build()?)
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            languages,
+            filenames,
+        })
+    }
+
+    /// Returns every language, in definition order.
+    pub fn languages(&self) -> &[Language] {
+        &self.languages
+    }
+
+    /// Returns the language of files with the extension `extension`, ignoring case.
+    pub fn find_by_extension(&self, extension: &str) -> Option<&Language> {
+        let extension = extension.to_ascii_lowercase();
+        self.languages
+            .iter()
+            .find(|language| language.extensions.contains(&extension))
+    }
+
+    /// Returns the language called `name`, which may be its display name,
+    /// its fence tag or one of its extensions, ignoring case.
+    pub fn find(&self, name: &str) -> Option<&Language> {
+        self.languages
+            .iter()
+            .find(|language| {
+                language.name.eq_ignore_ascii_case(name)
+                    || language.fence.eq_ignore_ascii_case(name)
+            })
+            .or_else(|| self.find_by_extension(name))
+    }
+
+    /// Returns the language whose file name patterns match `file_name`.
+    pub fn find_by_filename(&self, file_name: &str) -> Option<&Language> {
+        let index = self
+            .filenames
+            .iter()
+            .position(|patterns| patterns.is_match(file_name))?;
+        Some(&self.languages[index])
+    }
+
+    /// Returns the language of the interpreter named in the shebang line at
+    /// the start of `content`, if there is one.
+    pub fn find_by_shebang(&self, content: &[u8]) -> Option<&Language> {
+        let interpreter = shebang_interpreter(content)?;
+        let version = |suffix: &str| suffix.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+        self.languages.iter().find(|language| {
+            language
+                .interpreters
+                .iter()
+                .any(|name| interpreter.strip_prefix(name.as_str()).is_some_and(version))
+        })
+    }
+
+    /// Returns the language of the file at `path` from its name or extension,
+    /// if it is known.
+    pub fn for_path(&self, path: &Path) -> Option<&Language> {
+        let file_name = path.file_name()?.to_str()?;
+        self.find_by_filename(file_name).or_else(|| {
+            let extension = path.extension()?.to_str()?;
+            self.find_by_extension(extension)
+        })
+    }
+
+    /// Returns the language of the file at `path` with the given `content`:
+    /// from its name or extension, or else from its shebang line.
+    pub fn detect(&self, path: &Path, content: &[u8]) -> Option<&Language> {
+        self.for_path(path)
+            .or_else(|| self.find_by_shebang(content))
+    }
+}
+
+/// Returns the name of the interpreter in the shebang line at the start of
+/// `content`, skipping `env` and its options (`#!/usr/bin/env -S python3`).
+fn shebang_interpreter(content: &[u8]) -> Option<&str> {
+    let rest = content.strip_prefix(b"#!")?;
+    let line = &rest[..rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len())];
+    let line = std::str::from_utf8(line).ok()?;
+    let mut words = line.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        words.find(|word| !word.starts_with('-') && !word.contains('='))
+    } else {
+        Some(program)
+    }
+}
+
+/// The built-in language definitions, parsed once.
+fn builtin_languages() -> &'static LanguageDB {
+    static LANGUAGE_DB: OnceLock<LanguageDB> = OnceLock::new();
+    LANGUAGE_DB.get_or_init(|| {
+        LanguageDB::from_toml(LANGUAGES).expect("built-in language definitions are valid")
+    })
+}
+
+fn parse_language(entry: &toml::Table) -> anyhow::Result<Language> {
+    let name = entry
+        .get("name")
+        .and_then(|value| value.as_str())
+        .context("A language is missing its `name`")?;
+    let strings = |key: &str| -> anyhow::Result<Vec<String>> {
+        let Some(value) = entry.get(key) else {
+            return Ok(Vec::new());
+        };
+        value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|item| item.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .with_context(|| format!("`{key}` of {name} must be a list of strings"))
+    };
+    let flag = |key: &str| entry.get(key).and_then(|value| value.as_bool()) == Some(true);
+    let pairs = |key: &str| -> anyhow::Result<Vec<(String, String)>> {
+        let Some(value) = entry.get(key) else {
+            return Ok(Vec::new());
+        };
+        value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|item| match item.as_array()?.as_slice() {
+                [open, close] => {
+                    let (open, close) = (open.as_str()?, close.as_str()?);
+                    let valid = !open.is_empty() && !close.is_empty();
+                    valid.then(|| (open.to_string(), close.to_string()))
+                }
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .with_context(|| format!("`{key}` of {name} must be a list of [open, close] pairs"))
+    };
+    let language = Language {
+        name: name.to_string(),
+        fence: entry
+            .get("fence")
+            .and_then(|value| value.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        extensions: strings("extensions")?
+            .into_iter()
+            .map(|extension| extension.to_ascii_lowercase())
+            .collect(),
+        filenames: strings("filenames")?,
+        interpreters: strings("interpreters")?,
+        line_comments: strings("line_comments")?,
+        requires_boundary: flag("requires_boundary"),
+        block_comments: pairs("block_comments")?,
+        nested_comments: flag("nested_comments"),
+        strings: pairs("strings")?,
+        literal_strings: pairs("literal_strings")?,
+        raw_strings: flag("raw_strings"),
+        template_literals: flag("template_literals"),
+        heredocs: flag("heredocs"),
+        doc_comments: strings("doc_comments")?,
+        doc_block_comments: strings("doc_block_comments")?,
+        docstrings: flag("docstrings"),
+        preprocessor: flag("preprocessor"),
+        markup: flag("markup"),
+        jsx: flag("jsx"),
+        candidates: Candidates::default(),
+    };
+    // Built as the language is loaded, rather than for every file stripped.
+    language.candidates();
+    Ok(language)
+}
+
+/// Removes the comments of `language` from `content`. Lines that held only a
+/// comment are dropped, and whitespace left before a removed trailing comment
+/// is trimmed. Strings are copied verbatim, including comment markers in them.
+pub fn remove_comments(content: &str, language: &Language) -> String {
+    remove_comments_with(content, language, &StripOptions::default())
+}
+
+/// Removes the comments of `language` from `content` like [`remove_comments`],
+/// keeping the ones selected by `options`.
+pub fn remove_comments_with(content: &str, language: &Language, options: &StripOptions) -> String {
+    let content = if options.strip_disabled && language.preprocessor {
+        preprocessor::remove_disabled_blocks(content, options.preserve_lines)
+    } else {
+        Cow::Borrowed(content)
+    };
+    let content = content.as_ref();
+    let mut scanner = Scanner {
+        language,
+        options,
+        candidates: language.candidates(),
+        bytes: content.as_bytes(),
+        out: Vec::with_capacity(content.len()),
+        i: 0,
+        line_start: 0,
+        line_had_comment: false,
+        heredocs: Vec::new(),
+    };
+    // A shebang line is kept, even where `#` starts a comment.
+    if content.starts_with("#!") && !content.starts_with("#![") {
+        let end = content.find('\n').unwrap_or(content.len());
+        scanner.copy_to(end);
+    }
+    if let Some(mode) = options.keep_header
+        && let Some(end) = scanner.header_end()
+    {
+        let header = String::from_utf8_lossy(&scanner.bytes[scanner.i..end]).to_lowercase();
+        if mode == HeaderMode::Always || LICENSE_WORDS.iter().any(|word| header.contains(word)) {
+            scanner.copy_to(end);
+        }
+    }
+    scanner.scan(false);
+    scanner.finish_line();
+    // Delimiters are UTF-8 themselves, so they only match at character
+    // boundaries, and escapes skip whole characters: only whole comments are
+    // removed. Should a split character slip through anyway, it is replaced
+    // rather than handed on as invalid UTF-8.
+    match String::from_utf8(scanner.out) {
+        Ok(out) => out,
+        Err(error) => {
+            debug_assert!(false, "comment removal split a character: {error}");
+            String::from_utf8_lossy(error.as_bytes()).into_owned()
+        }
+    }
+}
+
+/// Builds the automaton that finds the next position where something other
+/// than plain code may start: a delimiter of `language`, a newline, or a
+/// construct with its own rules.
+fn candidates(language: &Language) -> AhoCorasick {
+    let delimiters = language
+        .block_comments
+        .iter()
+        .chain(&language.strings)
+        .chain(&language.literal_strings)
+        .map(|(open, _)| open.as_str())
+        .chain(language.line_comments.iter().map(String::as_str));
+    let mut patterns: Vec<&str> = delimiters.filter(|open| !open.is_empty()).collect();
+    patterns.push("\n");
+    if language.raw_strings {
+        patterns.extend(["r\"", "r#"]);
+    }
+    if language.template_literals || language.jsx {
+        // Braces matter while scanning the code of an interpolation.
+        patterns.extend(["`", "{", "}"]);
+    }
+    if language.heredocs {
+        patterns.push("<<");
+    }
+    if language.markup || language.jsx {
+        patterns.push("<");
+    }
+    AhoCorasick::builder()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build(patterns)
+        .expect("delimiters make a valid automaton")
+}
+
+/// A heredoc whose body starts on the next line.
+struct Heredoc {
+    terminator: Vec<u8>,
+    /// Whether the terminator may be indented (`<<-EOF`, `<<~EOF`).
+    indented: bool,
+}
+
+/// The state of comment removal over one file.
+struct Scanner<'a> {
+    language: &'a Language,
+    options: &'a StripOptions,
+    /// Finds the next position that needs a closer look; the code before it
+    /// is copied as it is.
+    candidates: &'a AhoCorasick,
+    bytes: &'a [u8],
+    out: Vec<u8>,
+    i: usize,
+    /// Where the current line starts in `out`.
+    line_start: usize,
+    line_had_comment: bool,
+    /// Heredocs opened on the current line, in order.
+    heredocs: Vec<Heredoc>,
+}
+
+impl Scanner<'_> {
+    /// Scans code until the end of the input or, with `in_braces`, until the
+    /// `}` closing a template literal interpolation (which is not consumed).
+    fn scan(&mut self, in_braces: bool) {
+        let language = self.language;
+        let mut depth = 0;
+        while self.i < self.bytes.len() {
+            let input = Input::new(self.bytes).span(self.i..self.bytes.len());
+            let next = self
+                .candidates
+                .find(input)
+                .map_or(self.bytes.len(), |found| found.start());
+            if next > self.i {
+                self.copy_to(next);
+                continue;
+            }
+            let rest = &self.bytes[self.i..];
+            if language.markup && self.markup() {
+                continue;
+            }
+            if let Some((open, close)) = language
+                .block_comments
+                .iter()
+                .find(|(open, _)| rest.starts_with(open.as_bytes()))
+            {
+                let end = block_comment_end(
+                    self.bytes,
+                    self.i + open.len(),
+                    open,
+                    close,
+                    language.nested_comments,
+                );
+                let is_doc = language.doc_block_comments.iter().any(|doc| {
+                    // `/**/` is an empty comment, not documentation.
+                    rest.starts_with(doc.as_bytes())
+                        && !rest[doc.len() - 1..].starts_with(close.as_bytes())
+                });
+                self.comment(end, is_doc);
+            } else if language
+                .line_comments
+                .iter()
+                .any(|marker| rest.starts_with(marker.as_bytes()))
+                && (!language.requires_boundary || self.at_word_start())
+            {
+                let end = self.i + memchr::memchr(b'\n', rest).unwrap_or(rest.len());
+                let is_doc = language
+                    .doc_comments
+                    .iter()
+                    .any(|doc| rest.starts_with(doc.as_bytes()));
+                self.comment(end, is_doc);
+            } else if let Some(end) = self.raw_string_end() {
+                self.copy_to(end);
+            } else if language.jsx && self.jsx_starts() {
+                self.jsx_element();
+            } else if language.template_literals && rest[0] == b'`' {
+                self.template_literal();
+            } else if let Some((heredoc, end)) = self.heredoc_at() {
+                self.heredocs.push(heredoc);
+                self.copy_to(end);
+            } else if let Some((open, close, escapes)) = string_at(rest, language) {
+                let end = string_end(self.bytes, self.i + open.len(), close, escapes);
+                if open.len() == 3 && self.is_docstring(end) {
+                    self.docstring(end);
+                } else {
+                    self.copy_to(end);
+                }
+            } else if rest[0] == b'\n' {
+                self.newline();
+            } else {
+                if in_braces {
+                    match rest[0] {
+                        b'{' => depth += 1,
+                        b'}' if depth == 0 => return,
+                        b'}' => depth -= 1,
+                        _ => {}
+                    }
+                }
+                self.copy_to(self.i + 1);
+            }
+        }
+    }
+
+    /// Removes the comment that ends at `end`, or keeps it if it is kept
+    /// documentation or starts with a kept marker.
+    fn comment(&mut self, end: usize, is_doc: bool) {
+        if (is_doc && self.options.keep_doc) || self.options.keeps_marker(&self.bytes[self.i..end])
+        {
+            self.copy_to(end);
+        } else {
+            self.skip_to(end);
+        }
+    }
+
+    /// Removes the input up to `end`, which is part of a comment. With
+    /// `preserve_lines`, the line breaks in it are kept.
+    fn skip_to(&mut self, end: usize) {
+        if self.options.preserve_lines {
+            let breaks = memchr::memchr_iter(b'\n', &self.bytes[self.i..end]).count();
+            for _ in 0..breaks {
+                self.line_had_comment = true;
+                self.finish_line();
+                self.out.push(b'\n');
+                self.line_start = self.out.len();
+            }
+        }
+        self.i = end;
+        self.line_had_comment = true;
+    }
+
+    /// Whether the triple-quoted string that starts at the current position
+    /// and ends at `end` is a docstring: a string alone on its line(s).
+    fn is_docstring(&self, end: usize) -> bool {
+        if !self.language.docstrings || !self.out[self.line_start..].trim_ascii().is_empty() {
+            return false;
+        }
+        let rest = &self.bytes[end..];
+        let line = &rest[..rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len())];
+        let line = line.trim_ascii();
+        line.is_empty()
+            || self
+                .language
+                .line_comments
+                .iter()
+                .any(|marker| line.starts_with(marker.as_bytes()))
+    }
+
+    /// Removes the docstring that ends at `end`, unless documentation is kept.
+    /// A docstring that is the whole body of a block becomes `...`, so the
+    /// code stays valid.
+    fn docstring(&mut self, end: usize) {
+        if self.options.keep_doc {
+            self.copy_to(end);
+            return;
+        }
+        let indent = self.out.len() - self.line_start;
+        if indent > 0 && !self.block_continues(end, indent) {
+            self.out.extend_from_slice(b"...");
+        }
+        self.skip_to(end);
+    }
+
+    /// Whether a line of code after `from` is indented at least `indent`
+    /// columns, that is, whether the block of the code at `from` goes on.
+    fn block_continues(&self, from: usize, indent: usize) -> bool {
+        let mut lines = self.bytes[from..].split(|&b| b == b'\n').skip(1);
+        let next = lines.find(|line| {
+            let code = line.trim_ascii();
+            !code.is_empty()
+                && !self
+                    .language
+                    .line_comments
+                    .iter()
+                    .any(|marker| code.starts_with(marker.as_bytes()))
+        });
+        next.is_some_and(|line| line.len() - line.trim_ascii_start().len() >= indent)
+    }
+
+    /// Returns the end of the comment block that starts the rest of the file,
+    /// after blank lines: one block comment, or consecutive lines of line
+    /// comments. The whitespace before the block is copied.
+    fn header_end(&mut self) -> Option<usize> {
+        let start = self.i
+            + self.bytes[self.i..]
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+        let rest = &self.bytes[start..];
+        let language = self.language;
+        let end = if let Some((open, close)) = language
+            .block_comments
+            .iter()
+            .find(|(open, _)| rest.starts_with(open.as_bytes()))
+        {
+            block_comment_end(
+                self.bytes,
+                start + open.len(),
+                open,
+                close,
+                language.nested_comments,
+            )
+        } else {
+            let is_comment = |line: &[u8]| {
+                let line = line.trim_ascii_start();
+                language
+                    .line_comments
+                    .iter()
+                    .any(|marker| line.starts_with(marker.as_bytes()))
+            };
+            let mut end = start;
+            for line in rest.split(|&b| b == b'\n') {
+                if !is_comment(line) {
+                    break;
+                }
+                end = (end + line.len() + 1).min(self.bytes.len());
+            }
+            // The newline after the block is left to the scanner.
+            if end > start && self.bytes[end - 1] == b'\n' {
+                end -= 1;
+            }
+            end
+        };
+        if end == start {
+            return None;
+        }
+        self.copy_to(start);
+        if let Some(newline) = memchr::memrchr(b'\n', &self.out) {
+            self.line_start = newline + 1;
+        }
+        Some(end)
+    }
+
+    /// Whether the current position starts a word, as far as comments are
+    /// concerned.
+    fn at_word_start(&self) -> bool {
+        self.i == 0
+            || matches!(
+                self.bytes[self.i - 1],
+                b' ' | b'\t' | b'\n' | b'\r' | b';' | b'|' | b'&' | b'(' | b')' | b'<' | b'>'
+            )
+    }
+
+    /// Copies the input up to `end` to the output.
+    fn copy_to(&mut self, end: usize) {
+        self.out.extend_from_slice(&self.bytes[self.i..end]);
+        self.i = end;
+    }
+
+    /// Ends the current line, then copies the bodies of the heredocs opened on
+    /// it verbatim, terminators included.
+    fn newline(&mut self) {
+        if self.finish_line() {
+            self.out.push(b'\n');
+        }
+        self.i += 1;
+        for heredoc in std::mem::take(&mut self.heredocs) {
+            while self.i < self.bytes.len() {
+                let rest = &self.bytes[self.i..];
+                let len = rest
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(rest.len(), |n| n + 1);
+                let line = &rest[..len];
+                self.copy_to(self.i + len);
+                if heredoc.is_terminator(line) {
+                    break;
+                }
+            }
+        }
+        self.line_start = self.out.len();
+        self.line_had_comment = false;
+    }
+
+    /// Trims the trailing whitespace of the current line if a comment was
+    /// removed from it, and drops the line if nothing is left, unless lines are
+    /// preserved. Returns whether the line is kept.
+    fn finish_line(&mut self) -> bool {
+        if !self.line_had_comment {
+            return true;
+        }
+        let line_start = self.line_start;
+        let kept = self.out[line_start..]
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .map_or(line_start, |last| line_start + last + 1);
+        self.out.truncate(kept);
+        kept > line_start || self.options.preserve_lines
+    }
+
+    /// Returns the end of the Rust raw string (`r"…"`, `r#"…"#`, `br#"…"#`)
+    /// starting at the current position, if there is one.
+    fn raw_string_end(&self) -> Option<usize> {
+        let (bytes, i) = (self.bytes, self.i);
+        if !self.language.raw_strings || bytes[i] != b'r' {
+            return None;
+        }
+        let is_ident = |at: usize| bytes[at].is_ascii_alphanumeric() || bytes[at] == b'_';
+        let prefixed = i > 0 && matches!(bytes[i - 1], b'b' | b'c');
+        let start_of_token = match i {
+            0 => true,
+            1 => !is_ident(0) || prefixed,
+            _ => !is_ident(i - 1) || (prefixed && !is_ident(i - 2)),
+        };
+        if !start_of_token {
+            return None;
+        }
+        let hashes = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+        let quote = i + 1 + hashes;
+        if bytes.get(quote) != Some(&b'"') {
+            return None;
+        }
+        let mut close = vec![b'"'];
+        close.extend(std::iter::repeat_n(b'#', hashes));
+        let end = memchr::memmem::find(&bytes[quote + 1..], &close)
+            .map_or(bytes.len(), |n| quote + 1 + n + close.len());
+        Some(end)
+    }
+
+    /// Copies the JavaScript template literal starting at the current position,
+    /// removing comments from the code of its `${…}` interpolations.
+    fn template_literal(&mut self) {
+        self.copy_to(self.i + 1);
+        while self.i < self.bytes.len() {
+            let rest = &self.bytes[self.i..];
+            if rest[0] == b'\\' {
+                self.copy_to(char_end(self.bytes, self.i + 1));
+            } else if rest[0] == b'`' {
+                self.copy_to(self.i + 1);
+                return;
+            } else if rest.starts_with(b"${") {
+                self.copy_to(self.i + 2);
+                self.scan(true);
+                if self.i < self.bytes.len() {
+                    self.copy_to(self.i + 1);
+                }
+            } else {
+                self.copy_to(self.i + 1);
+            }
+        }
+    }
+
+    /// Returns the heredoc opened at the current position (`<<EOF`, `<<-EOF`,
+    /// `<<'EOF'`, `<< "EOF"`) and the end of its operator, if there is one and
+    /// a later line terminates it.
+    fn heredoc_at(&self) -> Option<(Heredoc, usize)> {
+        let (bytes, i) = (self.bytes, self.i);
+        if !self.language.heredocs
+            || !bytes[i..].starts_with(b"<<")
+            || bytes.get(i + 2) == Some(&b'<')
+            || (i > 0 && bytes[i - 1] == b'<')
+        {
+            return None;
+        }
+        let mut at = i + 2;
+        let indented = matches!(bytes.get(at), Some(b'-' | b'~'));
+        if indented {
+            at += 1;
+        }
+        while matches!(bytes.get(at), Some(b' ' | b'\t')) {
+            at += 1;
+        }
+        let quote = bytes.get(at).copied().filter(|&b| b == b'\'' || b == b'"');
+        if quote.is_some() {
+            at += 1;
+        }
+        let word_len = bytes[at..]
+            .iter()
+            .take_while(|&&b| b.is_ascii_alphanumeric() || b == b'_')
+            .count();
+        if word_len == 0 || bytes[at].is_ascii_digit() {
+            return None;
+        }
+        let terminator = bytes[at..at + word_len].to_vec();
+        let mut end = at + word_len;
+        if let Some(quote) = quote {
+            if bytes.get(end) != Some(&quote) {
+                return None;
+            }
+            end += 1;
+        }
+        let heredoc = Heredoc {
+            terminator,
+            indented,
+        };
+        // Without a terminator this is not a heredoc (e.g., `$((x << y))`).
+        let body = bytes[end..].iter().position(|&b| b == b'\n')? + end + 1;
+        bytes[body..]
+            .split(|&b| b == b'\n')
+            .any(|line| heredoc.is_terminator(line))
+            .then_some((heredoc, end))
+    }
+}
+
+impl Heredoc {
+    /// Whether `line` (with or without its newline) ends the heredoc.
+    fn is_terminator(&self, line: &[u8]) -> bool {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = if self.indented {
+            line.trim_ascii_start()
+        } else {
+            line
+        };
+        line == self.terminator.as_slice()
+    }
+}
+
+/// Returns the end of the block comment whose body starts at `start`.
+fn block_comment_end(bytes: &[u8], start: usize, open: &str, close: &str, nested: bool) -> usize {
+    let mut depth = 1;
+    let mut i = start;
+    while let Some(close_at) = memchr::memmem::find(&bytes[i..], close.as_bytes()) {
+        if nested {
+            depth += memchr::memmem::find_iter(&bytes[i..i + close_at], open.as_bytes()).count();
+        }
+        depth -= 1;
+        i += close_at + close.len();
+        if depth == 0 {
+            return i;
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the delimiters of the string starting at `rest`, and whether it
+/// has backslash escapes.
+fn string_at<'l>(rest: &[u8], language: &'l Language) -> Option<(&'l str, &'l str, bool)> {
+    let escaped = language.strings.iter().map(|pair| (pair, true));
+    let literal = language.literal_strings.iter().map(|pair| (pair, false));
+    escaped
+        .chain(literal)
+        .find(|((open, _), _)| rest.starts_with(open.as_bytes()))
+        .map(|((open, close), escapes)| (open.as_str(), close.as_str(), escapes))
+}
+
+/// Returns the end of the string whose body starts at `start`, past its
+/// closing delimiter.
+fn string_end(bytes: &[u8], start: usize, close: &str, escapes: bool) -> usize {
+    let close = close.as_bytes();
+    let mut i = start;
+    while i < bytes.len() {
+        let next = if escapes {
+            memchr::memchr2(b'\\', close[0], &bytes[i..])
+        } else {
+            memchr::memchr(close[0], &bytes[i..])
+        };
+        let Some(next) = next else {
+            break;
+        };
+        i += next;
+        if escapes && bytes[i] == b'\\' {
+            i = char_end(bytes, i + 1);
+        } else if bytes[i..].starts_with(close) {
+            return i + close.len();
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the end of the character starting at `i`, or the end of `bytes` if
+/// `i` is past it. Escapes skip whole characters, such as the `é` of `\é`.
+fn char_end(bytes: &[u8], i: usize) -> usize {
+    match bytes.get(i) {
+        Some(_) => {
+            let continuation = bytes[i + 1..]
+                .iter()
+                .take_while(|&&b| b & 0xC0 == 0x80)
+                .count();
+            i + 1 + continuation
+        }
+        None => bytes.len(),
+    }
+}
+
+/// Removes comments from files in a known language (`--strip-comments`),
+/// detected from the file name, extension or shebang line.
+/// Files in other languages, or that are not UTF-8, are left unchanged.
+pub struct StripComments {
+    languages: LanguageDB,
+    options: StripOptions,
+}
+
+impl StripComments {
+    /// Creates a transform that removes comments as set by `options`, for the
+    /// built-in languages.
+    pub fn new(options: StripOptions) -> Self {
+        Self {
+            languages: LanguageDB::builtin(),
+            options,
+        }
+    }
+}
+
+impl Default for StripComments {
+    fn default() -> Self {
+        Self::new(StripOptions::default())
+    }
+}
+
+impl Transform for StripComments {
+    fn name(&self) -> &str {
+        "strip-comments"
+    }
+
+    fn cache_key(&self) -> String {
+        format!("strip-comments{:?}", self.options)
+    }
+
+    fn apply<'a>(&self, path: &Path, content: &'a [u8]) -> Cow<'a, [u8]> {
+        let Some(language) = self.languages.detect(path, content) else {
+            return Cow::Borrowed(content);
+        };
+        let Ok(text) = std::str::from_utf8(content) else {
+            return Cow::Borrowed(content);
+        };
+        let stripped = remove_comments_with(text, language, &self.options);
+        if stripped == text {
+            Cow::Borrowed(content)
+        } else {
+            Cow::Owned(stripped.into_bytes())
+        }
+    }
+}
+
+// --- Unit Tests for the Decommenter ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(extension: &str, content: &str) -> String {
+        let languages = LanguageDB::builtin();
+        let language = languages.find_by_extension(extension).unwrap();
+        remove_comments(content, language)
+    }
+
+    /// Verifies that languages are found by extension, ignoring case.
+    #[test]
+    fn test_find_language() {
+        let languages = LanguageDB::builtin();
+        assert_eq!(languages.find_by_extension("RS").unwrap().name, "Rust");
+        assert_eq!(
+            languages.for_path(Path::new("src/app.py")).unwrap().fence,
+            "python"
+        );
+        assert!(languages.for_path(Path::new("notes.unknown")).is_none());
+        assert_eq!(languages.find("c++").unwrap().name, "C++");
+        assert_eq!(languages.find("python").unwrap().name, "Python");
+        assert_eq!(languages.find("MTS").unwrap().name, "TypeScript");
+        assert!(LanguageDB::from_toml("[[language]]\nfence = \"x\"").is_err());
+    }
+
+    /// Verifies that extensionless files are recognized by name or shebang,
+    /// and that the shebang line survives stripping.
+    #[test]
+    fn test_detect_language() {
+        let languages = LanguageDB::builtin();
+        let name = |path: &str, content: &str| {
+            languages
+                .detect(Path::new(path), content.as_bytes())
+                .map(|language| language.name.as_str())
+        };
+        assert_eq!(name("Makefile", ""), Some("Makefile"));
+        assert_eq!(name("docker/Dockerfile.dev", ""), Some("Dockerfile"));
+        assert_eq!(name("CMakeLists.txt", ""), Some("CMake"));
+        assert_eq!(name("justfile", ""), Some("Just"));
+        assert_eq!(
+            name("bin/run", "#!/usr/bin/env -S python3.12 -u\n"),
+            Some("Python")
+        );
+        assert_eq!(name("bin/build", "#!/bin/bash\n"), Some("Shell"));
+        assert_eq!(name("bin/tool", "#!/usr/bin/pythonista\n"), None);
+        assert_eq!(name("README", "# Title\n"), None);
+
+        let script = "#!/bin/sh\n# comment\necho hi\n";
+        let shell = languages.find_by_shebang(script.as_bytes()).unwrap();
+        assert_eq!(remove_comments(script, shell), "#!/bin/sh\necho hi\n");
+    }
+
+    /// Verifies that comment-only lines are dropped, trailing comments trimmed,
+    /// and comment markers inside strings kept.
+    #[test]
+    fn test_remove_comments() {
+        let rust = "// header\nfn main() { // entry\n    let url = \"http://a/*b*/\";\n    /* one */ let x = 1;\n}\n";
+        assert_eq!(
+            strip("rs", rust),
+            "fn main() {\n    let url = \"http://a/*b*/\";\n     let x = 1;\n}\n"
+        );
+
+        let python = "# comment\nx = '#not a comment'  # trailing\n";
+        assert_eq!(strip("py", python), "x = '#not a comment'\n");
+
+        let shell = "echo 'a\\' # done\n";
+        assert_eq!(strip("sh", shell), "echo 'a\\'\n");
+    }
+
+    /// Verifies that raw strings, triple-quoted strings, template literals and
+    /// heredocs are copied verbatim, comment markers and all.
+    #[test]
+    fn test_multi_line_strings() {
+        let rust = "let s = r#\"a \"// b\"\"#; // c\nlet t = br\"/* d\"; // e\n";
+        assert_eq!(
+            strip("rs", rust),
+            "let s = r#\"a \"// b\"\"#;\nlet t = br\"/* d\";\n"
+        );
+
+        let python = "s = \"\"\"\n# kept \"quoted\"\n\"\"\"  # dropped\n";
+        assert_eq!(
+            strip("py", python),
+            "s = \"\"\"\n# kept \"quoted\"\n\"\"\"\n"
+        );
+
+        let js = "const s = `a // ${x /* y */ + `${\"}\"}`} */ b`; // c\n";
+        assert_eq!(
+            strip("js", js),
+            "const s = `a // ${x  + `${\"}\"}`} */ b`;\n"
+        );
+
+        let shell = "cat <<-'EOF' # note\n# kept\n\tEOF\necho $((1 << 2)) # dropped\n";
+        assert_eq!(
+            strip("sh", shell),
+            "cat <<-'EOF'\n# kept\n\tEOF\necho $((1 << 2))\n"
+        );
+    }
+
+    /// Verifies that documentation comments and docstrings are kept only in
+    /// `keep-doc` mode, and that a removed docstring leaves valid Python.
+    #[test]
+    fn test_keep_doc_comments() {
+        let languages = LanguageDB::builtin();
+        let keep_doc = StripOptions::from(StripMode::KeepDoc);
+        let rust = languages.find_by_extension("rs").unwrap();
+        let code = "//! Crate.\n/// Adds.\n// Note.\nfn add() {} /** Doc. */ /**/\n";
+        assert_eq!(
+            remove_comments_with(code, rust, &keep_doc),
+            "//! Crate.\n/// Adds.\nfn add() {} /** Doc. */\n"
+        );
+        assert_eq!(remove_comments(code, rust), "fn add() {}\n");
+
+        let python = languages.find_by_extension("py").unwrap();
+        let code = "\"\"\"Module.\"\"\"\nclass A:\n    \"\"\"A.\"\"\"\n    x = \"\"\"data\"\"\"\n\n    def f(self):\n        \"\"\"F.\"\"\"  # doc\n";
+        assert_eq!(
+            remove_comments(code, python),
+            "class A:\n    x = \"\"\"data\"\"\"\n\n    def f(self):\n        ...\n"
+        );
+        assert_eq!(
+            remove_comments_with(code, python, &keep_doc),
+            code.replace("  # doc", "")
+        );
+    }
+
+    /// Verifies that comments starting with a kept marker survive.
+    #[test]
+    fn test_keep_marker_comments() {
+        let languages = LanguageDB::builtin();
+        let options = StripOptions {
+            keep_markers: DEFAULT_KEEP_MARKERS.map(String::from).to_vec(),
+            ..Default::default()
+        };
+        let rust = languages.find_by_extension("rs").unwrap();
+        let code = "// TODO: retry\n// TODOS are elsewhere\nunsafe { f() } /* SAFETY: checked */\n// note: lowercase\n";
+        assert_eq!(
+            remove_comments_with(code, rust, &options),
+            "// TODO: retry\nunsafe { f() } /* SAFETY: checked */\n"
+        );
+
+        let python = languages.find_by_extension("py").unwrap();
+        assert_eq!(
+            remove_comments_with("x = 1  #FIXME(luiz) overflow\n", python, &options),
+            "x = 1  #FIXME(luiz) overflow\n"
+        );
+    }
+
+    /// Verifies that disabled preprocessor blocks are removed only on request,
+    /// and only in languages with a C preprocessor.
+    #[test]
+    fn test_strip_disabled_blocks() {
+        let languages = LanguageDB::builtin();
+        let options = StripOptions {
+            strip_disabled: true,
+            ..Default::default()
+        };
+        let code = "#if 0\nold(); // gone\n#endif\nnew();\n";
+        let c = languages.find_by_extension("c").unwrap();
+        assert_eq!(remove_comments_with(code, c, &options), "new();\n");
+        assert_eq!(remove_comments(code, c), "#if 0\nold();\n#endif\nnew();\n");
+
+        let python = languages.find_by_extension("py").unwrap();
+        assert_eq!(
+            remove_comments_with("#if 0\nx = 1\n", python, &options),
+            "x = 1\n"
+        );
+    }
+
+    /// Verifies that line comment markers inside words are not comments where
+    /// the language requires a word boundary.
+    #[test]
+    fn test_line_comment_boundaries() {
+        let shell = "echo ${path#*/} $# a#b;# done\n";
+        assert_eq!(strip("sh", shell), "echo ${path#*/} $# a#b;\n");
+
+        let yaml = "url: http://x/#top # anchor\n";
+        assert_eq!(strip("yaml", yaml), "url: http://x/#top\n");
+
+        let python = "x = 1# one\n";
+        assert_eq!(strip("py", python), "x = 1\n");
+    }
+
+    /// Verifies that with `preserve_lines` every line stays where it was.
+    #[test]
+    fn test_preserve_line_count() {
+        let languages = LanguageDB::builtin();
+        let options = StripOptions {
+            preserve_lines: true,
+            strip_disabled: true,
+            ..Default::default()
+        };
+        let c = languages.find_by_extension("c").unwrap();
+        let code = "/* a\n * b\n */\nint x; // x\n#if 0\nold();\n#endif\nint y;\n";
+        let stripped = remove_comments_with(code, c, &options);
+        assert_eq!(stripped, "\n\n\nint x;\n\n\n\nint y;\n");
+        assert_eq!(stripped.lines().count(), code.lines().count());
+
+        let python = languages.find_by_extension("py").unwrap();
+        let code = "def f():\n    \"\"\"Doc.\n\n    More.\n    \"\"\"\n";
+        assert_eq!(
+            remove_comments_with(code, python, &options),
+            "def f():\n    ...\n\n\n\n"
+        );
+    }
+
+    /// Verifies that the first comment block is kept if it is a license, or
+    /// always on request, and that later comments are still removed.
+    #[test]
+    fn test_keep_license_header() {
+        let languages = LanguageDB::builtin();
+        let license = StripOptions {
+            keep_header: Some(HeaderMode::License),
+            ..Default::default()
+        };
+        let always = StripOptions {
+            keep_header: Some(HeaderMode::Always),
+            ..Default::default()
+        };
+
+        let rust = languages.find_by_extension("rs").unwrap();
+        let code = "\n// Copyright 2024 Acme.\n// SPDX-License-Identifier: MIT\n\n// Entry.\nfn main() {}\n";
+        assert_eq!(
+            remove_comments_with(code, rust, &license),
+            "\n// Copyright 2024 Acme.\n// SPDX-License-Identifier: MIT\n\nfn main() {}\n"
+        );
+        assert_eq!(remove_comments(code, rust), "\n\nfn main() {}\n");
+
+        let code = "\n/* Helpers. */ fn f() {} // f\n";
+        assert_eq!(remove_comments_with(code, rust, &license), "\n fn f() {}\n");
+        assert_eq!(
+            remove_comments_with(code, rust, &always),
+            "\n/* Helpers. */ fn f() {}\n"
+        );
+
+        let shell = languages.find_by_extension("sh").unwrap();
+        let code = "#!/bin/sh\n# Licensed under the MIT License.\necho hi # hi\n";
+        assert_eq!(
+            remove_comments_with(code, shell, &license),
+            "#!/bin/sh\n# Licensed under the MIT License.\necho hi\n"
+        );
+    }
+
+    /// Verifies that nested block comments are removed as a whole where the
+    /// language nests them, and that non-ASCII text is preserved.
+    #[test]
+    fn test_nested_block_comments() {
+        assert_eq!(strip("rs", "/* a /* b */ c */\nlet é = 1;"), "let é = 1;");
+        assert_eq!(strip("c", "/* a /* b */ c */"), " c */");
+    }
+
+    /// Verifies that CJK and emoji text next to comments, strings and escapes
+    /// comes out whole, in code, markup and JSX.
+    #[test]
+    fn test_multi_byte_characters() {
+        let rust = "let s = \"日本\\語\"; // コメント 🎉\n/* 注释 */ let c = '\\é'; // ü\nlet e = \"🦀\"; /* 😀 */\n";
+        assert_eq!(
+            strip("rs", rust),
+            "let s = \"日本\\語\";\n let c = '\\é';\nlet e = \"🦀\";\n"
+        );
+
+        let js = "const t = `\\😀 ${n /* 数 */} 件`; // 件数\nconst s = '\\👍'; /* ✓ */\n";
+        assert_eq!(
+            strip("js", js),
+            "const t = `\\😀 ${n } 件`;\nconst s = '\\👍';\n"
+        );
+
+        let python = "s = \"\\🐍\"  # 蛇\n\"\"\"文档\"\"\"\nx = 1\n";
+        assert_eq!(strip("py", python), "s = \"\\🐍\"\nx = 1\n");
+
+        let html = "<p>你好</p><!-- 注释 🎉 --><script>f('é') // ü</script>\n";
+        assert_eq!(strip("html", html), "<p>你好</p><script>f('é')</script>\n");
+
+        let tsx = "const a = <p>日本語 // テキスト</p>; {/* 😀 */}\n";
+        assert_eq!(
+            strip("tsx", tsx),
+            "const a = <p>日本語 // テキスト</p>; {}\n"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Removal of preprocessor blocks that can never be compiled, such as
+//! `#if 0 … #endif`. Such dead code is effectively a comment.
+//!
+//! Only conditions that are constant on their own (`0`, `1`, `false`, `true`)
+//! are evaluated; every other conditional is kept as it is.
+
+use std::borrow::Cow;
+
+/// A conditional block (`#if … #endif`) enclosing the current line.
+enum Block {
+    /// A condition that is not evaluated; all its branches are kept.
+    Kept,
+    /// A constant condition. Its directives are dropped, and so are the
+    /// branches that are not taken.
+    Constant {
+        /// Whether the current branch is taken.
+        taken: bool,
+        /// Whether an earlier or the current branch is taken.
+        any_taken: bool,
+    },
+}
+
+impl Block {
+    fn is_live(&self) -> bool {
+        match self {
+            Self::Kept => true,
+            Self::Constant { taken, .. } => *taken,
+        }
+    }
+}
+
+/// Removes the branches of `#if`/`#elif`/`#else` blocks whose condition is a
+/// constant that rules them out, together with the directives of those blocks.
+/// With `preserve_lines`, removed lines are left blank instead.
+pub(super) fn remove_disabled_blocks(content: &str, preserve_lines: bool) -> Cow<'_, str> {
+    if !content.contains('#') {
+        return Cow::Borrowed(content);
+    }
+    let mut out = String::with_capacity(content.len());
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut changed = false;
+    for line in content.split_inclusive('\n') {
+        // Whether the blocks around the innermost one are live, which decides
+        // whether the directives of the innermost block are kept.
+        let outer_live = match blocks.split_last() {
+            Some((_, outer)) => outer.iter().all(Block::is_live),
+            None => true,
+        };
+        let mut line = Cow::Borrowed(line);
+        let keep = match directive(&line) {
+            Some((name @ ("if" | "ifdef" | "ifndef"), condition)) => {
+                let live = blocks.iter().all(Block::is_live);
+                let constant = (name == "if").then(|| constant(condition)).flatten();
+                match constant {
+                    Some(taken) if live => {
+                        blocks.push(Block::Constant {
+                            taken,
+                            any_taken: taken,
+                        });
+                        false
+                    }
+                    _ => {
+                        blocks.push(Block::Kept);
+                        live
+                    }
+                }
+            }
+            Some(("elif", condition)) => match blocks.last_mut() {
+                Some(Block::Constant { taken, any_taken }) if *any_taken => {
+                    *taken = false;
+                    false
+                }
+                Some(block @ Block::Constant { .. }) => match constant(condition) {
+                    Some(value) => {
+                        *block = Block::Constant {
+                            taken: value,
+                            any_taken: value,
+                        };
+                        false
+                    }
+                    None => {
+                        // The first branch that may be taken starts a
+                        // conditional of its own.
+                        *block = Block::Kept;
+                        line = Cow::Owned(line.replacen("elif", "if", 1));
+                        changed = true;
+                        outer_live
+                    }
+                },
+                _ => outer_live,
+            },
+            Some(("else", _)) => match blocks.last_mut() {
+                Some(Block::Constant { taken, any_taken }) => {
+                    *taken = !*any_taken;
+                    *any_taken = true;
+                    false
+                }
+                _ => outer_live,
+            },
+            Some(("endif", _)) => {
+                !matches!(blocks.pop(), Some(Block::Constant { .. })) && outer_live
+            }
+            _ => blocks.iter().all(Block::is_live),
+        };
+        if keep {
+            out.push_str(&line);
+        } else {
+            if preserve_lines && line.ends_with('\n') {
+                out.push('\n');
+            }
+            changed = true;
+        }
+    }
+    if changed {
+        Cow::Owned(out)
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+/// Splits a preprocessor directive into its name and argument, without any
+/// trailing comment.
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let name_len = rest.bytes().take_while(u8::is_ascii_alphabetic).count();
+    let (name, argument) = rest.split_at(name_len);
+    let argument = argument
+        .split("//")
+        .next()
+        .and_then(|argument| argument.split("/*").next())
+        .unwrap_or_default();
+    Some((name, argument.trim()))
+}
+
+/// Evaluates a condition that is a constant on its own, such as `0` or `(1)`.
+fn constant(condition: &str) -> Option<bool> {
+    let condition = condition
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim();
+    match condition {
+        "0" | "false" => Some(false),
+        "1" | "true" => Some(true),
+        _ => None,
+    }
+}
+
+// --- Unit Tests for Disabled Preprocessor Blocks ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that constant branches are resolved, other conditionals kept,
+    /// and nested blocks inside dead code dropped with it.
+    #[test]
+    fn test_remove_disabled_blocks() {
+        let code = "\
+a();
+#if 0 // old
+#ifdef DEBUG
+dead();
+#endif
+#else
+alive();
+#endif
+#if 1
+#ifdef DEBUG
+log();
+#endif
+#elif FAST
+dead();
+#else
+dead();
+#endif
+#if 0
+dead();
+#elif defined(FAST)
+fast();
+#else
+slow();
+#endif
+";
+        assert_eq!(
+            remove_disabled_blocks(code, false),
+            "\
+a();
+alive();
+#ifdef DEBUG
+log();
+#endif
+#if defined(FAST)
+fast();
+#else
+slow();
+#endif
+"
+        );
+        assert!(matches!(
+            remove_disabled_blocks("#ifdef X\nx();\n#endif\n", false),
+            Cow::Borrowed(_)
+        ));
+    }
+}
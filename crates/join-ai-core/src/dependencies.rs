@@ -0,0 +1,506 @@
+//! The dependency summary (`--dependencies`): the dependencies declared in the
+//! `Cargo.toml`, `package.json`, `pyproject.toml` and `go.mod` files of the
+//! input folder, listed compactly at the top of the output.
+//!
+//! A name and a version per dependency tell a model which ecosystem and APIs a
+//! project uses at a fraction of the tokens of the lockfiles, which are left
+//! out of the join when the summary is written.
+
+use crate::format::OutputFormat;
+use crate::options::JoinOptions;
+use crate::{walker, workspace};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The manifests that are summarized, by file name.
+pub(crate) const MANIFESTS: [&str; 4] = ["Cargo.toml", "package.json", "pyproject.toml", "go.mod"];
+
+/// The lockfiles that the summary replaces.
+pub const LOCKFILES: [&str; 8] = [
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "uv.lock",
+    "Pipfile.lock",
+    "go.sum",
+];
+
+/// What a dependency is needed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    /// Needed to build or run the package.
+    Normal,
+    /// Needed by a build script.
+    Build,
+    /// Needed for development and tests only.
+    Dev,
+    /// Expected to be provided by the user of the package.
+    Peer,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Normal => "dependencies",
+            Self::Build => "build",
+            Self::Dev => "dev",
+            Self::Peer => "peer",
+        }
+    }
+}
+
+/// A dependency declared in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    /// The version requirement, or where the dependency comes from (`path`,
+    /// `git`, `workspace`) when it has none.
+    pub version: Option<String>,
+    pub kind: Kind,
+    /// A qualifier such as `optional`, `indirect` or the extra that needs it.
+    pub note: Option<String>,
+}
+
+/// The dependencies declared in one manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    /// The path of the manifest, relative to the input folder.
+    pub path: PathBuf,
+    /// The name (and version) of the package, if the manifest declares one.
+    pub package: Option<String>,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Reads the manifests below the input folder of `options`, skipping those
+/// that cannot be parsed, those that declare no dependencies, and those that
+/// are excluded or never included.
+pub fn discover(options: &JoinOptions) -> anyhow::Result<Vec<Manifest>> {
+    let root = &options.input_folder;
+    let allowed = walker::allowed_outside_walk(options)?;
+    let found = workspace::find_manifests(root);
+    let mut paths: Vec<PathBuf> = MANIFESTS
+        .iter()
+        .flat_map(|name| {
+            found
+                .get(name)
+                .into_iter()
+                .flatten()
+                .map(move |dir| dir.join(name))
+        })
+        .filter(|path| allowed(&root.join(path)))
+        .collect();
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .filter_map(|path| {
+            let text = fs::read_to_string(root.join(&path)).ok()?;
+            let name = path.file_name()?.to_str()?;
+            let (package, dependencies) = match name {
+                "Cargo.toml" => parse_cargo(&text)?,
+                "package.json" => parse_npm(&text)?,
+                "pyproject.toml" => parse_pyproject(&text)?,
+                _ => parse_go(&text),
+            };
+            (!dependencies.is_empty()).then_some(Manifest {
+                path,
+                package,
+                dependencies,
+            })
+        })
+        .collect())
+}
+
+/// Renders `manifests` as a section of the output, with each manifest's path
+/// shown as returned by `display`.
+pub fn render(
+    manifests: &[Manifest],
+    format: OutputFormat,
+    mut display: impl FnMut(&Path) -> PathBuf,
+) -> String {
+    let mut text = String::new();
+    if format == OutputFormat::Markdown {
+        text.push_str("## Dependencies\n\n");
+    }
+    for manifest in manifests {
+        let path = display(&manifest.path);
+        let package = manifest
+            .package
+            .as_ref()
+            .map(|package| format!(" ({package})"))
+            .unwrap_or_default();
+        let _ = match format {
+            OutputFormat::Text => writeln!(text, "// DEPENDENCIES: {}{package}", path.display()),
+            OutputFormat::Markdown => writeln!(text, "- `{}`{package}", path.display()),
+        };
+        let mut kinds: Vec<Kind> = manifest.dependencies.iter().map(|d| d.kind).collect();
+        kinds.sort();
+        kinds.dedup();
+        for kind in kinds {
+            let list = manifest
+                .dependencies
+                .iter()
+                .filter(|dependency| dependency.kind == kind)
+                .map(|dependency| {
+                    let mut entry = dependency.name.clone();
+                    if let Some(version) = &dependency.version {
+                        entry.push_str(&format!(" {version}"));
+                    }
+                    if let Some(note) = &dependency.note {
+                        entry.push_str(&format!(" ({note})"));
+                    }
+                    entry
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = match format {
+                OutputFormat::Text => writeln!(text, "//   {}: {list}", kind.label()),
+                OutputFormat::Markdown => writeln!(text, "  - {}: {list}", kind.label()),
+            };
+        }
+    }
+    text.push('\n');
+    text
+}
+
+/// Returns `name version` from a table with both, or just the name.
+fn package_name(table: Option<&toml::Value>) -> Option<String> {
+    let table = table?;
+    let name = table.get("name")?.as_str()?;
+    Some(match table.get("version").and_then(|v| v.as_str()) {
+        Some(version) => format!("{name} {version}"),
+        None => name.to_string(),
+    })
+}
+
+type Parsed = (Option<String>, Vec<Dependency>);
+
+fn parse_cargo(text: &str) -> Option<Parsed> {
+    let manifest: toml::Table = text.parse().ok()?;
+    let mut tables = vec![&manifest];
+    // Platform-specific dependencies, as in `[target.'cfg(unix)'.dependencies]`.
+    if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+        tables.extend(targets.values().filter_map(|t| t.as_table()));
+    }
+    let mut dependencies = Vec::new();
+    for table in tables {
+        for (section, kind) in [
+            ("dependencies", Kind::Normal),
+            ("build-dependencies", Kind::Build),
+            ("dev-dependencies", Kind::Dev),
+        ] {
+            let Some(entries) = table.get(section).and_then(|d| d.as_table()) else {
+                continue;
+            };
+            for (name, spec) in entries {
+                let version = match spec {
+                    toml::Value::String(version) => Some(version.clone()),
+                    spec => spec
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string)
+                        .or_else(|| {
+                            ["workspace", "path", "git"]
+                                .into_iter()
+                                .find(|key| spec.get(key).is_some())
+                                .map(str::to_string)
+                        }),
+                };
+                let optional = spec.get("optional").and_then(|o| o.as_bool()) == Some(true);
+                dependencies.push(Dependency {
+                    name: name.clone(),
+                    version,
+                    kind,
+                    note: optional.then(|| "optional".to_string()),
+                });
+            }
+        }
+    }
+    Some((package_name(manifest.get("package")), dependencies))
+}
+
+fn parse_npm(text: &str) -> Option<Parsed> {
+    let manifest: serde_json::Value = serde_json::from_str(text).ok()?;
+    let package = manifest.get("name").and_then(|n| n.as_str()).map(|name| {
+        match manifest.get("version").and_then(|v| v.as_str()) {
+            Some(version) => format!("{name} {version}"),
+            None => name.to_string(),
+        }
+    });
+    let mut dependencies = Vec::new();
+    for (section, kind, note) in [
+        ("dependencies", Kind::Normal, None),
+        ("optionalDependencies", Kind::Normal, Some("optional")),
+        ("devDependencies", Kind::Dev, None),
+        ("peerDependencies", Kind::Peer, None),
+    ] {
+        let Some(entries) = manifest.get(section).and_then(|d| d.as_object()) else {
+            continue;
+        };
+        for (name, version) in entries {
+            dependencies.push(Dependency {
+                name: name.clone(),
+                version: version.as_str().map(str::to_string),
+                kind,
+                note: note.map(str::to_string),
+            });
+        }
+    }
+    Some((package, dependencies))
+}
+
+fn parse_pyproject(text: &str) -> Option<Parsed> {
+    let manifest: toml::Table = text.parse().ok()?;
+    let project = manifest.get("project");
+    let poetry = manifest.get("tool").and_then(|t| t.get("poetry"));
+    let mut dependencies = Vec::new();
+
+    // PEP 621 requirements, such as `requests[socks]>=2.31; python_version < "3.12"`.
+    let requirements = |value: Option<&toml::Value>| -> Vec<String> {
+        value
+            .and_then(|v| v.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|r| r.as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    for requirement in requirements(project.and_then(|p| p.get("dependencies"))) {
+        dependencies.push(python_requirement(&requirement, Kind::Normal, None));
+    }
+    if let Some(extras) = project
+        .and_then(|p| p.get("optional-dependencies"))
+        .and_then(|o| o.as_table())
+    {
+        for (extra, list) in extras {
+            for requirement in requirements(Some(list)) {
+                let note = format!("extra: {extra}");
+                dependencies.push(python_requirement(&requirement, Kind::Normal, Some(note)));
+            }
+        }
+    }
+    // PEP 735 dependency groups, which hold development tools.
+    if let Some(groups) = manifest.get("dependency-groups").and_then(|g| g.as_table()) {
+        for list in groups.values() {
+            for requirement in requirements(Some(list)) {
+                dependencies.push(python_requirement(&requirement, Kind::Dev, None));
+            }
+        }
+    }
+
+    // Poetry tables, where versions are strings or tables with a `version`.
+    let mut poetry_tables = vec![
+        (poetry.and_then(|p| p.get("dependencies")), Kind::Normal),
+        (poetry.and_then(|p| p.get("dev-dependencies")), Kind::Dev),
+    ];
+    if let Some(groups) = poetry
+        .and_then(|p| p.get("group"))
+        .and_then(|g| g.as_table())
+    {
+        poetry_tables.extend(groups.values().map(|g| (g.get("dependencies"), Kind::Dev)));
+    }
+    for (table, kind) in poetry_tables {
+        let Some(table) = table.and_then(|t| t.as_table()) else {
+            continue;
+        };
+        for (name, spec) in table {
+            // The Python version is a constraint, not a dependency.
+            if name == "python" {
+                continue;
+            }
+            let version = match spec {
+                toml::Value::String(version) => Some(version.clone()),
+                spec => spec
+                    .get("version")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+            };
+            let optional = spec.get("optional").and_then(|o| o.as_bool()) == Some(true);
+            dependencies.push(Dependency {
+                name: name.clone(),
+                version,
+                kind,
+                note: optional.then(|| "optional".to_string()),
+            });
+        }
+    }
+    Some((package_name(project.or(poetry)), dependencies))
+}
+
+/// Splits a Python requirement into its name and version specifier, leaving
+/// out extras and environment markers.
+fn python_requirement(requirement: &str, kind: Kind, note: Option<String>) -> Dependency {
+    let requirement = requirement.split(';').next().unwrap_or_default().trim();
+    let end = requirement
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(requirement.len());
+    let (name, rest) = requirement.split_at(end);
+    let rest = match rest.trim_start().strip_prefix('[') {
+        Some(extras) => extras.split_once(']').map_or("", |(_, rest)| rest),
+        None => rest,
+    };
+    let version = rest.trim().trim_matches(['(', ')']).replace(' ', "");
+    Dependency {
+        name: name.to_string(),
+        version: (!version.is_empty()).then_some(version),
+        kind,
+        note,
+    }
+}
+
+/// Reads the `require` directives of a `go.mod` file, in both forms.
+fn parse_go(text: &str) -> Parsed {
+    let mut package = None;
+    let mut dependencies = Vec::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        let (code, comment) = line.split_once("//").unwrap_or((line, ""));
+        let code = code.trim();
+        let entry = if in_block {
+            if code == ")" {
+                in_block = false;
+                continue;
+            }
+            code
+        } else if let Some(module) = code.strip_prefix("module ") {
+            package = Some(module.trim().trim_matches('"').to_string());
+            continue;
+        } else if let Some(rest) = code.strip_prefix("require") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+                continue;
+            }
+            rest
+        } else {
+            continue;
+        };
+        let mut fields = entry.split_whitespace();
+        if let (Some(name), Some(version)) = (fields.next(), fields.next()) {
+            dependencies.push(Dependency {
+                name: name.trim_matches('"').to_string(),
+                version: Some(version.to_string()),
+                kind: Kind::Normal,
+                note: (comment.trim() == "indirect").then(|| "indirect".to_string()),
+            });
+        }
+    }
+    (package, dependencies)
+}
+
+// --- Unit Tests for Dependency Summaries ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that the manifests of every ecosystem are found and parsed,
+    /// with their kinds and qualifiers, and rendered in both formats.
+    #[test]
+    fn test_discover_and_render() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("Cargo.toml").write_str(
+            r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+anyhow = "1.0"
+core = { path = "core" }
+notify = { version = "8.0", optional = true }
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[dev-dependencies]
+tempfile = "3"
+"#,
+        )?;
+        dir.child("web/package.json").write_str(
+            r#"{ "name": "web", "dependencies": { "react": "^18.2.0" },
+                 "devDependencies": { "vite": "^5.0.0" } }"#,
+        )?;
+        dir.child("py/pyproject.toml").write_str(
+            r#"
+[project]
+name = "tool"
+dependencies = ["requests[socks] >= 2.31; python_version < '3.12'", "click"]
+
+[project.optional-dependencies]
+docs = ["mkdocs>=1.5"]
+"#,
+        )?;
+        dir.child("go/go.mod").write_str(
+            "module example.com/svc\n\nrequire (\n\tgithub.com/gin-gonic/gin v1.9.1\n\tgolang.org/x/sys v0.15.0 // indirect\n)\n",
+        )?;
+        dir.child("empty/package.json")
+            .write_str(r#"{ "name": "empty" }"#)?;
+
+        let options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        let manifests = discover(&options)?;
+        let paths: Vec<&Path> = manifests.iter().map(|m| m.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("Cargo.toml"),
+                Path::new("go/go.mod"),
+                Path::new("py/pyproject.toml"),
+                Path::new("web/package.json"),
+            ]
+        );
+
+        let text = render(&manifests, OutputFormat::Text, Path::to_path_buf);
+        assert!(text.starts_with(
+            "// DEPENDENCIES: Cargo.toml (app 0.1.0)\n\
+             //   dependencies: anyhow 1.0, core path, notify 8.0 (optional), libc 0.2\n\
+             //   dev: tempfile 3\n"
+        ));
+        assert!(text.contains(
+            "//   dependencies: github.com/gin-gonic/gin v1.9.1, golang.org/x/sys v0.15.0 (indirect)\n"
+        ));
+        assert!(
+            text.contains(
+                "//   dependencies: requests >=2.31, click, mkdocs >=1.5 (extra: docs)\n"
+            )
+        );
+
+        let markdown = render(&manifests, OutputFormat::Markdown, Path::to_path_buf);
+        assert!(markdown.starts_with("## Dependencies\n\n- `Cargo.toml` (app 0.1.0)\n"));
+        assert!(markdown.contains(
+            "- `web/package.json` (web)\n  - dependencies: react ^18.2.0\n  - dev: vite ^5.0.0\n"
+        ));
+
+        Ok(())
+    }
+
+    /// Verifies that manifests that are excluded or never included are not
+    /// summarized.
+    #[test]
+    fn test_discover_skips_excluded_manifests() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child(crate::config::CONFIG_FILE)
+            .write_str("never_include = [\"internal/\"]\n")?;
+        for (folder, name) in [
+            ("", "app"),
+            ("internal/secretpkg/", "secret"),
+            ("web/", "web"),
+        ] {
+            dir.child(format!("{folder}package.json"))
+                .write_str(&format!(
+                    r#"{{ "name": "{name}", "dependencies": {{ "{name}-sdk": "9.9.9" }} }}"#
+                ))?;
+        }
+        let mut options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        options.exclude = Some(vec!["web/".to_string()]);
+        let manifests = discover(&options)?;
+        let packages: Vec<_> = manifests.iter().map(|m| m.package.as_deref()).collect();
+        assert_eq!(packages, [Some("app")]);
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//! Inline directives: magic comments near the top of a file that tell join-ai
+//! how to treat it, such as `// join-ai: exclude` or `# join-ai: priority=high`.
+//!
+//! File owners know best which files are noise, and directives keep that
+//! knowledge next to the code instead of in everyone's command line.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The marker that starts a directive, after the comment syntax of the file.
+const MARKER: &str = "join-ai:";
+
+/// How many lines at the top of a file are searched for directives.
+const HEAD_LINES: usize = 5;
+
+/// How many bytes at the top of a file are searched for directives.
+const HEAD_LEN: usize = 1024;
+
+/// How important a file is when the output is trimmed to a token budget.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Always kept, and placed before the other files.
+    High,
+    #[default]
+    Normal,
+    /// Placed after the other files, so it is the first to be left out.
+    Low,
+}
+
+/// The directives found in a file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Directives {
+    /// The file is left out of joins, regardless of patterns.
+    pub exclude: bool,
+    /// The priority of the file under a token budget (`priority=high|low`).
+    pub priority: Priority,
+}
+
+impl Directives {
+    /// Reads the directives of the file at `path`. Unreadable files have none.
+    pub fn read(path: &Path) -> Self {
+        let mut head = Vec::with_capacity(HEAD_LEN);
+        let read = File::open(crate::paths::for_io(path))
+            .and_then(|file| file.take(HEAD_LEN as u64).read_to_end(&mut head));
+        match read {
+            Ok(_) => Self::parse(&String::from_utf8_lossy(&head)),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Parses the directives in the first lines of `text`. A directive is a
+    /// comment whose text starts with `join-ai:`, followed by one or more
+    /// space- or comma-separated words.
+    pub fn parse(text: &str) -> Self {
+        let mut directives = Self::default();
+        for line in text.lines().take(HEAD_LINES) {
+            let Some(words) = directive_words(line) else {
+                continue;
+            };
+            for word in words.split([' ', ',']).filter(|word| !word.is_empty()) {
+                match word {
+                    "exclude" => directives.exclude = true,
+                    "priority=high" => directives.priority = Priority::High,
+                    "priority=normal" => directives.priority = Priority::Normal,
+                    "priority=low" => directives.priority = Priority::Low,
+                    _ => {}
+                }
+            }
+        }
+        directives
+    }
+}
+
+/// Returns the text after `join-ai:` if `line` is a comment holding a directive.
+fn directive_words(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let body = ["//", "#", "--", "/*", "<!--", ";", "%", "'"]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))?;
+    // Strip doc-comment markers (`///`, `//!`, `##`) and block comment ends.
+    let body = body.trim_start_matches(['/', '!', '#', '*', '-']).trim();
+    let words = body.strip_prefix(MARKER)?;
+    Some(words.trim_end_matches("*/").trim_end_matches("-->").trim())
+}
+
+// --- Unit Tests for Inline Directives ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that directives are found in the comment syntax of several
+    /// languages, and only near the top of the file.
+    #[test]
+    fn test_parse_exclude_directive() {
+        for head in [
+            "// join-ai: exclude\nfn main() {}",
+            "#!/bin/sh\n# join-ai: exclude\n",
+            "<!-- join-ai: exclude -->\n<p>",
+            "/* join-ai: exclude */",
+            "-- join-ai: exclude",
+        ] {
+            assert!(Directives::parse(head).exclude, "{head}");
+        }
+
+        assert!(!Directives::parse("let s = \"join-ai: exclude\";").exclude);
+        assert!(!Directives::parse("// join-ai: include-me").exclude);
+        let late = format!("{}// join-ai: exclude", "\n".repeat(HEAD_LINES));
+        assert!(!Directives::parse(&late).exclude);
+    }
+
+    /// Verifies that priorities are parsed, alone or next to other directives.
+    #[test]
+    fn test_parse_priority_directive() {
+        assert_eq!(
+            Directives::parse("// join-ai: priority=high").priority,
+            Priority::High
+        );
+        assert_eq!(
+            Directives::parse("# join-ai: priority=low, exclude"),
+            Directives {
+                exclude: true,
+                priority: Priority::Low
+            }
+        );
+        assert_eq!(
+            Directives::parse("// join-ai: priority=urgent").priority,
+            Priority::Normal
+        );
+    }
+}
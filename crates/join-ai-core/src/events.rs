@@ -0,0 +1,202 @@
+//! Progress events of a join: which files were included or skipped, warnings,
+//! and the final summary.
+//!
+//! Events are printed as human-readable messages by default, or as one JSON
+//! object per line on stderr with `LogFormat::Json`, for wrapper tools and
+//! editor extensions that need to parse them reliably.
+
+use serde_json::{Value, json};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// How events are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum LogFormat {
+    /// Human-readable messages.
+    #[default]
+    Text,
+    /// One JSON object per line on stderr.
+    Json,
+}
+
+/// The format of the whole process, as events are emitted from worker threads
+/// that do not see the options.
+static FORMAT: AtomicU8 = AtomicU8::new(0);
+
+/// Sets how events are printed for the rest of the process.
+pub fn set_format(format: LogFormat) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// Whether progress messages go to stderr, so that stdout carries nothing but
+/// the result of a command (e.g., the answer of `ask --raw`).
+static PROGRESS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Sends progress messages to stderr instead of stdout for the rest of the
+/// process.
+pub fn set_progress_to_stderr(to_stderr: bool) {
+    PROGRESS_TO_STDERR.store(to_stderr, Ordering::Relaxed);
+}
+
+/// Prints a progress message for humans, such as which folder is joined.
+pub fn progress(message: &str) {
+    if PROGRESS_TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{message}");
+    } else {
+        println!("{message}");
+    }
+}
+
+/// Returns how events are printed.
+pub fn format() -> LogFormat {
+    match FORMAT.load(Ordering::Relaxed) {
+        1 => LogFormat::Json,
+        _ => LogFormat::Text,
+    }
+}
+
+/// A file was written to the output.
+pub fn file_included(path: &Path, bytes: u64) {
+    if format() == LogFormat::Json {
+        emit_json(json!({
+            "event": "file_included",
+            "path": path.display().to_string(),
+            "bytes": bytes,
+        }));
+    }
+}
+
+/// A file was left out of the output. `detail` carries the error for unreadable
+/// files. In text mode only binary files are announced as they are skipped; the
+/// other reasons are summarized at the end of the run.
+pub fn file_skipped(path: &Path, reason: &str, detail: Option<&str>) {
+    match format() {
+        LogFormat::Text if reason == "binary" => {
+            progress(&format!("Skipping binary file: {}", path.display()));
+        }
+        LogFormat::Text => {}
+        LogFormat::Json => emit_json(skipped_event(path, reason, detail)),
+    }
+}
+
+fn skipped_event(path: &Path, reason: &str, detail: Option<&str>) -> Value {
+    let mut event = json!({
+        "event": "file_skipped",
+        "path": path.display().to_string(),
+        "reason": reason,
+    });
+    if let Some(detail) = detail {
+        event["detail"] = Value::from(detail);
+    }
+    event
+}
+
+/// Something went wrong, but the join goes on.
+pub fn warning(message: &str) {
+    match format() {
+        LogFormat::Text => eprintln!("{message}"),
+        LogFormat::Json => emit_json(json!({ "event": "warning", "message": message })),
+    }
+}
+
+/// The final counters of a join. Only printed in JSON mode; the command-line
+/// interface prints its own summary in text mode.
+pub fn summary(fields: Value) {
+    if format() == LogFormat::Json {
+        let mut event = json!({ "event": "summary" });
+        if let (Some(event), Value::Object(fields)) = (event.as_object_mut(), fields) {
+            event.extend(fields);
+        }
+        emit_json(event);
+    }
+}
+
+fn emit_json(event: Value) {
+    eprintln!("{event}");
+}
+
+/// Whether the paths in messages are links to their files, as set by the
+/// front end for terminals that support them.
+static HYPERLINKS: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`link`] print paths as terminal hyperlinks (OSC 8) for the rest of
+/// the process.
+pub fn set_hyperlinks(enabled: bool) {
+    HYPERLINKS.store(enabled, Ordering::Relaxed);
+}
+
+/// Renders `text`, which names the file at `path`, as a link that opens the
+/// file when hyperlinks are enabled. Paths that do not exist, such as
+/// anonymized ones, are left as they are.
+pub fn link(text: &str, path: &Path) -> String {
+    if !HYPERLINKS.load(Ordering::Relaxed) || format() == LogFormat::Json || !path.exists() {
+        return text.to_string();
+    }
+    match std::path::absolute(path) {
+        Ok(path) => hyperlink(text, &file_url(&path)),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// Wraps `text` in the OSC 8 escape sequences of a link to `url`.
+fn hyperlink(text: &str, url: &str) -> String {
+    format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
+/// Returns the `file://` URL of the absolute `path`, percent-encoding the
+/// bytes that cannot appear in a URL path.
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = String::from("file://");
+    // Windows paths start with a drive letter (`/C:/...`).
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' | b':' => {
+                url.push(byte as char)
+            }
+            byte => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    url
+}
+
+// --- Unit Tests for Events ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that skip events serialize to a single JSON line with their reason.
+    #[test]
+    fn test_skipped_event_json() {
+        let event = skipped_event(Path::new("src/secret.rs"), "unreadable", Some("denied"));
+        assert_eq!(
+            event.to_string(),
+            r#"{"detail":"denied","event":"file_skipped","path":"src/secret.rs","reason":"unreadable"}"#
+        );
+        let event = skipped_event(Path::new("logo.png"), "binary", None);
+        assert!(event.get("detail").is_none());
+    }
+
+    /// Verifies that links point to percent-encoded `file://` URLs, and that
+    /// paths are left as they are while hyperlinks are disabled.
+    #[test]
+    fn test_hyperlinks() {
+        assert_eq!(
+            file_url(Path::new("/home/me/my app/src/#1.rs")),
+            "file:///home/me/my%20app/src/%231.rs"
+        );
+        assert_eq!(
+            file_url(Path::new("C:\\src\\main.rs")),
+            "file:///C:/src/main.rs"
+        );
+        assert_eq!(
+            hyperlink("src/main.rs", "file:///app/src/main.rs"),
+            "\x1b]8;;file:///app/src/main.rs\x1b\\src/main.rs\x1b]8;;\x1b\\"
+        );
+        assert_eq!(link("Cargo.toml", Path::new("Cargo.toml")), "Cargo.toml");
+    }
+}
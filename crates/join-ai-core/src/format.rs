@@ -0,0 +1,340 @@
+//! The layout of the output: how each file is introduced and delimited, and
+//! how a large output is split into parts.
+
+use crate::decommenter::LanguageDB;
+use crate::selection::{estimate_tokens, format_size};
+use std::path::{Path, PathBuf};
+
+/// How the files are laid out in the output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum OutputFormat {
+    /// Plain text, each file introduced by `// FILE:` comment lines.
+    #[default]
+    Text,
+    /// Markdown, each file under a heading with its path, in a code block
+    /// tagged with its language.
+    Markdown,
+}
+
+/// What is said about a file before its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHeader {
+    /// The path of the file, as shown to readers.
+    pub path: PathBuf,
+    /// The last commit that changed the file (`--git-meta`).
+    pub last_commit: Option<String>,
+    /// Other paths of the same file, which are not written again.
+    pub hardlinks: Vec<PathBuf>,
+    /// The owners of the file in `CODEOWNERS` (`--show-owners`).
+    pub owners: Vec<String>,
+    /// The share of the lines of the file run by the tests (`--coverage`).
+    pub coverage: Option<String>,
+    /// Files almost the same as this one, which are not written, with their
+    /// similarity in percent (`--near-duplicates`).
+    pub near_duplicates: Vec<(PathBuf, u8)>,
+}
+
+impl FileHeader {
+    /// The near-duplicates of the file as a list, e.g. `b.rs (96%), c.rs (91%)`.
+    fn near_duplicates_list(&self) -> String {
+        self.near_duplicates
+            .iter()
+            .map(|(path, similarity)| format!("{} ({similarity}%)", path.display()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl OutputFormat {
+    /// Renders `header`. `head` is the start of the content (all of it when
+    /// it is in memory), so that a Markdown code block is delimited by a fence
+    /// that does not occur in it.
+    pub fn header(self, header: &FileHeader, head: &[u8]) -> String {
+        let path = header.path.display();
+        let hardlinks = header
+            .hardlinks
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let near_duplicates = header.near_duplicates_list();
+        match self {
+            Self::Text => {
+                let mut text = format!("// FILE: {path}\n");
+                if let Some(commit) = &header.last_commit {
+                    text.push_str(&format!("// LAST COMMIT: {commit}\n"));
+                }
+                if !hardlinks.is_empty() {
+                    text.push_str(&format!("// HARDLINKS: {hardlinks}\n"));
+                }
+                if !header.owners.is_empty() {
+                    text.push_str(&format!("// OWNERS: {}\n", header.owners.join(" ")));
+                }
+                if let Some(coverage) = &header.coverage {
+                    text.push_str(&format!("// COVERAGE: {coverage}\n"));
+                }
+                if !near_duplicates.is_empty() {
+                    text.push_str(&format!("// NEAR DUPLICATES: {near_duplicates}\n"));
+                }
+                text
+            }
+            Self::Markdown => {
+                let mut text = format!("## {path}\n\n");
+                if let Some(commit) = &header.last_commit {
+                    text.push_str(&format!("- Last commit: {commit}\n"));
+                }
+                if !hardlinks.is_empty() {
+                    text.push_str(&format!("- Hardlinks: {hardlinks}\n"));
+                }
+                if !header.owners.is_empty() {
+                    text.push_str(&format!("- Owners: {}\n", header.owners.join(" ")));
+                }
+                if let Some(coverage) = &header.coverage {
+                    text.push_str(&format!("- Coverage: {coverage}\n"));
+                }
+                if !near_duplicates.is_empty() {
+                    text.push_str(&format!("- Near duplicates: {near_duplicates}\n"));
+                }
+                if header.last_commit.is_some()
+                    || !hardlinks.is_empty()
+                    || !header.owners.is_empty()
+                    || header.coverage.is_some()
+                    || !near_duplicates.is_empty()
+                {
+                    text.push('\n');
+                }
+                let languages = LanguageDB::builtin();
+                let tag = languages
+                    .for_path(&header.path)
+                    .map_or("", |language| language.fence.as_str());
+                text.push_str(&format!("{}{tag}\n", fence(head)));
+                text
+            }
+        }
+    }
+
+    /// Renders the entry of a file in a list of files without their content
+    /// (`--list-only`): its path and size, with the same metadata as its
+    /// header.
+    pub fn entry(self, header: &FileHeader, bytes: u64) -> String {
+        let path = header.path.display();
+        let size = format!("{}, ~{} tokens", format_size(bytes), estimate_tokens(bytes));
+        let hardlinks = header
+            .hardlinks
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let near_duplicates = header.near_duplicates_list();
+        let mut text = match self {
+            Self::Text => format!("// FILE: {path} ({size})\n"),
+            Self::Markdown => format!("- `{path}` ({size})\n"),
+        };
+        let (commit, links, owners, covered, similar) = match self {
+            Self::Text => (
+                "// LAST COMMIT: ",
+                "// HARDLINKS: ",
+                "// OWNERS: ",
+                "// COVERAGE: ",
+                "// NEAR DUPLICATES: ",
+            ),
+            Self::Markdown => (
+                "  - Last commit: ",
+                "  - Hardlinks: ",
+                "  - Owners: ",
+                "  - Coverage: ",
+                "  - Near duplicates: ",
+            ),
+        };
+        if let Some(last_commit) = &header.last_commit {
+            text.push_str(&format!("{commit}{last_commit}\n"));
+        }
+        if !hardlinks.is_empty() {
+            text.push_str(&format!("{links}{hardlinks}\n"));
+        }
+        if !header.owners.is_empty() {
+            text.push_str(&format!("{owners}{}\n", header.owners.join(" ")));
+        }
+        if let Some(coverage) = &header.coverage {
+            text.push_str(&format!("{covered}{coverage}\n"));
+        }
+        if !near_duplicates.is_empty() {
+            text.push_str(&format!("{similar}{near_duplicates}\n"));
+        }
+        text
+    }
+
+    /// Renders the title of a section of the output (`--group-by-dir`), with
+    /// the number of files in it and their estimated tokens.
+    pub fn section(self, title: &str, files: usize, tokens: u64) -> String {
+        let files = match files {
+            1 => "1 file".to_string(),
+            files => format!("{files} files"),
+        };
+        match self {
+            Self::Text => format!("// SECTION: {title} ({files}, ~{tokens} tokens)\n\n"),
+            Self::Markdown => format!("# {title}\n\n{files}, ~{tokens} tokens.\n\n"),
+        }
+    }
+
+    /// Renders what follows the content of a file, given the start of the
+    /// content (as passed to [`header`](Self::header)) and its last byte.
+    pub fn footer(self, head: &[u8], last: Option<u8>) -> String {
+        match self {
+            Self::Text => "\n".to_string(),
+            Self::Markdown => {
+                let newline = if last.is_none_or(|b| b == b'\n') {
+                    ""
+                } else {
+                    "\n"
+                };
+                format!("{newline}{}\n\n", fence(head))
+            }
+        }
+    }
+}
+
+/// Returns a code fence longer than any run of backticks in `content`.
+pub(crate) fn fence(content: &[u8]) -> String {
+    let longest = content
+        .split(|&b| b != b'`')
+        .map(<[u8]>::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat((longest + 1).max(3))
+}
+
+/// Returns the path of part `number` (from 1) of an output split into parts:
+/// `out.txt` becomes `out-1.txt`, `out-2.txt`, and so on.
+pub fn part_path(output_file: &Path, number: usize) -> PathBuf {
+    let stem = output_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match output_file.extension() {
+        Some(extension) => format!("{stem}-{number}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{number}"),
+    };
+    output_file.with_file_name(name)
+}
+
+/// Whether `path` is a part of `output_file`, as named by [`part_path`].
+pub fn is_part_path(output_file: &Path, path: &Path) -> bool {
+    if path.parent() != output_file.parent() {
+        return false;
+    }
+    let (Some(stem), Some(name)) = (output_file.file_stem(), path.file_name()) else {
+        return false;
+    };
+    let name = name.to_string_lossy();
+    let rest = match output_file.extension() {
+        Some(extension) => name.strip_suffix(&format!(".{}", extension.to_string_lossy())),
+        None => Some(name.as_ref()),
+    };
+    rest.and_then(|rest| rest.strip_prefix(stem.to_string_lossy().as_ref()))
+        .and_then(|rest| rest.strip_prefix('-'))
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+// --- Unit Tests for Output Formats ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies the headers and footers of both formats, including a fence
+    /// that outgrows the backticks of the content.
+    #[test]
+    fn test_headers_and_footers() {
+        let header = FileHeader {
+            path: PathBuf::from("src/main.rs"),
+            last_commit: Some("abc123 Fix".to_string()),
+            hardlinks: vec![PathBuf::from("src/alias.rs")],
+            owners: vec!["@acme/core".to_string(), "@ana".to_string()],
+            coverage: None,
+            near_duplicates: Vec::new(),
+        };
+        assert_eq!(
+            OutputFormat::Text.header(&header, b""),
+            "// FILE: src/main.rs\n// LAST COMMIT: abc123 Fix\n// HARDLINKS: src/alias.rs\n\
+             // OWNERS: @acme/core @ana\n"
+        );
+        assert_eq!(
+            OutputFormat::Markdown.header(&header, b"fn main() {}"),
+            "## src/main.rs\n\n- Last commit: abc123 Fix\n- Hardlinks: src/alias.rs\n\
+             - Owners: @acme/core @ana\n\n```rust\n"
+        );
+        assert_eq!(
+            OutputFormat::Markdown.footer(b"fn main() {}", Some(b'}')),
+            "\n```\n\n"
+        );
+
+        let readme = FileHeader {
+            path: PathBuf::from("README"),
+            last_commit: None,
+            hardlinks: Vec::new(),
+            owners: Vec::new(),
+            coverage: None,
+            near_duplicates: Vec::new(),
+        };
+        let content = b"```sh\nls\n```\n";
+        assert_eq!(
+            OutputFormat::Markdown.header(&readme, content),
+            "## README\n\n````\n"
+        );
+        assert_eq!(
+            OutputFormat::Markdown.footer(content, Some(b'\n')),
+            "````\n\n"
+        );
+    }
+
+    /// Verifies the entries of a list of files in both formats.
+    #[test]
+    fn test_entries() {
+        let header = FileHeader {
+            path: PathBuf::from("src/main.rs"),
+            last_commit: Some("abc123 Fix".to_string()),
+            hardlinks: Vec::new(),
+            owners: Vec::new(),
+            coverage: None,
+            near_duplicates: Vec::new(),
+        };
+        assert_eq!(
+            OutputFormat::Text.entry(&header, 2048),
+            "// FILE: src/main.rs (2.0 KB, ~512 tokens)\n// LAST COMMIT: abc123 Fix\n"
+        );
+        assert_eq!(
+            OutputFormat::Markdown.entry(&header, 2048),
+            "- `src/main.rs` (2.0 KB, ~512 tokens)\n  - Last commit: abc123 Fix\n"
+        );
+    }
+
+    /// Verifies the section titles of both formats.
+    #[test]
+    fn test_sections() {
+        assert_eq!(
+            OutputFormat::Text.section("src/", 12, 3400),
+            "// SECTION: src/ (12 files, ~3400 tokens)\n\n"
+        );
+        assert_eq!(
+            OutputFormat::Markdown.section("docs/", 1, 20),
+            "# docs/\n\n1 file, ~20 tokens.\n\n"
+        );
+    }
+
+    /// Verifies the names of output parts, with and without an extension.
+    #[test]
+    fn test_part_path() {
+        assert_eq!(
+            part_path(Path::new("out/context.md"), 2),
+            Path::new("out/context-2.md")
+        );
+        assert_eq!(part_path(Path::new("context"), 1), Path::new("context-1"));
+
+        let output = Path::new("out/context.md");
+        assert!(is_part_path(output, Path::new("out/context-12.md")));
+        assert!(!is_part_path(output, Path::new("out/context-a.md")));
+        assert!(!is_part_path(output, Path::new("context-1.md")));
+        assert!(!is_part_path(output, Path::new("out/context-1.txt")));
+    }
+}
@@ -0,0 +1,825 @@
+//! Git metadata about the joined tree, read through `libgit2`. This module is
+//! only available when the crate is built with the `git` feature.
+
+use crate::options::JoinOptions;
+use crate::transform::Transform;
+use crate::walker;
+use anyhow::Context;
+use git2::{Repository, Sort, StatusOptions};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A snapshot of the repository that contains the joined folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoInfo {
+    /// The repository name, taken from the remote URL or the work tree folder.
+    pub name: String,
+    /// The checked-out branch, or `None` when HEAD is detached.
+    pub branch: Option<String>,
+    /// The full hash of the HEAD commit, or `None` in an empty repository.
+    pub commit: Option<String>,
+    /// The number of modified, staged or untracked files.
+    pub changed_files: usize,
+    /// The URL of the `origin` remote (or the first remote, if there is no origin).
+    pub remote_url: Option<String>,
+}
+
+impl RepoInfo {
+    /// Reads the metadata of the repository containing `path`.
+    ///
+    /// `ignore` is left out of the dirty-file count; it is meant for the join's
+    /// own output file, which would otherwise mark every re-run as dirty.
+    pub fn discover(path: &Path, ignore: &Path) -> Result<Self, git2::Error> {
+        let repo = Repository::discover(path)?;
+
+        let remote_url = {
+            let remotes = repo.remotes()?;
+            let names: Vec<&str> = remotes
+                .iter()
+                .filter_map(|name| name.ok().flatten())
+                .collect();
+            let name = names
+                .iter()
+                .find(|name| **name == "origin")
+                .or_else(|| names.first());
+            name.and_then(|name| repo.find_remote(name).ok())
+                .and_then(|remote| remote.url().ok().map(str::to_string))
+        };
+
+        let name = remote_url
+            .as_deref()
+            .and_then(|url| url.trim_end_matches('/').rsplit(['/', ':']).next())
+            .map(|name| name.trim_end_matches(".git").to_string())
+            .or_else(|| {
+                repo.workdir()
+                    .and_then(|dir| dir.file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_default();
+
+        let (branch, commit) = match repo.head() {
+            Ok(head) => (
+                head.is_branch()
+                    .then(|| head.shorthand().ok().map(str::to_string))
+                    .flatten(),
+                head.peel_to_commit().ok().map(|c| c.id().to_string()),
+            ),
+            // An unborn branch has no commit yet, but still has a name.
+            Err(_) => (
+                repo.find_reference("HEAD")
+                    .ok()
+                    .and_then(|head| head.symbolic_target().ok().flatten().map(str::to_string))
+                    .map(|target| target.trim_start_matches("refs/heads/").to_string()),
+                None,
+            ),
+        };
+
+        let ignored = repo
+            .workdir()
+            .and_then(|dir| {
+                let ignore = ignore.canonicalize().ok()?;
+                let dir = dir.canonicalize().ok()?;
+                ignore.strip_prefix(dir).ok().map(Path::to_path_buf)
+            })
+            .unwrap_or_default();
+        let mut status_options = StatusOptions::new();
+        status_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        let changed_files = repo
+            .statuses(Some(&mut status_options))?
+            .iter()
+            .filter(|entry| entry.path().ok().map(Path::new) != Some(ignored.as_path()))
+            .count();
+
+        Ok(Self {
+            name,
+            branch,
+            commit,
+            changed_files,
+            remote_url,
+        })
+    }
+}
+
+impl fmt::Display for RepoInfo {
+    /// Renders the metadata as a block of comment lines, in the same style as
+    /// the per-file headers.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "// REPOSITORY: {}", self.name)?;
+        writeln!(
+            f,
+            "// BRANCH: {}",
+            self.branch.as_deref().unwrap_or("(detached HEAD)")
+        )?;
+        writeln!(
+            f,
+            "// COMMIT: {}",
+            self.commit.as_deref().unwrap_or("(no commits)")
+        )?;
+        match self.changed_files {
+            0 => writeln!(f, "// STATUS: clean")?,
+            1 => writeln!(f, "// STATUS: dirty (1 changed file)")?,
+            n => writeln!(f, "// STATUS: dirty ({n} changed files)")?,
+        }
+        if let Some(url) = &self.remote_url {
+            writeln!(f, "// REMOTE: {url}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A transform that annotates each block of lines with the author and age of
+/// the commit that last touched it.
+///
+/// Blame is computed against the content being joined, so lines that were
+/// modified but not committed yet are reported as such.
+pub struct Blame {
+    repo: Mutex<Repository>,
+    workdir: PathBuf,
+}
+
+impl Blame {
+    /// Opens the repository containing `path`.
+    pub fn discover(path: &Path) -> Result<Self, git2::Error> {
+        let repo = Repository::discover(path)?;
+        let workdir = repo
+            .workdir()
+            .and_then(|dir| dir.canonicalize().ok())
+            .ok_or_else(|| git2::Error::from_str("repository has no work tree"))?;
+        Ok(Self {
+            repo: Mutex::new(repo),
+            workdir,
+        })
+    }
+
+    /// Renders the annotated content, or `None` if the file is not tracked.
+    fn annotate(&self, path: &Path, content: &[u8]) -> Option<Vec<u8>> {
+        let relative = relative_to(&self.workdir, path)?;
+        let repo = self.repo.lock().ok()?;
+        let file_blame = repo.blame_file(&relative, None).ok()?;
+        let blame = file_blame.blame_buffer(content).ok()?;
+        let now = unix_now();
+
+        let lines: Vec<&[u8]> = content.split_inclusive(|&b| b == b'\n').collect();
+        let mut annotated = Vec::with_capacity(content.len() + lines.len() * 8);
+        for hunk in blame.iter() {
+            let marker = if hunk.final_commit_id().is_zero() {
+                "// [blame] not committed yet".to_string()
+            } else {
+                let signature = hunk.final_signature();
+                let author = signature
+                    .as_ref()
+                    .and_then(|s| s.name().ok())
+                    .unwrap_or("unknown");
+                let when = signature
+                    .as_ref()
+                    .map(|s| s.when().seconds())
+                    .unwrap_or(now);
+                let id = hunk.final_commit_id().to_string();
+                format!(
+                    "// [blame] {author}, {}, {}",
+                    format_age(now - when),
+                    &id[..7]
+                )
+            };
+            annotated.extend_from_slice(marker.as_bytes());
+            annotated.push(b'\n');
+
+            let start = hunk.final_start_line().saturating_sub(1);
+            let end = (start + hunk.lines_in_hunk()).min(lines.len());
+            for line in lines.get(start..end).unwrap_or_default() {
+                annotated.extend_from_slice(line);
+            }
+        }
+        Some(annotated)
+    }
+}
+
+impl Transform for Blame {
+    fn name(&self) -> &str {
+        "blame"
+    }
+
+    fn apply<'a>(&self, path: &Path, content: &'a [u8]) -> Cow<'a, [u8]> {
+        match self.annotate(path, content) {
+            Some(annotated) => Cow::Owned(annotated),
+            None => Cow::Borrowed(content),
+        }
+    }
+}
+
+/// The commit that last changed a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// The full commit hash.
+    pub id: String,
+    /// The commit time, in seconds since the Unix epoch.
+    pub time: i64,
+    /// The first line of the commit message.
+    pub summary: String,
+}
+
+impl fmt::Display for CommitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) {}",
+            &self.id[..7.min(self.id.len())],
+            format_date(self.time),
+            self.summary
+        )
+    }
+}
+
+/// The last commit of every file tracked at HEAD.
+///
+/// The history is walked once, newest first, diffing each commit against its
+/// first parent; the walk stops as soon as every tracked file has been seen.
+pub struct FileHistory {
+    workdir: PathBuf,
+    last_commits: HashMap<PathBuf, CommitInfo>,
+}
+
+impl FileHistory {
+    /// Reads the history of the repository containing `path`.
+    pub fn discover(path: &Path) -> Result<Self, git2::Error> {
+        let repo = Repository::discover(path)?;
+        let workdir = repo
+            .workdir()
+            .and_then(|dir| dir.canonicalize().ok())
+            .ok_or_else(|| git2::Error::from_str("repository has no work tree"))?;
+
+        let mut remaining = HashSet::new();
+        let head_tree = repo.head()?.peel_to_tree()?;
+        head_tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob)
+                && let Ok(name) = entry.name()
+            {
+                remaining.insert(PathBuf::from(format!("{dir}{name}")));
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME)?;
+        revwalk.push_head()?;
+
+        let mut last_commits = HashMap::new();
+        for id in revwalk {
+            if remaining.is_empty() {
+                break;
+            }
+            let commit = repo.find_commit(id?)?;
+            let tree = commit.tree()?;
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
+            let info = CommitInfo {
+                id: commit.id().to_string(),
+                time: commit.time().seconds(),
+                summary: commit
+                    .summary()
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+            for delta in diff.deltas() {
+                if let Some(path) = delta.new_file().path()
+                    && remaining.remove(path)
+                {
+                    last_commits.insert(path.to_path_buf(), info.clone());
+                }
+            }
+        }
+
+        Ok(Self {
+            workdir,
+            last_commits,
+        })
+    }
+
+    /// Returns the last commit that changed the file at `path`, if it is tracked.
+    pub fn last_commit(&self, path: &Path) -> Option<&CommitInfo> {
+        self.last_commits.get(&relative_to(&self.workdir, path)?)
+    }
+}
+
+/// How many commits changed each file recently, for `--sort churn`.
+pub struct Churn {
+    workdir: PathBuf,
+    commits: HashMap<PathBuf, usize>,
+}
+
+impl Churn {
+    /// Counts the commits of the last `days` days in the history of the
+    /// repository containing `path`, diffing each against its first parent.
+    pub fn discover(path: &Path, days: u64) -> Result<Self, git2::Error> {
+        let repo = Repository::discover(path)?;
+        let workdir = repo
+            .workdir()
+            .and_then(|dir| dir.canonicalize().ok())
+            .ok_or_else(|| git2::Error::from_str("repository has no work tree"))?;
+        let since = unix_now().saturating_sub(days.saturating_mul(86_400) as i64);
+
+        let mut commits = HashMap::new();
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME)?;
+        if repo.head().is_ok() {
+            revwalk.push_head()?;
+        }
+        for id in revwalk {
+            let commit = repo.find_commit(id?)?;
+            if commit.time().seconds() < since {
+                break;
+            }
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            for delta in diff.deltas() {
+                if let Some(path) = delta.new_file().path() {
+                    *commits.entry(path.to_path_buf()).or_default() += 1;
+                }
+            }
+        }
+        Ok(Self { workdir, commits })
+    }
+
+    /// Returns the number of recent commits that changed the file at `path`.
+    pub fn commits(&self, path: &Path) -> usize {
+        relative_to(&self.workdir, path)
+            .and_then(|relative| self.commits.get(&relative).copied())
+            .unwrap_or(0)
+    }
+}
+
+/// Writes the files selected by `options` from the tree of `revision` (a
+/// branch, tag, commit or any other revision expression) into `dest`, without
+/// touching the work tree. Returns the number of files written.
+///
+/// If `path` is a subfolder of the repository, only that subfolder is exported.
+/// Symbolic links and submodules are skipped.
+pub fn export_revision(
+    path: &Path,
+    revision: &str,
+    options: &JoinOptions,
+    dest: &Path,
+) -> anyhow::Result<usize> {
+    let repo = Repository::discover(path)?;
+    let workdir = repo
+        .workdir()
+        .and_then(|dir| dir.canonicalize().ok())
+        .context("The repository has no work tree")?;
+    let prefix = relative_to(&workdir, path).unwrap_or_default();
+
+    let mut tree = repo
+        .revparse_single(revision)
+        .with_context(|| format!("Unknown revision '{revision}'"))?
+        .peel_to_tree()?;
+    if !prefix.as_os_str().is_empty() {
+        tree = tree
+            .get_path(&prefix)
+            .with_context(|| format!("{} does not exist at {revision}", prefix.display()))?
+            .to_object(&repo)?
+            .peel_to_tree()?;
+    }
+
+    // Collect the selected blobs first; the tree walk callback cannot fail.
+    // The `never_include` lists of the work tree apply to its history too.
+    let overrides = walker::build_overrides(options)?;
+    let never_include = crate::config::NeverInclude::discover(path)?;
+    let mut blobs = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        let is_file = entry.kind() == Some(git2::ObjectType::Blob)
+            && entry.filemode() != i32::from(git2::FileMode::Link);
+        if is_file && let Ok(name) = entry.name() {
+            let relative = PathBuf::from(format!("{dir}{name}"));
+            if walker::is_selected(&overrides, &relative)
+                && never_include
+                    .forbids(&path.join(&relative), false)
+                    .is_none()
+            {
+                blobs.push((relative, entry.id()));
+            }
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+
+    for (relative, id) in &blobs {
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, repo.find_blob(*id)?.content())?;
+    }
+    Ok(blobs.len())
+}
+
+/// The changes staged for the next commit, as a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedChanges {
+    /// The diff between HEAD (or nothing, before the first commit) and the index.
+    pub patch: String,
+    /// The number of files with staged changes.
+    pub files: usize,
+    /// The `.git` directory of the repository, where `COMMIT_EDITMSG` lives.
+    pub git_dir: PathBuf,
+}
+
+/// Reads the staged changes of the repository containing `path`.
+pub fn staged_changes(path: &Path) -> Result<StagedChanges, git2::Error> {
+    let repo = Repository::discover(path)?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head.as_ref(), None, None)?;
+    let mut patch = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |_, _, line| {
+        // Content lines carry their marker apart from the text.
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin() as u8);
+        }
+        patch.extend_from_slice(line.content());
+        true
+    })?;
+    Ok(StagedChanges {
+        patch: String::from_utf8_lossy(&patch).into_owned(),
+        files: diff.deltas().len(),
+        git_dir: repo.path().to_path_buf(),
+    })
+}
+
+/// A git submodule found inside the joined folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmoduleInfo {
+    /// The absolute path of the submodule's folder.
+    pub path: PathBuf,
+    /// The URL the submodule is cloned from, as configured in `.gitmodules`.
+    pub url: Option<String>,
+    /// The commit the superproject pins the submodule to.
+    pub commit: Option<String>,
+    /// Whether the submodule is checked out in the work tree.
+    pub initialized: bool,
+}
+
+impl fmt::Display for SubmoduleInfo {
+    /// Renders the submodule as a stub entry in the style of the file headers.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "// SUBMODULE: {}", self.path.display())?;
+        if let Some(url) = &self.url {
+            writeln!(f, "// URL: {url}")?;
+        }
+        writeln!(
+            f,
+            "// PINNED COMMIT: {}",
+            self.commit.as_deref().unwrap_or("(unknown)")
+        )?;
+        if !self.initialized {
+            writeln!(f, "// (not checked out; its files are not included)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the submodules of the repository containing `path` that live inside
+/// `path`. Returns an empty list if `path` is not in a repository.
+pub fn submodules(path: &Path) -> Vec<SubmoduleInfo> {
+    let Ok(repo) = Repository::discover(path) else {
+        return Vec::new();
+    };
+    let (Some(workdir), Ok(root)) = (repo.workdir(), path.canonicalize()) else {
+        return Vec::new();
+    };
+    let Ok(workdir) = workdir.canonicalize() else {
+        return Vec::new();
+    };
+
+    let mut found: Vec<SubmoduleInfo> = repo
+        .submodules()
+        .unwrap_or_default()
+        .iter()
+        .map(|submodule| SubmoduleInfo {
+            path: workdir.join(submodule.path()),
+            url: submodule.url().ok().flatten().map(str::to_string),
+            commit: submodule
+                .head_id()
+                .or_else(|| submodule.index_id())
+                .map(|id| id.to_string()),
+            initialized: submodule.open().is_ok(),
+        })
+        .filter(|submodule| submodule.path.starts_with(&root))
+        .map(|submodule| SubmoduleInfo {
+            // Report paths the way the walker does: relative to the input folder.
+            path: path.join(
+                submodule
+                    .path
+                    .strip_prefix(&root)
+                    .unwrap_or(&submodule.path),
+            ),
+            ..submodule
+        })
+        .collect();
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+/// Returns `path` relative to the canonical work tree `workdir`.
+fn relative_to(workdir: &Path, path: &Path) -> Option<PathBuf> {
+    let path = path.canonicalize().ok()?;
+    path.strip_prefix(workdir).ok().map(Path::to_path_buf)
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Formats a Unix timestamp as a `YYYY-MM-DD` date (UTC).
+pub fn format_date(seconds: i64) -> String {
+    // Converts days since the epoch to a civil date (Howard Hinnant's algorithm).
+    let days = seconds.div_euclid(24 * 60 * 60);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats an age in seconds the way `git log --date=relative` would, roughly.
+pub fn format_age(seconds: i64) -> String {
+    const DAY: i64 = 24 * 60 * 60;
+    let plural = |n: i64, unit: &str| {
+        if n == 1 {
+            format!("1 {unit} ago")
+        } else {
+            format!("{n} {unit}s ago")
+        }
+    };
+    match seconds.max(0) {
+        s if s < DAY => "today".to_string(),
+        s if s < 30 * DAY => plural(s / DAY, "day"),
+        s if s < 365 * DAY => plural(s / (30 * DAY), "month"),
+        s => plural(s / (365 * DAY), "year"),
+    }
+}
+
+// --- Unit Tests for Git Metadata ---
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Test helper that stages every file in the work tree and commits it.
+    pub(crate) fn commit_all(repo: &Repository, message: &str) -> git2::Oid {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Jane Doe", "jane@example.com").unwrap();
+        let parents: Vec<_> = repo
+            .head()
+            .ok()
+            .and_then(|h| h.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    /// Verifies that committed and uncommitted hunks are annotated separately.
+    #[test]
+    fn test_blame_annotates_hunks() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let repo = Repository::init(dir.path())?;
+        dir.child("lib.rs").write_str("fn a() {}\n")?;
+        commit_all(&repo, "Add a");
+
+        let path = dir.path().join("lib.rs");
+        let content = b"fn a() {}\nfn b() {}\n";
+        let blame = Blame::discover(dir.path())?;
+        let result = String::from_utf8(blame.apply(&path, content).into_owned())?;
+
+        let lines: Vec<&str> = result.lines().collect();
+        assert!(lines[0].starts_with("// [blame] Jane Doe, today, "));
+        assert_eq!(lines[1], "fn a() {}");
+        assert_eq!(lines[2], "// [blame] not committed yet");
+        assert_eq!(lines[3], "fn b() {}");
+
+        Ok(())
+    }
+
+    /// Verifies that each file is attributed to the last commit that changed it.
+    #[test]
+    fn test_file_history_last_commit() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let repo = Repository::init(dir.path())?;
+        dir.child("a.rs").write_str("a")?;
+        dir.child("b.rs").write_str("b")?;
+        let first = commit_all(&repo, "Add a and b");
+        dir.child("b.rs").write_str("b2")?;
+        let second = commit_all(&repo, "Change b\n\nLonger description.");
+
+        let history = FileHistory::discover(dir.path())?;
+        let a = history.last_commit(&dir.path().join("a.rs")).unwrap();
+        let b = history.last_commit(&dir.path().join("b.rs")).unwrap();
+
+        assert_eq!(a.id, first.to_string());
+        assert_eq!(b.id, second.to_string());
+        assert_eq!(b.summary, "Change b");
+        assert!(history.last_commit(&dir.path().join("c.rs")).is_none());
+
+        Ok(())
+    }
+
+    /// Verifies that each file is credited with the recent commits that changed it.
+    #[test]
+    fn test_churn_counts_commits() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let repo = Repository::init(dir.path())?;
+        assert_eq!(Churn::discover(dir.path(), 90)?.commits(dir.path()), 0);
+        dir.child("a.rs").write_str("a")?;
+        dir.child("b.rs").write_str("b")?;
+        commit_all(&repo, "Add a and b");
+        for content in ["b2", "b3"] {
+            dir.child("b.rs").write_str(content)?;
+            commit_all(&repo, "Change b");
+        }
+        dir.child("c.rs").write_str("c")?;
+
+        let churn = Churn::discover(dir.path(), 90)?;
+        assert_eq!(churn.commits(&dir.path().join("a.rs")), 1);
+        assert_eq!(churn.commits(&dir.path().join("b.rs")), 3);
+        assert_eq!(churn.commits(&dir.path().join("c.rs")), 0);
+
+        Ok(())
+    }
+
+    /// Verifies that a past revision is exported without touching the work tree.
+    #[test]
+    fn test_export_revision() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let repo = Repository::init(dir.path())?;
+        dir.child("src/lib.rs").write_str("v1")?;
+        dir.child("README.md").write_str("readme")?;
+        let first = commit_all(&repo, "v1");
+        dir.child("src/lib.rs").write_str("v2")?;
+        dir.child("src/new.rs").write_str("new")?;
+        commit_all(&repo, "v2");
+
+        let dest = TempDir::new()?;
+        let mut options = JoinOptions::new(dir.path().join("src"), "out.txt");
+        options.patterns = Some(vec!["*.rs".to_string()]);
+        let count = export_revision(
+            &dir.path().join("src"),
+            &first.to_string(),
+            &options,
+            dest.path(),
+        )?;
+
+        assert_eq!(count, 1);
+        assert_eq!(fs::read_to_string(dest.path().join("lib.rs"))?, "v1");
+        assert!(!dest.path().join("new.rs").exists());
+        assert_eq!(fs::read_to_string(dir.path().join("src/lib.rs"))?, "v2");
+
+        Ok(())
+    }
+
+    /// Verifies that only staged changes are diffed, before and after the
+    /// first commit.
+    #[test]
+    fn test_staged_changes() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let repo = Repository::init(dir.path())?;
+        dir.child("lib.rs").write_str("fn a() {}\n")?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new("lib.rs"))?;
+        index.write()?;
+
+        let staged = staged_changes(dir.path())?;
+        assert_eq!(staged.files, 1);
+        assert!(staged.patch.contains("+++ b/lib.rs\n"));
+        assert!(staged.patch.contains("+fn a() {}\n"));
+        assert_eq!(staged.git_dir, repo.path());
+
+        commit_all(&repo, "initial");
+        dir.child("lib.rs").write_str("fn b() {}\n")?;
+        dir.child("notes.md").write_str("unstaged")?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new("lib.rs"))?;
+        index.write()?;
+
+        let staged = staged_changes(dir.path())?;
+        assert_eq!(staged.files, 1);
+        assert!(staged.patch.contains("-fn a() {}\n+fn b() {}\n"));
+        assert!(!staged.patch.contains("notes.md"));
+
+        Ok(())
+    }
+
+    /// Verifies that submodules are listed with their URL and pinned commit.
+    #[test]
+    fn test_submodules_are_listed() -> anyhow::Result<()> {
+        let upstream_dir = TempDir::new()?;
+        let upstream = Repository::init(upstream_dir.path())?;
+        upstream_dir.child("lib.rs").write_str("lib")?;
+        let pinned = commit_all(&upstream, "upstream");
+
+        let dir = TempDir::new()?;
+        let repo = Repository::init(dir.path())?;
+        let url = upstream_dir.path().to_string_lossy().into_owned();
+        let mut submodule = repo.submodule(&url, Path::new("vendor/lib"), true)?;
+        submodule.clone(None)?;
+        submodule.add_finalize()?;
+
+        let found = submodules(dir.path());
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, dir.path().join("vendor/lib"));
+        assert_eq!(found[0].url.as_deref(), Some(url.as_str()));
+        assert_eq!(found[0].commit, Some(pinned.to_string()));
+        assert!(found[0].initialized);
+
+        Ok(())
+    }
+
+    /// Verifies the conversion of timestamps to calendar dates.
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_790_000_000), "2026-09-21");
+    }
+
+    /// Verifies that ages are rounded to the largest sensible unit.
+    #[test]
+    fn test_format_age() {
+        const DAY: i64 = 24 * 60 * 60;
+        assert_eq!(format_age(60), "today");
+        assert_eq!(format_age(DAY), "1 day ago");
+        assert_eq!(format_age(75 * DAY), "2 months ago");
+        assert_eq!(format_age(800 * DAY), "2 years ago");
+    }
+
+    /// Verifies branch, commit, remote and dirty status are reported.
+    #[test]
+    fn test_discover_repo_info() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let repo = Repository::init(dir.path())?;
+        repo.remote("origin", "https://github.com/luizvbo/join-ai.git")?;
+        dir.child("src/main.rs").write_str("fn main() {}")?;
+        let commit = commit_all(&repo, "Initial commit");
+
+        let output_file = dir.path().join("output.txt");
+        dir.child("output.txt").write_str("previous run")?;
+        let info = RepoInfo::discover(dir.path(), &output_file)?;
+
+        assert_eq!(info.name, "join-ai");
+        assert_eq!(info.commit, Some(commit.to_string()));
+        assert!(info.branch.is_some());
+        assert_eq!(info.changed_files, 0);
+
+        dir.child("src/main.rs")
+            .write_str("fn main() { changed() }")?;
+        let info = RepoInfo::discover(dir.path(), &output_file)?;
+        assert_eq!(info.changed_files, 1);
+        assert!(
+            info.to_string()
+                .contains("// STATUS: dirty (1 changed file)")
+        );
+        assert!(
+            info.to_string()
+                .contains("// REMOTE: https://github.com/luizvbo/join-ai.git")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,734 @@
+//! Import graphs for `--entry` and `--follow-imports`: the files that an entry
+//! file imports, the files those import, and so on up to a given depth.
+//!
+//! Imports are found with a regular expression per language, which is enough
+//! for the common forms: Rust `mod` and `crate::`/`super::` paths, Python
+//! imports, relative JavaScript/TypeScript specifiers, Go packages of the same
+//! module, C/C++ `#include "…"` and Java/Kotlin/Scala imports. Only imports
+//! that resolve to files of the join are followed, so third-party packages are
+//! left out.
+//!
+//! The same imports, between all the files of a join, make up the module graph
+//! (`--module-graph`): a section that states which files each file imports and
+//! is imported by, so questions about the architecture of a project can be
+//! answered without reading every file.
+
+use crate::format::OutputFormat;
+use crate::paths;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+/// How much of each file is scanned for imports.
+const MAX_SCANNED_LEN: u64 = 1024 * 1024;
+
+/// The extensions a JavaScript or TypeScript specifier may leave out.
+const SCRIPT_EXTENSIONS: [&str; 10] = [
+    "ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts", "vue", "svelte",
+];
+
+/// The extensions of the JVM languages whose imports name classes.
+const JVM_EXTENSIONS: [&str; 3] = ["java", "kt", "scala"];
+
+/// The regular expressions that find imports, compiled once.
+struct Patterns {
+    rust_mod: Regex,
+    rust_path: Regex,
+    rust_group: Regex,
+    python_from: Regex,
+    python_import: Regex,
+    script: Regex,
+    go_block: Regex,
+    go_single: Regex,
+    quoted: Regex,
+    include: Regex,
+    jvm_import: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let regex = |pattern| Regex::new(pattern).expect("built-in import pattern is valid");
+        Patterns {
+            rust_mod: regex(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)\s*;"),
+            rust_path: regex(r"\b(crate|super|self)((?:::\w+)+)"),
+            rust_group: regex(r"\b(crate|super|self)((?:::\w+)*)::\{([^}]*)\}"),
+            python_from: regex(r"(?m)^\s*from\s+(\.*)([\w.]*)\s+import\s+(\([^)]*\)|.+)"),
+            python_import: regex(r"(?m)^\s*import\s+(.+)"),
+            script: regex(
+                r#"(?:\bfrom\s*|\bimport\s*\(?\s*|\brequire\s*\(\s*)['"](\.{1,2}/[^'"]*)['"]"#,
+            ),
+            go_block: regex(r"(?s)\bimport\s*\(([^)]*)\)"),
+            go_single: regex(r#"\bimport\s+(?:[\w.]+\s+)?"([^"]+)""#),
+            quoted: regex(r#""([^"]+)""#),
+            include: regex(r#"(?m)^\s*#\s*include\s*"([^"]+)""#),
+            jvm_import: regex(r"(?m)^\s*import\s+(?:static\s+)?([\w.]+(?:\.\*)?)"),
+        }
+    })
+}
+
+/// Returns `entry` followed by the files it reaches through at most `depth`
+/// levels of imports, among `files`. Nearer files come first, and the files of
+/// each level are in path order.
+pub fn closure(entry: &Path, files: &[PathBuf], depth: usize) -> Vec<PathBuf> {
+    let known: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+    let mut seen = HashSet::from([entry.to_path_buf()]);
+    let mut level = vec![entry.to_path_buf()];
+    let mut reached = level.clone();
+    for _ in 0..depth {
+        let mut next: Vec<PathBuf> = level
+            .iter()
+            .flat_map(|path| imports(path, &known))
+            .filter(|path| seen.insert(path.clone()))
+            .collect();
+        if next.is_empty() {
+            break;
+        }
+        next.sort();
+        reached.extend(next.iter().cloned());
+        level = next;
+    }
+    reached
+}
+
+/// A file of the module graph, with the files it imports and is imported by,
+/// in path order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphNode {
+    pub path: PathBuf,
+    pub imports: Vec<PathBuf>,
+    pub imported_by: Vec<PathBuf>,
+}
+
+/// Returns the module graph of `files`: each file that imports or is imported
+/// by another of them, in the order of `files`.
+pub fn graph(files: &[PathBuf]) -> Vec<GraphNode> {
+    let known: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+    let mut nodes: Vec<GraphNode> = files
+        .iter()
+        .map(|path| {
+            let mut imports = imports(path, &known);
+            imports.sort();
+            GraphNode {
+                path: path.clone(),
+                imports,
+                imported_by: Vec::new(),
+            }
+        })
+        .collect();
+    for i in 0..nodes.len() {
+        for imported in nodes[i].imports.clone() {
+            let importer = nodes[i].path.clone();
+            if let Some(node) = nodes.iter_mut().find(|node| node.path == imported) {
+                node.imported_by.push(importer);
+            }
+        }
+    }
+    for node in &mut nodes {
+        node.imported_by.sort();
+    }
+    nodes.retain(|node| !node.imports.is_empty() || !node.imported_by.is_empty());
+    nodes
+}
+
+/// Renders the module graph as a section of the output, with each path shown
+/// as returned by `display`.
+pub fn render_graph(
+    nodes: &[GraphNode],
+    format: OutputFormat,
+    mut display: impl FnMut(&Path) -> PathBuf,
+) -> String {
+    let mut text = String::new();
+    match format {
+        OutputFormat::Text => text.push_str("// MODULE GRAPH\n"),
+        OutputFormat::Markdown => text.push_str("## Module Graph\n\n"),
+    }
+    for node in nodes {
+        let mut list = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|path| match format {
+                    OutputFormat::Text => display(path).display().to_string(),
+                    OutputFormat::Markdown => format!("`{}`", display(path).display()),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let imports = list(&node.imports);
+        let imported_by = list(&node.imported_by);
+        let path = display(&node.path);
+        let _ = match format {
+            OutputFormat::Text => writeln!(text, "// {}", path.display()),
+            OutputFormat::Markdown => writeln!(text, "- `{}`", path.display()),
+        };
+        for (label, list) in [("imports", imports), ("imported by", imported_by)] {
+            if list.is_empty() {
+                continue;
+            }
+            let _ = match format {
+                OutputFormat::Text => writeln!(text, "//   {label}: {list}"),
+                OutputFormat::Markdown => writeln!(text, "  - {label}: {list}"),
+            };
+        }
+    }
+    text.push('\n');
+    text
+}
+
+/// Returns the files among `known` that `path` imports.
+pub fn imports(path: &Path, known: &HashSet<&Path>) -> Vec<PathBuf> {
+    let mut content = Vec::new();
+    let _ = std::fs::File::open(paths::for_io(path))
+        .and_then(|file| file.take(MAX_SCANNED_LEN).read_to_end(&mut content));
+    let content = String::from_utf8_lossy(&content);
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let candidates = match extension.as_str() {
+        "rs" => rust_imports(path, &content, known),
+        "py" | "pyi" => python_imports(path, &content, known),
+        "go" => go_imports(path, &content, known),
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" | "m" | "mm" => {
+            include_imports(path, &content, known)
+        }
+        e if SCRIPT_EXTENSIONS.contains(&e) => script_imports(path, &content, known),
+        e if JVM_EXTENSIONS.contains(&e) => jvm_imports(&content, known),
+        _ => Vec::new(),
+    };
+    let mut found = Vec::new();
+    for candidate in candidates {
+        if candidate != path && !found.contains(&candidate) {
+            found.push(candidate);
+        }
+    }
+    found
+}
+
+/// Returns the first of `candidates` that is among `known`.
+fn first_known(
+    candidates: impl IntoIterator<Item = PathBuf>,
+    known: &HashSet<&Path>,
+) -> Option<PathBuf> {
+    candidates
+        .into_iter()
+        .map(|candidate| normalize(&candidate))
+        .find(|candidate| known.contains(candidate.as_path()))
+}
+
+/// Resolves `.` and `..` in `path` without touching the file system. A
+/// leading `.` is kept, as in the paths found by the walker.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for (i, component) in path.components().enumerate() {
+        match component {
+            Component::CurDir if i > 0 => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Rust: `mod name;` declarations, and `crate::`, `super::` and `self::` paths
+/// (also in `use` groups), resolved to the file of the longest module prefix.
+fn rust_imports(path: &Path, content: &str, known: &HashSet<&Path>) -> Vec<PathBuf> {
+    let patterns = patterns();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let is_root = matches!(
+        path.file_name().and_then(|name| name.to_str()),
+        Some("mod.rs" | "lib.rs" | "main.rs")
+    );
+    // Where the children of this module live, and where its siblings do.
+    let children = if is_root {
+        dir.to_path_buf()
+    } else {
+        dir.join(path.file_stem().unwrap_or_default())
+    };
+    let siblings = if is_root {
+        dir.parent().unwrap_or(dir).to_path_buf()
+    } else {
+        dir.to_path_buf()
+    };
+    let crate_root = dir
+        .ancestors()
+        .find(|ancestor| {
+            known.contains(ancestor.join("lib.rs").as_path())
+                || known.contains(ancestor.join("main.rs").as_path())
+        })
+        .unwrap_or(dir);
+
+    let mut found = Vec::new();
+    for captures in patterns.rust_mod.captures_iter(content) {
+        let name = &captures[1];
+        found.extend(first_known(
+            [
+                children.join(format!("{name}.rs")),
+                children.join(name).join("mod.rs"),
+            ],
+            known,
+        ));
+    }
+    let mut module_paths: Vec<(String, String)> = patterns
+        .rust_path
+        .captures_iter(content)
+        .map(|captures| (captures[1].to_string(), captures[2].to_string()))
+        .collect();
+    for captures in patterns.rust_group.captures_iter(content) {
+        for item in captures[3].split(',') {
+            let item = item.split_whitespace().next().unwrap_or_default();
+            if !item.is_empty() && item != "self" {
+                module_paths.push((captures[1].to_string(), format!("{}::{item}", &captures[2])));
+            }
+        }
+    }
+    for (anchor, rest) in module_paths {
+        let mut base = match anchor.as_str() {
+            "crate" => crate_root.to_path_buf(),
+            "super" => siblings.clone(),
+            _ => children.clone(),
+        };
+        let mut segments: Vec<&str> = rest.split("::").filter(|s| !s.is_empty()).collect();
+        while segments.first() == Some(&"super") {
+            segments.remove(0);
+            base = base.parent().unwrap_or(&base).to_path_buf();
+        }
+        for len in (1..=segments.len()).rev() {
+            let module = base.join(segments[..len].join("/"));
+            if let Some(file) =
+                first_known([module.with_extension("rs"), module.join("mod.rs")], known)
+            {
+                found.push(file);
+                break;
+            }
+        }
+    }
+    found
+}
+
+/// Python: `import a.b` and `from a.b import c`, absolute (from any folder
+/// above the file) or relative (`from .m import x`).
+fn python_imports(path: &Path, content: &str, known: &HashSet<&Path>) -> Vec<PathBuf> {
+    let patterns = patterns();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    // A module is a file or a package, looked up from each base in turn.
+    let resolve = |bases: &[PathBuf], module: &str| {
+        let relative = module.replace('.', "/");
+        first_known(
+            bases.iter().flat_map(|base| {
+                let module = base.join(&relative);
+                [module.with_extension("py"), module.join("__init__.py")]
+            }),
+            known,
+        )
+    };
+    let ancestors: Vec<PathBuf> = dir.ancestors().map(Path::to_path_buf).collect();
+
+    let mut found = Vec::new();
+    for captures in patterns.python_from.captures_iter(content) {
+        let dots = captures[1].len();
+        let module = &captures[2];
+        let bases = if dots == 0 {
+            ancestors.clone()
+        } else {
+            ancestors.get(dots - 1).cloned().into_iter().collect()
+        };
+        if !module.is_empty() {
+            found.extend(resolve(&bases, module));
+        }
+        // The imported names may be submodules themselves.
+        let names = captures[3].trim_matches(|c| c == '(' || c == ')');
+        for name in names.split(',') {
+            let name = name.split_whitespace().next().unwrap_or_default();
+            if name.is_empty() || name == "*" {
+                continue;
+            }
+            let submodule = if module.is_empty() {
+                name.to_string()
+            } else {
+                format!("{module}.{name}")
+            };
+            found.extend(resolve(&bases, &submodule));
+        }
+    }
+    for captures in patterns.python_import.captures_iter(content) {
+        for module in captures[1].split(',') {
+            if let Some(module) = module.split_whitespace().next() {
+                found.extend(resolve(&ancestors, module));
+            }
+        }
+    }
+    found
+}
+
+/// JavaScript and TypeScript: relative specifiers of `import`, `export … from`,
+/// `require()` and `import()`, with or without an extension or `/index`.
+fn script_imports(path: &Path, content: &str, known: &HashSet<&Path>) -> Vec<PathBuf> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    patterns()
+        .script
+        .captures_iter(content)
+        .filter_map(|captures| {
+            let target = dir.join(&captures[1]);
+            let mut candidates = vec![target.clone()];
+            // TypeScript imports `./x.js` from the source file `./x.ts`.
+            let stem = match target.extension().and_then(|e| e.to_str()) {
+                Some("js" | "jsx" | "mjs" | "cjs") => target.with_extension(""),
+                _ => target.clone(),
+            };
+            for extension in SCRIPT_EXTENSIONS {
+                let mut file = stem.clone().into_os_string();
+                file.push(format!(".{extension}"));
+                candidates.push(file.into());
+            }
+            for extension in SCRIPT_EXTENSIONS {
+                candidates.push(target.join(format!("index.{extension}")));
+            }
+            first_known(candidates, known)
+        })
+        .collect()
+}
+
+/// Go: the packages of the same module (named in the nearest `go.mod`), and
+/// the other files of the file's own package. Tests are left out.
+fn go_imports(path: &Path, content: &str, known: &HashSet<&Path>) -> Vec<PathBuf> {
+    let patterns = patterns();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let package_files = |package: &Path| {
+        let mut files: Vec<PathBuf> = known
+            .iter()
+            .filter(|file| {
+                file.parent() == Some(package)
+                    && file.extension().is_some_and(|e| e == "go")
+                    && !file.to_string_lossy().ends_with("_test.go")
+            })
+            .map(|file| file.to_path_buf())
+            .collect();
+        files.sort();
+        files
+    };
+
+    let mut found = package_files(dir);
+    let module = dir.ancestors().find_map(|ancestor| {
+        let manifest = std::fs::read_to_string(paths::for_io(&ancestor.join("go.mod"))).ok()?;
+        let name = manifest
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("module "))?
+            .trim()
+            .to_string();
+        Some((ancestor.to_path_buf(), name))
+    });
+    let Some((module_dir, module_name)) = module else {
+        return found;
+    };
+    let mut specs: Vec<String> = patterns
+        .go_single
+        .captures_iter(content)
+        .map(|captures| captures[1].to_string())
+        .collect();
+    for block in patterns.go_block.captures_iter(content) {
+        specs.extend(
+            patterns
+                .quoted
+                .captures_iter(&block[1])
+                .map(|captures| captures[1].to_string()),
+        );
+    }
+    for spec in specs {
+        let relative = if spec == module_name {
+            Some("")
+        } else {
+            spec.strip_prefix(&format!("{module_name}/"))
+        };
+        if let Some(relative) = relative {
+            found.extend(package_files(&normalize(&module_dir.join(relative))));
+        }
+    }
+    found
+}
+
+/// C, C++ and Objective-C: `#include "…"`, relative to the file, or to a
+/// folder above it (or its `include` folder).
+fn include_imports(path: &Path, content: &str, known: &HashSet<&Path>) -> Vec<PathBuf> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    patterns()
+        .include
+        .captures_iter(content)
+        .filter_map(|captures| {
+            let header = &captures[1];
+            let candidates = dir.ancestors().flat_map(|ancestor| {
+                [ancestor.join(header), ancestor.join("include").join(header)]
+            });
+            first_known(candidates, known)
+        })
+        .collect()
+}
+
+/// Java, Kotlin and Scala: `import a.b.C` names the file `a/b/C.java` (or
+/// `.kt`, `.scala`) under any source root, and `import a.b.*` every file of
+/// the package.
+fn jvm_imports(content: &str, known: &HashSet<&Path>) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for captures in patterns().jvm_import.captures_iter(content) {
+        let import = &captures[1];
+        if let Some(package) = import.strip_suffix(".*") {
+            let suffix = Path::new(&package.replace('.', "/")).to_path_buf();
+            let mut files: Vec<PathBuf> = known
+                .iter()
+                .filter(|file| {
+                    file.parent()
+                        .is_some_and(|parent| parent.ends_with(&suffix))
+                        && is_jvm_source(file)
+                })
+                .map(|file| file.to_path_buf())
+                .collect();
+            files.sort();
+            found.extend(files);
+            continue;
+        }
+        // A static import names a member after the class. A class is in a
+        // package, unless the import has a single segment.
+        let segments: Vec<&str> = import.split('.').collect();
+        for len in (segments.len().min(2)..=segments.len()).rev() {
+            let class = segments[..len].join("/");
+            let mut files: Vec<&Path> = known
+                .iter()
+                .copied()
+                .filter(|file| is_jvm_source(file) && file.with_extension("").ends_with(&class))
+                .collect();
+            if !files.is_empty() {
+                files.sort();
+                found.push(files[0].to_path_buf());
+                break;
+            }
+        }
+    }
+    found
+}
+
+/// Whether `path` is a Java, Kotlin or Scala source file.
+fn is_jvm_source(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| JVM_EXTENSIONS.iter().any(|jvm| e == *jvm))
+}
+
+// --- Unit Tests for Import Graphs ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Writes `files` under `dir` and returns their paths, in path order.
+    fn write_files(dir: &TempDir, files: &[(&str, &str)]) -> anyhow::Result<Vec<PathBuf>> {
+        for (name, content) in files {
+            dir.child(name).write_str(content)?;
+        }
+        let mut paths: Vec<PathBuf> = files
+            .iter()
+            .map(|(name, _)| dir.path().join(name))
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Returns the paths of `closure`, relative to `dir`.
+    fn relative(dir: &TempDir, paths: Vec<PathBuf>) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| {
+                let relative = path.strip_prefix(dir.path()).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect()
+    }
+
+    /// Verifies that Rust modules are found through `mod`, `use crate::`,
+    /// `super::` and grouped imports, level by level up to the depth.
+    #[test]
+    fn test_rust_closure() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let files = write_files(
+            &dir,
+            &[
+                ("src/main.rs", "mod api;\nmod db;\nfn main() {}"),
+                ("src/api/mod.rs", "pub mod handler;"),
+                (
+                    "src/api/handler.rs",
+                    "use crate::{db::Pool, util};\nuse super::auth::check;\nuse std::io;",
+                ),
+                ("src/api/auth.rs", "use crate::util::hash;"),
+                ("src/db.rs", "pub struct Pool;"),
+                ("src/util.rs", "pub fn hash() {}"),
+                ("src/unused.rs", ""),
+            ],
+        )?;
+        let entry = dir.path().join("src/api/handler.rs");
+        assert_eq!(
+            relative(&dir, closure(&entry, &files, 1)),
+            [
+                "src/api/handler.rs",
+                "src/api/auth.rs",
+                "src/db.rs",
+                "src/util.rs"
+            ]
+        );
+        assert_eq!(
+            relative(&dir, closure(&entry, &files, 0)),
+            ["src/api/handler.rs"]
+        );
+
+        let entry = dir.path().join("src/main.rs");
+        assert_eq!(
+            relative(&dir, closure(&entry, &files, 2)),
+            [
+                "src/main.rs",
+                "src/api/mod.rs",
+                "src/db.rs",
+                "src/api/handler.rs"
+            ]
+        );
+        Ok(())
+    }
+
+    /// Verifies Python absolute, relative and submodule imports.
+    #[test]
+    fn test_python_imports() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let files = write_files(
+            &dir,
+            &[
+                (
+                    "app/main.py",
+                    "import os\nfrom app.models import User\nfrom . import views\nfrom .util import (slug,\n  title)\n",
+                ),
+                ("app/__init__.py", ""),
+                ("app/models/__init__.py", ""),
+                ("app/views.py", ""),
+                ("app/util.py", ""),
+                ("app/other.py", ""),
+            ],
+        )?;
+        let known: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+        let found = imports(&dir.path().join("app/main.py"), &known);
+        assert_eq!(
+            relative(&dir, found),
+            ["app/models/__init__.py", "app/views.py", "app/util.py"]
+        );
+        Ok(())
+    }
+
+    /// Verifies relative JavaScript and TypeScript specifiers, with implied
+    /// extensions and index files, and that packages are ignored.
+    #[test]
+    fn test_script_imports() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let files = write_files(
+            &dir,
+            &[
+                (
+                    "src/app.ts",
+                    "import React from 'react';\nimport { api } from './api';\nexport * from \"../lib/util.js\";\nconst c = require('./components');",
+                ),
+                ("src/api.ts", ""),
+                ("src/components/index.tsx", ""),
+                ("lib/util.ts", ""),
+            ],
+        )?;
+        let known: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+        let found = imports(&dir.path().join("src/app.ts"), &known);
+        assert_eq!(
+            relative(&dir, found),
+            ["src/api.ts", "lib/util.ts", "src/components/index.tsx"]
+        );
+        Ok(())
+    }
+
+    /// Verifies that the module graph lists both directions of each import,
+    /// leaves out files with none, and renders paths as given.
+    #[test]
+    fn test_module_graph() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let files = write_files(
+            &dir,
+            &[
+                ("src/main.rs", "mod auth;\nmod db;\nfn main() {}"),
+                ("src/auth.rs", "use crate::db::Pool;"),
+                ("src/db.rs", ""),
+                ("build.rs", "fn main() {}"),
+            ],
+        )?;
+        let nodes = graph(&files);
+        let paths: Vec<_> = nodes.iter().map(|node| node.path.clone()).collect();
+        assert_eq!(
+            relative(&dir, paths),
+            ["src/auth.rs", "src/db.rs", "src/main.rs"]
+        );
+        assert_eq!(
+            relative(&dir, nodes[1].imported_by.clone()),
+            ["src/auth.rs", "src/main.rs"]
+        );
+
+        let display = |path: &Path| path.strip_prefix(dir.path()).unwrap().to_path_buf();
+        assert_eq!(
+            render_graph(&nodes, OutputFormat::Text, display),
+            "// MODULE GRAPH\n\
+             // src/auth.rs\n\
+             //   imports: src/db.rs\n\
+             //   imported by: src/main.rs\n\
+             // src/db.rs\n\
+             //   imported by: src/auth.rs, src/main.rs\n\
+             // src/main.rs\n\
+             //   imports: src/auth.rs, src/db.rs\n\n"
+        );
+        assert!(
+            render_graph(&nodes, OutputFormat::Markdown, display)
+                .starts_with("## Module Graph\n\n- `src/auth.rs`\n  - imports: `src/db.rs`\n")
+        );
+        Ok(())
+    }
+
+    /// Verifies Go packages of the same module, C includes and JVM imports.
+    #[test]
+    fn test_go_c_and_jvm_imports() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let files = write_files(
+            &dir,
+            &[
+                ("go.mod", "module example.com/shop\n\ngo 1.22\n"),
+                (
+                    "cmd/main.go",
+                    "package main\n\nimport (\n\t\"fmt\"\n\t\"example.com/shop/store\"\n)\n",
+                ),
+                ("cmd/flags.go", "package main"),
+                ("store/store.go", "package store"),
+                ("store/store_test.go", "package store"),
+                ("src/main.c", "#include <stdio.h>\n#include \"util.h\"\n"),
+                ("include/util.h", ""),
+                (
+                    "src/main/java/com/shop/App.java",
+                    "import com.shop.model.Item;\nimport static com.shop.util.Strings.slug;\nimport java.util.List;",
+                ),
+                ("src/main/java/com/shop/model/Item.java", ""),
+                ("src/main/java/com/shop/util/Strings.java", ""),
+            ],
+        )?;
+        let known: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+        let found = |name: &str| relative(&dir, imports(&dir.path().join(name), &known));
+        assert_eq!(found("cmd/main.go"), ["cmd/flags.go", "store/store.go"]);
+        assert_eq!(found("src/main.c"), ["include/util.h"]);
+        assert_eq!(
+            found("src/main/java/com/shop/App.java"),
+            [
+                "src/main/java/com/shop/model/Item.java",
+                "src/main/java/com/shop/util/Strings.java"
+            ]
+        );
+        Ok(())
+    }
+}
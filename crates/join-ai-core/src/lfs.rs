@@ -0,0 +1,183 @@
+//! Git LFS pointers: the small text files that Git LFS leaves in a checkout in
+//! place of files whose objects were not downloaded (e.g., a clone made with
+//! `GIT_LFS_SKIP_SMUDGE=1`, or without Git LFS installed). A pointer says
+//! nothing about the file it stands for, so pointers are left out of joins,
+//! or, with `--lfs-fetch`, replaced by the object they point to.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Pointer files are smaller than this, as required by the specification.
+pub const MAX_POINTER_LEN: u64 = 1024;
+
+/// The first line of every pointer file.
+const VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// A Git LFS pointer, which names an object by its SHA-256 hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pointer {
+    /// The SHA-256 hash of the object, in lowercase hexadecimal.
+    pub oid: String,
+    /// The size of the object, in bytes.
+    pub size: u64,
+    /// The pointer file itself, which `git lfs smudge` reads.
+    text: String,
+}
+
+impl Pointer {
+    /// Reads the file at `path`, of `size` bytes, as a pointer. Returns `None`
+    /// for any other file, or a file that cannot be read.
+    pub fn read(path: &Path, size: u64) -> Option<Self> {
+        if size >= MAX_POINTER_LEN {
+            return None;
+        }
+        Self::parse(&fs::read(crate::paths::for_io(path)).ok()?)
+    }
+
+    /// Parses a pointer file: `key value` lines, the version first, with at
+    /// least the `oid` and the `size` of the object.
+    pub fn parse(text: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(text).ok()?;
+        let mut lines = text.lines();
+        if lines.next()? != VERSION {
+            return None;
+        }
+        let (mut oid, mut size) = (None, None);
+        for line in lines {
+            match line.split_once(' ')? {
+                ("oid", value) => oid = value.strip_prefix("sha256:"),
+                ("size", value) => size = value.parse().ok(),
+                _ => {}
+            }
+        }
+        let oid =
+            oid.filter(|oid| oid.len() == 64 && oid.bytes().all(|b| b.is_ascii_hexdigit()))?;
+        Some(Self {
+            oid: oid.to_ascii_lowercase(),
+            size: size?,
+            text: text.to_string(),
+        })
+    }
+
+    /// Returns the content of the object, for the pointer file at `path`.
+    ///
+    /// The object is read from the local object store of the repository when
+    /// it was already downloaded (e.g., by `git lfs fetch`), and downloaded
+    /// with `git lfs smudge` otherwise, which needs Git LFS to be installed.
+    pub fn fetch(&self, path: &Path) -> io::Result<Vec<u8>> {
+        if let Some(object) = self.local_object(path)
+            && let Ok(contents) = fs::read(object)
+            && contents.len() as u64 == self.size
+        {
+            return Ok(contents);
+        }
+        let folder = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty());
+        let mut child = Command::new("git")
+            .args(["lfs", "smudge", "--"])
+            .arg(path.file_name().unwrap_or(path.as_os_str()))
+            .current_dir(crate::paths::for_io(folder.unwrap_or(Path::new("."))))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(self.text.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "git lfs smudge failed: {}",
+                message.lines().next().unwrap_or_default().trim()
+            )));
+        }
+        // Without Git LFS, git fails; a smudge that is not set up echoes the pointer.
+        if output.stdout.len() as u64 != self.size {
+            return Err(io::Error::other(
+                "the Git LFS object could not be downloaded (is Git LFS installed?)",
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Where the object is kept in the repository of the pointer file at
+    /// `path`: `.git/lfs/objects/ab/cd/abcd...`.
+    fn local_object(&self, path: &Path) -> Option<PathBuf> {
+        let absolute = std::path::absolute(path).ok()?;
+        let git_dir = absolute
+            .ancestors()
+            .skip(1)
+            .map(|folder| folder.join(".git"))
+            .find(|git| git.exists())?;
+        // In a linked worktree, `.git` is a file naming the git folder.
+        let git_dir = match git_dir.is_file() {
+            true => {
+                let text = fs::read_to_string(&git_dir).ok()?;
+                let linked = PathBuf::from(text.strip_prefix("gitdir:")?.trim());
+                let common = fs::read_to_string(linked.join("commondir")).ok();
+                match common {
+                    Some(common) => linked.join(common.trim()),
+                    None => linked,
+                }
+            }
+            false => git_dir,
+        };
+        Some(
+            git_dir
+                .join("lfs/objects")
+                .join(&self.oid[..2])
+                .join(&self.oid[2..4])
+                .join(&self.oid),
+        )
+    }
+}
+
+// --- Unit Tests for Git LFS Pointers ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    const OID: &str = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    /// Verifies that pointers are recognized and read, and that other files,
+    /// including ones that merely mention Git LFS, are not pointers.
+    #[test]
+    fn test_parse_pointer() {
+        let text = format!("{VERSION}\noid sha256:{OID}\nsize 12345\n");
+        let pointer = Pointer::parse(text.as_bytes()).expect("a valid pointer");
+        assert_eq!(pointer.oid, OID);
+        assert_eq!(pointer.size, 12345);
+
+        assert_eq!(Pointer::parse(b"fn main() {}\n"), None);
+        assert_eq!(
+            Pointer::parse(format!("{VERSION}\nsize 1\n").as_bytes()),
+            None
+        );
+        assert_eq!(
+            Pointer::parse(format!("# {VERSION}\noid sha256:{OID}\nsize 1\n").as_bytes()),
+            None
+        );
+    }
+
+    /// Verifies that an object already in the local store is read from it,
+    /// without running Git LFS.
+    #[test]
+    fn test_fetch_local_object() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child(format!(".git/lfs/objects/4d/7a/{OID}"))
+            .write_str("id,name\n1,a\n")?;
+        let text = format!("{VERSION}\noid sha256:{OID}\nsize 12\n");
+        dir.child("data/rows.csv").write_str(&text)?;
+        let path = dir.path().join("data/rows.csv");
+
+        let pointer = Pointer::read(&path, text.len() as u64).expect("a valid pointer");
+        assert_eq!(pointer.fetch(&path)?, b"id,name\n1,a\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! The core logic of `join-ai`: finding files, filtering them, transforming
+//! their content and concatenating them into a single output file.
+//!
+//! This crate has no dependency on the command-line interface, so it can be
+//! embedded in other tools without pulling in `clap` and friends.
+
+// Public modules that make up the library's functionality.
+pub mod agent_rules;
+pub mod anonymize;
+pub mod cache;
+pub mod codeowners;
+pub mod config;
+pub mod coverage;
+pub mod decommenter;
+pub mod dependencies;
+pub mod directives;
+pub mod events;
+pub mod format;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod imports;
+pub mod lfs;
+pub mod licenses;
+pub mod options;
+pub mod paths;
+pub mod pii;
+pub mod processor;
+pub mod project;
+pub mod relevance;
+pub mod secrets;
+pub mod selection;
+pub mod similarity;
+pub mod symbols;
+pub mod transform;
+pub mod verify;
+pub mod walker;
+pub mod workspace;
+
+pub use decommenter::{Language, LanguageDB, StripMode, remove_comments};
+pub use options::{ErrorPolicy, ExportLayout, JoinOptions, SortOrder, SubmoduleMode, VirtualFile};
+use transform::Pipeline;
+
+/// Builds the pipeline of built-in transforms requested by the options.
+/// Callers can push their own transforms onto the returned pipeline.
+pub fn default_pipeline(options: &JoinOptions) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    // Blame runs first so it sees the content exactly as it is on disk.
+    #[cfg(feature = "git")]
+    if options.blame {
+        match git::Blame::discover(&options.input_folder) {
+            Ok(blame) => {
+                pipeline.push(blame);
+            }
+            Err(e) => events::warning(&format!("Blame annotations are disabled: {}", e.message())),
+        }
+    }
+    if options.normalize {
+        pipeline.push(transform::Normalize);
+    }
+    if let Some(mode) = options.strip_comments {
+        pipeline.push(decommenter::StripComments::new(decommenter::StripOptions {
+            keep_markers: options.keep_comments.clone(),
+            strip_disabled: options.strip_disabled,
+            preserve_lines: options.preserve_lines,
+            keep_header: options.keep_header,
+            ..mode.into()
+        }));
+    }
+    if options.redact {
+        pipeline.push(transform::Redact::default());
+    }
+    if options.redact_pii {
+        pipeline.push(pii::RedactPii::default());
+    }
+    // Secrets are scanned last, in the content as it will be written.
+    if let Some(policy) = options.secrets_policy {
+        pipeline.push(secrets::Secrets::new(policy));
+    }
+    pipeline
+}
+
+/// Finds every file selected by `options` and writes their transformed content
+/// to the configured output file, or copies them into the export folder,
+/// returning a summary of what was written.
+pub fn join(options: &JoinOptions, pipeline: &Pipeline) -> anyhow::Result<processor::Report> {
+    if (options.git_info
+        || options.blame
+        || options.git_meta
+        || options.sort == options::SortOrder::Churn)
+        && !cfg!(feature = "git")
+    {
+        anyhow::bail!("Git metadata requires join-ai-core to be built with the `git` feature.");
+    }
+    if options.sort == options::SortOrder::Coverage && options.coverage.is_none() {
+        anyhow::bail!("Sorting files by coverage requires a coverage report.");
+    }
+    if options.blame && options.strip_comments.is_some() {
+        // Blame annotations are comments themselves.
+        anyhow::bail!("Blame annotations cannot be combined with comment stripping.");
+    }
+
+    options.validate()?;
+
+    // The walker runs in a background thread and sends file paths via a channel.
+    let receiver = match &options.files {
+        Some(files) => walker::listed_files(options, files)?,
+        None => walker::find_files(options)?,
+    };
+
+    // The processor reads each file and appends its content to the output file.
+    processor::process_files(receiver, options, pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::fs;
+
+    /// Verifies that the library can be driven directly through `JoinOptions`.
+    #[test]
+    fn test_join_with_options() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("a.rs").write_str("fn a() {}\r\n")?;
+        dir.child("b.md").write_str("# b")?;
+
+        let output_file = dir.path().join("output.txt");
+        let mut options = JoinOptions::new(dir.path(), &output_file);
+        options.patterns = Some(vec!["*.rs".to_string()]);
+        options.normalize = true;
+
+        join(&options, &default_pipeline(&options))?;
+
+        let result = fs::read_to_string(&output_file)?;
+        assert!(result.contains("fn a() {}\n"));
+        assert!(!result.contains('\r'));
+        assert!(!result.contains("b.md"));
+
+        Ok(())
+    }
+
+    /// Verifies that an explicit list of files is joined in its order, without
+    /// a walk, and that a listed file that is never included is an error.
+    #[test]
+    fn test_join_listed_files() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("a.rs").write_str("fn a() {}\n")?;
+        dir.child("b.rs").write_str("fn b() {}\n")?;
+        dir.child("c.rs").write_str("fn c() {}\n")?;
+
+        let output_file = dir.path().join("output.txt");
+        let mut options = JoinOptions::new(dir.path(), &output_file);
+        options.patterns = Some(vec!["a.rs".to_string()]);
+        options.files = Some(vec!["c.rs".into(), "a.rs".into()]);
+        join(&options, &default_pipeline(&options))?;
+
+        let result = fs::read_to_string(&output_file)?;
+        assert!(result.find("fn c()").unwrap() < result.find("fn a()").unwrap());
+        assert!(!result.contains("fn b()"));
+
+        dir.child(config::CONFIG_FILE)
+            .write_str("never_include = [\"c.rs\"]\n")?;
+        assert!(join(&options, &default_pipeline(&options)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+//! License detection (`--licenses`, `--deny-license`): the license of each
+//! included file, from its `SPDX-License-Identifier` header or else from the
+//! nearest `LICENSE` or `COPYING` file in its folder or the folders above it,
+//! up to the input folder.
+//!
+//! License files are recognized by the wording of common licenses. Several
+//! license files in one folder (e.g., `LICENSE-MIT` and `LICENSE-APACHE`) are
+//! read as a choice between them.
+
+use crate::paths;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The license of files with neither a header nor a license file above them.
+pub const NONE: &str = "none";
+
+/// The license of files whose license file is not recognized.
+pub const UNRECOGNIZED: &str = "unrecognized";
+
+/// How many bytes at the start of a file are searched for an SPDX header.
+const HEADER_LEN: usize = 4 * 1024;
+
+/// Stems of license file names, matched case-insensitively and followed by
+/// nothing, an extension, or a suffix such as `-MIT`.
+const LICENSE_FILES: &[&str] = &["license", "licence", "copying"];
+
+/// The wording that identifies common licenses, checked in order, as SPDX
+/// identifiers and phrases that must all appear.
+const WORDING: &[(&str, &[&str])] = &[
+    (
+        "AGPL-3.0",
+        &["GNU AFFERO GENERAL PUBLIC LICENSE", "Version 3"],
+    ),
+    (
+        "LGPL-3.0",
+        &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 3"],
+    ),
+    (
+        "LGPL-2.1",
+        &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 2.1"],
+    ),
+    ("GPL-3.0", &["GNU GENERAL PUBLIC LICENSE", "Version 3"]),
+    ("GPL-2.0", &["GNU GENERAL PUBLIC LICENSE", "Version 2"]),
+    ("Apache-2.0", &["Apache License", "Version 2.0"]),
+    ("MPL-2.0", &["Mozilla Public License", "2.0"]),
+    ("EPL-2.0", &["Eclipse Public License", "2.0"]),
+    ("MIT", &["Permission is hereby granted, free of charge"]),
+    (
+        "BSD-3-Clause",
+        &[
+            "Redistribution and use in source and binary forms",
+            "Neither the name",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["Redistribution and use in source and binary forms"],
+    ),
+    (
+        "ISC",
+        &["Permission to use, copy, modify, and/or distribute this software for any purpose"],
+    ),
+    (
+        "Unlicense",
+        &["This is free and unencumbered software released into the public domain"],
+    ),
+];
+
+/// Prefixes of the identifiers of copyleft licenses, which `copyleft` stands
+/// for in `--deny-license`.
+const COPYLEFT: &[&str] = &[
+    "GPL-", "AGPL-", "LGPL-", "MPL-", "EPL-", "EUPL-", "CDDL-", "OSL-",
+];
+
+/// The error of a join stopped by `--deny-license`, listing the files with a
+/// denied license.
+#[derive(Debug)]
+pub struct LicenseDenied(pub Vec<(PathBuf, String)>);
+
+impl fmt::Display for LicenseDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "The join was stopped because {} files have a denied license, and nothing was written:",
+            self.0.len()
+        )?;
+        for (path, license) in &self.0 {
+            writeln!(f, "  {}  {license}", path.display())?;
+        }
+        write!(f, "Exclude them, or allow their license.")
+    }
+}
+
+impl std::error::Error for LicenseDenied {}
+
+/// Finds the license of each file in `files`, looking for license files up to
+/// `root`.
+pub fn detect(files: &[PathBuf], root: &Path) -> Vec<(PathBuf, String)> {
+    let mut folders: HashMap<PathBuf, Option<String>> = HashMap::new();
+    files
+        .iter()
+        .map(|path| {
+            let license = header_license(path).unwrap_or_else(|| {
+                path.ancestors()
+                    .skip(1)
+                    .take_while(|folder| folder.starts_with(root))
+                    .find_map(|folder| {
+                        folders
+                            .entry(folder.to_path_buf())
+                            .or_insert_with(|| folder_license(folder))
+                            .clone()
+                    })
+                    .unwrap_or_else(|| NONE.to_string())
+            });
+            (path.clone(), license)
+        })
+        .collect()
+}
+
+/// Groups the files by license.
+pub fn by_license(licenses: Vec<(PathBuf, String)>) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut grouped: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for (path, license) in licenses {
+        grouped.entry(license).or_default().push(path);
+    }
+    grouped
+}
+
+/// Whether `license`, an SPDX expression, is denied by `denied`: a choice
+/// (`OR`) is denied when every alternative is, and a combination (`AND`) when
+/// any part is.
+pub fn is_denied(license: &str, denied: &[String]) -> bool {
+    let license = license.trim_matches(|c| c == '(' || c == ')');
+    license.split(" OR ").all(|alternative| {
+        alternative
+            .split(" AND ")
+            .any(|part| denied.iter().any(|deny| matches(part.trim(), deny)))
+    })
+}
+
+/// Whether the license `id` is the one `deny` names, in any of its variants
+/// (`GPL-3.0` denies `GPL-3.0-only`, `GPL-3.0-or-later` and `GPL-3.0+`).
+fn matches(id: &str, deny: &str) -> bool {
+    let id = id.split(" WITH ").next().unwrap_or(id).trim();
+    if deny.eq_ignore_ascii_case("copyleft") {
+        return COPYLEFT.iter().any(|prefix| {
+            id.len() > prefix.len() && id[..prefix.len()].eq_ignore_ascii_case(prefix)
+        });
+    }
+    let base = ["-only", "-or-later", "+"]
+        .iter()
+        .find_map(|suffix| id.strip_suffix(suffix))
+        .unwrap_or(id);
+    id.eq_ignore_ascii_case(deny) || base.eq_ignore_ascii_case(deny)
+}
+
+/// Reads the SPDX header at the start of the file at `path`.
+fn header_license(path: &Path) -> Option<String> {
+    let mut head = Vec::with_capacity(HEADER_LEN);
+    File::open(paths::for_io(path))
+        .ok()?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut head)
+        .ok()?;
+    spdx_identifier(&String::from_utf8_lossy(&head))
+}
+
+/// Finds an `SPDX-License-Identifier` line in `text`.
+fn spdx_identifier(text: &str) -> Option<String> {
+    static SPDX: OnceLock<Regex> = OnceLock::new();
+    let spdx = SPDX.get_or_init(|| {
+        Regex::new(r"SPDX-License-Identifier:\s*([A-Za-z0-9.+()\- ]*[A-Za-z0-9+)])")
+            .expect("built-in SPDX pattern is valid")
+    });
+    spdx.captures(text).map(|captures| captures[1].to_string())
+}
+
+/// Recognizes the license files directly in `folder`.
+fn folder_license(folder: &Path) -> Option<String> {
+    let mut licenses: Vec<String> = std::fs::read_dir(paths::for_io(folder))
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter(|entry| is_license_file(&entry.file_name().to_string_lossy()))
+        .map(|entry| {
+            let text = std::fs::read_to_string(entry.path()).unwrap_or_default();
+            recognize(&text).unwrap_or_else(|| UNRECOGNIZED.to_string())
+        })
+        .collect();
+    if licenses.is_empty() {
+        return None;
+    }
+    licenses.sort();
+    licenses.dedup();
+    Some(licenses.join(" OR "))
+}
+
+/// Whether `name` is the name of a license file.
+fn is_license_file(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    LICENSE_FILES.iter().any(|stem| {
+        name.strip_prefix(stem)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.') || rest.starts_with('-'))
+    })
+}
+
+/// Recognizes a license by its wording, or by an SPDX header.
+fn recognize(text: &str) -> Option<String> {
+    // Line breaks fall anywhere in license texts.
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    WORDING
+        .iter()
+        .find(|(_, phrases)| phrases.iter().all(|phrase| text.contains(phrase)))
+        .map(|(id, _)| id.to_string())
+        .or_else(|| spdx_identifier(&text))
+}
+
+// --- Unit Tests for License Detection ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that headers win over license files, that the nearest license
+    /// file applies, and that dual license files are read as a choice.
+    #[test]
+    fn test_detect() {
+        let dir = TempDir::new().unwrap();
+        dir.child("LICENSE-MIT")
+            .write_str("Permission is hereby granted, free of\ncharge, to any person")
+            .unwrap();
+        dir.child("LICENSE-APACHE")
+            .write_str("Apache License\n Version 2.0, January 2004")
+            .unwrap();
+        dir.child("src/main.rs").write_str("fn main() {}").unwrap();
+        dir.child("src/gpl.c")
+            .write_str("/* SPDX-License-Identifier: GPL-2.0-or-later */\nint x;")
+            .unwrap();
+        dir.child("vendor/lib/COPYING")
+            .write_str("GNU GENERAL PUBLIC LICENSE\nVersion 3, 29 June 2007")
+            .unwrap();
+        dir.child("vendor/lib/a.c").write_str("int a;").unwrap();
+        dir.child("other/LICENSE.txt")
+            .write_str("All rights reserved.")
+            .unwrap();
+        dir.child("other/b.c").write_str("int b;").unwrap();
+
+        let files: Vec<PathBuf> = ["src/main.rs", "src/gpl.c", "vendor/lib/a.c", "other/b.c"]
+            .iter()
+            .map(|path| dir.path().join(path))
+            .collect();
+        let licenses: Vec<String> = detect(&files, dir.path())
+            .into_iter()
+            .map(|(_, license)| license)
+            .collect();
+        assert_eq!(
+            licenses,
+            [
+                "Apache-2.0 OR MIT",
+                "GPL-2.0-or-later",
+                "GPL-3.0",
+                UNRECOGNIZED
+            ]
+        );
+
+        let outside = TempDir::new().unwrap();
+        outside.child("x.rs").write_str("").unwrap();
+        assert_eq!(
+            detect(&[outside.path().join("x.rs")], outside.path())[0].1,
+            NONE
+        );
+    }
+
+    /// Verifies that denied licenses match their variants, the `copyleft`
+    /// group, and SPDX expressions.
+    #[test]
+    fn test_is_denied() {
+        let deny = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert!(is_denied("GPL-3.0-only", &deny(&["GPL-3.0"])));
+        assert!(is_denied("GPL-3.0+", &deny(&["gpl-3.0"])));
+        assert!(!is_denied("LGPL-3.0", &deny(&["GPL-3.0"])));
+        assert!(!is_denied("MIT OR GPL-3.0", &deny(&["GPL-3.0"])));
+        assert!(is_denied("MIT AND GPL-3.0", &deny(&["GPL-3.0"])));
+        assert!(is_denied("(LGPL-2.1 OR MPL-2.0)", &deny(&["copyleft"])));
+        assert!(is_denied(
+            "GPL-2.0-only WITH Classpath-exception-2.0",
+            &deny(&["copyleft"])
+        ));
+        assert!(!is_denied("Apache-2.0", &deny(&["copyleft"])));
+    }
+}
@@ -0,0 +1,536 @@
+use crate::decommenter::{DEFAULT_KEEP_MARKERS, HeaderMode, StripMode};
+use crate::format::OutputFormat;
+use crate::secrets::SecretsPolicy;
+use ignore::overrides::OverrideBuilder;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How git submodules inside the input folder are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SubmoduleMode {
+    /// Leave submodules out of the join entirely.
+    Skip,
+    /// Traverse checked-out submodules like regular folders, and note the ones
+    /// that are not checked out.
+    #[default]
+    Include,
+    /// Do not traverse submodules; write a stub with their pinned commit instead.
+    Shallow,
+}
+
+/// What happens when a file cannot be read or transformed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ErrorPolicy {
+    /// Leave the file out, keep going, and list the failures at the end.
+    #[default]
+    Continue,
+    /// Stop the join at the first failure.
+    FailFast,
+    /// Leave the file out and keep going without reporting it.
+    SkipSilent,
+}
+
+/// The order in which files are written, and in which a token budget is spent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SortOrder {
+    /// By path, the order of the walk.
+    #[default]
+    Path,
+    /// By recent git activity: the files changed by the most commits within
+    /// `churn_days` first, then by path.
+    Churn,
+    /// Orientation first: READMEs and architecture docs, then manifests, then
+    /// entry points such as `main.rs`, each shallowest first, then the rest by
+    /// path.
+    Smart,
+    /// The least tested files first, by the share of their lines run by the
+    /// tests in the `coverage` report, then the files the report does not
+    /// cover, by path.
+    Coverage,
+}
+
+/// How the files are laid out in the folder of `export_dir`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ExportLayout {
+    /// At the same paths as in the input folder.
+    #[default]
+    Mirror,
+    /// All in the folder itself, with the folders of each path joined into
+    /// its name by `__` (e.g., `src__cli.rs`).
+    Flat,
+}
+
+/// A file that is not on disk, such as the output of a command or an issue
+/// fetched from its tracker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualFile {
+    /// The path shown in its header, relative to the input folder.
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+/// The full set of options that drive a join, independent of how they were
+/// collected (command-line flags, a config file, or code embedding the library).
+#[derive(Debug, Clone)]
+pub struct JoinOptions {
+    /// The root folder to start traversing for files.
+    pub input_folder: PathBuf,
+
+    /// The path to the file where the concatenated content will be written.
+    pub output_file: PathBuf,
+
+    /// Glob patterns for files to include. `None` includes every file.
+    pub patterns: Option<Vec<String>>,
+
+    /// Glob patterns for files or folders to exclude.
+    pub exclude: Option<Vec<String>>,
+
+    /// An exact list of files, relative to the input folder, that is joined in
+    /// this order instead of walking the folder. Patterns, exclusions and
+    /// `sort` do not apply to it; the other selection options do.
+    pub files: Option<Vec<PathBuf>>,
+
+    /// The maximum depth for directory traversal. `None` means unlimited.
+    pub max_depth: Option<usize>,
+
+    /// Files larger than this many bytes are left out. `None` means no limit.
+    pub max_file_size: Option<u64>,
+
+    /// The token budget of the output, estimated from file sizes. Files are
+    /// included in output order until the budget is spent. `None` means no limit.
+    pub max_tokens: Option<u64>,
+
+    /// A question or topic. When set, only the `top_k` files most relevant to
+    /// it are included, most relevant first.
+    pub relevant_to: Option<String>,
+
+    /// How many files `relevant_to` keeps, besides high-priority files.
+    pub top_k: usize,
+
+    /// A file, relative to the input folder. When set, only this file and the
+    /// files it imports (up to `follow_imports` levels deep) are included.
+    pub entry: Option<PathBuf>,
+
+    /// How many levels of imports are followed from `entry`.
+    pub follow_imports: usize,
+
+    /// The order of the files, before `entry`, `relevant_to` and priority
+    /// directives reorder them.
+    pub sort: SortOrder,
+
+    /// How many days of history `SortOrder::Churn` counts commits over.
+    pub churn_days: u64,
+
+    /// Whether to only report what would be included, without writing the output.
+    pub dry_run: bool,
+
+    /// Whether the output lists the files, with their size and metadata,
+    /// instead of their content.
+    pub list_only: bool,
+
+    /// Whether the output is read back once written, failing the join when a
+    /// file does not come back from it byte for byte.
+    pub verify: bool,
+
+    /// How the files are laid out in the output.
+    pub format: OutputFormat,
+
+    /// When set, the output is split into numbered parts (`out-1.txt`,
+    /// `out-2.txt`, …) of at most this many bytes, between files. A file
+    /// larger than this is written alone in its part.
+    pub split_size: Option<u64>,
+
+    /// When set, the files are copied into this folder, through the
+    /// transforms, instead of being joined into `output_file`.
+    pub export_dir: Option<PathBuf>,
+
+    /// How the files are laid out in `export_dir`.
+    pub export_layout: ExportLayout,
+
+    /// Whether hidden files and directories are included.
+    pub hidden: bool,
+
+    /// Whether the walker follows symbolic links.
+    pub follow_links: bool,
+
+    /// Whether files are read through memory maps instead of buffered reads.
+    /// Files that cannot be mapped fall back to buffered reads.
+    pub mmap: bool,
+
+    /// Whether rendered sections are cached in `.join-ai/cache` inside the input
+    /// folder, so unchanged files are not read or transformed again on the next run.
+    pub cache: bool,
+
+    /// Whether line endings are normalized and byte order marks removed.
+    pub normalize: bool,
+
+    /// Which comments are removed from files in a known language. `None`
+    /// keeps every comment.
+    pub strip_comments: Option<StripMode>,
+
+    /// Comments whose first word is one of these markers are kept while
+    /// stripping comments.
+    pub keep_comments: Vec<String>,
+
+    /// Whether preprocessor blocks that are never compiled (`#if 0 … #endif`)
+    /// are removed from C, C++ and Objective-C files while stripping comments.
+    pub strip_disabled: bool,
+
+    /// Whether lines removed while stripping comments are left blank, so each
+    /// file keeps its line numbers.
+    pub preserve_lines: bool,
+
+    /// When the first comment block of each file (e.g., a license header) is
+    /// kept while stripping comments. `None` removes it.
+    pub keep_header: Option<HeaderMode>,
+
+    /// Whether likely secrets are masked in the output.
+    pub redact: bool,
+
+    /// Whether personal data (emails, phone numbers, IP addresses and
+    /// person-like names) is masked in the output.
+    pub redact_pii: bool,
+
+    /// What happens to likely secrets found by rules and by entropy: they are
+    /// warned about, redacted, or stop the join. `None` does not scan for them.
+    pub secrets_policy: Option<SecretsPolicy>,
+
+    /// Whether a block describing the git repository (branch, commit, status,
+    /// remote) is written at the top of the output.
+    pub git_info: bool,
+
+    /// Only the files owned, in `CODEOWNERS`, by one of these users or teams
+    /// (e.g., `@acme/backend`) are joined. `None` joins every file.
+    pub owners: Option<Vec<String>>,
+
+    /// Whether each file header names the owners of the file in `CODEOWNERS`.
+    pub show_owners: bool,
+
+    /// An lcov or Cobertura XML coverage report: each file header states the
+    /// share of the lines of its file run by the tests, and `SortOrder::Coverage`
+    /// puts the least tested files first.
+    pub coverage: Option<PathBuf>,
+
+    /// A file at least this similar (in percent) to an earlier file is left
+    /// out, and listed in the header of the earlier file. `None` keeps every
+    /// file.
+    pub near_duplicates: Option<u8>,
+
+    /// Whether Git LFS pointer files are replaced by the objects they point
+    /// to, downloaded if need be. Otherwise, pointers are left out.
+    pub lfs_fetch: bool,
+
+    /// Whether a summary of the dependencies declared in the manifests of the
+    /// input folder is written at the top of the output, in place of the
+    /// lockfiles, which are left out.
+    pub dependencies: bool,
+
+    /// Whether the files that instruct coding assistants, such as `AGENTS.md`
+    /// and `.cursorrules`, are written in a section at the top of the output
+    /// instead of among the other files.
+    pub agent_rules: bool,
+
+    /// Whether a section listing, for each file, the files it imports and the
+    /// files that import it is written before the files.
+    pub module_graph: bool,
+
+    /// Whether the files of each top-level folder are written together, in a
+    /// section titled with the folder, its number of files and their tokens.
+    pub group_by_dir: bool,
+
+    /// Whether an index of the functions, types and classes defined in the
+    /// files, with the file and line of each, is written at the end of the
+    /// output.
+    pub symbols: bool,
+
+    /// Whether the license of each included file is detected and reported.
+    pub licenses: bool,
+
+    /// SPDX identifiers of licenses (or `copyleft`) that stop the join when an
+    /// included file has one. Implies `licenses`.
+    pub deny_licenses: Vec<String>,
+
+    /// Whether each block of lines is annotated with its last author and commit age.
+    pub blame: bool,
+
+    /// Whether each file header includes the file's last commit (hash, date, subject).
+    pub git_meta: bool,
+
+    /// How git submodules are handled.
+    pub submodules: SubmoduleMode,
+
+    /// How long a single file may take to read before it is reported as a
+    /// failure. `None` waits indefinitely. With a timeout, files are read in full
+    /// on a helper thread, bypassing `mmap` and `cache`.
+    pub read_timeout: Option<Duration>,
+
+    /// What happens when a file cannot be read or transformed.
+    pub error_policy: ErrorPolicy,
+
+    /// Workspace packages to join, together with the in-repo packages they
+    /// depend on. `None` joins the whole input folder.
+    pub packages: Option<Vec<String>>,
+
+    /// A label shown in place of the input folder in file headers. This is used
+    /// when the input was materialized into a temporary directory (e.g., a remote
+    /// repository), so headers show `org/repo/src/main.rs` instead of a temp path.
+    pub display_root: Option<PathBuf>,
+
+    /// Files written after the files of the input folder, through the same
+    /// transforms. They are not subject to patterns or the token budget.
+    pub virtual_files: Vec<VirtualFile>,
+
+    /// Files written ahead of the files of the input folder, such as the issue
+    /// the join is meant to address. Like `virtual_files`, they go through the
+    /// transforms but not the patterns or the token budget.
+    pub leading_files: Vec<VirtualFile>,
+
+    /// The mapping file of `--anonymize-paths`: when set, file headers show
+    /// pseudonyms such as `dir_01/file_07.rs`, whose real paths are kept in it.
+    pub anonymize_paths: Option<PathBuf>,
+}
+
+impl JoinOptions {
+    /// Creates options for joining `input_folder` into `output_file`, with every
+    /// other setting at its default value.
+    pub fn new(input_folder: impl Into<PathBuf>, output_file: impl Into<PathBuf>) -> Self {
+        Self {
+            input_folder: input_folder.into(),
+            output_file: output_file.into(),
+            patterns: None,
+            exclude: None,
+            files: None,
+            max_depth: None,
+            max_file_size: None,
+            max_tokens: None,
+            relevant_to: None,
+            top_k: 20,
+            entry: None,
+            follow_imports: 1,
+            sort: SortOrder::default(),
+            churn_days: 90,
+            dry_run: false,
+            list_only: false,
+            verify: false,
+            format: OutputFormat::default(),
+            split_size: None,
+            export_dir: None,
+            export_layout: ExportLayout::Mirror,
+            hidden: false,
+            follow_links: false,
+            mmap: false,
+            cache: false,
+            normalize: false,
+            strip_comments: None,
+            keep_comments: DEFAULT_KEEP_MARKERS.map(String::from).to_vec(),
+            strip_disabled: false,
+            preserve_lines: false,
+            keep_header: None,
+            redact: false,
+            redact_pii: false,
+            secrets_policy: None,
+            git_info: false,
+            owners: None,
+            show_owners: false,
+            coverage: None,
+            near_duplicates: None,
+            lfs_fetch: false,
+            dependencies: false,
+            agent_rules: false,
+            module_graph: false,
+            group_by_dir: false,
+            symbols: false,
+            licenses: false,
+            deny_licenses: Vec::new(),
+            blame: false,
+            git_meta: false,
+            submodules: SubmoduleMode::default(),
+            read_timeout: None,
+            error_policy: ErrorPolicy::default(),
+            packages: None,
+            display_root: None,
+            virtual_files: Vec::new(),
+            leading_files: Vec::new(),
+            anonymize_paths: None,
+        }
+    }
+
+    /// Checks the input folder and the patterns before anything is read, and
+    /// fails with every problem found, each with a hint on how to fix it.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut problems = self.input_problems();
+        if problems.is_empty() {
+            problems.extend(self.entry_problems());
+        }
+        problems.extend(self.pattern_problems());
+        fail_with(problems)
+    }
+
+    /// Checks only the patterns, for inputs whose folder does not exist yet
+    /// because it is downloaded or extracted first.
+    pub fn validate_patterns(&self) -> anyhow::Result<()> {
+        fail_with(self.pattern_problems())
+    }
+
+    fn input_problems(&self) -> Vec<String> {
+        let input = self.input_folder.display();
+        let is_output = match (
+            self.input_folder.canonicalize(),
+            self.output_file.canonicalize(),
+        ) {
+            (Ok(input), Ok(output)) => input == output,
+            _ => self.input_folder == self.output_file,
+        };
+        if is_output {
+            return vec![format!(
+                "{input} is the output file of the join. Pass the folder to join instead."
+            )];
+        }
+        match std::fs::metadata(&self.input_folder) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![format!(
+                "The input folder {input} does not exist. Check the path, which is relative to the current directory."
+            )],
+            Err(e) => vec![format!("The input folder {input} cannot be accessed: {e}.")],
+            Ok(metadata) if !metadata.is_dir() => vec![format!(
+                "{input} is a file, not a folder. To join a single file, pass its folder and select the file with a pattern."
+            )],
+            Ok(_) => match std::fs::read_dir(&self.input_folder) {
+                Ok(_) => Vec::new(),
+                Err(e) => vec![format!(
+                    "The input folder {input} cannot be read: {e}. Check its permissions."
+                )],
+            },
+        }
+    }
+
+    fn entry_problems(&self) -> Vec<String> {
+        let Some(entry) = &self.entry else {
+            return Vec::new();
+        };
+        let path = self.input_folder.join(entry);
+        if path.is_file() {
+            return Vec::new();
+        }
+        vec![format!(
+            "The entry file {} does not exist. The entry is relative to the input folder.",
+            path.display()
+        )]
+    }
+
+    /// The path of `entry` as the walker finds it.
+    pub fn entry_path(&self) -> Option<PathBuf> {
+        let entry = self.entry.as_ref()?;
+        let entry: PathBuf = entry
+            .components()
+            .filter(|component| *component != std::path::Component::CurDir)
+            .collect();
+        Some(self.input_folder.join(entry))
+    }
+
+    fn pattern_problems(&self) -> Vec<String> {
+        let patterns = self.patterns.iter().flatten();
+        let exclude = self.exclude.iter().flatten();
+        patterns
+            .chain(exclude)
+            .filter_map(|pattern| {
+                let error = OverrideBuilder::new(&self.input_folder)
+                    .add(pattern)
+                    .err()?;
+                Some(format!(
+                    "Invalid pattern '{pattern}': {error}. Patterns are globs, such as '*.rs' or 'src/**/*.ts'."
+                ))
+            })
+            .collect()
+    }
+
+    /// Whether `path` is written by the join: the output file, one of its
+    /// parts when the output is split, a file of the export folder, or the
+    /// mapping file of anonymized paths. It is never part of the input.
+    pub fn is_output(&self, path: &Path) -> bool {
+        // The walker's `./out.txt` is the `out.txt` of the command line.
+        let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.into());
+        let path = absolute(path);
+        let output = absolute(&self.output_file);
+        path == output
+            || self.anonymize_paths.as_deref().map(absolute).as_ref() == Some(&path)
+            || (self.split_size.is_some() && crate::format::is_part_path(&output, &path))
+            || self
+                .export_dir
+                .as_deref()
+                .is_some_and(|dir| path.starts_with(absolute(dir)))
+    }
+
+    /// Returns the path of `path` as it should appear in the output, without
+    /// any platform-specific prefix such as Windows' `\\?\`.
+    pub fn display_path(&self, path: &Path) -> PathBuf {
+        let path = match &self.display_root {
+            Some(root) => root.join(path.strip_prefix(&self.input_folder).unwrap_or(path)),
+            None => path.to_path_buf(),
+        };
+        crate::paths::for_display(&path).into_owned()
+    }
+}
+
+/// Fails with all `problems` at once, if there are any.
+fn fail_with(problems: Vec<String>) -> anyhow::Result<()> {
+    match problems.as_slice() {
+        [] => Ok(()),
+        [problem] => anyhow::bail!("{problem}"),
+        _ => anyhow::bail!(
+            "Found {} problems with the arguments:\n  - {}",
+            problems.len(),
+            problems.join("\n  - ")
+        ),
+    }
+}
+
+// --- Unit Tests for Option Validation ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that all problems are reported together.
+    #[test]
+    fn test_validate_reports_all_problems() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let mut options = JoinOptions::new(dir.path().join("missing"), "out.txt");
+        options.patterns = Some(vec!["*.rs".to_string(), "src/[".to_string()]);
+        options.exclude = Some(vec!["{a,b".to_string()]);
+        let error = options.validate().unwrap_err().to_string();
+        assert!(error.starts_with("Found 3 problems"), "{error}");
+        assert!(error.contains("does not exist"));
+        assert!(error.contains("'src/['"));
+        assert!(error.contains("'{a,b'"));
+        assert!(options.validate_patterns().is_err());
+
+        dir.child("out.txt").write_str("previous output")?;
+        let options = JoinOptions::new(dir.path().join("out.txt"), dir.path().join("out.txt"));
+        let error = options.validate().unwrap_err().to_string();
+        assert!(error.contains("is the output file"), "{error}");
+
+        let options = JoinOptions::new(dir.path(), "out.txt");
+        options.validate()?;
+
+        Ok(())
+    }
+
+    /// Verifies that the files written by the join are recognized however the
+    /// walker spells their path.
+    #[test]
+    fn test_is_output() {
+        let mut options = JoinOptions::new(".", "out.txt");
+        options.anonymize_paths = Some(PathBuf::from("paths.json"));
+        assert!(options.is_output(Path::new("./out.txt")));
+        assert!(options.is_output(Path::new("./paths.json")));
+        assert!(!options.is_output(Path::new("./out-1.txt")));
+        options.split_size = Some(1024);
+        assert!(options.is_output(Path::new("./out-1.txt")));
+        assert!(!options.is_output(Path::new("./src/out.txt")));
+    }
+}
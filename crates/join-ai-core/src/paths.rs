@@ -0,0 +1,97 @@
+//! Platform-specific path handling.
+//!
+//! On Windows, paths longer than `MAX_PATH` (260 characters) can only be opened
+//! in their verbatim form (`\\?\C:\...` or `\\?\UNC\server\share\...`), which in
+//! turn is not something readers of the output should see. Files are therefore
+//! opened through `for_io` and shown through `for_display`. On other platforms
+//! both are no-ops.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// The longest path Windows opens without the verbatim prefix.
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// Returns the form of `path` that should be used to open or stat the file.
+#[cfg(windows)]
+pub fn for_io(path: &Path) -> Cow<'_, Path> {
+    if path.as_os_str().len() < MAX_PATH {
+        return Cow::Borrowed(path);
+    }
+    // Verbatim paths are not normalized by Windows, so make the path absolute
+    // (which also resolves `.` and `..`) before adding the prefix.
+    match std::path::absolute(path) {
+        Ok(absolute) => Cow::Owned(to_verbatim(&absolute)),
+        Err(_) => Cow::Borrowed(path),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn for_io(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// Returns the form of `path` shown in the output, without verbatim prefixes:
+/// `\\?\C:\src` becomes `C:\src` and `\\?\UNC\server\share` becomes
+/// `\\server\share`.
+pub fn for_display(path: &Path) -> Cow<'_, Path> {
+    let Some(text) = path.to_str() else {
+        return Cow::Borrowed(path);
+    };
+    if let Some(unc) = text.strip_prefix(r"\\?\UNC\") {
+        Cow::Owned(PathBuf::from(format!(r"\\{unc}")))
+    } else if let Some(local) = text.strip_prefix(r"\\?\") {
+        Cow::Owned(PathBuf::from(local))
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Adds the verbatim prefix to an absolute Windows path, if it has none yet.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn to_verbatim(path: &Path) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    // Verbatim paths only accept backslashes as separators.
+    let text = text.replace('/', r"\");
+    if text.starts_with(r"\\?\") {
+        PathBuf::from(text)
+    } else if let Some(unc) = text.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{unc}"))
+    } else if text.as_bytes().get(1) == Some(&b':') {
+        PathBuf::from(format!(r"\\?\{text}"))
+    } else {
+        PathBuf::from(text)
+    }
+}
+
+// --- Unit Tests for Path Forms ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that drive and UNC paths round-trip through the verbatim form.
+    #[test]
+    fn test_verbatim_round_trip() {
+        let cases = [
+            (r"C:\repo\node_modules\a", r"\\?\C:\repo\node_modules\a"),
+            (r"\\server\share\repo", r"\\?\UNC\server\share\repo"),
+            (r"\\?\D:\already", r"\\?\D:\already"),
+        ];
+        for (path, verbatim) in cases {
+            assert_eq!(to_verbatim(Path::new(path)), PathBuf::from(verbatim));
+            let displayed = for_display(Path::new(verbatim)).into_owned();
+            assert_eq!(
+                displayed,
+                PathBuf::from(path.replace(r"\\?\D:", r"D:")),
+                "{verbatim}"
+            );
+        }
+        assert_eq!(
+            for_display(Path::new("src/main.rs")),
+            Path::new("src/main.rs")
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Detection of the type of a project from the manifests in its folder, so that
+//! a join without flags or configuration already leaves out build outputs and
+//! installed dependencies: a Rust, Node.js, Python or Go project is joined with
+//! the patterns of its sources and the exclusions of its ecosystem, as if they
+//! had been given with `-p` and `-x`.
+
+use crate::config::CONFIG_FILE;
+use crate::options::JoinOptions;
+use std::path::Path;
+
+/// The types of project that are detected, by their manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectType {
+    Rust,
+    Node,
+    Python,
+    Go,
+}
+
+impl ProjectType {
+    /// Every type of project, in the order they are detected.
+    pub const ALL: [ProjectType; 4] = [Self::Rust, Self::Node, Self::Python, Self::Go];
+
+    /// The name of the type, as printed.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rust => "Rust",
+            Self::Node => "Node.js",
+            Self::Python => "Python",
+            Self::Go => "Go",
+        }
+    }
+
+    /// The manifest that marks a folder as a project of this type.
+    pub fn manifest(self) -> &'static str {
+        match self {
+            Self::Rust => "Cargo.toml",
+            Self::Node => "package.json",
+            Self::Python => "pyproject.toml",
+            Self::Go => "go.mod",
+        }
+    }
+
+    /// The patterns of the sources and manifests of the ecosystem.
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["*.rs"],
+            Self::Node => &[
+                "*.js",
+                "*.jsx",
+                "*.mjs",
+                "*.cjs",
+                "*.ts",
+                "*.tsx",
+                "*.vue",
+                "*.svelte",
+                "*.css",
+                "*.scss",
+                "*.html",
+                "package.json",
+                "tsconfig.json",
+            ],
+            Self::Python => &["*.py", "*.pyi", "*.cfg", "requirements*.txt"],
+            Self::Go => &["*.go", "go.mod"],
+        }
+    }
+
+    /// The folders of build outputs and installed dependencies of the
+    /// ecosystem.
+    fn exclude(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["target/"],
+            Self::Node => &["node_modules/", "dist/", "build/", "coverage/", "*.min.js"],
+            Self::Python => &["__pycache__/", "venv/", "build/", "dist/", "*.egg-info/"],
+            Self::Go => &["vendor/"],
+        }
+    }
+}
+
+/// Files that are joined whatever the type of the project: documentation,
+/// configuration and build scripts.
+const COMMON_PATTERNS: &[&str] = &[
+    "*.md",
+    "*.toml",
+    "*.yml",
+    "*.yaml",
+    "*.sh",
+    "Makefile",
+    "Dockerfile",
+];
+
+/// The patterns inferred for a folder from its types of project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Defaults {
+    pub types: Vec<ProjectType>,
+    pub patterns: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Defaults {
+    /// Detects the types of the project in `folder` from the manifests at its
+    /// top. There are no defaults for a folder without a known manifest, or
+    /// one that is configured for join-ai, with a `.join-ai.toml` in it or in
+    /// a folder above it. A `.joinignore` leaves out files on top of them.
+    pub fn detect(folder: &Path) -> Option<Self> {
+        let absolute = std::path::absolute(folder).ok()?;
+        if absolute
+            .ancestors()
+            .any(|folder| folder.join(CONFIG_FILE).is_file())
+        {
+            return None;
+        }
+        let types: Vec<ProjectType> = ProjectType::ALL
+            .into_iter()
+            .filter(|kind| folder.join(kind.manifest()).is_file())
+            .collect();
+        if types.is_empty() {
+            return None;
+        }
+        let mut patterns: Vec<String> = Vec::new();
+        let mut exclude: Vec<String> = Vec::new();
+        let sources = types.iter().flat_map(|kind| kind.patterns());
+        for pattern in sources.chain(COMMON_PATTERNS) {
+            if !patterns.iter().any(|known| known == pattern) {
+                patterns.push(pattern.to_string());
+            }
+        }
+        for pattern in types.iter().flat_map(|kind| kind.exclude()) {
+            if !exclude.iter().any(|known| known == pattern) {
+                exclude.push(pattern.to_string());
+            }
+        }
+        Some(Self {
+            types,
+            patterns,
+            exclude,
+        })
+    }
+
+    /// Selects the files of `options` with these patterns.
+    pub fn apply_to(&self, options: &mut JoinOptions) {
+        options.patterns = Some(self.patterns.clone());
+        options.exclude = Some(self.exclude.clone());
+    }
+
+    /// Says what was inferred, and how to choose the files instead.
+    pub fn describe(&self) -> String {
+        let types: Vec<String> = self
+            .types
+            .iter()
+            .map(|kind| format!("{} ({})", kind.name(), kind.manifest()))
+            .collect();
+        format!(
+            "Detected a {} project. Using patterns: {}. Excluding patterns: {}. \
+             Pass -p or -x, or --no-detect, to choose the files yourself.",
+            types.join(" and "),
+            self.patterns.join(", "),
+            self.exclude.join(", ")
+        )
+    }
+}
+
+// --- Unit Tests for Project Detection ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that the patterns of every detected type are merged without
+    /// repeats, and that configured folders and folders without a manifest
+    /// have no defaults.
+    #[test]
+    fn test_detect_defaults() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        assert_eq!(Defaults::detect(dir.path()), None);
+
+        dir.child("Cargo.toml").write_str("[package]\n")?;
+        dir.child("pyproject.toml").write_str("[project]\n")?;
+        let defaults = Defaults::detect(dir.path()).expect("a Rust and Python project");
+        assert_eq!(defaults.types, [ProjectType::Rust, ProjectType::Python]);
+        assert_eq!(defaults.patterns[..3], ["*.rs", "*.py", "*.pyi"]);
+        assert_eq!(
+            defaults.exclude,
+            [
+                "target/",
+                "__pycache__/",
+                "venv/",
+                "build/",
+                "dist/",
+                "*.egg-info/"
+            ]
+        );
+        assert!(
+            defaults
+                .describe()
+                .starts_with("Detected a Rust (Cargo.toml) and Python (pyproject.toml) project.")
+        );
+
+        let mut options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        defaults.apply_to(&mut options);
+        assert_eq!(options.patterns.as_ref(), Some(&defaults.patterns));
+
+        dir.child("crates/app/Cargo.toml")
+            .write_str("[package]\n")?;
+        dir.child(CONFIG_FILE).write_str("")?;
+        assert_eq!(Defaults::detect(&dir.path().join("crates/app")), None);
+        Ok(())
+    }
+}
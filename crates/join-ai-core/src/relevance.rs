@@ -0,0 +1,144 @@
+//! Ranking of files by their relevance to a natural-language query
+//! (`--relevant-to`), with BM25 over the words of identifiers and paths.
+//!
+//! Identifiers are split into their words, so a query for "token refresh"
+//! matches `refreshToken`, `refresh_token` and `auth/token.rs` alike.
+
+use std::collections::HashMap;
+
+/// BM25's term-frequency saturation.
+const K1: f64 = 1.2;
+
+/// BM25's document-length normalization.
+const B: f64 = 0.75;
+
+/// Words of the query that say nothing about the code, such as "how does", as
+/// terms (hence `doe` and `thi`, without their `s`).
+const STOP_WORDS: &[&str] = &[
+    "an", "and", "are", "as", "at", "be", "by", "can", "do", "doe", "for", "from", "get", "how",
+    "in", "is", "it", "of", "on", "or", "the", "thi", "that", "to", "what", "when", "where",
+    "which", "who", "why", "with",
+];
+
+/// Splits `text` into lowercase terms: the words of its identifiers, with a
+/// plural `s` removed.
+pub fn terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<(usize, char)> = word.char_indices().collect();
+        let mut start = 0;
+        for (i, &(at, c)) in chars.iter().enumerate().skip(1) {
+            let previous = chars[i - 1].1;
+            let next_is_lower = chars
+                .get(i + 1)
+                .is_some_and(|(_, next)| next.is_lowercase());
+            // `camelCase`, `HTTPServer` and `v2Api` split before the capital.
+            if c.is_uppercase()
+                && (!previous.is_uppercase() || next_is_lower)
+                && previous.is_alphanumeric()
+            {
+                push_term(&mut terms, &word[start..at]);
+                start = at;
+            }
+        }
+        push_term(&mut terms, &word[start..]);
+    }
+    terms
+}
+
+/// Adds `word` to `terms`, normalized, unless it is too short to matter.
+fn push_term(terms: &mut Vec<String>, word: &str) {
+    if word.chars().count() < 2 {
+        return;
+    }
+    let mut term = word.to_lowercase();
+    if term.len() > 3 && term.ends_with('s') && !term.ends_with("ss") {
+        term.pop();
+    }
+    terms.push(term);
+}
+
+/// Scores each of `documents` (given as their terms) against `query` with
+/// BM25. A score of zero means that no word of the query occurs.
+pub fn scores(query: &str, documents: &[Vec<String>]) -> Vec<f64> {
+    let mut query_terms: Vec<String> = terms(query)
+        .into_iter()
+        .filter(|term| !STOP_WORDS.contains(&term.as_str()))
+        .collect();
+    query_terms.sort();
+    query_terms.dedup();
+
+    let frequencies: Vec<HashMap<&str, u32>> = documents
+        .iter()
+        .map(|terms| {
+            let mut counts = HashMap::new();
+            for term in terms {
+                *counts.entry(term.as_str()).or_insert(0) += 1;
+            }
+            counts
+        })
+        .collect();
+    let count = documents.len() as f64;
+    let average_len = documents.iter().map(Vec::len).sum::<usize>() as f64 / count.max(1.0);
+    let idf: Vec<f64> = query_terms
+        .iter()
+        .map(|term| {
+            let with_term = frequencies
+                .iter()
+                .filter(|counts| counts.contains_key(term.as_str()))
+                .count() as f64;
+            ((count - with_term + 0.5) / (with_term + 0.5)).ln_1p()
+        })
+        .collect();
+
+    documents
+        .iter()
+        .zip(&frequencies)
+        .map(|(terms, counts)| {
+            let len_norm = 1.0 - B + B * terms.len() as f64 / average_len.max(1.0);
+            query_terms
+                .iter()
+                .zip(&idf)
+                .map(|(term, idf)| {
+                    let tf = f64::from(counts.get(term.as_str()).copied().unwrap_or(0));
+                    idf * tf * (K1 + 1.0) / (tf + K1 * len_norm)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+// --- Unit Tests for Relevance Ranking ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that identifiers in every case style split into the same words.
+    #[test]
+    fn test_terms() {
+        assert_eq!(
+            terms("refreshToken refresh_token HTTPServer auth/tokens.rs x"),
+            [
+                "refresh", "token", "refresh", "token", "http", "server", "auth", "token", "rs"
+            ]
+        );
+    }
+
+    /// Verifies that documents mentioning the words of the query rank first,
+    /// rare words weigh more than common ones, and stop words are ignored.
+    #[test]
+    fn test_scores() {
+        let documents: Vec<Vec<String>> = [
+            "fn refresh_token(session: &Session) -> Token",
+            "fn render(session: &Session) -> Html",
+            "fn main() { how_does_this_run() }",
+        ]
+        .iter()
+        .map(|text| terms(text))
+        .collect();
+        let scores = scores("How does session token refresh work?", &documents);
+        assert!(scores[0] > scores[1]);
+        assert!(scores[1] > 0.0);
+        assert_eq!(scores[2], 0.0);
+    }
+}
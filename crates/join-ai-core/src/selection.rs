@@ -0,0 +1,721 @@
+//! The selection stage, which runs between the walk and the processing of files:
+//! it leaves out files that opt out with an inline directive, Git LFS pointers,
+//! files that are too large, that the entry file does not import, that are not
+//! relevant to the query, that are near-duplicates of another file, or that do
+//! not fit in the token budget.
+//!
+//! Decisions are based on file sizes and the first lines of each file (for
+//! directives), so a dry run can show exactly what a join would include without
+//! reading whole files. Only following imports, ranking files by relevance and
+//! finding near-duplicates read them.
+
+use crate::directives::{Directives, Priority};
+use crate::lfs::Pointer;
+use crate::options::{JoinOptions, SortOrder};
+use crate::similarity::{self, Signature};
+use crate::{events, imports, paths, relevance};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// How much of each file is read to rank it by relevance.
+const MAX_RANKED_LEN: u64 = 1024 * 1024;
+
+/// Why a file found by the walker is left out of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SkipReason {
+    /// The file opts out with a `join-ai: exclude` directive.
+    Directive,
+    /// The file is a Git LFS pointer, and `lfs_fetch` is not set.
+    LfsPointer,
+    /// The file is larger than `max_file_size`.
+    TooLarge,
+    /// The file is not reached from `entry` within `follow_imports` levels.
+    NotImported,
+    /// The file is not among the `top_k` most relevant to `relevant_to`.
+    Irrelevant,
+    /// The file is almost the same as another file, which is written instead.
+    NearDuplicate,
+    /// Including the file would exceed `max_tokens`.
+    Budget,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SkipReason::Directive => "directive",
+            SkipReason::LfsPointer => "LFS pointer",
+            SkipReason::TooLarge => "too large",
+            SkipReason::NotImported => "not imported",
+            SkipReason::Irrelevant => "not relevant",
+            SkipReason::NearDuplicate => "near duplicate",
+            SkipReason::Budget => "over budget",
+        })
+    }
+}
+
+/// A file selected for the output, with its size on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// The outcome of the selection stage.
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// The files to include, in output order.
+    pub included: Vec<Candidate>,
+    /// The files left out, with the reason why.
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+    /// The near-duplicates left out, with their similarity in percent, keyed
+    /// by the included file they are similar to.
+    pub near_duplicates: HashMap<PathBuf, Vec<(PathBuf, u8)>>,
+    /// The Git LFS pointers included with `lfs_fetch`, whose objects are
+    /// written in their place.
+    pub lfs_pointers: HashMap<PathBuf, Pointer>,
+}
+
+impl Selection {
+    /// The total size on disk of the included files.
+    pub fn total_size(&self) -> u64 {
+        self.included.iter().map(|candidate| candidate.size).sum()
+    }
+}
+
+/// Estimates the number of tokens of `bytes` bytes of text, using the common
+/// approximation of four bytes per token.
+pub fn estimate_tokens(bytes: u64) -> u64 {
+    bytes.div_ceil(4)
+}
+
+/// Applies the directives, the size limit, the import graph, the relevance
+/// ranking and the token budget of `options` to `paths`, which are taken in
+/// output order.
+///
+/// With `sort` set to churn, the files changed by the most recent commits are
+/// moved to the front, so that they are the last to go under a budget. Set to
+/// smart, docs, manifests and entry points are moved to the front, in that
+/// order, so that the orientation material is read first. Set to coverage,
+/// the least tested files are moved to the front.
+///
+/// With `entry`, only the entry file and the files it imports are kept, the
+/// entry first and then by distance in the import graph.
+///
+/// With `relevant_to`, only the `top_k` files that match the query best are
+/// kept (along with `priority=high` files), most relevant first.
+///
+/// With `near_duplicates`, a file at least that similar to an earlier file is
+/// left out, and listed with the earlier file in `Selection::near_duplicates`.
+///
+/// Under a token budget, files with a `priority=high` directive are moved to the
+/// front and always kept, and `priority=low` files are moved to the back; the
+/// budget is then spent in that order, and once it is spent, later files are
+/// left out.
+pub fn select(paths: Vec<PathBuf>, options: &JoinOptions) -> Selection {
+    let mut selection = Selection::default();
+    let mut candidates = Vec::new();
+    for path in paths {
+        // Unreadable files are kept, so they are reported when they are read.
+        let mut size = std::fs::metadata(paths::for_io(&path)).map_or(0, |m| m.len());
+        let directives = Directives::read(&path);
+        if directives.exclude {
+            selection.skipped.push((path, SkipReason::Directive));
+            continue;
+        }
+        // A pointer stands for its object, whose size counts against the limits.
+        if let Some(pointer) = Pointer::read(&path, size) {
+            if !options.lfs_fetch {
+                selection.skipped.push((path, SkipReason::LfsPointer));
+                continue;
+            }
+            size = pointer.size;
+            selection.lfs_pointers.insert(path.clone(), pointer);
+        }
+        if options.max_file_size.is_some_and(|max| size > max) {
+            selection.skipped.push((path, SkipReason::TooLarge));
+            continue;
+        }
+        candidates.push((Candidate { path, size }, directives.priority));
+    }
+    match options.sort {
+        SortOrder::Path => {}
+        // An explicit list of files keeps its own order.
+        _ if options.files.is_some() => {}
+        SortOrder::Churn => sort_by_churn(&mut candidates, options),
+        SortOrder::Coverage => sort_by_coverage(&mut candidates, options),
+        // A stable sort keeps the path order within each kind of file.
+        SortOrder::Smart => candidates.sort_by_cached_key(|(candidate, _)| {
+            let relative = candidate
+                .path
+                .strip_prefix(&options.input_folder)
+                .unwrap_or(&candidate.path);
+            match orientation(relative) {
+                Some(kind) => (kind, relative.components().count()),
+                None => (Orientation::Other, 0),
+            }
+        }),
+    }
+    if let Some(entry) = options.entry_path() {
+        candidates = follow_imports(candidates, &entry, options, &mut selection.skipped);
+    }
+    if let Some(query) = &options.relevant_to {
+        candidates = rank(candidates, query, options, &mut selection.skipped);
+    }
+    if let Some(threshold) = options.near_duplicates {
+        candidates = drop_near_duplicates(candidates, threshold, &mut selection);
+    }
+
+    let Some(max_tokens) = options.max_tokens else {
+        selection.included = candidates.into_iter().map(|(c, _)| c).collect();
+        return selection;
+    };
+    // A stable sort keeps the path order within each priority.
+    candidates.sort_by_key(|(_, priority)| *priority);
+    let mut tokens = 0;
+    for (candidate, priority) in candidates {
+        let file_tokens = estimate_tokens(candidate.size);
+        if priority != Priority::High && tokens + file_tokens > max_tokens {
+            selection.skipped.push((candidate.path, SkipReason::Budget));
+            continue;
+        }
+        tokens += file_tokens;
+        selection.included.push(candidate);
+    }
+    selection.skipped.sort();
+    selection
+}
+
+/// What a file tells a reader new to the project, in the order `SortOrder::Smart`
+/// puts them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Orientation {
+    Docs,
+    Manifest,
+    EntryPoint,
+    Other,
+}
+
+/// Files named like these (in any case, with any extension) describe the
+/// project as a whole.
+const DOC_NAMES: [&str; 5] = ["readme", "architecture", "design", "overview", "hacking"];
+
+/// Build and package manifests.
+const MANIFESTS: [&str; 13] = [
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "setup.cfg",
+    "requirements.txt",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "Gemfile",
+    "composer.json",
+    "CMakeLists.txt",
+];
+
+/// Files where programs and libraries start.
+const ENTRY_POINTS: [&str; 16] = [
+    "main.rs",
+    "lib.rs",
+    "main.py",
+    "__main__.py",
+    "app.py",
+    "main.go",
+    "index.js",
+    "index.ts",
+    "index.tsx",
+    "main.js",
+    "main.ts",
+    "Main.java",
+    "Main.kt",
+    "Program.cs",
+    "main.c",
+    "main.cpp",
+];
+
+/// Returns what the file at `relative`, a path relative to the input folder,
+/// is for a reader new to the project, or `None` for implementation files.
+/// Markdown files in a `docs` folder count as docs.
+fn orientation(relative: &std::path::Path) -> Option<Orientation> {
+    let name = relative.file_name()?.to_str()?;
+    let stem = name.split('.').next().unwrap_or(name).to_ascii_lowercase();
+    let in_docs = relative
+        .parent()
+        .is_some_and(|parent| parent.iter().any(|dir| dir == "docs" || dir == "doc"));
+    if DOC_NAMES.contains(&stem.as_str()) || (in_docs && name.ends_with(".md")) {
+        Some(Orientation::Docs)
+    } else if MANIFESTS.contains(&name) {
+        Some(Orientation::Manifest)
+    } else if ENTRY_POINTS.contains(&name) {
+        Some(Orientation::EntryPoint)
+    } else {
+        None
+    }
+}
+
+/// Orders `candidates` by the number of commits that changed them within
+/// `churn_days`, most first. Outside of a repository, the order is kept.
+#[cfg(feature = "git")]
+fn sort_by_churn(candidates: &mut [(Candidate, Priority)], options: &JoinOptions) {
+    match crate::git::Churn::discover(&options.input_folder, options.churn_days) {
+        // A stable sort keeps the path order between equally busy files.
+        Ok(churn) => candidates
+            .sort_by_cached_key(|(candidate, _)| std::cmp::Reverse(churn.commits(&candidate.path))),
+        Err(e) => events::warning(&format!(
+            "Files are sorted by path: the git history cannot be read ({}).",
+            e.message()
+        )),
+    }
+}
+
+#[cfg(not(feature = "git"))]
+fn sort_by_churn(_candidates: &mut [(Candidate, Priority)], _options: &JoinOptions) {}
+
+/// Orders `candidates` by the share of their lines run by the tests, least
+/// first, followed by the files the coverage report does not cover. Without a
+/// readable report, the order is kept.
+fn sort_by_coverage(candidates: &mut [(Candidate, Priority)], options: &JoinOptions) {
+    let Some(report) = &options.coverage else {
+        return;
+    };
+    match crate::coverage::Coverage::load(report, &options.input_folder) {
+        // A stable sort keeps the path order between equally tested files.
+        Ok(coverage) => candidates.sort_by_cached_key(|(candidate, _)| {
+            coverage
+                .of(&candidate.path)
+                .map_or((1, 0), |coverage| (0, coverage.basis_points()))
+        }),
+        Err(e) => events::warning(&format!("Files are sorted by path: {e:#}.")),
+    }
+}
+
+/// Keeps the `candidates` that `entry` reaches within `follow_imports` levels
+/// of imports, in the order they are reached, and adds the rest to `skipped`.
+fn follow_imports(
+    candidates: Vec<(Candidate, Priority)>,
+    entry: &std::path::Path,
+    options: &JoinOptions,
+    skipped: &mut Vec<(PathBuf, SkipReason)>,
+) -> Vec<(Candidate, Priority)> {
+    if !candidates
+        .iter()
+        .any(|(candidate, _)| candidate.path == entry)
+    {
+        events::warning(&format!(
+            "The entry file {} is not among the files selected by the patterns, so no file is included.",
+            options.display_path(entry).display()
+        ));
+    }
+    let files: Vec<PathBuf> = candidates.iter().map(|(c, _)| c.path.clone()).collect();
+    let reached = imports::closure(entry, &files, options.follow_imports);
+    let mut candidates: Vec<Option<(Candidate, Priority)>> =
+        candidates.into_iter().map(Some).collect();
+    let mut kept = Vec::new();
+    for path in reached {
+        if let Some(index) = files.iter().position(|file| *file == path)
+            && let Some(candidate) = candidates[index].take()
+        {
+            kept.push(candidate);
+        }
+    }
+    skipped.extend(
+        candidates
+            .into_iter()
+            .flatten()
+            .map(|(candidate, _)| (candidate.path, SkipReason::NotImported)),
+    );
+    kept
+}
+
+/// Orders `candidates` by their relevance to `query`, keeping the `top_k` with
+/// any relevance and every high-priority file, and adding the rest to `skipped`.
+fn rank(
+    candidates: Vec<(Candidate, Priority)>,
+    query: &str,
+    options: &JoinOptions,
+    skipped: &mut Vec<(PathBuf, SkipReason)>,
+) -> Vec<(Candidate, Priority)> {
+    let documents: Vec<Vec<String>> = candidates
+        .iter()
+        .map(|(candidate, _)| {
+            // Paths count twice: `auth/session.rs` is about sessions throughout.
+            let relative = candidate
+                .path
+                .strip_prefix(&options.input_folder)
+                .unwrap_or(&candidate.path)
+                .to_string_lossy()
+                .into_owned();
+            let mut content = Vec::new();
+            let _ = std::fs::File::open(paths::for_io(&candidate.path))
+                .and_then(|file| file.take(MAX_RANKED_LEN).read_to_end(&mut content));
+            let text = format!(
+                "{relative} {relative}\n{}",
+                String::from_utf8_lossy(&content)
+            );
+            relevance::terms(&text)
+        })
+        .collect();
+    let scores = relevance::scores(query, &documents);
+    let mut ranked: Vec<_> = candidates.into_iter().zip(scores).collect();
+    // A stable sort keeps the path order between equally relevant files.
+    ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    let mut kept = Vec::new();
+    let mut relevant = 0;
+    for ((candidate, priority), score) in ranked {
+        if priority == Priority::High || (score > 0.0 && relevant < options.top_k) {
+            relevant += usize::from(priority != Priority::High);
+            kept.push((candidate, priority));
+        } else {
+            skipped.push((candidate.path, SkipReason::Irrelevant));
+        }
+    }
+    kept
+}
+
+/// Leaves out the `candidates` that are at least `threshold` percent similar
+/// to an earlier one, recording them in `selection`. Small and binary files,
+/// and `priority=high` files, are always kept.
+fn drop_near_duplicates(
+    candidates: Vec<(Candidate, Priority)>,
+    threshold: u8,
+    selection: &mut Selection,
+) -> Vec<(Candidate, Priority)> {
+    let signatures: Vec<Option<Signature>> = candidates
+        .iter()
+        .map(|(candidate, _)| {
+            let mut content = Vec::new();
+            let _ = std::fs::File::open(paths::for_io(&candidate.path))
+                .and_then(|file| file.take(MAX_RANKED_LEN).read_to_end(&mut content));
+            (content.len() >= similarity::MIN_LEN && !content.contains(&0))
+                .then(|| Signature::of(&String::from_utf8_lossy(&content)))
+        })
+        .collect();
+    let found = similarity::near_duplicates(&signatures, threshold);
+    let originals: Vec<PathBuf> = candidates.iter().map(|(c, _)| c.path.clone()).collect();
+
+    let mut kept = Vec::new();
+    for ((candidate, priority), found) in candidates.into_iter().zip(found) {
+        match found {
+            Some((original, similarity)) if priority != Priority::High => {
+                selection
+                    .near_duplicates
+                    .entry(originals[original].clone())
+                    .or_default()
+                    .push((candidate.path.clone(), similarity));
+                selection
+                    .skipped
+                    .push((candidate.path, SkipReason::NearDuplicate));
+            }
+            _ => kept.push((candidate, priority)),
+        }
+    }
+    kept
+}
+
+/// Formats a size in bytes for humans, e.g. `12.3 KB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+// --- Unit Tests for File Selection ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies the size limit, that the budget is spent in path order, and
+    /// that exclusion directives apply regardless of both.
+    #[test]
+    fn test_size_and_budget_limits() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("a.txt").write_str(&"a".repeat(40))?;
+        dir.child("b.txt").write_str(&"b".repeat(400))?;
+        dir.child("c.txt").write_str(&"c".repeat(40))?;
+        dir.child("d.txt").write_str(&"d".repeat(40))?;
+        dir.child("e.rs").write_str("// join-ai: exclude\n")?;
+        let paths: Vec<PathBuf> = ["a.txt", "b.txt", "c.txt", "d.txt", "e.rs"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+
+        let mut options = JoinOptions::new(dir.path(), "out.txt");
+        options.max_file_size = Some(100);
+        options.max_tokens = Some(25);
+        let selection = select(paths, &options);
+
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(
+            included,
+            [&dir.path().join("a.txt"), &dir.path().join("c.txt")]
+        );
+        assert_eq!(
+            selection.skipped,
+            [
+                (dir.path().join("b.txt"), SkipReason::TooLarge),
+                (dir.path().join("d.txt"), SkipReason::Budget),
+                (dir.path().join("e.rs"), SkipReason::Directive),
+            ]
+        );
+        assert_eq!(selection.total_size(), 80);
+
+        Ok(())
+    }
+
+    /// Verifies that high-priority files are placed first and kept over the
+    /// budget, and that low-priority files are the first to be left out.
+    #[test]
+    fn test_priority_directives_under_budget() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("a.rs")
+            .write_str(&format!("// join-ai: priority=low\n{}", "a".repeat(20)))?;
+        dir.child("b.rs").write_str(&"b".repeat(40))?;
+        dir.child("c.rs")
+            .write_str(&format!("// join-ai: priority=high\n{}", "c".repeat(80)))?;
+        let paths: Vec<PathBuf> = ["a.rs", "b.rs", "c.rs"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+
+        let mut options = JoinOptions::new(dir.path(), "out.txt");
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[0], &paths[1], &paths[2]]);
+
+        options.max_tokens = Some(40);
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[2], &paths[1]]);
+        assert_eq!(selection.skipped, [(paths[0].clone(), SkipReason::Budget)]);
+
+        // The high-priority file alone is over this budget, but is still kept.
+        options.max_tokens = Some(10);
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[2]]);
+
+        Ok(())
+    }
+
+    /// Verifies that `--sort smart` puts docs, manifests and entry points first,
+    /// shallowest first, and keeps the path order of the rest.
+    #[test]
+    fn test_smart_order() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let names = [
+            "Cargo.toml",
+            "README.md",
+            "crates/core/Cargo.toml",
+            "crates/core/README.md",
+            "docs/guide.md",
+            "src/cli.rs",
+            "src/main.rs",
+            "src/util.rs",
+        ];
+        for name in names {
+            dir.child(name).write_str("x")?;
+        }
+        let paths: Vec<PathBuf> = names.iter().map(|name| dir.path().join(name)).collect();
+
+        let mut options = JoinOptions::new(dir.path(), "out.txt");
+        options.sort = SortOrder::Smart;
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&str> = selection
+            .included
+            .iter()
+            .map(|c| names[paths.iter().position(|path| *path == c.path).unwrap()])
+            .collect();
+        assert_eq!(
+            included,
+            [
+                "README.md",
+                "docs/guide.md",
+                "crates/core/README.md",
+                "Cargo.toml",
+                "crates/core/Cargo.toml",
+                "src/main.rs",
+                "src/cli.rs",
+                "src/util.rs",
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// Verifies that `--sort churn` puts the most changed files first, so they
+    /// are the ones kept under a budget.
+    #[cfg(feature = "git")]
+    #[test]
+    fn test_churn_order_under_budget() -> anyhow::Result<()> {
+        use crate::git::tests::commit_all;
+        let dir = TempDir::new()?;
+        let repo = git2::Repository::init(dir.path())?;
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            dir.child(name).write_str(&name.repeat(10))?;
+        }
+        commit_all(&repo, "Add files");
+        dir.child("c.rs").write_str(&"c".repeat(40))?;
+        commit_all(&repo, "Change c");
+        let paths: Vec<PathBuf> = ["a.rs", "b.rs", "c.rs"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+
+        let mut options = JoinOptions::new(dir.path(), "out.txt");
+        options.sort = SortOrder::Churn;
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[2], &paths[0], &paths[1]]);
+
+        options.max_tokens = Some(20);
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[2], &paths[0]]);
+
+        Ok(())
+    }
+
+    /// Verifies that `--sort coverage` puts the least tested files first, and
+    /// the files the report does not cover last.
+    #[test]
+    fn test_coverage_order() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        for name in ["a.rs", "b.rs", "c.rs", "README.md"] {
+            dir.child(name).write_str(name)?;
+        }
+        dir.child("lcov.info").write_str(
+            "SF:a.rs\nDA:1,1\nDA:2,1\nend_of_record\n\
+             SF:b.rs\nDA:1,0\nDA:2,0\nend_of_record\n\
+             SF:c.rs\nDA:1,1\nDA:2,0\nend_of_record\n",
+        )?;
+        let paths: Vec<PathBuf> = ["README.md", "a.rs", "b.rs", "c.rs"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+
+        let mut options = JoinOptions::new(dir.path(), "out.txt");
+        options.sort = SortOrder::Coverage;
+        options.coverage = Some(dir.path().join("lcov.info"));
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[2], &paths[3], &paths[1], &paths[0]]);
+
+        Ok(())
+    }
+
+    /// Verifies that only the most relevant files are kept, best first, and
+    /// that the budget is then spent in that order.
+    #[test]
+    fn test_relevant_files() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("auth/session.rs")
+            .write_str("fn refresh_token(session: &Session) {}\n")?;
+        dir.child("auth/token.rs")
+            .write_str("struct Token;\nfn refresh(token: Token) {}\nfn refreshToken() {}\n")?;
+        dir.child("ui/render.rs").write_str("fn render() {}\n")?;
+        dir.child("ui/button.rs").write_str("fn click() {}\n")?;
+        let paths: Vec<PathBuf> = [
+            "auth/session.rs",
+            "auth/token.rs",
+            "ui/button.rs",
+            "ui/render.rs",
+        ]
+        .iter()
+        .map(|name| dir.path().join(name))
+        .collect();
+
+        let mut options = JoinOptions::new(dir.path(), "out.txt");
+        options.relevant_to = Some("How does token refresh work?".to_string());
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[1], &paths[0]]);
+        assert_eq!(
+            selection.skipped,
+            [
+                (paths[2].clone(), SkipReason::Irrelevant),
+                (paths[3].clone(), SkipReason::Irrelevant),
+            ]
+        );
+
+        options.top_k = 1;
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[1]]);
+
+        Ok(())
+    }
+
+    /// Verifies that only the entry file and what it imports are kept, the
+    /// entry first, and that the rest is reported as not imported.
+    #[test]
+    fn test_entry_and_its_imports() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("src/lib.rs").write_str(
+            "mod api;
+mod db;
+",
+        )?;
+        dir.child("src/api.rs").write_str(
+            "use crate::db::Pool;
+",
+        )?;
+        dir.child("src/db.rs").write_str(
+            "pub struct Pool;
+",
+        )?;
+        dir.child("src/cli.rs").write_str(
+            "fn main() {}
+",
+        )?;
+        let paths: Vec<PathBuf> = ["src/api.rs", "src/cli.rs", "src/db.rs", "src/lib.rs"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+
+        let mut options = JoinOptions::new(dir.path(), "out.txt");
+        options.entry = Some(PathBuf::from("./src/api.rs"));
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[0], &paths[2]]);
+        assert_eq!(
+            selection.skipped,
+            [
+                (paths[1].clone(), SkipReason::NotImported),
+                (paths[3].clone(), SkipReason::NotImported),
+            ]
+        );
+
+        options.entry = Some(PathBuf::from("src/lib.rs"));
+        options.follow_imports = 0;
+        let selection = select(paths.clone(), &options);
+        let included: Vec<&PathBuf> = selection.included.iter().map(|c| &c.path).collect();
+        assert_eq!(included, [&paths[3]]);
+
+        Ok(())
+    }
+
+    /// Verifies human-readable sizes.
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(12_595), "12.3 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+    }
+}
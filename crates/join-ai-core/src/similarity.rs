@@ -0,0 +1,161 @@
+//! Near-duplicate detection (`--near-duplicates`): files that are almost the
+//! same as an earlier file, such as copied modules or generated variants, are
+//! written once.
+//!
+//! Each file is summarized by a MinHash signature of the runs of three words
+//! in it, whose share of equal values estimates how many of these runs two
+//! files have in common. Signatures are bucketed by bands (locality-sensitive
+//! hashing), so only files that share a band are compared, and a large join
+//! does not compare every pair of files.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// The number of values of a signature.
+const SIGNATURE_LEN: usize = 128;
+
+/// The number of values hashed together into a bucket. With 16 bands of 8,
+/// files 90% similar share a bucket with near certainty, and files 50%
+/// similar rarely do.
+const BAND_LEN: usize = 8;
+
+/// Files shorter than this are never merged: small files such as empty
+/// `__init__.py` modules are alike without being copies.
+pub const MIN_LEN: usize = 256;
+
+/// The words of a run that is hashed.
+const SHINGLE_LEN: usize = 3;
+
+/// The MinHash signature of a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature([u64; SIGNATURE_LEN]);
+
+impl Signature {
+    /// Computes the signature of `text`, whose words are compared regardless
+    /// of the whitespace between them.
+    pub fn of(text: &str) -> Self {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut values = [u64::MAX; SIGNATURE_LEN];
+        for shingle in words.windows(SHINGLE_LEN.min(words.len()).max(1)) {
+            let mut hasher = DefaultHasher::new();
+            shingle.hash(&mut hasher);
+            let hash = hasher.finish();
+            for (seed, value) in values.iter_mut().enumerate() {
+                *value =
+                    (*value).min(mix(hash ^ (seed as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+            }
+        }
+        Self(values)
+    }
+
+    /// The estimated similarity of two texts, in percent.
+    pub fn similarity(&self, other: &Self) -> u8 {
+        let equal = self.0.iter().zip(&other.0).filter(|(a, b)| a == b).count();
+        (equal * 100 / SIGNATURE_LEN) as u8
+    }
+
+    /// The keys of the buckets of the signature, one per band.
+    fn bands(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.0.chunks(BAND_LEN).enumerate().map(|(band, values)| {
+            let mut hasher = DefaultHasher::new();
+            values.hash(&mut hasher);
+            (band, hasher.finish())
+        })
+    }
+}
+
+/// A 64-bit finalizer (from SplitMix64), so that each seed gives an
+/// independent permutation of the hashes.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1330_11EB);
+    x ^ (x >> 31)
+}
+
+/// Groups the near-duplicates among `signatures`, given in output order
+/// (`None` for files that are never merged). Returns, for each file that is
+/// at least `threshold` percent similar to an earlier file, the index of the
+/// most similar earlier file that is kept, and their similarity.
+pub fn near_duplicates(
+    signatures: &[Option<Signature>],
+    threshold: u8,
+) -> Vec<Option<(usize, u8)>> {
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    let mut found = vec![None; signatures.len()];
+    for (index, signature) in signatures.iter().enumerate() {
+        let Some(signature) = signature else {
+            continue;
+        };
+        let mut best: Option<(usize, u8)> = None;
+        for key in signature.bands() {
+            for &kept in buckets.get(&key).into_iter().flatten() {
+                let Some(other) = &signatures[kept] else {
+                    continue;
+                };
+                let similarity = signature.similarity(other);
+                if similarity >= threshold && best.is_none_or(|(_, best)| similarity > best) {
+                    best = Some((kept, similarity));
+                }
+            }
+        }
+        match best {
+            Some(best) => found[index] = Some(best),
+            // Only kept files are compared against, so duplicates of a
+            // duplicate are listed under the file that is written.
+            None => {
+                for key in signature.bands() {
+                    buckets.entry(key).or_default().push(index);
+                }
+            }
+        }
+    }
+    found
+}
+
+// --- Unit Tests for Near-Duplicate Detection ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a file of `n` distinct lines, with `changed` of them edited.
+    fn module(n: usize, changed: usize) -> String {
+        (0..n)
+            .map(|i| match i < changed {
+                true => format!("let edited_{i} = compute({i}, \"other\");\n"),
+                false => format!("let value_{i} = compute({i}, \"input\");\n"),
+            })
+            .collect()
+    }
+
+    /// Verifies that near-copies are grouped under the first file, and that
+    /// different files are not.
+    #[test]
+    fn test_near_duplicates() {
+        let files = [
+            module(200, 0),
+            module(200, 2),
+            "fn unrelated() { println!(\"hello\"); }\n".repeat(40),
+            module(200, 120),
+        ];
+        let signatures: Vec<Option<Signature>> =
+            files.iter().map(|text| Some(Signature::of(text))).collect();
+        assert_eq!(
+            signatures[0]
+                .as_ref()
+                .unwrap()
+                .similarity(signatures[0].as_ref().unwrap()),
+            100
+        );
+
+        let found = near_duplicates(&signatures, 90);
+        assert_eq!(found[0], None);
+        assert!(matches!(found[1], Some((0, similarity)) if similarity >= 90));
+        assert_eq!(found[2], None);
+        assert_eq!(found[3], None);
+
+        // Files that are never merged are neither kept nor compared.
+        let found = near_duplicates(&[None, signatures[1].clone()], 90);
+        assert_eq!(found, [None, None]);
+    }
+}
@@ -0,0 +1,346 @@
+//! The symbol index (`--symbols`): the functions, types and classes defined in
+//! the joined files, with the file and line of each, appended to the output.
+//!
+//! A model can look a name up in the index, as in the index of a book, and go
+//! to its definition instead of scanning the whole output. Definitions are
+//! found with regular expressions per language (Rust, Python,
+//! JavaScript/TypeScript, Go, Java/Kotlin/Scala/C#, C/C++ and Ruby), which
+//! catch the usual forms at the start of a line; line numbers count from the
+//! start of each file as written in the output.
+
+use crate::format::OutputFormat;
+use regex::Regex;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// A definition found in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// What is defined: `function`, `struct`, `class`, `interface`, ...
+    pub kind: &'static str,
+    /// The line of the definition, from 1.
+    pub line: usize,
+}
+
+/// The patterns of one language, and the extensions of its files. Each
+/// pattern captures the defined `name`, and either the keyword that tells its
+/// kind (`kw`) or a fixed kind.
+struct Language {
+    extensions: &'static [&'static str],
+    patterns: Vec<(Regex, Option<&'static str>)>,
+}
+
+fn languages() -> &'static [Language] {
+    static LANGUAGES: OnceLock<Vec<Language>> = OnceLock::new();
+    LANGUAGES.get_or_init(|| {
+        let regex = |pattern| Regex::new(pattern).expect("built-in symbol pattern is valid");
+        vec![
+            Language {
+                extensions: &["rs"],
+                patterns: vec![
+                    (
+                        regex(
+                            r#"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:const|async|unsafe|extern\s+"[^"]*")\s+)*(?P<kw>fn|struct|enum|trait|union|type|mod)\s+(?P<name>\w+)"#,
+                        ),
+                        None,
+                    ),
+                    (regex(r"(?m)^\s*macro_rules!\s*(?P<name>\w+)"), Some("macro")),
+                ],
+            },
+            Language {
+                extensions: &["py", "pyi"],
+                patterns: vec![(
+                    regex(r"(?m)^\s*(?:async\s+)?(?P<kw>def|class)\s+(?P<name>\w+)"),
+                    None,
+                )],
+            },
+            Language {
+                extensions: &["js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts"],
+                patterns: vec![
+                    (
+                        regex(
+                            r"(?m)^\s*(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(?P<kw>function|class|interface|enum|type)\*?\s+(?P<name>[\w$]+)",
+                        ),
+                        None,
+                    ),
+                    (
+                        regex(
+                            r"(?m)^\s*(?:export\s+)?(?:const|let)\s+(?P<name>[\w$]+)\s*(?::[^=]+)?=\s*(?:async\s*)?(?:\([^)]*\)|[\w$]+)\s*(?::[^=]+)?=>",
+                        ),
+                        Some("function"),
+                    ),
+                ],
+            },
+            Language {
+                extensions: &["go"],
+                patterns: vec![
+                    (
+                        regex(r"(?m)^(?P<kw>func)\s+(?:\([^)]*\)\s*)?(?P<name>\w+)"),
+                        None,
+                    ),
+                    (regex(r"(?m)^(?P<kw>type)\s+(?P<name>\w+)"), None),
+                ],
+            },
+            Language {
+                extensions: &["java", "kt", "kts", "scala", "cs"],
+                patterns: vec![
+                    (
+                        regex(
+                            r"(?m)^\s*(?:(?:public|private|protected|internal|static|final|abstract|sealed|partial|data|open|case|inner)\s+)*(?P<kw>class|interface|enum|record|object|trait|struct)\s+(?P<name>\w+)",
+                        ),
+                        None,
+                    ),
+                    (
+                        regex(
+                            r"(?m)^\s*(?:(?:public|private|protected|internal|static|final|abstract|override|suspend|inline|open)\s+)*(?P<kw>fun|def)\s+(?:<[^>]*>\s*)?(?:\w+\.)?(?P<name>\w+)",
+                        ),
+                        None,
+                    ),
+                    (
+                        regex(
+                            r"(?m)^\s*(?:(?:public|private|protected|internal|static|final|abstract|synchronized|override|virtual|async)\s+)+[\w<>\[\],.?]+\s+(?P<name>\w+)\s*\(",
+                        ),
+                        Some("method"),
+                    ),
+                ],
+            },
+            Language {
+                extensions: &["c", "h", "cc", "cpp", "cxx", "hpp", "hh", "hxx"],
+                patterns: vec![
+                    (
+                        regex(
+                            r"(?m)^(?:typedef\s+)?(?P<kw>struct|class|enum|union)\s+(?P<name>\w+)\s*(?:[:{][^;\n]*)?$",
+                        ),
+                        None,
+                    ),
+                    (
+                        regex(
+                            r"(?m)^(?:[A-Za-z_][\w:<>,]*[\s*&]+)+\**(?P<name>[A-Za-z_][\w:~]*)\s*\([^;]*$",
+                        ),
+                        Some("function"),
+                    ),
+                ],
+            },
+            Language {
+                extensions: &["rb"],
+                patterns: vec![(
+                    regex(r"(?m)^\s*(?P<kw>def|class|module)\s+(?:self\.)?(?P<name>[\w:]+[?!=]?)"),
+                    None,
+                )],
+            },
+        ]
+    })
+}
+
+/// Names a definition by the keyword that introduces it.
+fn kind(keyword: &str) -> &'static str {
+    match keyword {
+        "fn" | "def" | "func" | "fun" | "function" => "function",
+        "struct" => "struct",
+        "enum" => "enum",
+        "trait" => "trait",
+        "union" => "union",
+        "mod" | "module" => "module",
+        "class" => "class",
+        "interface" => "interface",
+        "record" => "record",
+        "object" => "object",
+        _ => "type",
+    }
+}
+
+/// Finds the definitions in `content`, the text of the file at `path`, in
+/// line order. Files of other languages have none.
+pub fn extract(path: &Path, content: &[u8]) -> Vec<Symbol> {
+    let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+        return Vec::new();
+    };
+    let Some(language) = languages()
+        .iter()
+        .find(|language| language.extensions.contains(&extension))
+    else {
+        return Vec::new();
+    };
+    let text = String::from_utf8_lossy(content);
+    let mut found: Vec<(usize, Symbol)> = Vec::new();
+    for (pattern, fixed) in &language.patterns {
+        for captures in pattern.captures_iter(&text) {
+            let Some(name) = captures.name("name") else {
+                continue;
+            };
+            let kind =
+                fixed.unwrap_or_else(|| kind(captures.name("kw").map_or("", |kw| kw.as_str())));
+            // Control flow that looks like a C function or a Java method.
+            if ["if", "for", "while", "switch", "return", "catch"].contains(&name.as_str()) {
+                continue;
+            }
+            found.push((
+                name.start(),
+                Symbol {
+                    name: name.as_str().to_string(),
+                    kind,
+                    line: 0,
+                },
+            ));
+        }
+    }
+    // Definitions matched by several patterns are listed once.
+    found.sort_by_key(|(offset, _)| *offset);
+    found.dedup_by_key(|(offset, _)| *offset);
+    let mut line = 1;
+    let mut counted = 0;
+    found
+        .into_iter()
+        .map(|(offset, mut symbol)| {
+            line += memchr::memchr_iter(b'\n', &text.as_bytes()[counted..offset]).count();
+            counted = offset;
+            symbol.line = line;
+            symbol
+        })
+        .collect()
+}
+
+/// Renders the definitions of each file as the index section of the output,
+/// sorted by name.
+pub fn render(files: &[(PathBuf, Vec<Symbol>)], format: OutputFormat) -> String {
+    let mut entries: Vec<(&Symbol, &Path)> = files
+        .iter()
+        .flat_map(|(path, symbols)| symbols.iter().map(move |symbol| (symbol, path.as_path())))
+        .collect();
+    entries.sort_by(|(a, a_path), (b, b_path)| {
+        (a.name.to_lowercase(), &a.name, a_path, a.line).cmp(&(
+            b.name.to_lowercase(),
+            &b.name,
+            b_path,
+            b.line,
+        ))
+    });
+    let mut text = match format {
+        OutputFormat::Text => "// SYMBOL INDEX\n".to_string(),
+        OutputFormat::Markdown => "## Symbol Index\n\n".to_string(),
+    };
+    for (symbol, path) in entries {
+        let _ = match format {
+            OutputFormat::Text => writeln!(
+                text,
+                "// {} ({}) {}:{}",
+                symbol.name,
+                symbol.kind,
+                path.display(),
+                symbol.line
+            ),
+            OutputFormat::Markdown => writeln!(
+                text,
+                "- `{}` ({}): `{}:{}`",
+                symbol.name,
+                symbol.kind,
+                path.display(),
+                symbol.line
+            ),
+        };
+    }
+    text.push('\n');
+    text
+}
+
+// --- Unit Tests for the Symbol Index ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(path: &str, content: &str) -> Vec<(String, &'static str, usize)> {
+        extract(Path::new(path), content.as_bytes())
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.line))
+            .collect()
+    }
+
+    /// Verifies that definitions are found with their kind and line in several
+    /// languages, and that other files have none.
+    #[test]
+    fn test_extract() {
+        assert_eq!(
+            names(
+                "lib.rs",
+                "use std::fs;\n\npub struct Config {}\n\nimpl Config {\n    pub(crate) async fn load() {}\n}\nmacro_rules! log {}\n"
+            ),
+            [
+                ("Config".to_string(), "struct", 3),
+                ("load".to_string(), "function", 6),
+                ("log".to_string(), "macro", 8),
+            ]
+        );
+        assert_eq!(
+            names(
+                "app.py",
+                "class App:\n    async def run(self):\n        pass\n"
+            ),
+            [
+                ("App".to_string(), "class", 1),
+                ("run".to_string(), "function", 2)
+            ]
+        );
+        assert_eq!(
+            names(
+                "api.ts",
+                "export interface User {}\nexport const fetchUser = async (id: string) => {};\nexport default function main() {}\n"
+            ),
+            [
+                ("User".to_string(), "interface", 1),
+                ("fetchUser".to_string(), "function", 2),
+                ("main".to_string(), "function", 3),
+            ]
+        );
+        assert_eq!(
+            names(
+                "main.go",
+                "type Server struct{}\n\nfunc (s *Server) Start() {}\n"
+            ),
+            [
+                ("Server".to_string(), "type", 1),
+                ("Start".to_string(), "function", 3)
+            ]
+        );
+        assert_eq!(
+            names(
+                "util.c",
+                "static int add(int a, int b)\n{\n    if (a) {}\n}\n"
+            ),
+            [("add".to_string(), "function", 1)]
+        );
+        assert!(names("notes.txt", "fn main() {}\n").is_empty());
+    }
+
+    /// Verifies that the index is sorted by name in both formats.
+    #[test]
+    fn test_render() {
+        let files = vec![
+            (
+                PathBuf::from("src/lib.rs"),
+                vec![Symbol {
+                    name: "parse".to_string(),
+                    kind: "function",
+                    line: 12,
+                }],
+            ),
+            (
+                PathBuf::from("src/main.rs"),
+                vec![Symbol {
+                    name: "Args".to_string(),
+                    kind: "struct",
+                    line: 3,
+                }],
+            ),
+        ];
+        assert_eq!(
+            render(&files, OutputFormat::Text),
+            "// SYMBOL INDEX\n// Args (struct) src/main.rs:3\n// parse (function) src/lib.rs:12\n\n"
+        );
+        assert_eq!(
+            render(&files, OutputFormat::Markdown),
+            "## Symbol Index\n\n- `Args` (struct): `src/main.rs:3`\n- `parse` (function): `src/lib.rs:12`\n\n"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Output verification (`--verify`): the written output is read back the way a
+//! tool splitting it into files would, and each file must come back byte for
+//! byte.
+//!
+//! In the text format a file ends where the next header starts, so a file with
+//! a line of its own that starts with `// FILE: ` (or `// SECTION: `, or reads
+//! `// SYMBOL INDEX`) cannot be told apart from the files around it. In
+//! Markdown a file ends at its closing fence, which is chosen longer than the
+//! backticks of its first bytes; a large file with a longer run further down
+//! closes its block early. Markdown code blocks always end with a line break,
+//! so a file without a final one is read back as equal to the file with it.
+
+use crate::format::OutputFormat;
+use std::hash::{DefaultHasher, Hasher};
+use std::path::PathBuf;
+
+/// What a file is checked against once the output is written: the hash of its
+/// content as written, and whether that content ends with a line break (or is
+/// empty).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    hash: u64,
+    newline: bool,
+}
+
+impl Fingerprint {
+    /// Fingerprints the content of a file as it is written to the output.
+    pub fn of(content: &[u8]) -> Self {
+        Self {
+            hash: hash(content),
+            newline: content.last().is_none_or(|&b| b == b'\n'),
+        }
+    }
+}
+
+fn hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(content);
+    hasher.finish()
+}
+
+/// Splits `output` into the files it holds, each with the path of its header
+/// and its content, in output order. Sections and indexes around the files
+/// are left out.
+pub fn parse(output: &[u8], format: OutputFormat) -> Vec<(PathBuf, &[u8])> {
+    match format {
+        OutputFormat::Text => parse_text(output),
+        OutputFormat::Markdown => parse_markdown(output),
+    }
+}
+
+/// The lines of `output`, each with the offset where it starts, without line
+/// breaks.
+fn lines(output: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut start = 0;
+    output
+        .split(|&b| b == b'\n')
+        .map(|line| {
+            let at = start;
+            start += line.len() + 1;
+            (at, line)
+        })
+        .collect()
+}
+
+fn path(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn parse_text(output: &[u8]) -> Vec<(PathBuf, &[u8])> {
+    let lines = lines(output);
+    let mut files = Vec::new();
+    // The path of the file being read, and where its content starts.
+    let mut current: Option<(PathBuf, usize)> = None;
+    // Everything up to the blank line that closes the file is its content.
+    let mut close = |current: Option<(PathBuf, usize)>, end: usize| {
+        if let Some((path, start)) = current {
+            let content = &output[start.min(end)..end];
+            files.push((path, content.strip_suffix(b"\n").unwrap_or(content)));
+        }
+    };
+    let mut i = 0;
+    while i < lines.len() {
+        let (start, line) = lines[i];
+        i += 1;
+        if line.starts_with(b"// SECTION: ") || line == b"// SYMBOL INDEX" {
+            close(current.take(), start);
+        } else if let Some(header) = line.strip_prefix(b"// FILE: ") {
+            close(current.take(), start);
+            while lines.get(i).is_some_and(|(_, line)| {
+                line.starts_with(b"// LAST COMMIT: ")
+                    || line.starts_with(b"// HARDLINKS: ")
+                    || line.starts_with(b"// OWNERS: ")
+                    || line.starts_with(b"// COVERAGE: ")
+                    || line.starts_with(b"// NEAR DUPLICATES: ")
+            }) {
+                i += 1;
+            }
+            let content = lines.get(i).map_or(output.len(), |(start, _)| *start);
+            current = Some((path(header), content));
+        }
+    }
+    close(current, output.len());
+    files
+}
+
+fn parse_markdown(output: &[u8]) -> Vec<(PathBuf, &[u8])> {
+    let lines = lines(output);
+    let mut files = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let (_, line) = lines[i];
+        i += 1;
+        let Some(header) = line.strip_prefix(b"## ") else {
+            continue;
+        };
+        // A heading is a file when a code block follows it, after the blank
+        // line and the metadata list.
+        let mut next = i;
+        if lines.get(next).is_some_and(|(_, line)| line.is_empty()) {
+            next += 1;
+        }
+        let meta = |line: &[u8]| {
+            line.starts_with(b"- Last commit: ")
+                || line.starts_with(b"- Hardlinks: ")
+                || line.starts_with(b"- Owners: ")
+                || line.starts_with(b"- Coverage: ")
+                || line.starts_with(b"- Near duplicates: ")
+        };
+        if lines.get(next).is_some_and(|(_, line)| meta(line)) {
+            while lines.get(next).is_some_and(|(_, line)| meta(line)) {
+                next += 1;
+            }
+            if lines.get(next).is_some_and(|(_, line)| line.is_empty()) {
+                next += 1;
+            }
+        }
+        let Some(&(_, opening)) = lines.get(next) else {
+            continue;
+        };
+        let ticks = opening.iter().take_while(|&&b| b == b'`').count();
+        if ticks < 3 || opening[ticks..].contains(&b'`') {
+            continue;
+        }
+        let fence = &opening[..ticks];
+        let start = lines
+            .get(next + 1)
+            .map_or(output.len(), |(start, _)| *start);
+        let end = match lines[next + 1..]
+            .iter()
+            .position(|(_, line)| *line == fence)
+        {
+            Some(closing) => {
+                i = next + 2 + closing;
+                lines[next + 1 + closing].0
+            }
+            None => {
+                i = lines.len();
+                output.len()
+            }
+        };
+        files.push((path(header), &output[start.min(end)..end]));
+    }
+    files
+}
+
+/// Reads back the files of `output`, written in `format`, and returns the
+/// paths of the `expected` files (in output order) whose content does not
+/// come back as written, or does not come back at all.
+pub fn check(
+    output: &[u8],
+    format: OutputFormat,
+    expected: &[(PathBuf, Fingerprint)],
+) -> Vec<PathBuf> {
+    let parsed = parse(output, format);
+    let mut failed = Vec::new();
+    let mut at = 0;
+    for (path, fingerprint) in expected {
+        // A file split in two by a line that reads as a header is followed by
+        // a file that was never joined, which is passed over.
+        let Some(found) = parsed[at..].iter().position(|(parsed, _)| parsed == path) else {
+            failed.push(path.clone());
+            continue;
+        };
+        let content = parsed[at + found].1;
+        at += found + 1;
+        let content = match (format, fingerprint.newline) {
+            (OutputFormat::Markdown, false) => content.strip_suffix(b"\n").unwrap_or(content),
+            _ => content,
+        };
+        if hash(content) != fingerprint.hash {
+            failed.push(path.clone());
+        }
+    }
+    failed
+}
+
+// --- Unit Tests for Output Verification ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'a>(files: &[(PathBuf, &'a [u8])]) -> Vec<(String, &'a str)> {
+        files
+            .iter()
+            .map(|(path, content)| {
+                (
+                    path.display().to_string(),
+                    std::str::from_utf8(content).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    /// Verifies that files are read back from both formats, past metadata,
+    /// sections and indexes.
+    #[test]
+    fn test_parse() {
+        let text = b"// REPOSITORY: app\n\n// SECTION: src/ (2 files, ~5 tokens)\n\n\
+            // FILE: src/a.rs\n// LAST COMMIT: abc Fix\nfn a() {}\n\n\
+            // FILE: src/b.rs\nfn b() {}\n\n// FILE: empty\n\n\
+            // SYMBOL INDEX\n// a (function) src/a.rs:1\n\n";
+        assert_eq!(
+            texts(&parse(text, OutputFormat::Text)),
+            [
+                ("src/a.rs".to_string(), "fn a() {}\n"),
+                ("src/b.rs".to_string(), "fn b() {}\n"),
+                ("empty".to_string(), ""),
+            ]
+        );
+
+        let markdown = b"## Dependencies\n\n- serde\n\n# src/\n\n1 file, ~5 tokens.\n\n\
+            ## src/a.rs\n\n- Last commit: abc Fix\n\n```rust\nfn a() {}\n```\n\n\
+            ## README.md\n\n````markdown\n```sh\nls\n```\n````\n\n\
+            ## Symbol Index\n\n- `a` (function): `src/a.rs:1`\n\n";
+        assert_eq!(
+            texts(&parse(markdown, OutputFormat::Markdown)),
+            [
+                ("src/a.rs".to_string(), "fn a() {}\n"),
+                ("README.md".to_string(), "```sh\nls\n```\n"),
+            ]
+        );
+    }
+
+    /// Verifies that a file with a line that reads as a header fails the
+    /// check, and that the files around it pass.
+    #[test]
+    fn test_check() {
+        let expected = |files: &[(&str, &[u8])]| -> Vec<(PathBuf, Fingerprint)> {
+            files
+                .iter()
+                .map(|(path, content)| (PathBuf::from(path), Fingerprint::of(content)))
+                .collect()
+        };
+        let ambiguous: &[u8] = b"let s = \"\n// FILE: fake.rs\n\";\n";
+        let text = b"// FILE: a.rs\nfn a() {}\n\n\
+            // FILE: tricky.rs\nlet s = \"\n// FILE: fake.rs\n\";\n\n\
+            // FILE: b.rs\nfn b() {}\n";
+        let files = expected(&[
+            ("a.rs", b"fn a() {}\n"),
+            ("tricky.rs", ambiguous),
+            ("b.rs", b"fn b() {}"),
+        ]);
+        assert_eq!(
+            check(text, OutputFormat::Text, &files),
+            [PathBuf::from("tricky.rs")]
+        );
+
+        let markdown = b"## tricky.rs\n\n```rust\nlet s = \"\n// FILE: fake.rs\n\";\n```\n\n\
+            ## b.rs\n\n```rust\nfn b() {}\n```\n\n";
+        let files = expected(&[("tricky.rs", ambiguous), ("b.rs", b"fn b() {}")]);
+        assert!(check(markdown, OutputFormat::Markdown, &files).is_empty());
+
+        let files = expected(&[("tricky.rs", ambiguous), ("missing.rs", b"")]);
+        assert_eq!(
+            check(markdown, OutputFormat::Markdown, &files),
+            [PathBuf::from("missing.rs")]
+        );
+    }
+}
@@ -0,0 +1,584 @@
+use crate::codeowners::CodeOwners;
+use crate::config::NeverInclude;
+use crate::events;
+use crate::options::{JoinOptions, SubmoduleMode};
+use crate::workspace;
+use anyhow::Context;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{Match, WalkBuilder, WalkState};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc};
+
+/// The ignore file of join-ai in the input folder, in gitignore syntax, which
+/// applies to joins only (e.g., as written by `join-ai suggest --apply`).
+/// Unlike `.gitignore` files, include patterns do not bring its paths back.
+pub const IGNORE_FILE: &str = ".joinignore";
+
+/// This module is responsible for efficiently finding all files that match the
+/// user's criteria using the `ignore` crate, which is excellent at respecting
+/// rules like `.gitignore` and handling parallel directory traversal.
+///
+/// The walker runs in a separate thread pool and sends valid file paths back to the
+/// main thread through a multi-producer, single-consumer (mpsc) channel.
+///
+/// # Arguments
+/// * `options` - A reference to the `JoinOptions` describing what to select.
+///
+/// # Returns
+/// A `Result` containing the receiver end of the channel, which will be used by
+/// the processor to receive file paths.
+pub fn find_files(options: &JoinOptions) -> anyhow::Result<mpsc::Receiver<PathBuf>> {
+    // Create a channel for communication between the walker threads and the main thread.
+    let (tx, rx) = mpsc::channel();
+    let input_folder = options.input_folder.clone();
+
+    // The `never_include` lists of the repository apply whatever the options.
+    let never_include = NeverInclude::discover(&input_folder)?;
+    never_include.check_requests(options)?;
+    let never_include = Arc::new(never_include);
+    let owners = Arc::new(owner_filter(options)?);
+
+    // --- 1. Configure the base walker ---
+    // When packages are requested, only their folders are walked. The overrides
+    // stay rooted at the input folder, so patterns behave the same either way.
+    let roots = match &options.packages {
+        Some(names) => package_roots(options, names)?,
+        None => vec![input_folder.clone()],
+    };
+    let mut walker_builder = WalkBuilder::new(&roots[0]);
+    for root in &roots[1..] {
+        walker_builder.add(root);
+    }
+    walker_builder
+        .follow_links(options.follow_links)
+        .max_depth(options.max_depth);
+
+    // --- 2. Apply the override rules for inclusion and exclusion ---
+    walker_builder.overrides(build_overrides(options)?);
+
+    // Prune git submodules unless they should be traversed like regular folders,
+    // and the paths of the `.joinignore` file.
+    let submodule_paths = match options.submodules {
+        SubmoduleMode::Include => Vec::new(),
+        _ => submodule_paths(options),
+    };
+    let join_ignore = join_ignore(&input_folder)?;
+    if !submodule_paths.is_empty() || join_ignore.is_some() {
+        walker_builder.filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|kind| kind.is_dir());
+            !submodule_paths.iter().any(|path| path == entry.path())
+                && !join_ignore
+                    .as_ref()
+                    .is_some_and(|rules| rules.matched(entry.path(), is_dir).is_ignore())
+        });
+    }
+
+    // --- 3. Run the walker in parallel ---
+    let walker = walker_builder.build_parallel();
+    // Each thread tells the application's own output apart from the input.
+    let output_options = options.clone();
+
+    // The `run` method spawns a thread pool to perform the walk.
+    // We provide a closure that builds a "move closure" for each thread.
+    walker.run(move || {
+        // Clone the transmitter and other necessary data for each thread.
+        let tx = tx.clone();
+        let output_options = output_options.clone();
+        let never_include = Arc::clone(&never_include);
+        let owners = Arc::clone(&owners);
+
+        // This inner closure is executed for each directory entry found.
+        Box::new(move |result| {
+            if let Ok(entry) = result {
+                let path = entry.path();
+                // Skip directories, the application's own output, the files
+                // that are never included and those of other owners.
+                if path.is_dir()
+                    || output_options.is_output(path)
+                    || never_include.forbids(path, false).is_some()
+                    || owners
+                        .as_ref()
+                        .as_ref()
+                        .is_some_and(|(rules, wanted)| !rules.is_owned_by(path, wanted))
+                {
+                    return WalkState::Continue;
+                }
+
+                // All filtering is now handled by the `overrides`, so we don't
+                // need to manually check extensions or folders here.
+
+                // If all checks pass, send the valid file path to the processor.
+                tx.send(path.to_path_buf()).expect("Failed to send path");
+            }
+            // Continue the walk regardless of the result.
+            WalkState::Continue
+        })
+    });
+
+    // Return the receiver end of the channel to the caller.
+    Ok(rx)
+}
+
+/// Sends the files of `options.files` to the processor in their order, instead
+/// of walking the input folder. A listed file that is never included is an
+/// error, as a pattern naming it would be.
+pub fn listed_files(
+    options: &JoinOptions,
+    files: &[PathBuf],
+) -> anyhow::Result<mpsc::Receiver<PathBuf>> {
+    let never_include = NeverInclude::discover(&options.input_folder)?;
+    let (tx, rx) = mpsc::channel();
+    for file in files {
+        let path = options.input_folder.join(file);
+        if let Some((config, pattern)) = never_include.forbids(&path, false) {
+            anyhow::bail!(
+                "The listed file {} is never included, by '{pattern}' in {}.",
+                file.display(),
+                config.display()
+            );
+        }
+        tx.send(path).expect("the receiver is alive");
+    }
+    Ok(rx)
+}
+
+/// The `CODEOWNERS` rules and the owners whose files are selected, when
+/// `options.owners` is set. Asking for owners without a `CODEOWNERS` file is an
+/// error.
+fn owner_filter(options: &JoinOptions) -> anyhow::Result<Option<(CodeOwners, Vec<String>)>> {
+    let Some(wanted) = &options.owners else {
+        return Ok(None);
+    };
+    let rules = CodeOwners::discover(&options.input_folder)?.with_context(|| {
+        format!(
+            "--owner needs a CODEOWNERS file, but none was found in {} or the folders above it \
+             (looked for {}).",
+            options.input_folder.display(),
+            crate::codeowners::LOCATIONS.join(", ")
+        )
+    })?;
+    Ok(Some((rules, wanted.clone())))
+}
+
+/// Resolves the requested workspace packages, plus the in-repo packages they
+/// depend on, to the folders that should be walked.
+fn package_roots(options: &JoinOptions, names: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let packages = workspace::detect(&options.input_folder)?;
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for name in names {
+        let closure = workspace::package_closure(&packages, name)?;
+        let dependencies: Vec<&str> = closure[1..].iter().map(|p| p.name.as_str()).collect();
+        if dependencies.is_empty() {
+            events::progress(&format!("Joining package '{}'", closure[0].name));
+        } else {
+            events::progress(&format!(
+                "Joining package '{}' with in-repo dependencies: {}",
+                closure[0].name,
+                dependencies.join(", ")
+            ));
+        }
+        for package in closure {
+            if !roots.contains(&package.dir) {
+                roots.push(package.dir.clone());
+            }
+        }
+    }
+    // A package nested in another one would otherwise be walked twice.
+    let nested: Vec<PathBuf> = roots
+        .iter()
+        .filter(|dir| {
+            roots
+                .iter()
+                .any(|other| other != *dir && dir.starts_with(other))
+        })
+        .cloned()
+        .collect();
+    roots.retain(|dir| !nested.contains(dir));
+    if roots.is_empty() {
+        anyhow::bail!("No package was selected.");
+    }
+    Ok(roots)
+}
+
+/// Returns the folders of the git submodules inside the input folder.
+#[cfg(feature = "git")]
+fn submodule_paths(options: &JoinOptions) -> Vec<PathBuf> {
+    crate::git::submodules(&options.input_folder)
+        .into_iter()
+        .map(|submodule| submodule.path)
+        .collect()
+}
+
+#[cfg(not(feature = "git"))]
+fn submodule_paths(_options: &JoinOptions) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Reads the `.joinignore` file of `input_folder`, if there is one.
+fn join_ignore(input_folder: &Path) -> anyhow::Result<Option<Gitignore>> {
+    let path = input_folder.join(IGNORE_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    match Gitignore::new(&path) {
+        (rules, None) => Ok(Some(rules)),
+        (_, Some(e)) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Builds the set of override rules for inclusion and exclusion described by
+/// `options`.
+///
+/// The `OverrideBuilder` allows us to programmatically add glob patterns that
+/// take precedence over any `.gitignore` or similar rules.
+pub fn build_overrides(options: &JoinOptions) -> anyhow::Result<Override> {
+    let mut override_builder = OverrideBuilder::new(&options.input_folder);
+    for glob in override_globs(options) {
+        override_builder.add(&glob)?;
+    }
+    Ok(override_builder.build()?)
+}
+
+/// Returns the override globs described by `options`, in order. Exclusions are
+/// prefixed with `!`.
+fn override_globs(options: &JoinOptions) -> Vec<String> {
+    let mut globs = Vec::new();
+
+    // Add inclusion patterns. If none are provided, default to including everything.
+    if let Some(patterns) = &options.patterns {
+        globs.extend(patterns.iter().cloned());
+    } else {
+        // A single "*" will match all files, which is a good default.
+        globs.push("*".to_string());
+    }
+
+    // Add all exclusion patterns. These are prefixed with "!" to negate the match.
+    if let Some(exclude_patterns) = &options.exclude {
+        globs.extend(exclude_patterns.iter().map(|pattern| format!("!{pattern}")));
+    }
+
+    // Lockfiles are summarized by the dependency section instead.
+    if options.dependencies {
+        globs.extend(
+            crate::dependencies::LOCKFILES
+                .iter()
+                .map(|lockfile| format!("!{lockfile}")),
+        );
+    }
+
+    // Agent instructions are written in a section of their own instead.
+    if options.agent_rules {
+        let (nested, root) = (crate::agent_rules::NESTED, crate::agent_rules::ROOT);
+        globs.extend(nested.iter().map(|name| format!("!{name}")));
+        globs.extend(root.iter().map(|name| format!("!/{name}")));
+    }
+
+    // Never join the content cache of incremental joins.
+    globs.push(format!("!{}/", crate::cache::CACHE_DIR));
+
+    // If hidden files are not requested, add a global ignore pattern for them.
+    // This is necessary because the `*` override would otherwise include them.
+    if !options.hidden {
+        globs.push(HIDDEN_GLOB.to_string());
+    }
+    globs
+}
+
+/// The override glob that leaves out hidden files and folders.
+const HIDDEN_GLOB: &str = "!.*";
+
+/// Decides whether a file, given by its path relative to the input folder, is
+/// selected by `overrides`.
+///
+/// This mirrors what the walker does on disk: a file is rejected if any of its
+/// parent directories is excluded, or if the file itself does not match. It is
+/// used for inputs that are not walked from the filesystem, such as remote trees.
+pub fn is_selected(overrides: &Override, relative_path: &Path) -> bool {
+    let mut ancestors = relative_path.ancestors().skip(1);
+    let excluded_parent = ancestors
+        .any(|dir| !dir.as_os_str().is_empty() && overrides.matched(dir, true).is_ignore());
+    !excluded_parent && !overrides.matched(relative_path, false).is_ignore()
+}
+
+/// Decides whether a file of the input folder that is found without the
+/// walker, such as a manifest or an instruction file, may be read: it must
+/// not be excluded with `--exclude`, nor listed in `never_include`.
+pub(crate) fn allowed_outside_walk(
+    options: &JoinOptions,
+) -> anyhow::Result<impl Fn(&Path) -> bool + use<>> {
+    let root = options.input_folder.clone();
+    let never_include = NeverInclude::discover(&root)?;
+    let mut excludes = OverrideBuilder::new(&root);
+    for pattern in options.exclude.iter().flatten() {
+        excludes.add(&format!("!{pattern}"))?;
+    }
+    let excludes = excludes.build()?;
+    Ok(move |path: &Path| {
+        let relative = path.strip_prefix(&root).unwrap_or(path);
+        is_selected(&excludes, relative) && never_include.forbids(path, false).is_none()
+    })
+}
+
+/// The files the walker leaves out, grouped by the rule that decided it (e.g.,
+/// "excluded by pattern `target/`"), largest group first.
+///
+/// This walks the whole input folder, including the folders the walker prunes
+/// without visiting, so it is meant for diagnostics rather than every join.
+pub fn explain_exclusions(options: &JoinOptions) -> anyhow::Result<Vec<(String, Vec<PathBuf>)>> {
+    let selected: HashSet<PathBuf> = find_files(options)?.into_iter().collect();
+    let mut explainer = Explainer::new(options)?;
+
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    // The rule that excluded each folder, which also applies to its content.
+    let mut folder_rules: HashMap<PathBuf, Option<String>> = HashMap::new();
+    let walker = WalkBuilder::new(&options.input_folder)
+        .standard_filters(false)
+        .follow_links(options.follow_links)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    for entry in walker.flatten() {
+        let path = entry.path();
+        if entry.depth() == 0 || options.is_output(path) {
+            continue;
+        }
+        let is_dir = entry.file_type().is_some_and(|kind| kind.is_dir());
+        let inherited = path
+            .parent()
+            .and_then(|parent| folder_rules.get(parent))
+            .cloned()
+            .flatten();
+        let rule = if !is_dir && selected.contains(path) {
+            None
+        } else if inherited.is_some() {
+            inherited
+        } else {
+            explainer.rule(path, is_dir, entry.depth())
+        };
+        if is_dir {
+            folder_rules.insert(path.to_path_buf(), rule);
+        } else if let Some(rule) = rule {
+            groups.entry(rule).or_default().push(path.to_path_buf());
+        }
+    }
+
+    let mut groups: Vec<(String, Vec<PathBuf>)> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+    Ok(groups)
+}
+
+/// Finds the rule that leaves out a path, applying the rules in the order the
+/// walker does: depth, packages, submodules and `.joinignore`, overrides, then
+/// ignore files.
+struct Explainer<'a> {
+    options: &'a JoinOptions,
+    /// The overrides as a plain gitignore matcher, which (unlike `Override`)
+    /// tells which glob matched. Matches are inverted: a whitelisted path is
+    /// excluded.
+    overrides: Gitignore,
+    package_dirs: Option<Vec<PathBuf>>,
+    submodules: Vec<PathBuf>,
+    /// The `CODEOWNERS` rules and the owners whose files are selected.
+    owners: Option<(CodeOwners, Vec<String>)>,
+    /// The `.joinignore` file of the input folder.
+    join_ignore: Option<Gitignore>,
+    /// The `.ignore` and `.gitignore` files read so far, by path.
+    ignore_files: HashMap<PathBuf, Gitignore>,
+}
+
+impl<'a> Explainer<'a> {
+    fn new(options: &'a JoinOptions) -> anyhow::Result<Self> {
+        let mut builder = GitignoreBuilder::new(&options.input_folder);
+        for glob in override_globs(options) {
+            builder.add_line(None, &glob)?;
+        }
+        let package_dirs = match &options.packages {
+            Some(names) => {
+                let packages = workspace::detect(&options.input_folder)?;
+                let mut dirs = Vec::new();
+                for name in names {
+                    let closure = workspace::package_closure(&packages, name)?;
+                    dirs.extend(closure.into_iter().map(|package| package.dir.clone()));
+                }
+                Some(dirs)
+            }
+            None => None,
+        };
+        let submodules = if options.submodules == SubmoduleMode::Include {
+            Vec::new()
+        } else {
+            submodule_paths(options)
+        };
+        Ok(Self {
+            options,
+            overrides: builder.build()?,
+            package_dirs,
+            submodules,
+            owners: owner_filter(options)?,
+            join_ignore: join_ignore(&options.input_folder)?,
+            ignore_files: HashMap::new(),
+        })
+    }
+
+    /// Returns the rule that leaves out `path`, if any. Files that are left out
+    /// without a known rule are put down to ignore rules outside the folder.
+    fn rule(&mut self, path: &Path, is_dir: bool, depth: usize) -> Option<String> {
+        if let Some(max_depth) = self.options.max_depth
+            && depth > max_depth
+        {
+            return Some(format!("deeper than the maximum depth of {max_depth}"));
+        }
+        if let Some(dirs) = &self.package_dirs {
+            let related =
+                |dir: &PathBuf| path.starts_with(dir) || (is_dir && dir.starts_with(path));
+            if !dirs.iter().any(related) {
+                return Some("outside the selected packages".to_string());
+            }
+        }
+        if self.submodules.iter().any(|submodule| submodule == path) {
+            return Some("in a git submodule".to_string());
+        }
+        if let Some(rules) = &self.join_ignore
+            && let Match::Ignore(glob) = rules.matched(path, is_dir)
+        {
+            return Some(format!(
+                "ignored by `{}` in {}",
+                glob.original(),
+                self.options.input_folder.join(IGNORE_FILE).display()
+            ));
+        }
+
+        let relative = path
+            .strip_prefix(&self.options.input_folder)
+            .unwrap_or(path);
+        match self.overrides.matched(relative, is_dir) {
+            Match::Whitelist(glob) if glob.original() == HIDDEN_GLOB => {
+                return Some("hidden".to_string());
+            }
+            Match::Whitelist(glob) => {
+                let pattern = glob.original().trim_start_matches('!');
+                return Some(format!("excluded by pattern `{pattern}`"));
+            }
+            Match::Ignore(_) => return self.fallback(is_dir),
+            Match::None if self.options.patterns.is_some() && !is_dir => {
+                return Some("not matched by any include pattern".to_string());
+            }
+            Match::None => {}
+        }
+
+        if !is_dir
+            && let Some((rules, wanted)) = &self.owners
+            && !rules.is_owned_by(path, wanted)
+        {
+            return Some(format!("not owned by {}", wanted.join(" or ")));
+        }
+
+        // Like the walker, `.ignore` files take precedence over `.gitignore`
+        // files, and deeper files over shallower ones.
+        for name in [".ignore", ".gitignore"] {
+            for dir in path.ancestors().skip(1) {
+                let file = dir.join(name);
+                let matcher = self
+                    .ignore_files
+                    .entry(file.clone())
+                    .or_insert_with(|| Gitignore::new(&file).0);
+                match matcher.matched(path, is_dir) {
+                    Match::Ignore(glob) => {
+                        return Some(format!(
+                            "ignored by `{}` in {}",
+                            glob.original(),
+                            file.display()
+                        ));
+                    }
+                    Match::Whitelist(_) => return self.fallback(is_dir),
+                    Match::None => {}
+                }
+                if dir == self.options.input_folder {
+                    break;
+                }
+            }
+        }
+        self.fallback(is_dir)
+    }
+
+    fn fallback(&self, is_dir: bool) -> Option<String> {
+        (!is_dir).then(|| "ignored by other rules (e.g., .git/info/exclude)".to_string())
+    }
+}
+
+// --- Unit Tests for Override Matching ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that relative paths are matched like the on-disk walk would.
+    #[test]
+    fn test_is_selected_mirrors_walk_rules() -> anyhow::Result<()> {
+        let mut options = JoinOptions::new("repo", "out.txt");
+        options.patterns = Some(vec!["*.rs".to_string()]);
+        options.exclude = Some(vec!["target/".to_string()]);
+        let overrides = build_overrides(&options)?;
+
+        assert!(is_selected(&overrides, Path::new("src/main.rs")));
+        assert!(!is_selected(&overrides, Path::new("README.md")));
+        assert!(!is_selected(&overrides, Path::new("target/debug/build.rs")));
+        assert!(!is_selected(&overrides, Path::new(".github/ci.rs")));
+
+        Ok(())
+    }
+
+    /// Verifies that left-out files are grouped by the rule that decided, with
+    /// the content of excluded folders counted under the folder's rule.
+    #[test]
+    fn test_explain_exclusions() -> anyhow::Result<()> {
+        use assert_fs::TempDir;
+        use assert_fs::prelude::*;
+
+        let dir = TempDir::new()?;
+        dir.child("src/main.rs").write_str("main")?;
+        dir.child("src/notes.txt").write_str("notes")?;
+        dir.child("target/a.rs").write_str("a")?;
+        dir.child("target/debug/b.rs").write_str("b")?;
+        dir.child(".env").write_str("SECRET=1")?;
+        dir.child(".ignore").write_str("generated/\n")?;
+        dir.child("generated/api.rs").write_str("generated")?;
+        dir.child(IGNORE_FILE).write_str("src/legacy/\n")?;
+        dir.child("src/legacy/old.rs").write_str("old")?;
+
+        let mut options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        options.patterns = Some(vec!["*.rs".to_string()]);
+        options.exclude = Some(vec!["target/".to_string()]);
+        let groups = explain_exclusions(&options)?;
+
+        let summary: Vec<(&str, usize)> = groups
+            .iter()
+            .map(|(rule, paths)| (rule.as_str(), paths.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("hidden", 3),
+                ("excluded by pattern `target/`", 2),
+                (
+                    &*format!(
+                        "ignored by `generated/` in {}",
+                        dir.path().join(".ignore").display()
+                    ),
+                    1
+                ),
+                (
+                    &*format!(
+                        "ignored by `src/legacy/` in {}",
+                        dir.path().join(IGNORE_FILE).display()
+                    ),
+                    1
+                ),
+                ("not matched by any include pattern", 1),
+            ]
+        );
+        assert_eq!(groups[4].1, [dir.path().join("src/notes.txt")]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,499 @@
+//! Detection of multi-package repositories (Cargo workspaces, npm/pnpm
+//! workspaces and Go modules), so a single package can be joined together
+//! with the in-repo packages it depends on.
+
+use anyhow::{Context, bail};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The package ecosystems that are recognized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Go,
+}
+
+/// A package (crate, npm package or Go module) of a multi-package repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    /// The package name (the module path, for Go).
+    pub name: String,
+    pub ecosystem: Ecosystem,
+    /// The folder containing the package manifest.
+    pub dir: PathBuf,
+    /// The names of the other in-repo packages this package depends on.
+    pub dependencies: Vec<String>,
+}
+
+/// Folders that never contain workspace members, even when not git-ignored.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor"];
+
+/// Finds every workspace package below `root`.
+///
+/// Cargo and npm/pnpm members are taken from the workspace definition at the
+/// root. For Go, the modules listed in `go.work` are used, or every `go.mod`
+/// found when there is no `go.work`.
+pub fn detect(root: &Path) -> anyhow::Result<Vec<Package>> {
+    let manifests = find_manifests(root);
+    let mut packages = Vec::new();
+    packages.extend(detect_cargo(root, &manifests)?);
+    packages.extend(detect_npm(root, &manifests)?);
+    packages.extend(detect_go(root, &manifests)?);
+    Ok(packages)
+}
+
+/// Returns the package called `name` followed by all the in-repo packages it
+/// transitively depends on.
+///
+/// Go modules can also be referred to by the last segment of their path.
+pub fn package_closure<'a>(
+    packages: &'a [Package],
+    name: &str,
+) -> anyhow::Result<Vec<&'a Package>> {
+    let find = |name: &str| {
+        packages.iter().find(|p| p.name == name).or_else(|| {
+            packages
+                .iter()
+                .find(|p| p.ecosystem == Ecosystem::Go && p.name.rsplit('/').next() == Some(name))
+        })
+    };
+    let Some(start) = find(name) else {
+        let available: BTreeSet<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        if available.is_empty() {
+            bail!("Package '{name}' not found: no Cargo, npm/pnpm or Go workspace was detected.");
+        }
+        bail!(
+            "Package '{name}' not found. Available packages: {}",
+            available.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    };
+
+    let mut closure = vec![start];
+    let mut index = 0;
+    while index < closure.len() {
+        for dependency in &closure[index].dependencies {
+            if let Some(package) = find(dependency)
+                && !closure.iter().any(|p| p.dir == package.dir)
+            {
+                closure.push(package);
+            }
+        }
+        index += 1;
+    }
+    Ok(closure)
+}
+
+/// The manifest files found in the tree, keyed by file name.
+pub(crate) type Manifests = HashMap<&'static str, Vec<PathBuf>>;
+
+/// Collects the folders (relative to `root`) containing each kind of manifest.
+pub(crate) fn find_manifests(root: &Path) -> Manifests {
+    let mut manifests: Manifests = HashMap::new();
+    let walker = WalkBuilder::new(root)
+        .filter_entry(|entry| !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+        .build();
+    for entry in walker.flatten() {
+        let name = entry.file_name().to_string_lossy();
+        for manifest in crate::dependencies::MANIFESTS {
+            if name == manifest
+                && let Some(dir) = entry.path().parent()
+            {
+                let relative = dir.strip_prefix(root).unwrap_or(dir).to_path_buf();
+                manifests.entry(manifest).or_default().push(relative);
+            }
+        }
+    }
+    manifests
+}
+
+/// Builds a glob set from workspace member patterns.
+fn member_globs<'a>(patterns: impl IntoIterator<Item = &'a str>) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+        builder.add(
+            Glob::new(pattern).with_context(|| format!("Invalid member pattern '{pattern}'"))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+/// Resolves `.` and `..` in a relative path without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn detect_cargo(root: &Path, manifests: &Manifests) -> anyhow::Result<Vec<Package>> {
+    let Ok(text) = fs::read_to_string(root.join("Cargo.toml")) else {
+        return Ok(Vec::new());
+    };
+    let manifest: toml::Table = text.parse().context("Failed to parse Cargo.toml")?;
+    let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) else {
+        return Ok(Vec::new());
+    };
+
+    let strings = |key: &str| -> Vec<&str> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default()
+    };
+    let members = member_globs(strings("members"))?;
+    let excluded = member_globs(strings("exclude"))?;
+    // Paths of `[workspace.dependencies]` entries, relative to the root.
+    let workspace_paths: HashMap<&str, PathBuf> = workspace
+        .get("dependencies")
+        .and_then(|d| d.as_table())
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, spec)| {
+            Some((
+                name.as_str(),
+                normalize(Path::new(spec.get("path")?.as_str()?)),
+            ))
+        })
+        .collect();
+
+    // First pass: every member with its manifest, so dependencies can be resolved by folder.
+    let mut members_found = Vec::new();
+    for dir in manifests.get("Cargo.toml").into_iter().flatten() {
+        let is_root = dir.as_os_str().is_empty();
+        if !is_root && (!members.is_match(dir) || excluded.is_match(dir)) {
+            continue;
+        }
+        let text = fs::read_to_string(root.join(dir).join("Cargo.toml"))?;
+        let manifest: toml::Table = text
+            .parse()
+            .with_context(|| format!("Failed to parse {}", dir.join("Cargo.toml").display()))?;
+        if let Some(name) = manifest
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+        {
+            members_found.push((name.to_string(), dir.clone(), manifest));
+        }
+    }
+    let names_by_dir: HashMap<&Path, &str> = members_found
+        .iter()
+        .map(|(name, dir, _)| (dir.as_path(), name.as_str()))
+        .collect();
+
+    let mut packages = Vec::new();
+    for (name, dir, manifest) in &members_found {
+        let mut dependencies = BTreeSet::new();
+        for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+            let Some(table) = manifest.get(section).and_then(|d| d.as_table()) else {
+                continue;
+            };
+            for (key, spec) in table {
+                let path = if let Some(path) = spec.get("path").and_then(|p| p.as_str()) {
+                    Some(normalize(&dir.join(path)))
+                } else if spec.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                    workspace_paths.get(key.as_str()).cloned()
+                } else {
+                    None
+                };
+                if let Some(dependency) = path.and_then(|p| names_by_dir.get(p.as_path()).copied())
+                {
+                    dependencies.insert(dependency.to_string());
+                }
+            }
+        }
+        packages.push(Package {
+            name: name.clone(),
+            ecosystem: Ecosystem::Cargo,
+            dir: root.join(dir),
+            dependencies: dependencies.into_iter().collect(),
+        });
+    }
+    Ok(packages)
+}
+
+fn detect_npm(root: &Path, manifests: &Manifests) -> anyhow::Result<Vec<Package>> {
+    let mut patterns: Vec<String> = Vec::new();
+    if let Ok(text) = fs::read_to_string(root.join("package.json")) {
+        let manifest: serde_json::Value =
+            serde_json::from_str(&text).context("Failed to parse package.json")?;
+        let workspaces = manifest.get("workspaces");
+        let list = workspaces.and_then(|w| w.as_array()).or_else(|| {
+            workspaces
+                .and_then(|w| w.get("packages"))
+                .and_then(|p| p.as_array())
+        });
+        patterns.extend(
+            list.into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+                .map(str::to_string),
+        );
+    }
+    if let Ok(text) = fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        patterns.extend(pnpm_packages(&text));
+    }
+    if patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (negated, positive): (Vec<&String>, Vec<&String>) =
+        patterns.iter().partition(|p| p.starts_with('!'));
+    let members = member_globs(positive.iter().map(|p| p.as_str()))?;
+    let excluded = member_globs(negated.iter().map(|p| &p[1..]))?;
+
+    let mut found = Vec::new();
+    for dir in manifests.get("package.json").into_iter().flatten() {
+        if dir.as_os_str().is_empty() || !members.is_match(dir) || excluded.is_match(dir) {
+            continue;
+        }
+        let text = fs::read_to_string(root.join(dir).join("package.json"))?;
+        let manifest: serde_json::Value = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", dir.join("package.json").display()))?;
+        if let Some(name) = manifest.get("name").and_then(|n| n.as_str()) {
+            found.push((name.to_string(), dir.clone(), manifest));
+        }
+    }
+
+    let names: BTreeSet<&str> = found.iter().map(|(name, _, _)| name.as_str()).collect();
+    Ok(found
+        .iter()
+        .map(|(name, dir, manifest)| {
+            let dependencies: BTreeSet<String> = [
+                "dependencies",
+                "devDependencies",
+                "peerDependencies",
+                "optionalDependencies",
+            ]
+            .iter()
+            .filter_map(|section| manifest.get(section).and_then(|d| d.as_object()))
+            .flat_map(|deps| deps.keys())
+            .filter(|dependency| names.contains(dependency.as_str()))
+            .cloned()
+            .collect();
+            Package {
+                name: name.clone(),
+                ecosystem: Ecosystem::Npm,
+                dir: root.join(dir),
+                dependencies: dependencies.into_iter().collect(),
+            }
+        })
+        .collect())
+}
+
+/// Extracts the `packages:` list of a `pnpm-workspace.yaml` file.
+fn pnpm_packages(text: &str) -> Vec<String> {
+    let mut packages = Vec::new();
+    let mut in_packages = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if !line.starts_with([' ', '\t', '-']) && !trimmed.is_empty() {
+            in_packages = trimmed == "packages:";
+        } else if in_packages && let Some(item) = trimmed.strip_prefix('-') {
+            packages.push(item.trim().trim_matches(['\'', '"']).to_string());
+        }
+    }
+    packages
+}
+
+fn detect_go(root: &Path, manifests: &Manifests) -> anyhow::Result<Vec<Package>> {
+    let dirs: Vec<PathBuf> = match fs::read_to_string(root.join("go.work")) {
+        Ok(text) => go_directives(&text, "use")
+            .into_iter()
+            .map(|dir| normalize(Path::new(&dir)))
+            .collect(),
+        Err(_) => manifests.get("go.mod").cloned().unwrap_or_default(),
+    };
+
+    let mut modules = Vec::new();
+    for dir in dirs {
+        let Ok(text) = fs::read_to_string(root.join(&dir).join("go.mod")) else {
+            continue;
+        };
+        if let Some(name) = go_directives(&text, "module").into_iter().next() {
+            modules.push((name, dir, text));
+        }
+    }
+    // A single module is not a multi-package repository.
+    if modules.len() < 2 {
+        return Ok(Vec::new());
+    }
+
+    let names_by_dir: HashMap<PathBuf, String> = modules
+        .iter()
+        .map(|(name, dir, _)| (dir.clone(), name.clone()))
+        .collect();
+    Ok(modules
+        .iter()
+        .map(|(name, dir, text)| {
+            let mut dependencies: BTreeSet<String> = go_directives(text, "require")
+                .into_iter()
+                .filter(|module| names_by_dir.values().any(|name| name == module))
+                .collect();
+            for target in go_directives(text, "replace") {
+                if target.starts_with('.')
+                    && let Some(module) = names_by_dir.get(&normalize(&dir.join(&target)))
+                {
+                    dependencies.insert(module.clone());
+                }
+            }
+            dependencies.remove(name);
+            Package {
+                name: name.clone(),
+                ecosystem: Ecosystem::Go,
+                dir: root.join(dir),
+                dependencies: dependencies.into_iter().collect(),
+            }
+        })
+        .collect())
+}
+
+/// Returns the first argument of every `directive` in a `go.mod`/`go.work` file,
+/// for both the single-line and the parenthesized block forms. For `replace`,
+/// the replacement target is returned instead.
+fn go_directives(text: &str, directive: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let entry = if in_block {
+            if line == ")" {
+                in_block = false;
+                continue;
+            }
+            line
+        } else if let Some(rest) = line.strip_prefix(directive)
+            && rest.starts_with([' ', '\t', '('])
+        {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+                continue;
+            }
+            rest
+        } else {
+            continue;
+        };
+
+        let value = if directive == "replace" {
+            entry
+                .split("=>")
+                .nth(1)
+                .and_then(|t| t.split_whitespace().next())
+        } else {
+            entry.split_whitespace().next()
+        };
+        if let Some(value) = value {
+            values.push(value.trim_matches('"').to_string());
+        }
+    }
+    values
+}
+
+// --- Unit Tests for Workspace Detection ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn names(packages: &[&Package]) -> Vec<String> {
+        packages.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// Verifies Cargo members and path/workspace dependencies are resolved.
+    #[test]
+    fn test_cargo_workspace_closure() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("Cargo.toml").write_str(
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.dependencies]\nutil = { path = \"crates/util\" }\n",
+        )?;
+        dir.child("crates/app/Cargo.toml").write_str(
+            "[package]\nname = \"app\"\n\n[dependencies]\ncore = { path = \"../core\" }\nserde = \"1\"\n",
+        )?;
+        dir.child("crates/core/Cargo.toml").write_str(
+            "[package]\nname = \"core\"\n\n[dependencies]\nutil = { workspace = true }\n",
+        )?;
+        dir.child("crates/util/Cargo.toml")
+            .write_str("[package]\nname = \"util\"\n")?;
+        dir.child("crates/other/Cargo.toml")
+            .write_str("[package]\nname = \"other\"\n")?;
+
+        let packages = detect(dir.path())?;
+        assert_eq!(packages.len(), 4);
+
+        let closure = package_closure(&packages, "app")?;
+        assert_eq!(names(&closure), vec!["app", "core", "util"]);
+        assert_eq!(closure[0].dir, dir.path().join("crates/app"));
+
+        let err = package_closure(&packages, "missing")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Available packages: app, core, other, util"));
+
+        Ok(())
+    }
+
+    /// Verifies npm and pnpm workspaces are detected with their internal dependencies.
+    #[test]
+    fn test_npm_and_pnpm_workspaces() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("pnpm-workspace.yaml")
+            .write_str("packages:\n  - 'packages/*'\n  - '!packages/ignored'\n")?;
+        dir.child("packages/web/package.json").write_str(
+            r#"{"name": "@acme/web", "dependencies": {"@acme/ui": "workspace:*", "react": "18"}}"#,
+        )?;
+        dir.child("packages/ui/package.json")
+            .write_str(r#"{"name": "@acme/ui"}"#)?;
+        dir.child("packages/ignored/package.json")
+            .write_str(r#"{"name": "ignored"}"#)?;
+        dir.child("packages/web/node_modules/react/package.json")
+            .write_str(r#"{"name": "react"}"#)?;
+
+        let packages = detect(dir.path())?;
+        let closure = package_closure(&packages, "@acme/web")?;
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(names(&closure), vec!["@acme/web", "@acme/ui"]);
+
+        Ok(())
+    }
+
+    /// Verifies Go modules are linked through `require` and local `replace` directives.
+    #[test]
+    fn test_go_modules() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("go.work")
+            .write_str("go 1.22\n\nuse (\n\t./api\n\t./lib\n\t./tools\n)\n")?;
+        dir.child("api/go.mod").write_str(
+            "module example.com/api\n\nrequire (\n\texample.com/lib v0.0.0\n\tgithub.com/pkg/errors v0.9.1\n)\n",
+        )?;
+        dir.child("lib/go.mod").write_str(
+            "module example.com/lib\n\nrequire example.com/tools v0.0.0\nreplace example.com/tools => ../tools\n",
+        )?;
+        dir.child("tools/go.mod")
+            .write_str("module example.com/tools\n")?;
+
+        let packages = detect(dir.path())?;
+        let closure = package_closure(&packages, "api")?;
+
+        assert_eq!(
+            names(&closure),
+            vec!["example.com/api", "example.com/lib", "example.com/tools"]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+//! Extraction of archive inputs (.zip, .tar, .tar.gz) so they can be joined
+//! exactly like a regular folder. These features are only available when the
+//! crate is built with the `archive` feature.
+
+use anyhow::Context;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// The archive formats accepted as input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Detects the archive format from the file name, if it is a supported one.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extracts the archive at `path` into `dest`.
+///
+/// Entries are streamed to disk one at a time, and entries whose paths would
+/// escape `dest` (absolute paths or `..` components) are never written.
+pub fn extract(path: &Path, kind: ArchiveKind, dest: &Path) -> anyhow::Result<()> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open archive {}", path.display()))?;
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(file))
+                .with_context(|| format!("{} is not a valid zip archive", path.display()))?;
+            archive.extract(dest)?;
+        }
+        ArchiveKind::Tar => extract_tar(BufReader::new(file), dest)?,
+        ArchiveKind::TarGz => extract_tar(GzDecoder::new(BufReader::new(file)), dest)?,
+    }
+    Ok(())
+}
+
+/// Extracts an uncompressed tar stream into `dest`.
+///
+/// Symbolic and hard links are skipped: once extracted, a link could point
+/// the join at any file of the host, such as `~/.aws/credentials`.
+pub fn extract_tar<R: Read>(reader: R, dest: &Path) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().context("Failed to extract tar archive")? {
+        let mut entry = entry.context("Failed to extract tar archive")?;
+        if matches!(
+            entry.header().entry_type(),
+            tar::EntryType::Symlink | tar::EntryType::Link
+        ) {
+            join_ai_core::events::warning(&format!(
+                "Skipped the link {} in the archive",
+                entry.path()?.display()
+            ));
+            continue;
+        }
+        entry
+            .unpack_in(dest)
+            .context("Failed to extract tar archive")?;
+    }
+    Ok(())
+}
+
+/// Extracts a tar stream of unknown compression (such as stdin) into `dest`.
+/// Gzip-compressed streams are detected from their magic bytes.
+pub fn extract_tar_stream<R: Read>(reader: R, dest: &Path) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let is_gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+    if is_gzip {
+        extract_tar(GzDecoder::new(reader), dest)
+    } else {
+        extract_tar(reader, dest)
+    }
+}
+
+// --- Unit Tests for Archive Detection ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that archive formats are detected from the file name.
+    #[test]
+    fn test_archive_kind_from_path() {
+        let kind = |name: &str| ArchiveKind::from_path(Path::new(name));
+        assert_eq!(kind("drop.zip"), Some(ArchiveKind::Zip));
+        assert_eq!(kind("drop.TAR.GZ"), Some(ArchiveKind::TarGz));
+        assert_eq!(kind("drop.tgz"), Some(ArchiveKind::TarGz));
+        assert_eq!(kind("drop.tar"), Some(ArchiveKind::Tar));
+        assert_eq!(kind("src"), None);
+        assert_eq!(kind("notes.gz"), None);
+    }
+
+    /// Verifies that plain and gzip-compressed tar streams are both extracted.
+    #[test]
+    fn test_extract_tar_stream() -> anyhow::Result<()> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "src/main.rs", &b"hello"[..])?;
+        let tar = builder.into_inner()?;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, &tar)?;
+        let tar_gz = encoder.finish()?;
+
+        for stream in [tar, tar_gz] {
+            let dest = tempfile::TempDir::new()?;
+            extract_tar_stream(stream.as_slice(), dest.path())?;
+            assert_eq!(
+                std::fs::read_to_string(dest.path().join("src/main.rs"))?,
+                "hello"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Verifies that links in a tar archive are not extracted, so that a link
+    /// to a file outside of the archive cannot bring it into the join.
+    #[test]
+    fn test_extract_tar_skips_links() -> anyhow::Result<()> {
+        let outside = tempfile::TempDir::new()?;
+        let secret = outside.path().join("credentials");
+        std::fs::write(&secret, "TOPSECRET")?;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "app/main.rs", &b"hello"[..])?;
+        for (kind, name) in [
+            (tar::EntryType::Symlink, "app/link.txt"),
+            (tar::EntryType::Link, "app/hard.txt"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(kind);
+            header.set_size(0);
+            header.set_mode(0o644);
+            builder.append_link(&mut header, name, &secret)?;
+        }
+        let archive = outside.path().join("evil.tar");
+        std::fs::write(&archive, builder.into_inner()?)?;
+
+        let dest = tempfile::TempDir::new()?;
+        extract(&archive, ArchiveKind::Tar, dest.path())?;
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("app/main.rs"))?,
+            "hello"
+        );
+        assert!(!dest.path().join("app/link.txt").exists());
+        assert!(!dest.path().join("app/link.txt").is_symlink());
+        assert!(!dest.path().join("app/hard.txt").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,465 @@
+//! The `ask` command: joins files like `join`, then asks a language model a
+//! question about them and prints its answer. With `--staged`, the model reads
+//! the staged diff instead, and `--task commit-message` has it write the
+//! commit message for it.
+//!
+//! The answer is streamed to the terminal as it is generated; `--raw` keeps
+//! everything else off stdout, so that it can be piped.
+//!
+//! Given a context pack (`--pack`) as input, the model reads the output held
+//! in the pack, exactly as it was joined, and no join is run.
+//!
+//! A context larger than the model's window is asked about in parts: the
+//! question is asked of each part, and a final request combines the partial
+//! answers (a map-reduce over the context).
+
+use crate::cli::{AskArgs, Task};
+use crate::join_to_memory;
+use crate::pack;
+use anyhow::bail;
+use join_ai_core::events;
+use join_ai_core::selection::estimate_tokens;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// The instructions sent with every question.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const SYSTEM_PROMPT: &str = "You answer questions about a code base. Its files \
+    follow, each introduced by a header with its path. Base your answer on \
+    these files, and name the files you refer to.";
+
+/// The instructions sent with each part of a context that is asked in parts.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const PART_PROMPT: &str = "You answer questions about a code base that is too \
+    large to read at once, so you only see one part of it. Its files follow, \
+    each introduced by a header with its path. Answer from this part alone, \
+    naming the files you refer to. If it holds nothing relevant, say so in one \
+    sentence.";
+
+/// The instructions sent to combine the answers given for each part.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const SYNTHESIS_PROMPT: &str = "You answer questions about a code base. It was \
+    read in parts, and the answers given for each part follow. Combine them \
+    into one answer to the question, leaving out parts with nothing relevant.";
+
+/// The instructions sent with a staged diff.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const DIFF_PROMPT: &str = "You answer questions about changes to a code base. \
+    They follow as the unified diff of the changes staged for the next commit. \
+    Base your answer on this diff, and name the files you refer to.";
+
+/// The instructions sent with each part of a staged diff that is asked in parts.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const DIFF_PART_PROMPT: &str = "You answer questions about changes to a code \
+    base that are too large to read at once, so you only see one part of their \
+    unified diff. Answer from this part alone, naming the files you refer to. \
+    If it holds nothing relevant, say so in one sentence.";
+
+/// What `--task commit-message` asks for.
+const COMMIT_MESSAGE_TASK: &str = "Write a commit message for these changes in \
+    the Conventional Commits style: a subject line of the form `type(scope): \
+    summary` in the imperative mood and under 72 characters, a blank line, then \
+    a short body on what changed and why. Reply with the message only, without \
+    a code block.";
+
+/// What the model reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// The joined files.
+    Files,
+    /// The unified diff of the staged changes.
+    StagedDiff,
+}
+
+impl Source {
+    /// The instructions sent with the whole context.
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    fn prompt(self) -> &'static str {
+        match self {
+            Self::Files => SYSTEM_PROMPT,
+            Self::StagedDiff => DIFF_PROMPT,
+        }
+    }
+
+    /// The instructions sent with each part of a context asked in parts.
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    fn part_prompt(self) -> &'static str {
+        match self {
+            Self::Files => PART_PROMPT,
+            Self::StagedDiff => DIFF_PART_PROMPT,
+        }
+    }
+
+    /// The line that starts each file in the context.
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    fn file_header(self) -> &'static str {
+        match self {
+            Self::Files => "// FILE: ",
+            Self::StagedDiff => "diff --git ",
+        }
+    }
+}
+
+/// Joins the files selected by `args`, or reads the staged diff, and prints
+/// the model's answer to the question. With `--dry-run`, only reports what
+/// would be sent.
+pub fn ask(args: &AskArgs) -> anyhow::Result<ExitCode> {
+    events::set_progress_to_stderr(args.raw);
+    // The `.git` directory is only known, and needed, for staged changes.
+    let mut git_dir = None;
+    let (source, context, code) = if args.staged {
+        let (patch, dir) = staged_diff()?;
+        git_dir = Some(dir);
+        (Source::StagedDiff, patch, ExitCode::SUCCESS)
+    } else if let Some(path) = args
+        .join
+        .input_folder
+        .as_deref()
+        .filter(|p| pack::is_pack(p))
+    {
+        let Some(context) = read_pack(path, args)? else {
+            return Ok(ExitCode::SUCCESS);
+        };
+        (Source::Files, context, ExitCode::SUCCESS)
+    } else {
+        let (report, code, output) = join_to_memory(
+            &args.join,
+            "ask",
+            args.base_url
+                .as_deref()
+                .unwrap_or(args.provider.default_base_url()),
+        )?;
+        let Some(output) = output.filter(|_| report.files_written > 0) else {
+            return Ok(code);
+        };
+        let context = String::from_utf8_lossy(&output).into_owned();
+        (Source::Files, context, code)
+    };
+    let question = match (args.task, &args.question) {
+        (Some(Task::CommitMessage), _) => COMMIT_MESSAGE_TASK.to_string(),
+        (None, question) => format!("Question: {}", question.as_deref().unwrap_or_default()),
+    };
+    match args.task {
+        Some(Task::CommitMessage) => {
+            // The message is only printed once it is cleaned up.
+            let answer = complete(args, source, &question, &context, None)?;
+            let message = commit_message(&answer);
+            if !args.raw {
+                println!();
+            }
+            println!("{message}");
+            if args.write
+                && let Some(git_dir) = git_dir
+            {
+                let path = git_dir.join("COMMIT_EDITMSG");
+                std::fs::write(&path, format!("{message}\n"))?;
+                events::progress(&format!(
+                    "\nThe message has been written to {}; commit with `git commit -eF {}`.",
+                    path.display(),
+                    path.display()
+                ));
+            }
+        }
+        None => {
+            let mut stdout = std::io::stdout();
+            let mut started = false;
+            let mut print = |text: &str| {
+                if !started && !args.raw {
+                    println!();
+                }
+                started = true;
+                print!("{text}");
+                let _ = stdout.flush();
+            };
+            let answer = complete(args, source, &question, &context, Some(&mut print))?;
+            if !answer.ends_with('\n') {
+                println!();
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// Reads the output of the pack at `path` (`--pack`), which is asked about as
+/// it was joined. Returns `None` in a dry run.
+fn read_pack(path: &Path, args: &AskArgs) -> anyhow::Result<Option<String>> {
+    if args.join.audit_log.is_some() {
+        bail!("`--audit-log` records the files of a join, and a pack is asked about without one.");
+    }
+    let output = pack::read_output(path)?;
+    events::progress(&format!(
+        "Reading the pack {} (~{} tokens).",
+        path.display(),
+        estimate_tokens(output.len() as u64)
+    ));
+    if args.join.dry_run {
+        events::progress("Dry run: nothing was sent.");
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output).into_owned()))
+}
+
+/// Reads the diff of the changes staged in the repository of the current
+/// directory, along with the `.git` directory of the repository.
+#[cfg(feature = "git")]
+fn staged_diff() -> anyhow::Result<(String, PathBuf)> {
+    let staged = join_ai_core::git::staged_changes(std::path::Path::new("."))
+        .map_err(|e| anyhow::anyhow!("Could not read the staged changes: {}", e.message()))?;
+    if staged.files == 0 {
+        bail!("There are no staged changes; stage them with `git add` first.");
+    }
+    events::progress(&format!(
+        "Reading the staged changes of {} files (~{} tokens).",
+        staged.files,
+        estimate_tokens(staged.patch.len() as u64)
+    ));
+    Ok((staged.patch, staged.git_dir))
+}
+
+#[cfg(not(feature = "git"))]
+fn staged_diff() -> anyhow::Result<(String, PathBuf)> {
+    bail!("`--staged` requires join-ai to be built with the `git` feature.")
+}
+
+/// Extracts the commit message from the model's answer, without a code block
+/// around it.
+fn commit_message(answer: &str) -> String {
+    let answer = answer.trim();
+    let unfenced = answer
+        .strip_prefix("```")
+        .and_then(|rest| rest.split_once('\n'))
+        .and_then(|(_, rest)| rest.trim_end().strip_suffix("```"));
+    unfenced.unwrap_or(answer).trim().to_string()
+}
+
+/// Sends `question` about `context` to the configured provider, in parts if
+/// the context does not fit in the model's window. The final answer is passed
+/// to `on_text` as it is generated, if given.
+#[cfg(feature = "network")]
+fn complete(
+    args: &AskArgs,
+    source: Source,
+    question: &str,
+    context: &str,
+    on_text: Option<&mut dyn FnMut(&str)>,
+) -> anyhow::Result<String> {
+    use crate::remote::llm::{Client, Message, ReplyCache};
+
+    let mut client = Client::new(
+        args.provider,
+        args.model.as_deref(),
+        args.base_url.as_deref(),
+    )
+    .with_context_window(args.context_window);
+    if !args.no_cache {
+        let dir = std::path::Path::new(join_ai_core::cache::CACHE_DIR).join("llm");
+        client = client.with_cache(ReplyCache::new(dir, args.cache_ttl));
+    }
+    let part_tokens = part_budget(args.context_window, source.part_prompt(), question)?;
+    let parts = split_context(context, source.file_header(), part_tokens);
+    let name = format!("{} ({})", args.provider.name(), client.model());
+
+    if let [context] = parts.as_slice() {
+        events::progress(&format!("Asking {name}..."));
+        let messages = [
+            Message::system(source.prompt()),
+            Message::user(format!("{context}\n\n{question}")),
+        ];
+        return answer(&client, &messages, on_text);
+    }
+
+    events::progress(&format!(
+        "The context (~{} tokens) does not fit in --context-window {}; asking {name} in {} parts...",
+        estimate_tokens(context.len() as u64),
+        args.context_window,
+        parts.len()
+    ));
+    let mut answers = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        events::progress(&format!(
+            "Asking about part {} of {}...",
+            i + 1,
+            parts.len()
+        ));
+        let messages = [
+            Message::system(source.part_prompt()),
+            Message::user(format!("{part}\n\n{question}")),
+        ];
+        let answer = client.chat(&messages).map_err(crate::exit::network)?;
+        answers.push_str(&format!("## Part {}\n\n{}\n\n", i + 1, answer.trim()));
+    }
+
+    events::progress("Combining the answers...");
+    let messages = [
+        Message::system(SYNTHESIS_PROMPT),
+        Message::user(format!("{answers}{question}")),
+    ];
+    answer(&client, &messages, on_text)
+}
+
+/// Asks `client` for the final answer, streaming it to `on_text` if given.
+#[cfg(feature = "network")]
+fn answer(
+    client: &crate::remote::llm::Client,
+    messages: &[crate::remote::llm::Message],
+    on_text: Option<&mut dyn FnMut(&str)>,
+) -> anyhow::Result<String> {
+    match on_text {
+        Some(on_text) => client.chat_stream(messages, on_text),
+        None => client.chat(messages),
+    }
+    .map_err(crate::exit::network)
+}
+
+#[cfg(not(feature = "network"))]
+fn complete(
+    _args: &AskArgs,
+    _source: Source,
+    _question: &str,
+    _context: &str,
+    _on_text: Option<&mut dyn FnMut(&str)>,
+) -> anyhow::Result<String> {
+    bail!("`ask` requires join-ai to be built with the `network` feature.")
+}
+
+/// Returns how many tokens of context fit in each request to a model with
+/// `context_window` tokens, leaving room for the `instructions`, the question
+/// and a quarter of the window for the answer.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+fn part_budget(context_window: u64, instructions: &str, question: &str) -> anyhow::Result<u64> {
+    let prompt = estimate_tokens((instructions.len() + question.len()) as u64);
+    let budget = (context_window - context_window / 4).saturating_sub(prompt);
+    if budget < prompt {
+        bail!("--context-window {context_window} is too small to ask this question.");
+    }
+    Ok(budget)
+}
+
+/// Splits `context` into parts of at most `max_tokens` (estimated) each. Parts
+/// are split between files, which start with `file_header`, where possible,
+/// then between lines.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+fn split_context(context: &str, file_header: &str, max_tokens: u64) -> Vec<String> {
+    // The inverse of `estimate_tokens`, and at least one character.
+    let max_len = (max_tokens as usize).saturating_mul(4).max(4);
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    for section in file_sections(context, file_header) {
+        if !part.is_empty() && part.len() + section.len() > max_len {
+            parts.push(std::mem::take(&mut part));
+        }
+        if section.len() <= max_len {
+            part.push_str(section);
+            continue;
+        }
+        for line in section.split_inclusive('\n') {
+            if !part.is_empty() && part.len() + line.len() > max_len {
+                parts.push(std::mem::take(&mut part));
+            }
+            let mut line = line;
+            while line.len() > max_len {
+                let mut at = max_len;
+                while !line.is_char_boundary(at) {
+                    at -= 1;
+                }
+                parts.push(line[..at].to_string());
+                line = &line[at..];
+            }
+            part.push_str(line);
+        }
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+/// Splits the context into the text of each file, headers included. Anything
+/// before the first file, such as a `--git-info` block, stays with it.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+fn file_sections<'a>(context: &'a str, file_header: &str) -> Vec<&'a str> {
+    let mut starts: Vec<usize> = context
+        .match_indices(&format!("\n{file_header}"))
+        .map(|(at, _)| at + 1)
+        .collect();
+    starts.push(context.len());
+    let mut sections = Vec::with_capacity(starts.len());
+    let mut start = 0;
+    for end in starts {
+        if end > start {
+            sections.push(&context[start..end]);
+        }
+        start = end;
+    }
+    sections
+}
+
+// --- Unit Tests for Asking in Parts ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that parts are cut between files, that a file larger than a
+    /// part is cut between lines, and that nothing is lost or reordered.
+    #[test]
+    fn test_split_context() {
+        let context = "repo: x\n// FILE: a.rs\nfn a() {}\n\n// FILE: b.rs\nfn b() {}\n\n";
+        let header = Source::Files.file_header();
+        assert_eq!(
+            split_context(context, header, 1000),
+            vec![context.to_string()]
+        );
+
+        let parts = split_context(context, header, 9);
+        assert_eq!(
+            parts,
+            vec![
+                "repo: x\n// FILE: a.rs\nfn a() {}\n\n",
+                "// FILE: b.rs\nfn b() {}\n\n"
+            ]
+        );
+
+        let large = format!("// FILE: big.rs\n{}", "let x = 1;\n".repeat(10));
+        let parts = split_context(&large, header, 10);
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.len() <= 40));
+        assert_eq!(parts.concat(), large);
+
+        let wide = "é".repeat(30);
+        let parts = split_context(&wide, header, 3);
+        assert!(parts.iter().all(|part| part.len() <= 12));
+        assert_eq!(parts.concat(), wide);
+    }
+
+    /// Verifies that the budget leaves room for the answer, and that windows
+    /// too small for the question are rejected.
+    #[test]
+    fn test_part_budget() {
+        let budget = part_budget(32_000, PART_PROMPT, "Question: why?").unwrap();
+        assert!(budget < 24_000 && budget > 23_000);
+        assert!(part_budget(100, PART_PROMPT, "Question: why?").is_err());
+    }
+
+    /// Verifies that a diff is split between files.
+    #[test]
+    fn test_split_diff() {
+        let diff = "diff --git a/a.rs b/a.rs\n+fn a() {}\ndiff --git a/b.rs b/b.rs\n-fn b() {}\n";
+        assert_eq!(
+            split_context(diff, Source::StagedDiff.file_header(), 10),
+            vec![
+                "diff --git a/a.rs b/a.rs\n+fn a() {}\n",
+                "diff --git a/b.rs b/b.rs\n-fn b() {}\n"
+            ]
+        );
+    }
+
+    /// Verifies that a code block around a commit message is removed.
+    #[test]
+    fn test_commit_message() {
+        let message = "feat(cli): add --staged\n\nAsk about the staged diff.";
+        assert_eq!(commit_message(&format!("\n{message}\n")), message);
+        assert_eq!(commit_message(&format!("```text\n{message}\n```")), message);
+        assert_eq!(commit_message("fix: `x` in ```"), "fix: `x` in ```");
+    }
+}
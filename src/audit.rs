@@ -0,0 +1,225 @@
+//! The compliance audit log (`--audit-log`): one JSON record per join, appended
+//! to a file, telling who sent which files where and when.
+//!
+//! Each record holds the SHA-256 of the record before it, so that removing or
+//! editing a record breaks the chain, and is signed with HMAC-SHA256 using the
+//! key in [`KEY_VAR`], so that records cannot be forged without the key. The
+//! signature covers the record as compact JSON with sorted keys and without its
+//! `signature` field.
+
+use crate::cli::JoinArgs;
+use crate::input;
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use join_ai_core::JoinOptions;
+use join_ai_core::anonymize::PathMap;
+use join_ai_core::processor::Report;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The environment variable holding the key that signs audit records.
+pub const KEY_VAR: &str = "JOIN_AI_AUDIT_KEY";
+
+/// Returns the signing key, failing when it is not set, so that a join is not
+/// run without being logged.
+pub fn key() -> anyhow::Result<Vec<u8>> {
+    std::env::var(KEY_VAR)
+        .ok()
+        .filter(|key| !key.is_empty())
+        .map(String::into_bytes)
+        .with_context(|| {
+            format!("`--audit-log` signs each record with the key in {KEY_VAR}, which is not set.")
+        })
+}
+
+/// Where the joined files went: the output files of `join`, or the provider
+/// that `ask` and `embed` sent them to, with the joined content.
+pub enum Destination<'a> {
+    Files(&'a [PathBuf]),
+    Provider { url: &'a str, content: &'a [u8] },
+}
+
+/// Describes a join: who ran it and when, what was read, with which filters,
+/// and where the result went.
+pub fn record(
+    command: &str,
+    args: &JoinArgs,
+    options: &JoinOptions,
+    report: &Report,
+    destination: Destination,
+) -> Value {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let input = match (input::remote(args), args.stdin_tar) {
+        (Some((remote, spec)), _) => format!("{}:{spec}", remote.flag().trim_start_matches('-')),
+        (None, true) => "stdin".to_string(),
+        (None, false) => absolute(&options.input_folder),
+    };
+    let path_map = options
+        .anonymize_paths
+        .as_deref()
+        .and_then(|file| PathMap::load(file).ok());
+    let files: Vec<Value> = report
+        .included
+        .iter()
+        .map(|(path, bytes)| {
+            let hash = fs::read(source_path(options, path_map.as_ref(), path))
+                .ok()
+                .map(|content| sha256(&content));
+            json!({
+                "path": path.display().to_string(),
+                "bytes": bytes,
+                "sha256": hash,
+            })
+        })
+        .collect();
+    let (destination, output) = match destination {
+        Destination::Files(outputs) => (
+            outputs
+                .iter()
+                .map(|path| absolute(path))
+                .collect::<Vec<_>>(),
+            outputs
+                .iter()
+                .map(|path| fs::read(path).ok().map(|content| sha256(&content)))
+                .collect::<Vec<_>>(),
+        ),
+        Destination::Provider { url, content } => {
+            (vec![url.to_string()], vec![Some(sha256(content))])
+        }
+    };
+    json!({
+        "timestamp": timestamp,
+        "user": user(),
+        "host": host(),
+        "command": command,
+        "arguments": std::env::args().skip(1).collect::<Vec<_>>(),
+        "input": input,
+        "filters": {
+            "patterns": args.patterns,
+            "exclude": args.exclude,
+        },
+        "files": files,
+        "destination": destination,
+        "output_sha256": output,
+    })
+}
+
+/// Appends `record` to the log at `log`, chained to the last record and signed
+/// with `key`.
+pub fn append(log: &Path, mut record: Value, key: &[u8]) -> anyhow::Result<()> {
+    let previous = fs::read_to_string(log)
+        .ok()
+        .and_then(|text| text.lines().last().map(|line| sha256(line.as_bytes())));
+    record["previous"] = Value::from(previous);
+    record["signature"] = Value::from(sign(&record, key));
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .with_context(|| format!("Failed to open the audit log {}", log.display()))?;
+    writeln!(file, "{record}")
+        .with_context(|| format!("Failed to write to the audit log {}", log.display()))
+}
+
+/// Signs `record`, leaving out any `signature` field it already has.
+fn sign(record: &Value, key: &[u8]) -> String {
+    let mut unsigned = record.clone();
+    if let Some(fields) = unsigned.as_object_mut() {
+        fields.remove("signature");
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(unsigned.to_string().as_bytes());
+    hex(&mac.finalize().into_bytes())
+}
+
+pub(crate) fn sha256(content: &[u8]) -> String {
+    hex(&Sha256::digest(content))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn absolute(path: &Path) -> String {
+    std::path::absolute(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// The path on disk of a file listed in the report under its displayed path,
+/// or under its pseudonym in `path_map`.
+pub(crate) fn source_path(
+    options: &JoinOptions,
+    path_map: Option<&PathMap>,
+    shown: &Path,
+) -> PathBuf {
+    if let Some(real) = path_map.and_then(|map| map.real(shown)) {
+        return options.input_folder.join(real);
+    }
+    match &options.display_root {
+        Some(root) => options
+            .input_folder
+            .join(shown.strip_prefix(root).unwrap_or(shown)),
+        None => shown.to_path_buf(),
+    }
+}
+
+/// The name of the user running the process.
+fn user() -> Option<String> {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|name| !name.is_empty()))
+}
+
+/// The name of the machine running the process.
+fn host() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+// --- Unit Tests for the Audit Log ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+
+    /// Verifies that records are appended as signed JSON lines, each chained to
+    /// the one before it, and that changing a record breaks its signature.
+    #[test]
+    fn test_append_chains_and_signs_records() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let log = dir.path().join("audit.jsonl");
+        append(
+            &log,
+            json!({ "command": "join", "files": ["a.rs"] }),
+            b"key",
+        )?;
+        append(&log, json!({ "command": "ask", "files": ["b.rs"] }), b"key")?;
+
+        let text = fs::read_to_string(&log)?;
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: Value = serde_json::from_str(lines[0])?;
+        let second: Value = serde_json::from_str(lines[1])?;
+        assert!(first["previous"].is_null());
+        assert_eq!(second["previous"], sha256(lines[0].as_bytes()));
+        assert_eq!(second["signature"], sign(&second, b"key"));
+        assert_ne!(second["signature"], sign(&second, b"other key"));
+
+        let mut forged = second.clone();
+        forged["files"] = json!(["c.rs"]);
+        assert_ne!(forged["signature"], sign(&forged, b"key"));
+        Ok(())
+    }
+}
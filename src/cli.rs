@@ -1,21 +1,154 @@
-use clap::{Args as ClapArgs, ColorChoice, Parser, Subcommand};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use join_ai_core::decommenter::{DEFAULT_KEEP_MARKERS, HeaderMode};
+use join_ai_core::events::LogFormat;
+use join_ai_core::format::OutputFormat;
+use join_ai_core::secrets::SecretsPolicy;
+use join_ai_core::{ErrorPolicy, ExportLayout, JoinOptions, SortOrder, StripMode, SubmoduleMode};
+use std::ffi::OsString;
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// A CLI application to traverse files in a folder and concatenate them
 /// into a single text file, suitable for GenAI model input.
 #[derive(Parser, Debug, Clone)]
-#[command(author, version, about, long_about = None, color = ColorChoice::Always)]
+#[command(author, version, about, long_about = None)]
 pub struct Cli {
     /// The subcommand to execute (e.g., 'join' or 'update').
     #[command(subcommand)]
     pub command: Commands,
+
+    /// When to use colors: auto (only on terminals, honoring NO_COLOR), always,
+    /// or never.
+    #[arg(long, global = true, value_enum, default_value_t = ColorMode::Auto, value_name = "WHEN")]
+    pub color: ColorMode,
+}
+
+/// When colored output is used.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Use colors when writing to a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    /// Always use colors.
+    Always,
+    /// Never use colors.
+    Never,
+}
+
+impl ColorMode {
+    /// Determines the color mode before the arguments are parsed, since the help
+    /// and error messages of the parser itself are already styled.
+    ///
+    /// An explicit `--color` wins; otherwise a non-empty `no_color` (the value of
+    /// the `NO_COLOR` environment variable) disables colors.
+    pub fn detect(args: impl IntoIterator<Item = OsString>, no_color: Option<OsString>) -> Self {
+        let mut args = args
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned());
+        let mut explicit = None;
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                break;
+            }
+            let value = match arg.strip_prefix("--color") {
+                Some("") => args.next(),
+                Some(rest) => rest.strip_prefix('=').map(str::to_string),
+                None => None,
+            };
+            if let Some(mode) = value.and_then(|value| Self::from_str(&value, true).ok()) {
+                explicit = Some(mode);
+            }
+        }
+        match explicit {
+            Some(mode) => mode,
+            None if no_color.is_some_and(|value| !value.is_empty()) => Self::Never,
+            None => Self::Auto,
+        }
+    }
+
+    /// Whether paths are printed as terminal hyperlinks (OSC 8) to their
+    /// files, given the environment variable lookup `var` and whether the
+    /// output goes to a `terminal`.
+    ///
+    /// Links are used with `--color never` never, and otherwise on terminals
+    /// known to support them (even when redirected, with `--color always`). A
+    /// `FORCE_HYPERLINK` variable overrides the detection: `0` disables them,
+    /// any other value enables them.
+    pub fn hyperlinks(self, var: impl Fn(&str) -> Option<String>, terminal: bool) -> bool {
+        if self == Self::Never {
+            return false;
+        }
+        if let Some(force) = var("FORCE_HYPERLINK").filter(|value| !value.is_empty()) {
+            return force != "0";
+        }
+        if self == Self::Auto && !terminal {
+            return false;
+        }
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let term = var("TERM").unwrap_or_default();
+        [
+            "iTerm.app",
+            "WezTerm",
+            "vscode",
+            "ghostty",
+            "Hyper",
+            "Tabby",
+        ]
+        .contains(&program.as_str())
+            || [
+                "xterm-kitty",
+                "alacritty",
+                "foot",
+                "xterm-ghostty",
+                "wezterm",
+            ]
+            .contains(&term.as_str())
+            || var("VTE_VERSION")
+                .and_then(|version| version.parse::<u32>().ok())
+                .is_some_and(|version| version >= 5000)
+            || ["WT_SESSION", "KONSOLE_VERSION", "DOMTERM"]
+                .iter()
+                .any(|name| var(name).is_some())
+    }
+}
+
+#[cfg(feature = "color")]
+impl From<ColorMode> for clap::ColorChoice {
+    fn from(mode: ColorMode) -> Self {
+        match mode {
+            ColorMode::Auto => clap::ColorChoice::Auto,
+            ColorMode::Always => clap::ColorChoice::Always,
+            ColorMode::Never => clap::ColorChoice::Never,
+        }
+    }
 }
 
 /// Defines the available subcommands for the application.
+// The enum is built once per run, so the size of `JoinArgs` does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     /// Concatenate files into a single text file.
     Join(JoinArgs),
+    /// Strip comments from a single file or stdin and write it to stdout.
+    Strip(StripArgs),
+    /// Join files and ask a language model a question about them.
+    Ask(AskArgs),
+    /// Split files into chunks and write their embeddings to a JSONL file.
+    Embed(EmbedArgs),
+    /// Estimate the tokens of the files a join would include, and where they
+    /// come from.
+    Tokens(TokensArgs),
+    /// Propose patterns that leave out vendored, generated, data and huge
+    /// files, with the tokens each saves.
+    Suggest(SuggestArgs),
+    /// Serve joins over HTTP, for tools that pack repositories on request.
+    Serve(ServeArgs),
+    /// Check `.join-ai.toml` and keep its context files up to date from a git
+    /// pre-commit hook.
+    Hook(HookArgs),
+    /// Keep the tokens of language models and code hosts in the OS keyring.
+    Config(ConfigArgs),
     /// Update the application to the latest version [placeholder].
     Update(UpdateArgs),
 }
@@ -23,16 +156,125 @@ pub enum Commands {
 /// Defines the arguments for the 'join' subcommand.
 #[derive(ClapArgs, Debug, Clone)]
 pub struct JoinArgs {
-    /// The root folder to start traversing for files. This is a required argument.
-    #[arg(required = true)]
-    pub input_folder: PathBuf,
+    /// The root folder to start traversing for files, or an archive (.zip, .tar,
+    /// .tar.gz) to extract and join. `ask` also reads a context pack
+    /// (.joinpack) written by `--pack`. Required unless another input such as
+    /// `--github` or `--stdin-tar` is given.
+    #[arg(required_unless_present_any = ["remote", "stdin_tar"], conflicts_with = "remote")]
+    pub input_folder: Option<PathBuf>,
+
+    /// Fetch the files of a GitHub repository through the API instead of reading a
+    /// local folder, in the form owner/repo[@ref]. Uses GITHUB_TOKEN if set.
+    #[arg(long, value_name = "OWNER/REPO[@REF]", group = "remote")]
+    pub github: Option<String>,
+
+    /// Fetch the files changed by a GitHub pull request, as they are at its
+    /// head, in the form owner/repo#number. Deleted files are left out. Uses
+    /// GITHUB_TOKEN if set.
+    #[arg(
+        long,
+        value_name = "OWNER/REPO#NUMBER",
+        group = "remote",
+        group = "request"
+    )]
+    pub github_pr: Option<String>,
+
+    /// Fetch the files of a GitLab project through the API, in the form
+    /// group/project[@ref]. Uses GITLAB_TOKEN if set, and GITLAB_API_URL for
+    /// self-hosted instances.
+    #[arg(long, value_name = "GROUP/PROJECT[@REF]", group = "remote")]
+    pub gitlab: Option<String>,
+
+    /// Fetch the files changed by a GitLab merge request, as they are at its
+    /// head, in the form group/project!number. Deleted files are left out.
+    #[arg(
+        long,
+        value_name = "GROUP/PROJECT!NUMBER",
+        group = "remote",
+        group = "request"
+    )]
+    pub gitlab_mr: Option<String>,
+
+    /// Fetch the files of a Bitbucket Cloud repository through the API, in the
+    /// form workspace/repo[@ref]. Uses BITBUCKET_TOKEN, or BITBUCKET_USERNAME
+    /// and BITBUCKET_APP_PASSWORD, if set.
+    #[arg(long, value_name = "WORKSPACE/REPO[@REF]", group = "remote")]
+    pub bitbucket: Option<String>,
+
+    /// Fetch the files changed by a Bitbucket Cloud pull request, as they are
+    /// at its head, in the form workspace/repo#number. Deleted files are left
+    /// out.
+    #[arg(
+        long,
+        value_name = "WORKSPACE/REPO#NUMBER",
+        group = "remote",
+        group = "request"
+    )]
+    pub bitbucket_pr: Option<String>,
+
+    /// With `--github-pr`, `--gitlab-mr` or `--bitbucket-pr`, also include the
+    /// description of the request, its reviews and comments, as
+    /// `PULL_REQUEST.md`.
+    #[arg(
+        long,
+        requires = "request",
+        // A requirement is waived when a conflicting input is given instead.
+        conflicts_with_all = ["input_folder", "github", "gitlab", "bitbucket", "stdin_tar"]
+    )]
+    pub pr_discussion: bool,
+
+    /// Read the input tree from a tar stream (optionally gzip-compressed) on stdin,
+    /// e.g. `git archive HEAD | join-ai join --stdin-tar`.
+    #[arg(long, conflicts_with_all = ["input_folder", "remote", "rev"])]
+    pub stdin_tar: bool,
 
     /// The path to the output file where the concatenated content will be written.
     #[arg(short, long, default_value = "concatenated.txt")]
     pub output_file: PathBuf,
 
+    /// How the files are laid out: `text`, with `// FILE:` headers, or
+    /// `markdown`, with a heading and a fenced code block per file.
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub format: Option<OutputFormat>,
+
+    /// Split the output into numbered parts (e.g., `concatenated-1.txt`) of at
+    /// most this size, between files, in bytes or with a unit suffix.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub split_size: Option<u64>,
+
+    /// Lay out the output for the tool it is uploaded to, picking the format and
+    /// the split size that suit it. Explicit `--format` and `--split-size` win.
+    #[arg(long = "for", value_enum, value_name = "TOOL")]
+    pub target: Option<Target>,
+
+    /// Copy the files into this folder instead of joining them, through the
+    /// same filters and transforms (e.g., `--strip-comments`, `--redact`), for
+    /// tools that read a folder of files rather than one file.
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = [
+            "split_size", "target", "list_only", "verify", "check", "open", "git_info",
+            "dependencies", "include_agent_rules", "module_graph", "group_by_dir", "symbols",
+        ]
+    )]
+    pub export_dir: Option<PathBuf>,
+
+    /// How `--export-dir` lays out the files: mirror, at their paths in the
+    /// input folder, or flat, all in the folder itself with their folders
+    /// joined into their names (e.g., `src__cli.rs`).
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ExportLayout::Mirror,
+        value_name = "LAYOUT",
+        requires = "export_dir"
+    )]
+    pub export_layout: ExportLayout,
+
     /// Glob patterns for files to *include*. Can be specified multiple times.
-    /// If not provided, all files are considered (subject to exclusions).
+    /// If neither these nor exclusions are provided, the patterns of the type
+    /// of project are used (see `--no-detect`), or else all files.
     /// Example: -p "*.rs" -p "*.md"
     #[arg(short = 'p', long, action = clap::ArgAction::Append, value_name = "PATTERN")]
     pub patterns: Option<Vec<String>>,
@@ -43,6 +285,33 @@ pub struct JoinArgs {
     #[arg(short = 'x', long, action = clap::ArgAction::Append, value_name = "PATTERN")]
     pub exclude: Option<Vec<String>>,
 
+    /// Join all files when no patterns are given, rather than the sources of
+    /// the project type detected from Cargo.toml, package.json, pyproject.toml
+    /// or go.mod, without its build outputs and dependencies. Projects with a
+    /// `.join-ai.toml` are never detected.
+    #[arg(long)]
+    pub no_detect: bool,
+
+    /// Run COMMAND and include its output as a file named NAME, after the files
+    /// of the input folder, e.g. `--exec-file "deps.txt:cargo tree --depth 2"`.
+    /// The command runs in the current directory through the shell, and a
+    /// failure stops the join. Can be specified multiple times.
+    #[arg(long, action = clap::ArgAction::Append, value_name = "NAME:COMMAND", value_parser = parse_exec_file)]
+    pub exec_file: Vec<ExecFile>,
+
+    /// Fetch a GitHub issue with its comments and include it as `ISSUE-N.md`,
+    /// ahead of the files, given as a URL, as owner/repo#number, or as a
+    /// number in the repository of the input (its `origin` remote for a local
+    /// folder). Uses GITHUB_TOKEN if set. Can be specified multiple times.
+    #[arg(long, action = clap::ArgAction::Append, value_name = "ISSUE")]
+    pub issue: Vec<String>,
+
+    /// Fetch URL and include the document as a file named after its last path
+    /// segment, ahead of the files, e.g. a ticket or a design document. Can be
+    /// specified multiple times.
+    #[arg(long, action = clap::ArgAction::Append, value_name = "URL")]
+    pub context_url: Vec<String>,
+
     /// If set, the output file will be deleted before writing new content.
     #[arg(short, long)]
     pub clear_file: bool,
@@ -52,6 +321,161 @@ pub struct JoinArgs {
     #[arg(long)]
     pub max_depth: Option<usize>,
 
+    /// Leave out files larger than this size, in bytes or with a unit suffix
+    /// (e.g., 500K, 2M).
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_file_size: Option<u64>,
+
+    /// Warn when the output is larger than this size, listing the largest files
+    /// and folders and how to exclude them. Use 0 to disable the warning.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "5M")]
+    pub warn_size: u64,
+
+    /// The token budget of the output, estimated at four bytes per token. Files are
+    /// included in order until the budget is spent; the rest are left out. Files
+    /// with a `join-ai: priority=high` comment go first and are always kept.
+    #[arg(long, value_name = "TOKENS")]
+    pub max_tokens: Option<u64>,
+
+    /// Include only the files most relevant to this question or topic, most
+    /// relevant first, ranked by the words of their identifiers and paths
+    /// (BM25). Combine with `--max-tokens` to fit them in a budget.
+    #[arg(long, value_name = "QUERY")]
+    pub relevant_to: Option<String>,
+
+    /// How many files `--relevant-to` includes, besides files with a
+    /// `join-ai: priority=high` comment.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "relevant_to"
+    )]
+    pub top_k: u64,
+
+    /// Include only this file (relative to the input folder) and the files it
+    /// imports, found per language (Rust, Python, JavaScript/TypeScript, Go,
+    /// C/C++, Java/Kotlin/Scala). Patterns and exclusions still apply.
+    #[arg(long, value_name = "FILE")]
+    pub entry: Option<PathBuf>,
+
+    /// How many levels of imports `--entry` follows: 1 includes the files the
+    /// entry imports, 2 also the files those import, and so on.
+    #[arg(long, value_name = "DEPTH", default_value_t = 1, requires = "entry")]
+    pub follow_imports: usize,
+
+    /// The order of the files: by path; by churn (the files changed by the
+    /// most git commits within `--churn-days` first), so that the busiest code
+    /// survives a `--max-tokens` budget; smart (READMEs and architecture
+    /// docs, then manifests, then entry points, then the rest by path); or
+    /// coverage (the files least run by the tests of `--coverage` first, e.g.
+    /// to ask for tests). `--entry`, `--relevant-to` and `join-ai: priority`
+    /// comments still come first.
+    #[arg(long, value_enum, default_value_t = SortOrder::Path, value_name = "ORDER")]
+    pub sort: SortOrder,
+
+    /// How many days of history `--sort churn` counts commits over.
+    #[arg(long, value_name = "DAYS", default_value_t = 90)]
+    pub churn_days: u64,
+
+    /// An lcov (lcov.info) or Cobertura XML coverage report. Each file header
+    /// states the share of the file's lines run by the tests, and
+    /// `--sort coverage` puts the least tested files first.
+    #[arg(long, value_name = "FILE")]
+    pub coverage: Option<PathBuf>,
+
+    /// Write files that are almost the same as an earlier file (copied
+    /// modules, generated variants) only once: the header of the file that is
+    /// written lists the others. `--near-duplicates=95` sets how similar, in
+    /// percent, files must be (default 90).
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "90",
+        value_parser = clap::value_parser!(u8).range(50..=100)
+    )]
+    pub near_duplicates: Option<u8>,
+
+    /// Show which files would be included, with their sizes and the projected
+    /// total, without writing the output file.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Join into memory instead of the output file, report the size of the
+    /// output, and exit with code 4 if it does not fit in `--max-tokens`. Unlike
+    /// a normal join, no file is left out to fit the budget.
+    #[arg(long, requires = "max_tokens", conflicts_with_all = ["dry_run", "watch"])]
+    pub check: bool,
+
+    /// Write only the paths of the files, with their size, estimated tokens
+    /// and the metadata of their headers (e.g., `--git-meta`), in the chosen
+    /// format: a table of contents for a first pass of a model, which can then
+    /// ask for specific files.
+    #[arg(long, conflicts_with_all = ["symbols", "verify"])]
+    pub list_only: bool,
+
+    /// Read the output back once written, splitting it into files at their
+    /// headers, and fail if a file does not come back byte for byte, as when
+    /// its content has a line that starts with `// FILE:`.
+    #[arg(long, conflicts_with_all = ["dry_run", "check"])]
+    pub verify: bool,
+
+    /// Write `join-ai.lock` in the current directory, listing the files of the
+    /// join in order with the SHA-256 of each, for `--frozen` to reproduce.
+    #[arg(long, conflicts_with_all = ["frozen", "dry_run", "check"])]
+    pub lock: bool,
+
+    /// Also bundle the output, a manifest, the lock file of the join and its
+    /// configuration into this `.joinpack` file, to share a context that can
+    /// be checked and joined again. `ask` reads a pack in place of an input
+    /// folder.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["dry_run", "check", "export_dir"])]
+    pub pack: Option<PathBuf>,
+
+    /// Encrypt the output, and the pack of `--pack`, with age to this
+    /// recipient: an age public key (`age1...`), an SSH public key, or a file
+    /// that lists them one per line. The encrypted files get an `.age`
+    /// extension, and no plaintext copy is left. Can be specified multiple
+    /// times.
+    #[arg(
+        long,
+        value_name = "RECIPIENT",
+        action = clap::ArgAction::Append,
+        conflicts_with_all = ["dry_run", "check", "export_dir", "open"]
+    )]
+    pub encrypt_to: Option<Vec<String>>,
+
+    /// Join exactly the files listed in `join-ai.lock`, in its order, and fail
+    /// if any of them has changed or is missing. Patterns, `--sort` and the
+    /// options that select files are not applied again.
+    #[arg(long, conflicts_with = "watch")]
+    pub frozen: bool,
+
+    /// Open the output file after the join, in `$VISUAL`/`$EDITOR` or else with
+    /// the default application of the operating system.
+    #[arg(long, conflicts_with = "dry_run")]
+    pub open: bool,
+
+    /// Append a signed, timestamped JSON record of the join to this file: who
+    /// ran it, the input, the filters, the hash of each file read, and where
+    /// the output went. Records are signed with the key in `JOIN_AI_AUDIT_KEY`.
+    #[arg(long, value_name = "FILE")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Keep running, and join again whenever one of the selected files changes.
+    /// Only local folders can be watched.
+    #[arg(long, conflicts_with_all = ["remote", "stdin_tar", "rev"])]
+    pub watch: bool,
+
+    /// In watch mode, show a desktop notification after each re-join with the
+    /// new token count and whether it exceeds `--max-tokens`. Requires the
+    /// `notify` feature.
+    #[arg(long, requires = "watch")]
+    pub notify: bool,
+
     /// If set, hidden files and directories (those starting with a '.') will be included.
     #[arg(long)]
     pub hidden: bool,
@@ -59,6 +483,815 @@ pub struct JoinArgs {
     /// If set to false, the walker will follow symbolic links. Defaults to true (no-follow).
     #[arg(long, default_value_t = true)]
     pub no_follow: bool,
+
+    /// If set, files are read through memory maps instead of buffered reads, which
+    /// can be faster for large repositories on fast disks. Files that cannot be
+    /// mapped are read normally.
+    #[arg(long)]
+    pub mmap: bool,
+
+    /// If set, transformed files are cached in `.join-ai/cache` inside the input
+    /// folder, and only files that changed since the last run are read and
+    /// transformed again.
+    #[arg(long)]
+    pub cache: bool,
+
+    /// If set, line endings are normalized to LF and byte order marks are removed.
+    #[arg(long)]
+    pub normalize: bool,
+
+    /// If set, comments are removed from source files in a known language
+    /// (detected from the file name, extension or shebang). Other files are
+    /// left unchanged. `--strip-comments=keep-doc` keeps documentation comments
+    /// and docstrings.
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "all",
+        conflicts_with = "blame"
+    )]
+    pub strip_comments: Option<StripMode>,
+
+    /// Comma-separated markers of comments kept by `--strip-comments`: a
+    /// comment whose first word is a marker (e.g., `// TODO: retry`) survives.
+    /// Pass `--keep-comments=` to remove those comments too.
+    #[arg(
+        long,
+        value_name = "MARKERS",
+        value_delimiter = ',',
+        default_values = DEFAULT_KEEP_MARKERS
+    )]
+    pub keep_comments: Vec<String>,
+
+    /// If set with `--strip-comments`, preprocessor blocks that are never
+    /// compiled (`#if 0 … #endif`, and the `#else` of `#if 1`) are removed from
+    /// C, C++ and Objective-C files.
+    #[arg(long, requires = "strip_comments")]
+    pub strip_disabled: bool,
+
+    /// If set with `--strip-comments`, removed lines are left blank, so each
+    /// file keeps the line numbers of the original.
+    #[arg(long, requires = "strip_comments")]
+    pub preserve_lines: bool,
+
+    /// If set with `--strip-comments`, the first comment block of each file is
+    /// kept when it looks like a license or copyright notice, or always with
+    /// `--keep-header=always`.
+    #[arg(
+        long,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "license",
+        requires = "strip_comments"
+    )]
+    pub keep_header: Option<HeaderMode>,
+
+    /// If set, likely secrets (private keys, API tokens, passwords) are replaced
+    /// with a `[REDACTED]` placeholder.
+    #[arg(long)]
+    pub redact: bool,
+
+    /// If set, personal data (email addresses, phone numbers, IP addresses and
+    /// person-like names) is replaced with a placeholder of its kind, such as
+    /// `[EMAIL]`. What was masked in each file is reported at the end.
+    #[arg(long)]
+    pub redact_pii: bool,
+
+    /// Scan for likely secrets, by known formats and by the entropy of quoted
+    /// and assigned values, and warn about them, redact them, or stop the join
+    /// without writing anything (`block`). Lines marked with a
+    /// `join-ai: allow-secret` comment are not scanned.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    pub secrets_policy: Option<SecretsPolicy>,
+
+    /// Describe images (PNG, JPEG, GIF, WebP and SVG) with a vision model, and
+    /// include the description instead of leaving the image out.
+    #[arg(long)]
+    pub describe_images: bool,
+
+    /// The provider of the vision model, as in `ask`.
+    #[arg(long, value_enum, default_value_t = Provider::Ollama, requires = "describe_images")]
+    pub vision_provider: Provider,
+
+    /// The vision model. Defaults to `llava` for Ollama and `gpt-4o-mini` for
+    /// OpenAI.
+    #[arg(long, value_name = "MODEL", requires = "describe_images")]
+    pub vision_model: Option<String>,
+
+    /// The base URL of the vision provider's API, as `--base-url` in `ask`.
+    #[arg(long, value_name = "URL", requires = "describe_images")]
+    pub vision_base_url: Option<String>,
+
+    /// Images larger than this are left out rather than described.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        default_value = "4M",
+        requires = "describe_images"
+    )]
+    pub max_image_size: u64,
+
+    /// If set, a block with the repository name, branch, HEAD commit, dirty/clean
+    /// status and remote URL is written at the top of the output.
+    #[arg(long)]
+    pub git_info: bool,
+
+    /// Replace the paths in file headers with stable pseudonyms such as
+    /// `dir_01/file_07.rs`, which keep the folder structure and extensions, and
+    /// write the real paths to the mapping file FILE, which is meant to stay
+    /// private. Later joins with the same mapping file reuse its pseudonyms.
+    #[arg(long, value_name = "FILE", conflicts_with = "git_info")]
+    pub anonymize_paths: Option<PathBuf>,
+
+    /// If set, the dependencies declared in Cargo.toml, package.json,
+    /// pyproject.toml and go.mod files are listed at the top of the output, and
+    /// lockfiles (Cargo.lock, package-lock.json, go.sum, …) are left out.
+    #[arg(long)]
+    pub dependencies: bool,
+
+    /// If set, the instructions the repository gives to coding assistants
+    /// (AGENTS.md and CLAUDE.md files, .cursorrules and
+    /// .github/copilot-instructions.md) are written in a labeled section at
+    /// the top of the output instead of among the other files.
+    #[arg(long, conflicts_with = "list_only")]
+    pub include_agent_rules: bool,
+
+    /// If set, a module graph is written before the files: for each file, the
+    /// other files of the join it imports and the ones that import it, found
+    /// as for `--entry`.
+    #[arg(long)]
+    pub module_graph: bool,
+
+    /// If set, the output is organized in sections, one per top-level folder
+    /// (and one for the files at the top), each titled with the folder, its
+    /// number of files and their estimated tokens.
+    #[arg(long)]
+    pub group_by_dir: bool,
+
+    /// If set, an index of the functions, types and classes defined in the
+    /// files (Rust, Python, JavaScript/TypeScript, Go, Java/Kotlin/Scala/C#,
+    /// C/C++, Ruby), each with its file and line, is written at the end of the
+    /// output. The index is not counted in `--max-tokens`.
+    #[arg(long)]
+    pub symbols: bool,
+
+    /// If set, the license of each included file is detected, from its
+    /// `SPDX-License-Identifier` header or the nearest LICENSE or COPYING file,
+    /// and the licenses are reported at the end of the run.
+    #[arg(long)]
+    pub licenses: bool,
+
+    /// Comma-separated SPDX identifiers of licenses (e.g., `GPL-3.0`), or
+    /// `copyleft` for all copyleft licenses, that stop the join, before
+    /// anything is written, when an included file has one. Implies `--licenses`.
+    #[arg(long, value_name = "LICENSES", value_delimiter = ',')]
+    pub deny_license: Vec<String>,
+
+    /// If set, each block of lines is annotated with the author and age of the
+    /// commit that last changed it (like `git blame`).
+    #[arg(long)]
+    pub blame: bool,
+
+    /// If set, each file header includes the hash, date and subject of the last
+    /// commit that changed the file.
+    #[arg(long)]
+    pub git_meta: bool,
+
+    /// Join the files as they are at a git revision (branch, tag or commit) instead
+    /// of the working tree. Contents are read from the object database, so local
+    /// changes are neither included nor disturbed.
+    #[arg(long, value_name = "REF", conflicts_with = "remote")]
+    pub rev: Option<String>,
+
+    /// How git submodules are handled: skip them, include checked-out ones like
+    /// regular folders, or write only a stub with their pinned commit (shallow).
+    /// Remote inputs are downloaded without their submodules, so it cannot be
+    /// set for them; join a `git clone --recurse-submodules` instead.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SubmoduleMode::Include,
+        value_name = "MODE",
+        conflicts_with = "remote"
+    )]
+    pub submodules: SubmoduleMode,
+
+    /// Write the objects of Git LFS pointer files in their place, read from
+    /// the local object store or downloaded with `git lfs smudge`. Objects
+    /// that are binary are skipped. Without it, pointers are left out.
+    #[arg(long)]
+    pub lfs_fetch: bool,
+
+    /// Join only this workspace package (Cargo, npm/pnpm or Go module) plus the
+    /// in-repo packages it depends on. Can be specified multiple times.
+    #[arg(long = "package", action = clap::ArgAction::Append, value_name = "NAME")]
+    pub packages: Option<Vec<String>>,
+
+    /// Join only the files that CODEOWNERS assigns to this user or team, e.g.
+    /// `@acme/backend`, or `@backend` for a team of any organization. Can be
+    /// specified multiple times.
+    #[arg(long = "owner", action = clap::ArgAction::Append, value_name = "OWNER")]
+    pub owners: Option<Vec<String>>,
+
+    /// If set, each file header names the owners of the file in CODEOWNERS.
+    #[arg(long)]
+    pub show_owners: bool,
+
+    /// Give up on a file whose read takes longer than this many seconds (e.g., on
+    /// a hung network mount), and report it as a failure. FIFOs and devices are
+    /// never read, with or without a timeout.
+    #[arg(long, value_name = "SECS")]
+    pub read_timeout: Option<f64>,
+
+    /// What happens when a file cannot be read or transformed: continue and list
+    /// the failures at the end, stop at the first one (fail-fast), or leave such
+    /// files out without reporting them (skip-silent).
+    #[arg(long, value_enum, default_value_t = ErrorPolicy::Continue, value_name = "POLICY")]
+    pub error_policy: ErrorPolicy,
+
+    /// Explain which files the walk left out: -v counts them, -vv groups them by
+    /// the pattern or ignore rule that decided, and -vvv also lists the files.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// How progress is reported: human-readable text, or one JSON event per line
+    /// on stderr (file_included, file_skipped, warning, summary) for tools.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, value_name = "FORMAT")]
+    pub log_format: LogFormat,
+
+    /// The exit code used when some files could not be read (e.g., permission
+    /// errors) under the `continue` error policy. The join still completes, and
+    /// the failures are listed at the end. Use 0 to succeed regardless.
+    #[arg(long, default_value_t = 3, value_name = "CODE")]
+    pub read_error_exit_code: u8,
+}
+
+/// A tool the output is uploaded to, with the layout it ingests best.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Claude Projects: Markdown files well under the 30 MB upload limit.
+    ClaudeProjects,
+    /// NotebookLM: Markdown sources small enough to be indexed whole.
+    #[value(name = "notebooklm")]
+    NotebookLm,
+    /// Gemini: one Markdown file, which its long context reads at once.
+    Gemini,
+    /// Cursor: Markdown files small enough to attach to a chat.
+    Cursor,
+}
+
+impl Target {
+    /// The format the tool reads best. All of them render Markdown headings
+    /// and code blocks, which keep the path and language of each file apparent.
+    pub fn format(self) -> OutputFormat {
+        OutputFormat::Markdown
+    }
+
+    /// The largest file the tool handles well, if any.
+    pub fn split_size(self) -> Option<u64> {
+        match self {
+            Self::ClaudeProjects => Some(20 * 1024 * 1024),
+            Self::NotebookLm => Some(2 * 1024 * 1024),
+            Self::Gemini => None,
+            Self::Cursor => Some(1024 * 1024),
+        }
+    }
+}
+
+impl From<&JoinArgs> for JoinOptions {
+    /// Converts the parsed command-line arguments into the options understood
+    /// by the core library.
+    fn from(args: &JoinArgs) -> Self {
+        JoinOptions {
+            input_folder: args.input_folder.clone().unwrap_or_default(),
+            output_file: args.output_file.clone(),
+            patterns: args.patterns.clone(),
+            exclude: args.exclude.clone(),
+            files: None,
+            max_depth: args.max_depth,
+            max_file_size: args.max_file_size,
+            max_tokens: args.max_tokens,
+            relevant_to: args.relevant_to.clone(),
+            top_k: args.top_k as usize,
+            entry: args.entry.clone(),
+            follow_imports: args.follow_imports,
+            sort: args.sort,
+            churn_days: args.churn_days,
+            coverage: args.coverage.clone(),
+            near_duplicates: args.near_duplicates,
+            lfs_fetch: args.lfs_fetch,
+            dry_run: args.dry_run,
+            list_only: args.list_only,
+            verify: args.verify,
+            format: args
+                .format
+                .or(args.target.map(Target::format))
+                .unwrap_or_default(),
+            split_size: args
+                .split_size
+                .or_else(|| args.target.and_then(Target::split_size)),
+            export_dir: args.export_dir.clone(),
+            export_layout: args.export_layout,
+            hidden: args.hidden,
+            follow_links: !args.no_follow,
+            mmap: args.mmap,
+            cache: args.cache,
+            normalize: args.normalize,
+            strip_comments: args.strip_comments,
+            keep_comments: args
+                .keep_comments
+                .iter()
+                .filter(|marker| !marker.is_empty())
+                .cloned()
+                .collect(),
+            strip_disabled: args.strip_disabled,
+            preserve_lines: args.preserve_lines,
+            keep_header: args.keep_header,
+            redact: args.redact,
+            redact_pii: args.redact_pii,
+            secrets_policy: args.secrets_policy,
+            git_info: args.git_info,
+            dependencies: args.dependencies,
+            agent_rules: args.include_agent_rules,
+            module_graph: args.module_graph,
+            group_by_dir: args.group_by_dir,
+            symbols: args.symbols,
+            licenses: args.licenses,
+            deny_licenses: args.deny_license.clone(),
+            blame: args.blame,
+            git_meta: args.git_meta,
+            submodules: args.submodules,
+            read_timeout: args.read_timeout.map(Duration::from_secs_f64),
+            error_policy: args.error_policy,
+            packages: args.packages.clone(),
+            owners: args.owners.clone(),
+            show_owners: args.show_owners,
+            display_root: None,
+            // Commands are run when the input is prepared.
+            virtual_files: Vec::new(),
+            leading_files: Vec::new(),
+            anonymize_paths: args.anonymize_paths.clone(),
+        }
+    }
+}
+
+/// A command whose output is included as a file (`--exec-file`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecFile {
+    /// The path shown in the header, relative to the input folder.
+    pub name: PathBuf,
+    pub command: String,
+}
+
+/// Parses `NAME:COMMAND`, where NAME is a relative path.
+fn parse_exec_file(value: &str) -> Result<ExecFile, String> {
+    let Some((name, command)) = value.split_once(':') else {
+        return Err(format!(
+            "'{value}' is not NAME:COMMAND (e.g., deps.txt:cargo tree)"
+        ));
+    };
+    let name = PathBuf::from(name.trim());
+    let relative = name
+        .components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)));
+    if name.as_os_str().is_empty() || !relative {
+        return Err(format!(
+            "the name '{}' must be a relative path without `..`",
+            name.display()
+        ));
+    }
+    if command.trim().is_empty() {
+        return Err(format!("no command is given for '{}'", name.display()));
+    }
+    Ok(ExecFile {
+        name,
+        command: command.trim().to_string(),
+    })
+}
+
+/// Parses a size such as `4096`, `500K` or `2MB` into bytes.
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{value}' is not a size (e.g., 4096, 500K, 2M)"))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(format!("unknown size unit in '{value}' (use K, M or G)")),
+    };
+    Ok(number * multiplier)
+}
+
+/// Parses a duration such as `90`, `30s`, `15m`, `12h` or `7d`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{value}' is not a duration (e.g., 90s, 30m, 12h, 7d)"))?;
+    let seconds = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown duration unit in '{value}' (use s, m, h or d)"
+            ));
+        }
+    };
+    Ok(Duration::from_secs(number * seconds))
+}
+
+/// Defines the arguments for the 'strip' subcommand.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct StripArgs {
+    /// The file to strip, or `-` to read from stdin.
+    pub file: PathBuf,
+
+    /// The language of the input, by name, fence tag or extension (e.g., `rs`,
+    /// `python`). Detected from the file name or shebang line if not given.
+    #[arg(long, value_name = "LANG")]
+    pub lang: Option<String>,
+
+    /// If set, documentation comments and docstrings are kept.
+    #[arg(long)]
+    pub keep_doc: bool,
+
+    /// Comma-separated markers of comments to keep, as in `join --keep-comments`.
+    #[arg(
+        long,
+        value_name = "MARKERS",
+        value_delimiter = ',',
+        default_values = DEFAULT_KEEP_MARKERS
+    )]
+    pub keep_comments: Vec<String>,
+
+    /// If set, preprocessor blocks that are never compiled (`#if 0 … #endif`)
+    /// are removed from C, C++ and Objective-C input.
+    #[arg(long)]
+    pub strip_disabled: bool,
+
+    /// If set, removed lines are left blank, so the output keeps the line
+    /// numbers of the input.
+    #[arg(long)]
+    pub preserve_lines: bool,
+
+    /// If set, the first comment block is kept when it looks like a license or
+    /// copyright notice, or always with `--keep-header=always`.
+    #[arg(
+        long,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "license"
+    )]
+    pub keep_header: Option<HeaderMode>,
+}
+
+/// Defines the arguments for the 'ask' subcommand.
+#[derive(ClapArgs, Debug, Clone)]
+#[command(mut_arg("input_folder", |arg| {
+    arg.required_unless_present_any(["remote", "stdin_tar", "staged"])
+}))]
+pub struct AskArgs {
+    /// The question to ask about the joined files.
+    #[arg(required_unless_present = "task", conflicts_with = "task")]
+    pub question: Option<String>,
+
+    /// Ask about the changes staged in the git repository of the current
+    /// directory, as a diff, instead of about the content of files.
+    #[arg(long, conflicts_with_all = ["input_folder", "remote", "stdin_tar", "rev"])]
+    pub staged: bool,
+
+    /// Carry out a built-in task instead of answering a question:
+    /// `commit-message` writes a Conventional Commits message for the staged
+    /// changes.
+    #[arg(long, value_enum, value_name = "TASK", requires = "staged")]
+    pub task: Option<Task>,
+
+    /// Write the commit message to `.git/COMMIT_EDITMSG` as well, to be used
+    /// with `git commit -eF .git/COMMIT_EDITMSG`.
+    #[arg(long, requires = "task")]
+    pub write: bool,
+
+    // The files sent as context are selected with the options of `join`. They
+    // are joined into a temporary file, so `--output-file` is not used.
+    #[command(flatten)]
+    pub join: JoinArgs,
+
+    /// The provider that serves the model: a local Ollama server, or the OpenAI
+    /// API or any server compatible with it (uses OPENAI_API_KEY if set).
+    #[arg(long, value_enum, default_value_t = Provider::Ollama)]
+    pub provider: Provider,
+
+    /// The model to ask. Defaults to `llama3.2` for Ollama and `gpt-4o-mini`
+    /// for OpenAI.
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// The base URL of the provider's API, such as `http://localhost:8080/v1`
+    /// for a local OpenAI-compatible server. Defaults to
+    /// `http://localhost:11434` for Ollama and `https://api.openai.com/v1` for
+    /// OpenAI.
+    #[arg(long, value_name = "URL")]
+    pub base_url: Option<String>,
+
+    /// The number of tokens the model can read at once. A larger context is
+    /// asked about in parts, whose answers are then combined.
+    #[arg(long, value_name = "TOKENS", default_value_t = 32_768)]
+    pub context_window: u64,
+
+    /// Print nothing but the answer on stdout, for piping it to other tools.
+    /// Progress messages go to stderr.
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Always ask the model, instead of reusing an answer cached in
+    /// `.join-ai/cache/llm` for the same prompt, model and parameters.
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// How long cached answers are reused, such as `90s`, `30m`, `12h` or `7d`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "7d")]
+    pub cache_ttl: Duration,
+}
+
+/// A built-in task of `ask`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    /// Write a commit message for the staged changes.
+    CommitMessage,
+}
+
+/// Defines the arguments for the 'embed' subcommand.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct EmbedArgs {
+    // The files are selected and transformed with the options of `join`. They
+    // are joined into a temporary file, so `--output-file` is not used.
+    #[command(flatten)]
+    pub join: JoinArgs,
+
+    /// The JSON Lines file the embeddings are written to, one chunk per line.
+    #[arg(long, value_name = "FILE", default_value = "embeddings.jsonl")]
+    pub out: PathBuf,
+
+    /// The provider that computes the embeddings, as in `ask`.
+    #[arg(long, value_enum, default_value_t = Provider::Ollama)]
+    pub provider: Provider,
+
+    /// The embedding model. Defaults to `nomic-embed-text` for Ollama and
+    /// `text-embedding-3-small` for OpenAI.
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// The base URL of the provider's API, as in `ask`.
+    #[arg(long, value_name = "URL")]
+    pub base_url: Option<String>,
+
+    /// The largest chunk, in estimated tokens. Files are split between lines.
+    #[arg(long, value_name = "TOKENS", default_value_t = 512)]
+    pub chunk_tokens: u64,
+}
+
+/// Defines the arguments for the 'tokens' subcommand.
+#[derive(ClapArgs, Debug, Clone)]
+#[command(group(clap::ArgGroup::new("breakdown").args(["analyze", "export"]).multiple(true)))]
+pub struct TokensArgs {
+    // The files are selected and transformed with the options of `join`. They
+    // are joined into a temporary file, so `--output-file` is not used.
+    #[command(flatten)]
+    pub join: JoinArgs,
+
+    /// Break the tokens down by directory and by extension, as histograms,
+    /// and list the heaviest files, each with its share of the total.
+    #[arg(long)]
+    pub analyze: bool,
+
+    /// Write the tables of `--analyze` to PATH, as CSV, JSON or a static HTML
+    /// report, by its extension (`.csv`, `.json`, `.html`), e.g. for
+    /// dashboards that track the context of several repositories.
+    #[arg(long, value_name = "PATH")]
+    pub export: Option<PathBuf>,
+
+    /// How many rows each table of `--analyze` shows.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "breakdown"
+    )]
+    pub top: u64,
+
+    /// How many levels of directories `--analyze` groups files by.
+    #[arg(
+        long,
+        value_name = "LEVELS",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "breakdown"
+    )]
+    pub depth: u64,
+}
+
+/// Defines the arguments for the 'suggest' subcommand.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct SuggestArgs {
+    // The files are selected with the options of `join`, and the suggestions
+    // leave out more of them. Nothing is joined, so `--output-file` is not used.
+    #[command(flatten)]
+    pub join: JoinArgs,
+
+    /// Add the suggested patterns to the `.joinignore` file of the input
+    /// folder, which later joins read.
+    #[arg(long)]
+    pub apply: bool,
+
+    /// The size from which a file that is not otherwise left out is suggested
+    /// on its own, e.g. `100K` or `1M`.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "100K")]
+    pub huge: u64,
+}
+
+/// The service that answers the questions of `ask`, or computes embeddings.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Provider {
+    /// A local Ollama server. No API key is needed.
+    #[default]
+    Ollama,
+    /// The OpenAI chat completions API, or a server compatible with it.
+    #[value(name = "openai")]
+    OpenAi,
+}
+
+impl Provider {
+    /// The name of the provider, for messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ollama => "Ollama",
+            Self::OpenAi => "OpenAI",
+        }
+    }
+
+    /// The chat model used when `--model` is not given.
+    pub fn default_model(self) -> &'static str {
+        match self {
+            Self::Ollama => "llama3.2",
+            Self::OpenAi => "gpt-4o-mini",
+        }
+    }
+
+    /// The vision model used when `--vision-model` is not given.
+    pub fn default_vision_model(self) -> &'static str {
+        match self {
+            Self::Ollama => "llava",
+            Self::OpenAi => "gpt-4o-mini",
+        }
+    }
+
+    /// The embedding model used when `--model` is not given.
+    pub fn default_embedding_model(self) -> &'static str {
+        match self {
+            Self::Ollama => "nomic-embed-text",
+            Self::OpenAi => "text-embedding-3-small",
+        }
+    }
+
+    /// The API endpoint used when `--base-url` is not given.
+    pub fn default_base_url(self) -> &'static str {
+        match self {
+            Self::Ollama => "http://localhost:11434",
+            Self::OpenAi => "https://api.openai.com/v1",
+        }
+    }
+}
+
+/// Defines the arguments for the 'serve' subcommand.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ServeArgs {
+    /// The address to listen on. Requests are not authenticated, so listen on
+    /// another address than localhost only behind an authenticating proxy.
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub http: String,
+
+    /// The folder that requests can join. The paths of requests are relative to
+    /// it and cannot leave it.
+    #[arg(long, value_name = "FOLDER", default_value = ".")]
+    pub root: PathBuf,
+
+    /// Answer JSON-RPC 2.0 requests (`join`, `tree`, `tokens`, `explain`), one
+    /// per line on stdin, with one response per line on stdout, instead of
+    /// listening for HTTP requests. For editor extensions.
+    #[arg(long, conflicts_with = "http")]
+    pub stdio: bool,
+}
+
+/// Defines the arguments for the 'hook' subcommand.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct HookArgs {
+    #[command(subcommand)]
+    pub action: HookAction,
+}
+
+/// What the 'hook' subcommand does.
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum HookAction {
+    /// Install a git pre-commit hook that runs `join-ai hook run`.
+    Install {
+        /// Have the hook regenerate stale context files, as `run --regenerate`.
+        #[arg(long)]
+        regenerate: bool,
+
+        /// Replace an existing pre-commit hook that join-ai did not install.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check that the patterns in `.join-ai.toml` compile and that each context
+    /// file it lists is up to date, and fail otherwise.
+    Run {
+        /// Rewrite the stale context files. The run still fails, so that they
+        /// are staged before committing.
+        #[arg(long)]
+        regenerate: bool,
+    },
+}
+
+/// Defines the arguments for the 'config' subcommand.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+/// What the 'config' subcommand does.
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigAction {
+    /// Store a token in the OS keyring, where it is used in place of its
+    /// environment variable. The token is prompted for, or read from stdin,
+    /// so that it is not left in the shell history.
+    SetSecret {
+        /// The service the token is for.
+        name: Secret,
+    },
+    /// Remove a token from the OS keyring.
+    DeleteSecret {
+        /// The service the token is for.
+        name: Secret,
+    },
+}
+
+/// A token that `config set-secret` keeps in the OS keyring.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Secret {
+    /// The API key of OpenAI, or of a server compatible with it.
+    #[value(name = "openai")]
+    OpenAi,
+    /// A GitHub token, for `--github` and `--github-pr`.
+    #[value(name = "github")]
+    GitHub,
+    /// A GitLab token, for `--gitlab` and `--gitlab-mr`.
+    #[value(name = "gitlab")]
+    GitLab,
+    /// A Bitbucket access token, for `--bitbucket` and `--bitbucket-pr`.
+    Bitbucket,
+}
+
+impl Secret {
+    /// The name of the token in the keyring, as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Secret::OpenAi => "openai",
+            Secret::GitHub => "github",
+            Secret::GitLab => "gitlab",
+            Secret::Bitbucket => "bitbucket",
+        }
+    }
+
+    /// The environment variables the token is read from when the keyring has
+    /// none, in order of preference.
+    pub fn env_vars(self) -> &'static [&'static str] {
+        match self {
+            Secret::OpenAi => &["OPENAI_API_KEY"],
+            Secret::GitHub => &["GITHUB_TOKEN", "GH_TOKEN"],
+            Secret::GitLab => &["GITLAB_TOKEN", "GL_TOKEN"],
+            Secret::Bitbucket => &["BITBUCKET_TOKEN"],
+        }
+    }
 }
 
 /// Defines the arguments for the 'update' subcommand. Currently a placeholder.
@@ -80,15 +1313,96 @@ mod tests {
 
         match cli.command {
             Commands::Join(join_args) => {
-                assert_eq!(join_args.input_folder, PathBuf::from("./my-project"));
+                assert_eq!(join_args.input_folder, Some(PathBuf::from("./my-project")));
                 // Assert default values
                 assert_eq!(join_args.output_file, PathBuf::from("concatenated.txt"));
+                assert!(join_args.format.is_none());
+                assert!(join_args.split_size.is_none());
+                assert!(join_args.target.is_none());
                 assert!(!join_args.clear_file);
                 assert!(!join_args.hidden);
                 assert!(join_args.patterns.is_none());
                 assert!(join_args.exclude.is_none());
+                assert!(!join_args.no_detect);
+                assert!(join_args.exec_file.is_empty());
+                assert!(join_args.issue.is_empty());
+                assert!(join_args.context_url.is_empty());
+                assert!(join_args.github.is_none());
+                assert!(join_args.github_pr.is_none());
+                assert!(join_args.gitlab.is_none());
+                assert!(join_args.gitlab_mr.is_none());
+                assert!(join_args.bitbucket.is_none());
+                assert!(join_args.bitbucket_pr.is_none());
+                assert!(!join_args.pr_discussion);
+                assert!(!join_args.stdin_tar);
                 assert!(join_args.max_depth.is_none());
+                assert!(join_args.max_file_size.is_none());
+                assert!(join_args.max_tokens.is_none());
+                assert!(join_args.relevant_to.is_none());
+                assert_eq!(join_args.top_k, 20);
+                assert!(join_args.entry.is_none());
+                assert_eq!(join_args.follow_imports, 1);
+                assert_eq!(join_args.sort, SortOrder::Path);
+                assert_eq!(join_args.churn_days, 90);
+                assert!(join_args.coverage.is_none());
+                assert!(join_args.near_duplicates.is_none());
+                assert!(join_args.export_dir.is_none());
+                assert!(join_args.pack.is_none());
+                assert!(join_args.encrypt_to.is_none());
+                assert_eq!(join_args.export_layout, ExportLayout::Mirror);
+                assert!(!join_args.lfs_fetch);
+                assert_eq!(join_args.warn_size, 5 * 1024 * 1024);
+                assert!(!join_args.dry_run);
+                assert!(!join_args.check);
+                assert!(!join_args.open);
+                assert!(join_args.audit_log.is_none());
+                assert!(!join_args.watch);
+                assert!(!join_args.notify);
                 assert!(join_args.no_follow); // Default is true
+                assert!(!join_args.mmap);
+                assert!(!join_args.cache);
+                assert!(!join_args.normalize);
+                assert_eq!(join_args.strip_comments, None);
+                assert!(!join_args.strip_disabled);
+                assert!(!join_args.preserve_lines);
+                assert_eq!(join_args.keep_header, None);
+                assert_eq!(
+                    join_args.keep_comments,
+                    ["TODO", "FIXME", "SAFETY", "HACK", "NOTE"]
+                );
+                assert!(!join_args.redact);
+                assert!(!join_args.redact_pii);
+                assert_eq!(join_args.secrets_policy, None);
+                assert!(!join_args.describe_images);
+                assert_eq!(join_args.vision_provider, Provider::Ollama);
+                assert!(join_args.vision_model.is_none());
+                assert!(join_args.vision_base_url.is_none());
+                assert_eq!(join_args.max_image_size, 4 * 1024 * 1024);
+                assert!(!join_args.git_info);
+                assert!(join_args.anonymize_paths.is_none());
+                assert!(!join_args.dependencies);
+                assert!(!join_args.include_agent_rules);
+                assert!(!join_args.list_only);
+                assert!(!join_args.verify);
+                assert!(!join_args.lock);
+                assert!(!join_args.frozen);
+                assert!(!join_args.module_graph);
+                assert!(!join_args.group_by_dir);
+                assert!(!join_args.symbols);
+                assert!(!join_args.licenses);
+                assert!(join_args.deny_license.is_empty());
+                assert!(!join_args.blame);
+                assert!(!join_args.git_meta);
+                assert!(join_args.rev.is_none());
+                assert_eq!(join_args.submodules, SubmoduleMode::Include);
+                assert!(join_args.packages.is_none());
+                assert!(join_args.owners.is_none());
+                assert!(!join_args.show_owners);
+                assert!(join_args.read_timeout.is_none());
+                assert_eq!(join_args.error_policy, ErrorPolicy::Continue);
+                assert_eq!(join_args.read_error_exit_code, 3);
+                assert_eq!(join_args.log_format, LogFormat::Text);
+                assert_eq!(join_args.verbose, 0);
             }
             _ => panic!("Expected Join command to be parsed"),
         }
@@ -104,6 +1418,12 @@ mod tests {
             "src",
             "-o",
             "output.txt",
+            "--format",
+            "markdown",
+            "--split-size",
+            "2M",
+            "--for",
+            "notebooklm",
             "-p",
             "*.rs",
             "-p",
@@ -113,16 +1433,84 @@ mod tests {
             "target/",
             "--exclude",
             "*.log",
+            "--exec-file",
+            "log.txt:git log --oneline -20",
             "--max-depth",
             "10",
             "--hidden",
+            "--mmap",
+            "--cache",
+            "--normalize",
+            "--strip-comments=keep-doc",
+            "--keep-comments",
+            "TODO,XXX",
+            "--strip-disabled",
+            "--preserve-lines",
+            "--keep-header=always",
+            "--redact",
+            "--redact-pii",
+            "--secrets-policy",
+            "block",
+            "--describe-images",
+            "--vision-provider",
+            "openai",
+            "--vision-model",
+            "gpt-4o",
+            "--vision-base-url",
+            "http://localhost:8080/v1",
+            "--max-image-size",
+            "1M",
+            "--submodules",
+            "shallow",
+            "--package",
+            "app",
+            "--error-policy",
+            "fail-fast",
+            "--read-timeout",
+            "2.5",
+            "--max-file-size",
+            "500K",
+            "--max-tokens",
+            "120000",
+            "--relevant-to",
+            "how does auth token refresh work?",
+            "--top-k",
+            "5",
+            "--entry",
+            "src/api/handler.rs",
+            "--follow-imports",
+            "2",
+            "--sort",
+            "churn",
+            "--churn-days",
+            "30",
+            "--warn-size",
+            "0",
+            "--dry-run",
+            "--dependencies",
+            "--module-graph",
+            "--group-by-dir",
+            "--symbols",
+            "--anonymize-paths",
+            "paths.json",
+            "--licenses",
+            "--deny-license",
+            "GPL-3.0,AGPL-3.0",
+            "--audit-log",
+            "audit.jsonl",
+            "--log-format",
+            "json",
+            "-vv",
         ];
         let cli = Cli::try_parse_from(args).unwrap();
 
         match cli.command {
             Commands::Join(join_args) => {
-                assert_eq!(join_args.input_folder, PathBuf::from("src"));
+                assert_eq!(join_args.input_folder, Some(PathBuf::from("src")));
                 assert_eq!(join_args.output_file, PathBuf::from("output.txt"));
+                assert_eq!(join_args.format, Some(OutputFormat::Markdown));
+                assert_eq!(join_args.split_size, Some(2 * 1024 * 1024));
+                assert_eq!(join_args.target, Some(Target::NotebookLm));
                 assert_eq!(
                     join_args.patterns,
                     Some(vec!["*.rs".to_string(), "*.toml".to_string()])
@@ -132,12 +1520,689 @@ mod tests {
                     join_args.exclude,
                     Some(vec!["target/".to_string(), "*.log".to_string()])
                 );
+                assert_eq!(
+                    join_args.exec_file,
+                    [ExecFile {
+                        name: PathBuf::from("log.txt"),
+                        command: "git log --oneline -20".to_string(),
+                    }]
+                );
                 assert_eq!(join_args.max_depth, Some(10));
+                assert_eq!(join_args.max_file_size, Some(500 * 1024));
+                assert_eq!(join_args.max_tokens, Some(120_000));
+                assert_eq!(
+                    join_args.relevant_to.as_deref(),
+                    Some("how does auth token refresh work?")
+                );
+                assert_eq!(join_args.top_k, 5);
+                assert_eq!(join_args.entry, Some(PathBuf::from("src/api/handler.rs")));
+                assert_eq!(join_args.follow_imports, 2);
+                assert_eq!(join_args.sort, SortOrder::Churn);
+                assert_eq!(join_args.churn_days, 30);
+                assert_eq!(join_args.warn_size, 0);
+                assert!(join_args.dry_run);
+                assert!(join_args.dependencies);
+                assert!(join_args.module_graph);
+                assert!(join_args.group_by_dir);
+                assert!(join_args.symbols);
+                assert_eq!(join_args.anonymize_paths, Some(PathBuf::from("paths.json")));
+                assert!(join_args.licenses);
+                assert_eq!(join_args.audit_log, Some(PathBuf::from("audit.jsonl")));
+                assert_eq!(join_args.deny_license, ["GPL-3.0", "AGPL-3.0"]);
+                assert_eq!(join_args.log_format, LogFormat::Json);
+                assert_eq!(join_args.verbose, 2);
                 assert!(join_args.hidden);
                 assert!(join_args.no_follow);
+                assert!(join_args.mmap);
+                assert!(join_args.cache);
+                assert!(join_args.normalize);
+                assert_eq!(join_args.strip_comments, Some(StripMode::KeepDoc));
+                assert_eq!(join_args.keep_comments, ["TODO", "XXX"]);
+                assert!(join_args.strip_disabled);
+                assert!(join_args.preserve_lines);
+                assert_eq!(join_args.keep_header, Some(HeaderMode::Always));
+                assert!(join_args.redact);
+                assert!(join_args.redact_pii);
+                assert_eq!(join_args.secrets_policy, Some(SecretsPolicy::Block));
+                assert!(join_args.describe_images);
+                assert_eq!(join_args.vision_provider, Provider::OpenAi);
+                assert_eq!(join_args.vision_model.as_deref(), Some("gpt-4o"));
+                assert_eq!(
+                    join_args.vision_base_url.as_deref(),
+                    Some("http://localhost:8080/v1")
+                );
+                assert_eq!(join_args.max_image_size, 1024 * 1024);
+                assert_eq!(join_args.submodules, SubmoduleMode::Shallow);
+                assert_eq!(join_args.packages, Some(vec!["app".to_string()]));
+                assert_eq!(join_args.error_policy, ErrorPolicy::FailFast);
+                assert_eq!(join_args.read_timeout, Some(2.5));
+            }
+            _ => panic!("Expected Join command to be parsed"),
+        }
+    }
+
+    /// Verifies that `--github` replaces the required input folder.
+    #[test]
+    fn test_github_input_without_folder() {
+        let args = vec!["join-ai", "join", "--github", "luizvbo/join-ai@main"];
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Join(join_args) => {
+                assert!(join_args.input_folder.is_none());
+                assert_eq!(join_args.github.as_deref(), Some("luizvbo/join-ai@main"));
+            }
+            _ => panic!("Expected Join command to be parsed"),
+        }
+    }
+
+    /// Verifies that `--github-pr` replaces the required input folder, and that
+    /// `--pr-discussion` needs it.
+    #[test]
+    fn test_github_pr_input_without_folder() {
+        let args = vec![
+            "join-ai",
+            "join",
+            "--github-pr",
+            "luizvbo/join-ai#12",
+            "--pr-discussion",
+        ];
+        let Commands::Join(join_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Join command to be parsed");
+        };
+        assert!(join_args.input_folder.is_none());
+        assert_eq!(join_args.github_pr.as_deref(), Some("luizvbo/join-ai#12"));
+        assert!(join_args.pr_discussion);
+
+        let args = vec!["join-ai", "join", ".", "--pr-discussion"];
+        assert!(Cli::try_parse_from(args).is_err());
+        let args = vec!["join-ai", "join", ".", "--github-pr", "luizvbo/join-ai#12"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    /// Verifies that GitLab and Bitbucket inputs replace the required input
+    /// folder, that remote inputs exclude each other, and that
+    /// `--pr-discussion` takes any pull or merge request.
+    #[test]
+    fn test_gitlab_and_bitbucket_inputs() {
+        let args = vec!["join-ai", "join", "--gitlab", "acme/platform/api@main"];
+        let Commands::Join(join_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Join command to be parsed");
+        };
+        assert!(join_args.input_folder.is_none());
+        assert_eq!(join_args.gitlab.as_deref(), Some("acme/platform/api@main"));
+
+        for args in [
+            vec![
+                "join-ai",
+                "join",
+                "--gitlab-mr",
+                "acme/api!7",
+                "--pr-discussion",
+            ],
+            vec![
+                "join-ai",
+                "join",
+                "--bitbucket-pr",
+                "acme/api#7",
+                "--pr-discussion",
+            ],
+            vec!["join-ai", "join", "--bitbucket", "acme/api"],
+        ] {
+            assert!(Cli::try_parse_from(&args).is_ok(), "{args:?}");
+        }
+        for args in [
+            vec!["join-ai", "join", "--gitlab", "acme/api", "--pr-discussion"],
+            vec!["join-ai", "join", "--gitlab", "a/b", "--bitbucket", "a/b"],
+            vec!["join-ai", "join", ".", "--bitbucket-pr", "a/b#1"],
+            vec!["join-ai", "join", "--gitlab-mr", "a/b!1", "--rev", "HEAD"],
+        ] {
+            assert!(Cli::try_parse_from(&args).is_err(), "{args:?}");
+        }
+    }
+
+    /// Verifies that `--submodules` is refused for remote inputs, which are
+    /// downloaded without their submodules, while they still parse without it.
+    #[test]
+    fn test_submodules_refused_for_remote_inputs() {
+        for mode in ["include", "shallow", "skip"] {
+            let args = vec!["join-ai", "join", "--github", "a/b", "--submodules", mode];
+            let error = Cli::try_parse_from(&args).unwrap_err();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+        }
+        let args = vec!["join-ai", "join", "--github", "a/b"];
+        let Commands::Join(join_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Join command to be parsed");
+        };
+        assert_eq!(join_args.submodules, SubmoduleMode::Include);
+    }
+
+    /// Verifies that `--stdin-tar` replaces the required input folder.
+    #[test]
+    fn test_stdin_tar_input_without_folder() {
+        let args = vec!["join-ai", "join", "--stdin-tar", "-o", "ctx.txt"];
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Join(join_args) => {
+                assert!(join_args.input_folder.is_none());
+                assert!(join_args.stdin_tar);
+            }
+            _ => panic!("Expected Join command to be parsed"),
+        }
+
+        let args = vec!["join-ai", "join", "src", "--stdin-tar"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    /// Verifies that a bare `--strip-comments` removes all comments and does not
+    /// take the input folder as its mode, and that the keep-list can be cleared.
+    #[test]
+    fn test_strip_comments_mode() {
+        let args = vec!["join-ai", "join", "--strip-comments", "src"];
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Join(join_args) => {
+                assert_eq!(join_args.strip_comments, Some(StripMode::All));
+                assert_eq!(join_args.input_folder, Some(PathBuf::from("src")));
             }
             _ => panic!("Expected Join command to be parsed"),
         }
+
+        let args = vec!["join-ai", "join", "src", "--strip-comments", "--blame"];
+        assert!(Cli::try_parse_from(args).is_err());
+        let args = vec!["join-ai", "join", "src", "--strip-disabled"];
+        assert!(Cli::try_parse_from(args).is_err());
+
+        let args = vec!["join-ai", "join", "src", "--keep-comments="];
+        let Commands::Join(join_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Join command to be parsed");
+        };
+        assert!(JoinOptions::from(&join_args).keep_comments.is_empty());
+    }
+
+    /// Verifies that the `strip` command accepts a file or stdin and a language.
+    #[test]
+    fn test_strip_command_is_parsed() {
+        let args = vec![
+            "join-ai",
+            "strip",
+            "-",
+            "--lang",
+            "rs",
+            "--keep-doc",
+            "--keep-header",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Strip(strip_args) => {
+                assert_eq!(strip_args.file, PathBuf::from("-"));
+                assert_eq!(strip_args.lang.as_deref(), Some("rs"));
+                assert!(strip_args.keep_doc);
+                assert_eq!(strip_args.keep_comments.len(), 5);
+                assert!(!strip_args.strip_disabled);
+                assert!(!strip_args.preserve_lines);
+                assert_eq!(strip_args.keep_header, Some(HeaderMode::License));
+            }
+            _ => panic!("Expected Strip command to be parsed"),
+        }
+    }
+
+    /// Verifies that the `ask` command takes the question before the options
+    /// of `join`, and defaults to a local Ollama server.
+    #[test]
+    fn test_ask_command_is_parsed() {
+        let args = vec![
+            "join-ai",
+            "ask",
+            "Where is the entry point?",
+            "src",
+            "-p",
+            "*.rs",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Ask(ask_args) => {
+                assert_eq!(
+                    ask_args.question.as_deref(),
+                    Some("Where is the entry point?")
+                );
+                assert!(!ask_args.staged);
+                assert_eq!(ask_args.task, None);
+                assert_eq!(ask_args.join.input_folder, Some(PathBuf::from("src")));
+                assert_eq!(ask_args.join.patterns, Some(vec!["*.rs".to_string()]));
+                assert_eq!(ask_args.provider, Provider::Ollama);
+                assert_eq!(ask_args.model, None);
+                assert_eq!(ask_args.base_url, None);
+                assert_eq!(ask_args.context_window, 32_768);
+                assert!(!ask_args.raw);
+                assert!(!ask_args.no_cache);
+                assert_eq!(ask_args.cache_ttl, Duration::from_secs(7 * 24 * 60 * 60));
+            }
+            _ => panic!("Expected Ask command to be parsed"),
+        }
+
+        let args = vec![
+            "join-ai",
+            "ask",
+            "Why?",
+            ".",
+            "--provider",
+            "openai",
+            "--model",
+            "qwen2.5-coder",
+            "--base-url",
+            "http://localhost:8080/v1",
+            "--context-window",
+            "128000",
+            "--raw",
+            "--no-cache",
+            "--cache-ttl",
+            "12h",
+        ];
+        let Commands::Ask(ask_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Ask command to be parsed");
+        };
+        assert_eq!(ask_args.provider, Provider::OpenAi);
+        assert_eq!(ask_args.model.as_deref(), Some("qwen2.5-coder"));
+        assert_eq!(
+            ask_args.base_url.as_deref(),
+            Some("http://localhost:8080/v1")
+        );
+        assert_eq!(ask_args.context_window, 128_000);
+        assert!(ask_args.raw);
+        assert!(ask_args.no_cache);
+        assert_eq!(ask_args.cache_ttl, Duration::from_secs(12 * 60 * 60));
+
+        let args = vec![
+            "join-ai",
+            "ask",
+            "--staged",
+            "--task",
+            "commit-message",
+            "--write",
+        ];
+        let Commands::Ask(ask_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Ask command to be parsed");
+        };
+        assert!(ask_args.staged);
+        assert_eq!(ask_args.task, Some(Task::CommitMessage));
+        assert!(ask_args.write);
+        assert_eq!(ask_args.question, None);
+        assert_eq!(ask_args.join.input_folder, None);
+
+        // A task needs staged changes, and a question or a task is required.
+        assert!(Cli::try_parse_from(["join-ai", "ask", "--task", "commit-message"]).is_err());
+        assert!(Cli::try_parse_from(["join-ai", "ask", "--staged"]).is_err());
+        assert!(Cli::try_parse_from(["join-ai", "ask", "Why?"]).is_err());
+    }
+
+    /// Verifies that the `embed` command takes the options of `join` and
+    /// writes to `embeddings.jsonl` by default.
+    #[test]
+    fn test_embed_command_is_parsed() {
+        let args = vec!["join-ai", "embed", "src", "-x", "*.lock"];
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Embed(embed_args) => {
+                assert_eq!(embed_args.join.input_folder, Some(PathBuf::from("src")));
+                assert_eq!(embed_args.join.exclude, Some(vec!["*.lock".to_string()]));
+                assert_eq!(embed_args.out, PathBuf::from("embeddings.jsonl"));
+                assert_eq!(embed_args.provider, Provider::Ollama);
+                assert_eq!(embed_args.chunk_tokens, 512);
+            }
+            _ => panic!("Expected Embed command to be parsed"),
+        }
+    }
+
+    /// Verifies that the `tokens` command takes the options of `join`, and
+    /// that the options of `--analyze` require it or `--export`.
+    #[test]
+    fn test_tokens_command_is_parsed() {
+        let args = vec!["join-ai", "tokens", "src", "--analyze", "--depth", "2"];
+        let Commands::Tokens(tokens_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Tokens command to be parsed");
+        };
+        assert_eq!(tokens_args.join.input_folder, Some(PathBuf::from("src")));
+        assert!(tokens_args.analyze);
+        assert_eq!(tokens_args.top, 20);
+        assert_eq!(tokens_args.depth, 2);
+        assert!(tokens_args.export.is_none());
+
+        let args = vec!["join-ai", "tokens", "src", "--top", "5"];
+        assert!(Cli::try_parse_from(args).is_err());
+
+        let args = vec![
+            "join-ai",
+            "tokens",
+            "src",
+            "--analyze",
+            "--export",
+            "stats.html",
+            "--top",
+            "5",
+        ];
+        let Commands::Tokens(tokens_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Tokens command to be parsed");
+        };
+        assert_eq!(tokens_args.export, Some(PathBuf::from("stats.html")));
+        assert_eq!(tokens_args.top, 5);
+    }
+
+    /// Verifies that the `suggest` command takes the options of `join`, and
+    /// reads the size of huge files.
+    #[test]
+    fn test_suggest_command_is_parsed() {
+        let args = vec!["join-ai", "suggest", "src", "--apply", "--huge", "1M"];
+        let Commands::Suggest(suggest_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Suggest command to be parsed");
+        };
+        assert_eq!(suggest_args.join.input_folder, Some(PathBuf::from("src")));
+        assert!(suggest_args.apply);
+        assert_eq!(suggest_args.huge, 1024 * 1024);
+
+        let args = vec!["join-ai", "suggest", "src"];
+        let Commands::Suggest(suggest_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Suggest command to be parsed");
+        };
+        assert!(!suggest_args.apply);
+        assert_eq!(suggest_args.huge, 100 * 1024);
+    }
+
+    /// Verifies that `--for` picks a layout, and that explicit options win.
+    #[test]
+    fn test_target_presets() {
+        let options = |extra: &[&str]| {
+            let args = [&["join-ai", "join", "src"], extra].concat();
+            match Cli::try_parse_from(args).unwrap().command {
+                Commands::Join(join_args) => JoinOptions::from(&join_args),
+                _ => panic!("Expected Join command to be parsed"),
+            }
+        };
+
+        let plain = options(&[]);
+        assert_eq!(plain.format, OutputFormat::Text);
+        assert_eq!(plain.split_size, None);
+
+        let notebooklm = options(&["--for", "notebooklm"]);
+        assert_eq!(notebooklm.format, OutputFormat::Markdown);
+        assert_eq!(notebooklm.split_size, Some(2 * 1024 * 1024));
+        assert_eq!(options(&["--for", "gemini"]).split_size, None);
+
+        let explicit = options(&["--for", "cursor", "--format", "text", "--split-size", "64K"]);
+        assert_eq!(explicit.format, OutputFormat::Text);
+        assert_eq!(explicit.split_size, Some(64 * 1024));
+    }
+
+    /// Verifies that sizes are accepted with and without units.
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("2M"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_size("1 gb"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("3T").is_err());
+    }
+
+    /// Verifies that durations are accepted with and without units.
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(
+            parse_duration("7D"),
+            Ok(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("2w").is_err());
+    }
+
+    /// Verifies that `--color` wins over `NO_COLOR`, which wins over the default.
+    #[test]
+    fn test_color_mode_detection() {
+        let args = |list: &[&str]| -> Vec<OsString> { list.iter().map(OsString::from).collect() };
+        let no_color = Some(OsString::from("1"));
+
+        assert_eq!(
+            ColorMode::detect(args(&["join-ai", "join", "."]), None),
+            ColorMode::Auto
+        );
+        assert_eq!(
+            ColorMode::detect(args(&["join-ai", "join", "."]), no_color.clone()),
+            ColorMode::Never
+        );
+        assert_eq!(
+            ColorMode::detect(
+                args(&["join-ai", "--color", "always", "join"]),
+                no_color.clone()
+            ),
+            ColorMode::Always
+        );
+        assert_eq!(
+            ColorMode::detect(args(&["join-ai", "join", ".", "--color=never"]), None),
+            ColorMode::Never
+        );
+        assert_eq!(
+            ColorMode::detect(
+                args(&["join-ai", "join", ".", "--color"]),
+                Some(OsString::new())
+            ),
+            ColorMode::Auto
+        );
+
+        let cli = Cli::try_parse_from(["join-ai", "join", ".", "--color", "never"]).unwrap();
+        assert_eq!(cli.color, ColorMode::Never);
+    }
+
+    /// Verifies that hyperlinks are used on terminals known to support them,
+    /// unless colors are disabled, and that `FORCE_HYPERLINK` wins.
+    #[test]
+    fn test_hyperlink_detection() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let kitty = env(&[("TERM", "xterm-kitty")]);
+        assert!(ColorMode::Auto.hyperlinks(kitty, true));
+        assert!(!ColorMode::Auto.hyperlinks(kitty, false));
+        assert!(ColorMode::Always.hyperlinks(kitty, false));
+        assert!(!ColorMode::Never.hyperlinks(kitty, true));
+        assert!(ColorMode::Auto.hyperlinks(env(&[("VTE_VERSION", "6003")]), true));
+        assert!(!ColorMode::Auto.hyperlinks(env(&[("VTE_VERSION", "4200")]), true));
+        assert!(!ColorMode::Auto.hyperlinks(env(&[("TERM", "xterm")]), true));
+        let forced = env(&[("TERM", "xterm"), ("FORCE_HYPERLINK", "1")]);
+        assert!(ColorMode::Auto.hyperlinks(forced, false));
+        let disabled = env(&[("TERM", "xterm-kitty"), ("FORCE_HYPERLINK", "0")]);
+        assert!(!ColorMode::Auto.hyperlinks(disabled, true));
+    }
+
+    /// Verifies that `--check` needs a budget and cannot be a dry run.
+    #[test]
+    fn test_check_requires_a_budget() {
+        let args = vec!["join-ai", "join", "src", "--check", "--max-tokens", "1000"];
+        let Commands::Join(join_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Join command to be parsed");
+        };
+        assert!(join_args.check);
+        assert!(Cli::try_parse_from(vec!["join-ai", "join", "src", "--check"]).is_err());
+        let args = vec![
+            "join-ai",
+            "join",
+            "src",
+            "--check",
+            "--max-tokens",
+            "1",
+            "--dry-run",
+        ];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    /// Verifies that `--exec-file` needs a relative name and a command.
+    #[test]
+    fn test_exec_file_is_parsed() {
+        assert_eq!(
+            parse_exec_file("build/deps.txt: cargo tree --depth 2"),
+            Ok(ExecFile {
+                name: PathBuf::from("build/deps.txt"),
+                command: "cargo tree --depth 2".to_string(),
+            })
+        );
+        assert!(parse_exec_file("cargo tree").is_err());
+        assert!(parse_exec_file("deps.txt:").is_err());
+        assert!(parse_exec_file("../deps.txt:cargo tree").is_err());
+        assert!(parse_exec_file("/tmp/deps.txt:cargo tree").is_err());
+    }
+
+    /// Verifies that `--lock` and `--frozen` cannot be combined, and that a
+    /// lock is not written by a dry run.
+    #[test]
+    fn test_lock_options_are_parsed() {
+        let Commands::Join(join_args) = Cli::try_parse_from(vec!["join-ai", "join", ".", "--lock"])
+            .unwrap()
+            .command
+        else {
+            panic!("Expected Join command to be parsed");
+        };
+        assert!(join_args.lock);
+        for conflict in ["--frozen", "--dry-run"] {
+            let args = vec!["join-ai", "join", ".", "--lock", conflict];
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+    }
+
+    /// Verifies that `--list-only` is parsed, and that it cannot be combined
+    /// with the options that need the content in the output.
+    #[test]
+    fn test_list_only_is_parsed() {
+        let Commands::Join(join_args) =
+            Cli::try_parse_from(vec!["join-ai", "join", ".", "--list-only"])
+                .unwrap()
+                .command
+        else {
+            panic!("Expected Join command to be parsed");
+        };
+        assert!(join_args.list_only);
+        for conflict in ["--symbols", "--verify"] {
+            let args = vec!["join-ai", "join", ".", "--list-only", conflict];
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+    }
+
+    /// Verifies that `--verify` is parsed, and that it cannot be combined with
+    /// the runs that write no output.
+    #[test]
+    fn test_verify_is_parsed() {
+        let Commands::Join(join_args) =
+            Cli::try_parse_from(vec!["join-ai", "join", ".", "--verify"])
+                .unwrap()
+                .command
+        else {
+            panic!("Expected Join command to be parsed");
+        };
+        assert!(join_args.verify);
+        let args = vec!["join-ai", "join", ".", "--verify", "--dry-run"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    /// Verifies that the `serve` command defaults to localhost and the current
+    /// folder.
+    #[test]
+    fn test_serve_command_is_parsed() {
+        let Commands::Serve(serve_args) = Cli::try_parse_from(vec!["join-ai", "serve"])
+            .unwrap()
+            .command
+        else {
+            panic!("Expected Serve command to be parsed");
+        };
+        assert_eq!(serve_args.http, "127.0.0.1:8080");
+        assert_eq!(serve_args.root, PathBuf::from("."));
+
+        let args = vec![
+            "join-ai",
+            "serve",
+            "--http",
+            "0.0.0.0:9000",
+            "--root",
+            "repos",
+        ];
+        let Commands::Serve(serve_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Serve command to be parsed");
+        };
+        assert_eq!(serve_args.http, "0.0.0.0:9000");
+        assert_eq!(serve_args.root, PathBuf::from("repos"));
+        assert!(!serve_args.stdio);
+
+        let Commands::Serve(serve_args) = Cli::try_parse_from(vec!["join-ai", "serve", "--stdio"])
+            .unwrap()
+            .command
+        else {
+            panic!("Expected Serve command to be parsed");
+        };
+        assert!(serve_args.stdio);
+        let args = vec!["join-ai", "serve", "--stdio", "--http", "0.0.0.0:9000"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    /// Verifies that the `hook` command requires an action, and parses its flags.
+    #[test]
+    fn test_hook_command_is_parsed() {
+        let args = vec!["join-ai", "hook", "install", "--regenerate"];
+        let Commands::Hook(hook_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Hook command to be parsed");
+        };
+        assert_eq!(
+            hook_args.action,
+            HookAction::Install {
+                regenerate: true,
+                force: false
+            }
+        );
+
+        let args = vec!["join-ai", "hook", "run"];
+        let Commands::Hook(hook_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Hook command to be parsed");
+        };
+        assert_eq!(hook_args.action, HookAction::Run { regenerate: false });
+        assert!(Cli::try_parse_from(vec!["join-ai", "hook"]).is_err());
+    }
+
+    /// Verifies that `config set-secret` takes the name of a known token, and
+    /// never the token itself.
+    #[test]
+    fn test_config_command_is_parsed() {
+        let args = vec!["join-ai", "config", "set-secret", "openai"];
+        let Commands::Config(config_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Config command to be parsed");
+        };
+        assert_eq!(
+            config_args.action,
+            ConfigAction::SetSecret {
+                name: Secret::OpenAi
+            }
+        );
+
+        let args = vec!["join-ai", "config", "delete-secret", "github"];
+        let Commands::Config(config_args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("Expected Config command to be parsed");
+        };
+        assert_eq!(
+            config_args.action,
+            ConfigAction::DeleteSecret {
+                name: Secret::GitHub
+            }
+        );
+        let args = vec!["join-ai", "config", "set-secret", "github", "ghp_token"];
+        assert!(Cli::try_parse_from(args).is_err());
+        let args = vec!["join-ai", "config", "set-secret", "jira"];
+        assert!(Cli::try_parse_from(args).is_err());
     }
 
     /// Ensures the `update` subcommand is recognized and parsed correctly.
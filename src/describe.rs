@@ -0,0 +1,134 @@
+//! Descriptions of images by a vision model (`--describe-images`), written in
+//! place of the images, which would otherwise be left out as binary.
+//!
+//! Architecture diagrams and screenshots often say more about a project than
+//! any single source file, so a paragraph about each keeps them in the output.
+
+use crate::cli::JoinArgs;
+use join_ai_core::transform::Pipeline;
+use std::path::Path;
+
+/// What a raster image is asked about.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const IMAGE_PROMPT: &str = "This image is a file of a software project. \
+    Describe it in one paragraph for a reader who cannot see it: what it \
+    shows, and any text, labels, components and connections in it.";
+
+/// What an SVG image is asked about, followed by its source.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const SVG_PROMPT: &str = "The SVG source below is an image from a software \
+    project. Describe in one paragraph what it depicts, for a reader who \
+    cannot see it: any text, labels, components and connections in it.";
+
+/// The kinds of images that are described.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+enum ImageKind {
+    /// A raster image, sent to the model with its media type.
+    Raster(&'static str),
+    /// A vector image, whose source is sent as text.
+    Svg,
+}
+
+/// Returns the kind of image `path` is, by its extension.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+fn image_kind(path: &Path) -> Option<ImageKind> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let kind = match extension.as_str() {
+        "png" => ImageKind::Raster("image/png"),
+        "jpg" | "jpeg" => ImageKind::Raster("image/jpeg"),
+        "gif" => ImageKind::Raster("image/gif"),
+        "webp" => ImageKind::Raster("image/webp"),
+        "svg" => ImageKind::Svg,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Describes the images of the join with the vision model of `args`.
+#[cfg(feature = "network")]
+pub fn describe_images(pipeline: &mut Pipeline, args: &JoinArgs) -> anyhow::Result<()> {
+    pipeline.describe_with(ImageDescriber::new(args));
+    Ok(())
+}
+
+#[cfg(not(feature = "network"))]
+pub fn describe_images(_pipeline: &mut Pipeline, _args: &JoinArgs) -> anyhow::Result<()> {
+    anyhow::bail!("`--describe-images` requires join-ai to be built with the `network` feature.")
+}
+
+/// Describes images no larger than `max_size` with a vision model.
+#[cfg(feature = "network")]
+struct ImageDescriber {
+    client: crate::remote::llm::Client,
+    max_size: u64,
+}
+
+#[cfg(feature = "network")]
+impl ImageDescriber {
+    fn new(args: &JoinArgs) -> Self {
+        let provider = args.vision_provider;
+        let model = args
+            .vision_model
+            .as_deref()
+            .unwrap_or(provider.default_vision_model());
+        Self {
+            client: crate::remote::llm::Client::new(
+                provider,
+                Some(model),
+                args.vision_base_url.as_deref(),
+            ),
+            max_size: args.max_image_size,
+        }
+    }
+}
+
+#[cfg(feature = "network")]
+impl join_ai_core::transform::Describe for ImageDescriber {
+    fn accepts(&self, path: &Path) -> bool {
+        image_kind(path).is_some()
+            && std::fs::metadata(join_ai_core::paths::for_io(path))
+                .is_ok_and(|metadata| metadata.len() <= self.max_size)
+    }
+
+    fn describe(&self, path: &Path) -> anyhow::Result<String> {
+        use crate::remote::llm::Message;
+
+        let content = std::fs::read(join_ai_core::paths::for_io(path))?;
+        let message = match image_kind(path) {
+            Some(ImageKind::Raster(mime)) => Message::user(IMAGE_PROMPT).with_image(mime, &content),
+            _ => Message::user(format!(
+                "{SVG_PROMPT}\n\n{}",
+                String::from_utf8_lossy(&content)
+            )),
+        };
+        let description = self.client.chat(&[message])?;
+        Ok(format!(
+            "[Image described by {}]\n{}\n",
+            self.client.model(),
+            description.trim()
+        ))
+    }
+}
+
+// --- Unit Tests for Image Descriptions ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that images are recognized by their extension, in any case.
+    #[test]
+    fn test_image_kind() {
+        assert_eq!(
+            image_kind(Path::new("docs/architecture.PNG")),
+            Some(ImageKind::Raster("image/png"))
+        );
+        assert_eq!(
+            image_kind(Path::new("design/logo.jpeg")),
+            Some(ImageKind::Raster("image/jpeg"))
+        );
+        assert_eq!(image_kind(Path::new("docs/flow.svg")), Some(ImageKind::Svg));
+        assert_eq!(image_kind(Path::new("docs/manual.pdf")), None);
+        assert_eq!(image_kind(Path::new("Makefile")), None);
+    }
+}
@@ -0,0 +1,213 @@
+//! The `embed` command: splits the files selected like `join` into chunks and
+//! writes an embedding of each chunk to a JSON Lines file, as the ingestion
+//! step of a retrieval-augmented generation (RAG) setup.
+
+use crate::cli::EmbedArgs;
+use crate::join_to_memory;
+use join_ai_core::processor::Report;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::process::ExitCode;
+
+/// How many chunks are sent to the provider in one request.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const BATCH_LEN: usize = 32;
+
+/// The header lines that may follow `// FILE:` in the joined output.
+const EXTRA_HEADERS: [&[u8]; 5] = [
+    b"// LAST COMMIT: ",
+    b"// HARDLINKS: ",
+    b"// OWNERS: ",
+    b"// COVERAGE: ",
+    b"// NEAR DUPLICATES: ",
+];
+
+/// A chunk of a file, and where it lies in the (transformed) file.
+#[derive(Debug, PartialEq, Eq)]
+struct Chunk<'a> {
+    path: String,
+    /// The byte range of the chunk in the file.
+    range: Range<usize>,
+    text: Cow<'a, str>,
+}
+
+/// Joins the files selected by `args` and writes the embeddings of their
+/// chunks to `--out`. With `--dry-run`, only reports which files would be read.
+pub fn embed(args: &EmbedArgs) -> anyhow::Result<ExitCode> {
+    let (report, code, output) = join_to_memory(
+        &args.join,
+        "embed",
+        args.base_url
+            .as_deref()
+            .unwrap_or(args.provider.default_base_url()),
+    )?;
+    let Some(output) = output.filter(|_| report.files_written > 0) else {
+        return Ok(code);
+    };
+    // The inverse of `estimate_tokens`.
+    let max_len = (args.chunk_tokens as usize).saturating_mul(4).max(4);
+    let chunks = chunks(&output, &report, max_len);
+    write_embeddings(args, &chunks)?;
+    Ok(code)
+}
+
+/// Computes the embeddings of `chunks` and writes them to `--out`.
+#[cfg(feature = "network")]
+fn write_embeddings(args: &EmbedArgs, chunks: &[Chunk]) -> anyhow::Result<()> {
+    use crate::remote::llm::Client;
+    use anyhow::Context;
+    use std::io::{BufWriter, Write};
+
+    let model = args
+        .model
+        .as_deref()
+        .unwrap_or(args.provider.default_embedding_model());
+    let client = Client::new(args.provider, Some(model), args.base_url.as_deref());
+    println!(
+        "Embedding {} chunks with {} ({model})...",
+        chunks.len(),
+        args.provider.name()
+    );
+
+    let file = std::fs::File::create(&args.out)
+        .with_context(|| format!("Failed to create {}", args.out.display()))?;
+    let mut out = BufWriter::new(file);
+    for batch in chunks.chunks(BATCH_LEN) {
+        let inputs: Vec<&str> = batch.iter().map(|chunk| chunk.text.as_ref()).collect();
+        let embeddings = client.embed(&inputs).map_err(crate::exit::network)?;
+        for (chunk, embedding) in batch.iter().zip(embeddings) {
+            let record = serde_json::json!({
+                "path": chunk.path,
+                "start": chunk.range.start,
+                "end": chunk.range.end,
+                "text": chunk.text,
+                "embedding": embedding,
+            });
+            writeln!(out, "{record}")?;
+        }
+    }
+    out.flush()?;
+    println!("Embeddings have been written to {}", args.out.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "network"))]
+fn write_embeddings(_args: &EmbedArgs, _chunks: &[Chunk]) -> anyhow::Result<()> {
+    anyhow::bail!("`embed` requires join-ai to be built with the `network` feature.")
+}
+
+/// Splits each file of the joined `output` into chunks of at most `max_len`
+/// bytes. Chunks holding only whitespace are left out.
+fn chunks<'a>(output: &'a [u8], report: &Report, max_len: usize) -> Vec<Chunk<'a>> {
+    let mut chunks = Vec::new();
+    let mut at = 0;
+    for (path, len) in &report.included {
+        let path = path.display().to_string();
+        let header = format!("// FILE: {path}\n");
+        let header = header.as_bytes();
+        // Only a `--git-info` block comes before the first header.
+        let Some(start) = output[at..]
+            .windows(header.len())
+            .position(|window| window == header)
+        else {
+            break;
+        };
+        at += start + header.len();
+        while let Some(extra) = EXTRA_HEADERS
+            .iter()
+            .find(|extra| output[at..].starts_with(extra))
+        {
+            at += output[at..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(extra.len(), |n| n + 1);
+        }
+        let end = (at + *len as usize).min(output.len());
+        let content = &output[at..end];
+        for range in chunk_ranges(content, max_len) {
+            let text = String::from_utf8_lossy(&content[range.clone()]);
+            if !text.trim().is_empty() {
+                chunks.push(Chunk {
+                    path: path.clone(),
+                    range,
+                    text,
+                });
+            }
+        }
+        at = end;
+    }
+    chunks
+}
+
+/// Splits `content` into consecutive ranges of at most `max_len` bytes,
+/// between lines where possible and between characters otherwise.
+fn chunk_ranges(content: &[u8], max_len: usize) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while content.len() - start > max_len {
+        let window = &content[start..start + max_len];
+        let end = match window.iter().rposition(|&b| b == b'\n') {
+            Some(newline) => start + newline + 1,
+            None => {
+                // Back off to the start of a UTF-8 character.
+                let mut end = start + max_len;
+                while end > start + 1 && content[end] & 0xC0 == 0x80 {
+                    end -= 1;
+                }
+                end
+            }
+        };
+        ranges.push(start..end);
+        start = end;
+    }
+    if start < content.len() {
+        ranges.push(start..content.len());
+    }
+    ranges
+}
+
+// --- Unit Tests for Embedding Chunks ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Verifies that chunks are cut between lines, or between characters in
+    /// a long line, and cover the content exactly.
+    #[test]
+    fn test_chunk_ranges() {
+        let content = b"one\ntwo\nthree\n";
+        assert_eq!(chunk_ranges(content, 100), vec![0..14]);
+        assert_eq!(chunk_ranges(content, 9), vec![0..8, 8..14]);
+
+        let wide = "é".repeat(5);
+        let ranges = chunk_ranges(wide.as_bytes(), 5);
+        assert_eq!(ranges, vec![0..4, 4..8, 8..10]);
+    }
+
+    /// Verifies that files are found by their headers, extra header lines are
+    /// skipped, and offsets are relative to each file.
+    #[test]
+    fn test_chunks_of_joined_output() {
+        let output = b"repo: x\n// FILE: a.rs\n// LAST COMMIT: abc\nfn a() {}\nfn b() {}\n\n// FILE: b.md\n \n\n";
+        let report = Report {
+            included: vec![(PathBuf::from("a.rs"), 20), (PathBuf::from("b.md"), 2)],
+            ..Default::default()
+        };
+        assert_eq!(
+            chunks(output, &report, 12),
+            vec![
+                Chunk {
+                    path: "a.rs".to_string(),
+                    range: 0..10,
+                    text: Cow::Borrowed("fn a() {}\n"),
+                },
+                Chunk {
+                    path: "a.rs".to_string(),
+                    range: 10..20,
+                    text: Cow::Borrowed("fn b() {}\n"),
+                },
+            ]
+        );
+    }
+}
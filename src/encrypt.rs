@@ -0,0 +1,188 @@
+//! Encrypted outputs (`--encrypt-to`): the output, or the pack, is written
+//! encrypted with [age](https://age-encryption.org), so that a context shared
+//! over chat or email can only be read by the recipients it was meant for.
+//!
+//! Recipients are age public keys (`age1...`) or SSH public keys
+//! (`ssh-ed25519 ...`, `ssh-rsa ...`), given directly or as a file that lists
+//! them one per line, as `age -R` reads them. The encrypted files are named
+//! after the plaintext ones with `.age` appended, and are decrypted with
+//! `age -d -i KEY`.
+//!
+//! Outputs are only encrypted when the crate is built with the `encrypt`
+//! feature.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "encrypt")]
+use {
+    anyhow::Context,
+    std::fs::{self, File},
+    std::io::{BufWriter, Write},
+    std::str::FromStr,
+};
+
+/// The extension appended to the name of encrypted files.
+pub const EXTENSION: &str = "age";
+
+/// The recipients of an encrypted output.
+#[cfg(feature = "encrypt")]
+pub struct Recipients(Vec<Box<dyn age::Recipient>>);
+
+#[cfg(feature = "encrypt")]
+impl Recipients {
+    /// Parses the recipients given to `--encrypt-to`: keys, or files of keys.
+    pub fn parse(values: &[String]) -> anyhow::Result<Self> {
+        let mut recipients = Vec::new();
+        for value in values {
+            let path = Path::new(value);
+            if !looks_like_key(value) && path.is_file() {
+                let text = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                for (number, line) in text.lines().enumerate() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let recipient = parse_key(line).with_context(|| {
+                        format!(
+                            "Line {} of {} is not a recipient",
+                            number + 1,
+                            path.display()
+                        )
+                    })?;
+                    recipients.push(recipient);
+                }
+            } else {
+                recipients.push(parse_key(value)?);
+            }
+        }
+        if recipients.is_empty() {
+            anyhow::bail!("`--encrypt-to` was given no recipients.");
+        }
+        Ok(Self(recipients))
+    }
+
+    /// Encrypts the file at `path` into `path.age`, and removes the plaintext
+    /// file. Returns the path of the encrypted file.
+    pub fn encrypt_file(&self, path: &Path) -> anyhow::Result<PathBuf> {
+        let plaintext =
+            fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let encrypted = encrypted_path(path);
+        let encryptor =
+            age::Encryptor::with_recipients(self.0.iter().map(|recipient| recipient.as_ref()))?;
+        let file = File::create(&encrypted)
+            .with_context(|| format!("Failed to create {}", encrypted.display()))?;
+        let mut writer = encryptor.wrap_output(BufWriter::new(file))?;
+        writer.write_all(&plaintext)?;
+        writer.finish()?.flush()?;
+        fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        Ok(encrypted)
+    }
+}
+
+#[cfg(not(feature = "encrypt"))]
+pub struct Recipients;
+
+#[cfg(not(feature = "encrypt"))]
+impl Recipients {
+    pub fn parse(_values: &[String]) -> anyhow::Result<Self> {
+        anyhow::bail!("`--encrypt-to` requires join-ai to be built with the `encrypt` feature.")
+    }
+
+    pub fn encrypt_file(&self, _path: &Path) -> anyhow::Result<PathBuf> {
+        anyhow::bail!("`--encrypt-to` requires join-ai to be built with the `encrypt` feature.")
+    }
+}
+
+/// The path of the encrypted copy of the file at `path`: `out.txt.age`.
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{EXTENSION}"));
+    PathBuf::from(name)
+}
+
+/// Whether `value` is written as a key rather than the path of a file.
+#[cfg(feature = "encrypt")]
+fn looks_like_key(value: &str) -> bool {
+    value.starts_with("age1") || value.starts_with("ssh-")
+}
+
+/// Parses one recipient: an age public key, or an SSH public key.
+#[cfg(feature = "encrypt")]
+fn parse_key(key: &str) -> anyhow::Result<Box<dyn age::Recipient>> {
+    // The key is not echoed, which would print a secret to the terminal.
+    if key.starts_with("AGE-SECRET-KEY-") {
+        anyhow::bail!("`--encrypt-to` takes public keys (`age1...`), and was given a secret key.");
+    }
+    if key.starts_with("ssh-") {
+        return match age::ssh::Recipient::from_str(key) {
+            Ok(recipient) => Ok(Box::new(recipient)),
+            Err(e) => anyhow::bail!("`{key}` is not a supported SSH public key: {e:?}"),
+        };
+    }
+    match age::x25519::Recipient::from_str(key) {
+        Ok(recipient) => Ok(Box::new(recipient)),
+        Err(e) => anyhow::bail!(
+            "`{key}` is not an age recipient ({e}): expected an `age1...` public key, an SSH public key, or a file of them."
+        ),
+    }
+}
+
+// --- Unit Tests for Encrypted Outputs ---
+#[cfg(all(test, feature = "encrypt"))]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::io::Read;
+
+    /// Verifies that an encrypted output is read back by the identity of any
+    /// of its recipients, given as keys or in a file, and that the plaintext
+    /// is removed.
+    #[test]
+    fn test_encrypt_file() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let (alice, bob) = (
+            age::x25519::Identity::generate(),
+            age::x25519::Identity::generate(),
+        );
+        dir.child("recipients.txt")
+            .write_str(&format!("# Bob\n{}\n", bob.to_public()))?;
+        dir.child("out.txt")
+            .write_str("// FILE: a.rs\nfn a() {}\n")?;
+        let recipients = Recipients::parse(&[
+            alice.to_public().to_string(),
+            dir.path().join("recipients.txt").display().to_string(),
+        ])?;
+
+        let path = dir.path().join("out.txt");
+        let encrypted = recipients.encrypt_file(&path)?;
+        assert_eq!(encrypted, dir.path().join("out.txt.age"));
+        assert!(!path.exists());
+        for identity in [&alice, &bob] {
+            let decryptor = age::Decryptor::new(File::open(&encrypted)?)?;
+            let mut plaintext = String::new();
+            decryptor
+                .decrypt(std::iter::once(identity as &dyn age::Identity))?
+                .read_to_string(&mut plaintext)?;
+            assert_eq!(plaintext, "// FILE: a.rs\nfn a() {}\n");
+        }
+        let stranger = age::x25519::Identity::generate();
+        let decryptor = age::Decryptor::new(File::open(&encrypted)?)?;
+        assert!(
+            decryptor
+                .decrypt(std::iter::once(&stranger as &dyn age::Identity))
+                .is_err()
+        );
+
+        let secret = alice.to_string();
+        let err = Recipients::parse(&[secret.expose_secret().to_string()]).err();
+        assert!(
+            err.is_some_and(|e| e.to_string().contains("was given a secret key")
+                && !e.to_string().contains(secret.expose_secret()))
+        );
+        assert!(Recipients::parse(&["age1invalid".to_string()]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+//! The exit codes of the command-line interface, so scripts and CI gates can
+//! branch on the outcome of a run without parsing its output.
+
+use join_ai_core::licenses::LicenseDenied;
+use join_ai_core::secrets::SecretsFound;
+use std::fmt;
+use std::process::ExitCode;
+
+/// The run succeeded.
+pub const SUCCESS: u8 = 0;
+/// Invalid arguments or configuration, or any other failure.
+pub const ERROR: u8 = 1;
+/// The join completed, but no file was included.
+pub const NOTHING_MATCHED: u8 = 2;
+/// Some files could not be read (the default of `--read-error-exit-code`).
+pub const PARTIAL_FAILURE: u8 = 3;
+/// Some files were left out because they did not fit in `--max-tokens`.
+pub const BUDGET_EXCEEDED: u8 = 4;
+/// A remote provider (e.g., the GitHub API) could not be reached or failed.
+pub const NETWORK: u8 = 5;
+/// The join was stopped by `--secrets-policy block`, and nothing was written.
+pub const SECRETS_FOUND: u8 = 6;
+/// The join was stopped by `--deny-license`, and nothing was written.
+pub const LICENSE_DENIED: u8 = 7;
+
+/// An error from a remote provider, which makes the process exit with
+/// [`NETWORK`] instead of [`ERROR`].
+#[derive(Debug)]
+pub struct NetworkError(pub anyhow::Error);
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Marks `error` as coming from a remote provider.
+pub fn network(error: anyhow::Error) -> anyhow::Error {
+    anyhow::Error::new(NetworkError(error))
+}
+
+/// Returns the exit code for a run that failed with `error`.
+pub fn for_error(error: &anyhow::Error) -> ExitCode {
+    if error.chain().any(|cause| cause.is::<NetworkError>()) {
+        ExitCode::from(NETWORK)
+    } else if error.chain().any(|cause| cause.is::<SecretsFound>()) {
+        ExitCode::from(SECRETS_FOUND)
+    } else if error.chain().any(|cause| cause.is::<LicenseDenied>()) {
+        ExitCode::from(LICENSE_DENIED)
+    } else {
+        ExitCode::from(ERROR)
+    }
+}
+
+// --- Unit Tests for Exit Codes ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    /// Verifies that network errors are recognized through added context.
+    #[test]
+    fn test_network_errors_exit_with_their_code() {
+        let error = Err::<(), _>(network(anyhow::anyhow!("connection refused")))
+            .context("Failed to fetch the repository")
+            .unwrap_err();
+        assert_eq!(for_error(&error), ExitCode::from(NETWORK));
+        assert_eq!(
+            for_error(&anyhow::anyhow!("bad pattern")),
+            ExitCode::from(ERROR)
+        );
+    }
+
+    /// Verifies that joins stopped by secrets or licenses exit with their own
+    /// codes.
+    #[test]
+    fn test_blocked_joins_exit_with_their_code() {
+        let error = anyhow::Error::new(SecretsFound(Vec::new()));
+        assert_eq!(for_error(&error), ExitCode::from(SECRETS_FOUND));
+        let error = anyhow::Error::new(LicenseDenied(Vec::new()));
+        assert_eq!(for_error(&error), ExitCode::from(LICENSE_DENIED));
+    }
+}
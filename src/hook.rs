@@ -0,0 +1,203 @@
+//! Git hooks (`join-ai hook`): a pre-commit hook that checks the configuration
+//! in `.join-ai.toml` and keeps the context files it lists in sync with the
+//! code, for teams that version their AI context.
+
+use crate::cli::{Cli, Commands, HookAction, HookArgs, JoinArgs, Target};
+use crate::{input, pipeline_for};
+use anyhow::Context;
+use clap::Parser;
+use join_ai_core::JoinOptions;
+use join_ai_core::config::{self, CONFIG_FILE, ContextFile, NeverInclude};
+use join_ai_core::events;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+/// Marks the hooks written by `hook install`, which it may replace.
+const MARKER: &str = "# Installed by `join-ai hook install`.";
+
+/// Handles the logic for the 'hook' command.
+pub fn hook(args: &HookArgs) -> anyhow::Result<ExitCode> {
+    match args.action {
+        HookAction::Install { regenerate, force } => install(regenerate, force),
+        HookAction::Run { regenerate } => run(Path::new(CONFIG_FILE), regenerate),
+    }
+}
+
+/// Writes a pre-commit hook running `join-ai hook run` to the repository of
+/// the current directory, leaving hooks written by other tools alone unless
+/// `force` is set.
+fn install(regenerate: bool, force: bool) -> anyhow::Result<ExitCode> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("The current directory is not in a git repository.");
+    }
+    let hooks = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    let hook = hooks.join("pre-commit");
+    if let Ok(existing) = fs::read_to_string(&hook)
+        && !existing.contains(MARKER)
+        && !force
+    {
+        anyhow::bail!(
+            "{} already exists. Call `join-ai hook run` from it, or pass `--force` to replace it.",
+            hook.display()
+        );
+    }
+    let command = match regenerate {
+        true => "join-ai hook run --regenerate",
+        false => "join-ai hook run",
+    };
+    fs::create_dir_all(&hooks)?;
+    fs::write(&hook, format!("#!/bin/sh\n{MARKER}\nexec {command}\n"))
+        .with_context(|| format!("Failed to write {}", hook.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))?;
+    }
+    events::progress(&format!(
+        "Installed the pre-commit hook {}.",
+        hook.display()
+    ));
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Checks the configuration file `config`, and that each context file it
+/// lists is what its join writes now. Stale files are rewritten when
+/// `regenerate` is set; either way the run fails, so that the commit waits
+/// for them.
+fn run(config: &Path, regenerate: bool) -> anyhow::Result<ExitCode> {
+    let folder = config
+        .parent()
+        .filter(|folder| !folder.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    // Invalid `never_include` patterns fail here.
+    NeverInclude::discover(folder)?;
+
+    let mut stale = Vec::new();
+    for context in config::contexts(config)? {
+        let args = join_args(&context).with_context(|| {
+            format!(
+                "The context {} in {} is invalid",
+                context.output.display(),
+                config.display()
+            )
+        })?;
+        let fresh = join(&context, &args)?;
+        if fs::read(&context.output).ok().as_deref() == Some(fresh.as_slice()) {
+            events::progress(&format!("{} is up to date.", context.output.display()));
+            continue;
+        }
+        if regenerate {
+            if let Some(parent) = context.output.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&context.output, &fresh)
+                .with_context(|| format!("Failed to write {}", context.output.display()))?;
+        }
+        stale.push(context.output.display().to_string());
+    }
+    match (stale.as_slice(), regenerate) {
+        ([], _) => Ok(ExitCode::SUCCESS),
+        (stale, false) => anyhow::bail!(
+            "Out of date: {}. Regenerate with `join-ai hook run --regenerate`, and stage the changes.",
+            stale.join(", ")
+        ),
+        (stale, true) => anyhow::bail!(
+            "Regenerated {}. Stage the changes with `git add` and commit again.",
+            stale.join(", ")
+        ),
+    }
+}
+
+/// Parses the arguments of `context` as a `join` command, and checks its
+/// input and patterns.
+fn join_args(context: &ContextFile) -> anyhow::Result<JoinArgs> {
+    if context
+        .args
+        .iter()
+        .any(|arg| arg == "-o" || arg.starts_with("--output-file"))
+    {
+        anyhow::bail!("`args` must not set the output file, which is `output`.");
+    }
+    let command = ["join-ai", "join"].into_iter().map(String::from);
+    let Commands::Join(args) = Cli::try_parse_from(command.chain(context.args.clone()))?.command
+    else {
+        unreachable!("the command is always `join`");
+    };
+    if args.stdin_tar
+        || args.watch
+        || args.split_size.is_some()
+        || args.target.and_then(Target::split_size).is_some()
+    {
+        anyhow::bail!(
+            "A context file is written in one piece: `--stdin-tar`, `--watch` and split outputs cannot be used."
+        );
+    }
+    input::validate(&args)?;
+    Ok(args)
+}
+
+/// Runs the join of `context` into a temporary file, returning the output.
+fn join(context: &ContextFile, args: &JoinArgs) -> anyhow::Result<Vec<u8>> {
+    let temp_dir = tempfile::TempDir::new()?;
+    let mut args = args.clone();
+    args.output_file = temp_dir.path().join("context");
+    let pipeline = pipeline_for(&args)?;
+    let input = input::prepare(&args)?;
+    let mut options = JoinOptions::from(&args);
+    input.apply_to(&mut options);
+    // The context file is not part of its own input.
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.into());
+    if let Ok(relative) = absolute(&context.output).strip_prefix(absolute(&options.input_folder)) {
+        let pattern = format!("/{}", relative.to_string_lossy().replace('\\', "/"));
+        options.exclude.get_or_insert_default().push(pattern);
+    }
+    join_ai_core::join(&options, &pipeline)?;
+    Ok(fs::read(&args.output_file)?)
+}
+
+// --- Unit Tests for Git Hooks ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that a missing or outdated context file fails the run, that
+    /// `--regenerate` rewrites it, and that invalid patterns are reported.
+    #[test]
+    fn test_run_checks_context_files() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("src/main.rs").write_str("fn main() {}\n")?;
+        let output = dir.path().join("CONTEXT.txt");
+        let config = dir.path().join(CONFIG_FILE);
+        let write_config = |pattern: &str| {
+            let context = format!(
+                "[[context]]\noutput = {:?}\nargs = [{:?}, \"-p\", {pattern:?}]\n",
+                output.display().to_string(),
+                dir.path().display().to_string(),
+            );
+            fs::write(&config, context)
+        };
+        write_config("*.rs")?;
+
+        assert!(run(&config, false).is_err());
+        let error = run(&config, true).unwrap_err().to_string();
+        assert!(error.starts_with("Regenerated"), "{error}");
+        assert!(fs::read_to_string(&output)?.contains("fn main() {}"));
+        assert!(run(&config, false).is_ok());
+
+        dir.child("src/lib.rs").write_str("pub fn lib() {}\n")?;
+        let error = run(&config, false).unwrap_err().to_string();
+        assert!(error.starts_with("Out of date"), "{error}");
+
+        write_config("src/[")?;
+        let error = format!("{:#}", run(&config, false).unwrap_err());
+        assert!(error.contains("Invalid pattern 'src/['"), "{error}");
+        Ok(())
+    }
+}
@@ -0,0 +1,456 @@
+//! Resolution of the different input sources of the 'join' command (local
+//! folders, git revisions, archives, remote repositories) into a folder on the
+//! local filesystem that the walker can traverse.
+
+use crate::cli::{ExecFile, JoinArgs};
+use anyhow::Context;
+use join_ai_core::{JoinOptions, VirtualFile};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// The tree a join reads from, after any archive or remote input has been
+/// materialized on the local filesystem.
+pub struct PreparedInput {
+    /// The folder the walker starts from.
+    pub root: PathBuf,
+    /// The label shown in file headers instead of `root`, for temporary trees.
+    pub display_root: Option<PathBuf>,
+    /// The output of the `--exec-file` commands.
+    pub virtual_files: Vec<VirtualFile>,
+    /// The issues and documents of `--issue` and `--context-url`.
+    pub leading_files: Vec<VirtualFile>,
+    /// Keeps a temporary tree alive until the join has finished.
+    _temp_dir: Option<TempDir>,
+}
+
+impl PreparedInput {
+    /// An input that is already a folder on disk.
+    fn local(root: PathBuf) -> Self {
+        Self {
+            root,
+            display_root: None,
+            virtual_files: Vec::new(),
+            leading_files: Vec::new(),
+            _temp_dir: None,
+        }
+    }
+
+    /// An input materialized into a temporary directory, shown as `label`.
+    #[cfg_attr(
+        not(any(feature = "network", feature = "archive", feature = "git")),
+        allow(dead_code)
+    )]
+    fn temporary(temp_dir: TempDir, label: impl Into<PathBuf>) -> Self {
+        Self {
+            root: temp_dir.path().to_path_buf(),
+            display_root: Some(label.into()),
+            virtual_files: Vec::new(),
+            leading_files: Vec::new(),
+            _temp_dir: Some(temp_dir),
+        }
+    }
+
+    /// Points `options` at this input.
+    pub fn apply_to(&self, options: &mut JoinOptions) {
+        options.input_folder = self.root.clone();
+        options.display_root = self.display_root.clone();
+        options.virtual_files = self.virtual_files.clone();
+        options.leading_files = self.leading_files.clone();
+    }
+}
+
+/// Checks the input and the patterns of `args` up front, so that mistakes are
+/// all reported at once, before anything is downloaded or extracted.
+pub fn validate(args: &JoinArgs) -> anyhow::Result<()> {
+    if let Some(path) = &args.input_folder
+        && crate::pack::is_pack(path)
+    {
+        anyhow::bail!(
+            "{} is a context pack, which `ask` reads in place of a join. To join its files again, run `--frozen` with the join-ai.lock it holds.",
+            path.display()
+        );
+    }
+    let options = JoinOptions::from(args);
+    match &args.input_folder {
+        Some(path) if remote(args).is_none() && !args.stdin_tar && !is_archive(path) => {
+            options.validate()
+        }
+        _ => options.validate_patterns(),
+    }
+}
+
+/// Whether `path` is an archive input, which is extracted before the join.
+#[cfg(feature = "archive")]
+fn is_archive(path: &Path) -> bool {
+    path.is_file() && crate::archive::ArchiveKind::from_path(path).is_some()
+}
+
+/// Without archive support, files are left to `prepare`, which explains why
+/// they cannot be joined.
+#[cfg(not(feature = "archive"))]
+fn is_archive(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Resolves the input described by `args`, downloading or extracting it first
+/// if needed. Unless it is a dry run, runs the `--exec-file` commands and
+/// fetches the issues and documents to include.
+pub fn prepare(args: &JoinArgs) -> anyhow::Result<PreparedInput> {
+    let mut input = if let Some((remote, spec)) = remote(args) {
+        fetch_remote(remote, spec, args)?
+    } else if args.stdin_tar {
+        extract_stdin_tar()?
+    } else {
+        match &args.input_folder {
+            Some(folder) if args.rev.is_some() => export_revision(folder, args)?,
+            Some(path) if path.is_file() => extract_archive(path)?,
+            Some(folder) => PreparedInput::local(folder.clone()),
+            None => anyhow::bail!("No input folder was given."),
+        }
+    };
+    if !args.dry_run {
+        for file in &args.exec_file {
+            input.virtual_files.push(run_exec_file(file)?);
+        }
+        input.leading_files = fetch_context(args)?;
+    }
+    Ok(input)
+}
+
+/// Fetches the issues of `--issue` and the documents of `--context-url`, in
+/// that order.
+#[cfg(feature = "network")]
+fn fetch_context(args: &JoinArgs) -> anyhow::Result<Vec<VirtualFile>> {
+    use crate::remote::github::{self, GitHub};
+
+    let mut files = Vec::new();
+    if !args.issue.is_empty() {
+        let github = GitHub::from_env();
+        let repository = default_repository(args);
+        for spec in &args.issue {
+            let spec = github::parse_issue(spec, repository.as_deref())?;
+            join_ai_core::events::progress(&format!("Fetching the issue {spec}..."));
+            let issue = github.issue(&spec).map_err(crate::exit::network)?;
+            files.push(VirtualFile {
+                path: PathBuf::from(format!("ISSUE-{}.md", spec.number)),
+                contents: issue.render().into_bytes(),
+            });
+        }
+    }
+    for url in &args.context_url {
+        join_ai_core::events::progress(&format!("Fetching {url}..."));
+        let contents = crate::remote::fetch_url(url).map_err(crate::exit::network)?;
+        let mut name = url_file_name(url);
+        // Documents of the same name are told apart by their position.
+        if files.iter().any(|file| file.path == Path::new(&name)) {
+            name = format!("{}-{name}", files.len() + 1);
+        }
+        files.push(VirtualFile {
+            path: PathBuf::from(name),
+            contents,
+        });
+    }
+    Ok(files)
+}
+
+#[cfg(not(feature = "network"))]
+fn fetch_context(args: &JoinArgs) -> anyhow::Result<Vec<VirtualFile>> {
+    if !args.issue.is_empty() || !args.context_url.is_empty() {
+        anyhow::bail!(
+            "`--issue` and `--context-url` require join-ai to be built with the `network` feature."
+        );
+    }
+    Ok(Vec::new())
+}
+
+/// The repository that bare issue numbers refer to, as `owner/repo`: the
+/// GitHub repository of the input, or the `origin` remote of a local folder.
+#[cfg(feature = "network")]
+fn default_repository(args: &JoinArgs) -> Option<String> {
+    use crate::remote::{PrSpec, RepoSpec};
+
+    match remote(args) {
+        Some((Remote::GitHub, spec)) => {
+            let spec: RepoSpec = spec.parse().ok()?;
+            Some(format!("{}/{}", spec.owner, spec.repo))
+        }
+        Some((Remote::GitHubPr, spec)) => {
+            let spec: PrSpec = spec.parse().ok()?;
+            Some(format!("{}/{}", spec.owner, spec.repo))
+        }
+        Some(_) => None,
+        #[cfg(feature = "git")]
+        None => {
+            let folder = args.input_folder.as_deref()?;
+            let info = join_ai_core::git::RepoInfo::discover(folder, &args.output_file).ok()?;
+            crate::remote::github::repository_of_remote(info.remote_url.as_deref()?)
+        }
+        #[cfg(not(feature = "git"))]
+        None => None,
+    }
+}
+
+/// Names the document at `url` after the last segment of its path, or its
+/// host when it has none.
+#[cfg(feature = "network")]
+fn url_file_name(url: &str) -> String {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    let path = url.split_once("://").map_or(url, |(_, rest)| rest);
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or("context")
+        .to_string()
+}
+
+/// Runs the command of `file` through the shell, returning its output as a
+/// file. A command that fails stops the join, with what it wrote to stderr.
+fn run_exec_file(file: &ExecFile) -> anyhow::Result<VirtualFile> {
+    join_ai_core::events::progress(&format!(
+        "Running `{}` for {}",
+        file.command,
+        file.name.display()
+    ));
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let output = Command::new(shell)
+        .args([flag, &file.command])
+        .output()
+        .with_context(|| format!("Failed to run `{}`", file.command))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`{}` (for {}) failed with {}: {}",
+            file.command,
+            file.name.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(VirtualFile {
+        path: file.name.clone(),
+        contents: output.stdout,
+    })
+}
+
+/// Exports the `--rev` tree of the repository containing `folder` into a
+/// temporary directory. Headers keep showing `folder`, as for a normal join.
+#[cfg(feature = "git")]
+fn export_revision(folder: &Path, args: &JoinArgs) -> anyhow::Result<PreparedInput> {
+    let revision = args.rev.as_deref().unwrap_or("HEAD");
+    join_ai_core::events::progress(&format!(
+        "Reading {} at revision {revision}",
+        folder.display()
+    ));
+
+    let temp_dir = TempDir::new()?;
+    join_ai_core::git::export_revision(
+        folder,
+        revision,
+        &JoinOptions::from(args),
+        temp_dir.path(),
+    )?;
+    Ok(PreparedInput::temporary(temp_dir, folder))
+}
+
+#[cfg(not(feature = "git"))]
+fn export_revision(_folder: &Path, _args: &JoinArgs) -> anyhow::Result<PreparedInput> {
+    anyhow::bail!("`--rev` requires join-ai to be built with the `git` feature.")
+}
+
+/// Extracts an archive input into a temporary directory.
+#[cfg(feature = "archive")]
+fn extract_archive(path: &Path) -> anyhow::Result<PreparedInput> {
+    use crate::archive::{self, ArchiveKind};
+
+    let Some(kind) = ArchiveKind::from_path(path) else {
+        anyhow::bail!(
+            "{} is a file, not a folder. Supported archive inputs are .zip, .tar, .tar.gz and .tgz.",
+            path.display()
+        );
+    };
+    join_ai_core::events::progress(&format!("Extracting archive: {}", path.display()));
+
+    let temp_dir = TempDir::new()?;
+    archive::extract(path, kind, temp_dir.path())?;
+
+    let label = path.file_name().map(PathBuf::from).unwrap_or_default();
+    Ok(PreparedInput::temporary(temp_dir, label))
+}
+
+#[cfg(not(feature = "archive"))]
+fn extract_archive(path: &Path) -> anyhow::Result<PreparedInput> {
+    anyhow::bail!(
+        "{} is a file, not a folder. Archive inputs require join-ai to be built with the `archive` feature.",
+        path.display()
+    )
+}
+
+/// Extracts a tar stream (optionally gzip-compressed) read from stdin into a
+/// temporary directory, e.g. the output of `git archive HEAD`.
+#[cfg(feature = "archive")]
+fn extract_stdin_tar() -> anyhow::Result<PreparedInput> {
+    use std::io::IsTerminal;
+
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        anyhow::bail!(
+            "`--stdin-tar` expects a tar stream on stdin, e.g. `git archive HEAD | join-ai join --stdin-tar`."
+        );
+    }
+    join_ai_core::events::progress("Reading tar stream from stdin...");
+    extract_tar_input(stdin.lock())
+}
+
+/// Extracts the tar stream of `--stdin-tar` into a temporary directory. Links
+/// in the stream are skipped, as it comes from an untrusted source.
+#[cfg(feature = "archive")]
+fn extract_tar_input(reader: impl std::io::Read) -> anyhow::Result<PreparedInput> {
+    let temp_dir = TempDir::new()?;
+    crate::archive::extract_tar_stream(reader, temp_dir.path())?;
+    Ok(PreparedInput::temporary(temp_dir, "stdin"))
+}
+
+#[cfg(not(feature = "archive"))]
+fn extract_stdin_tar() -> anyhow::Result<PreparedInput> {
+    anyhow::bail!("`--stdin-tar` requires join-ai to be built with the `archive` feature.")
+}
+
+/// A repository or a pull (merge) request on one of the supported hosts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Remote {
+    GitHub,
+    GitHubPr,
+    GitLab,
+    GitLabMr,
+    Bitbucket,
+    BitbucketPr,
+}
+
+impl Remote {
+    /// The option that selects this input, e.g. `--gitlab-mr`.
+    pub fn flag(self) -> &'static str {
+        match self {
+            Self::GitHub => "--github",
+            Self::GitHubPr => "--github-pr",
+            Self::GitLab => "--gitlab",
+            Self::GitLabMr => "--gitlab-mr",
+            Self::Bitbucket => "--bitbucket",
+            Self::BitbucketPr => "--bitbucket-pr",
+        }
+    }
+
+    /// Whether the input is the changes of a pull or merge request.
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    fn is_request(self) -> bool {
+        matches!(self, Self::GitHubPr | Self::GitLabMr | Self::BitbucketPr)
+    }
+}
+
+/// The remote input of `args`, if any, with its spec.
+pub fn remote(args: &JoinArgs) -> Option<(Remote, &str)> {
+    [
+        (Remote::GitHub, &args.github),
+        (Remote::GitHubPr, &args.github_pr),
+        (Remote::GitLab, &args.gitlab),
+        (Remote::GitLabMr, &args.gitlab_mr),
+        (Remote::Bitbucket, &args.bitbucket),
+        (Remote::BitbucketPr, &args.bitbucket_pr),
+    ]
+    .into_iter()
+    .find_map(|(remote, spec)| Some((remote, spec.as_deref()?)))
+}
+
+/// Downloads a remote repository, or the files changed by a pull or merge
+/// request, through the API of its host into a temporary directory. The
+/// discussion of a request is added as `PULL_REQUEST.md` if asked for.
+#[cfg(feature = "network")]
+fn fetch_remote(remote: Remote, spec: &str, args: &JoinArgs) -> anyhow::Result<PreparedInput> {
+    use crate::remote::{Host, bitbucket::Bitbucket, github::GitHub, gitlab::GitLab};
+
+    let (name, host): (&str, Box<dyn Host>) = match remote {
+        Remote::GitHub | Remote::GitHubPr => ("GitHub", Box::new(GitHub::from_env())),
+        Remote::GitLab | Remote::GitLabMr => ("GitLab", Box::new(GitLab::from_env())),
+        Remote::Bitbucket | Remote::BitbucketPr => ("Bitbucket", Box::new(Bitbucket::from_env())),
+    };
+    let repository = host.repository(spec, remote.is_request())?;
+    let what = match remote {
+        Remote::GitLabMr => "the merge request ",
+        _ if remote.is_request() => "the pull request ",
+        _ => "",
+    };
+    join_ai_core::events::progress(&format!("Fetching {what}{spec} from the {name} API..."));
+
+    let temp_dir = TempDir::new()?;
+    let options = JoinOptions::from(args);
+    let count = match remote.is_request() {
+        true => host.fetch_request(spec, &options, temp_dir.path()),
+        false => host.fetch(spec, &options, temp_dir.path()),
+    }
+    .map_err(crate::exit::network)?;
+    let files = match remote.is_request() {
+        true => "changed files of",
+        false => "files from",
+    };
+    join_ai_core::events::progress(&format!("Downloaded {count} {files} {spec}."));
+
+    let mut input = PreparedInput::temporary(temp_dir, repository);
+    if args.pr_discussion {
+        let discussion = host.discussion(spec).map_err(crate::exit::network)?;
+        input.virtual_files.push(VirtualFile {
+            path: PathBuf::from("PULL_REQUEST.md"),
+            contents: discussion.render().into_bytes(),
+        });
+    }
+    Ok(input)
+}
+
+#[cfg(not(feature = "network"))]
+fn fetch_remote(remote: Remote, _spec: &str, _args: &JoinArgs) -> anyhow::Result<PreparedInput> {
+    anyhow::bail!(
+        "`{}` requires join-ai to be built with the `network` feature.",
+        remote.flag()
+    )
+}
+
+// --- Unit Tests for Input Resolution ---
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use super::*;
+
+    /// Verifies that a link in a `--stdin-tar` stream to a file outside of it
+    /// does not bring that file into the join.
+    #[test]
+    fn test_stdin_tar_skips_links() -> anyhow::Result<()> {
+        let outside = TempDir::new()?;
+        let secret = outside.path().join("credentials");
+        std::fs::write(&secret, "TOPSECRET")?;
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "app/main.txt", &b"hello"[..])?;
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o644);
+        builder.append_link(&mut header, "app/link.txt", &secret)?;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, &builder.into_inner()?)?;
+        let stream = encoder.finish()?;
+
+        let input = extract_tar_input(stream.as_slice())?;
+        let output = outside.path().join("out.txt");
+        let mut options = JoinOptions::new(&input.root, &output);
+        input.apply_to(&mut options);
+        join_ai_core::join(&options, &join_ai_core::default_pipeline(&options))?;
+        let joined = std::fs::read_to_string(&output)?;
+        assert!(joined.contains("// FILE: stdin/app/main.txt\nhello"));
+        assert!(!joined.contains("link.txt"));
+        assert!(!joined.contains("TOPSECRET"));
+        Ok(())
+    }
+}
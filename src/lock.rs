@@ -0,0 +1,158 @@
+//! Lock files (`--lock`, `--frozen`): the exact list of files of a join, with
+//! the SHA-256 of each, written to `join-ai.lock`, so that a later join reads
+//! the very same content or fails. Teams comparing model answers across runs
+//! can then rule out a change in the context.
+
+use crate::audit::{sha256, source_path};
+use anyhow::Context;
+use join_ai_core::JoinOptions;
+use join_ai_core::anonymize::PathMap;
+use join_ai_core::processor::Report;
+use serde_json::{Value, json};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the lock file, in the current directory.
+pub const LOCK_FILE: &str = "join-ai.lock";
+
+/// Writes the files of `report` to the lock file at `path`, in output order,
+/// each relative to the input folder with the hash of its content on disk.
+/// Files that are not on disk, such as the output of `--exec-file` commands, are
+/// not listed.
+pub fn write(path: &Path, options: &JoinOptions, report: &Report) -> anyhow::Result<()> {
+    fs::write(path, render(options, report)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Returns the lock file of the files of `report`, as `write` writes it.
+pub fn render(options: &JoinOptions, report: &Report) -> anyhow::Result<String> {
+    let path_map = options
+        .anonymize_paths
+        .as_deref()
+        .and_then(|file| PathMap::load(file).ok());
+    let mut files = Vec::new();
+    for (shown, _) in &report.included {
+        let source = source_path(options, path_map.as_ref(), shown);
+        let relative = source
+            .strip_prefix(&options.input_folder)
+            .unwrap_or(&source);
+        if options
+            .virtual_files
+            .iter()
+            .chain(&options.leading_files)
+            .any(|file| file.path == relative)
+        {
+            continue;
+        }
+        let content =
+            fs::read(&source).with_context(|| format!("Failed to read {}", source.display()))?;
+        files.push(json!({
+            "path": relative.to_string_lossy().replace('\\', "/"),
+            "sha256": sha256(&content),
+        }));
+    }
+    let lock = json!({ "version": 1, "files": files });
+    Ok(format!("{}\n", serde_json::to_string_pretty(&lock)?))
+}
+
+/// Reads the lock file at `path` and makes `options` join exactly the files it
+/// lists, in its order, failing if any of them changed or is missing. The
+/// options that select files are cleared, as the list is their outcome.
+pub fn freeze(path: &Path, options: &mut JoinOptions) -> anyhow::Result<()> {
+    let text = fs::read_to_string(path).with_context(|| {
+        format!(
+            "Failed to read {}. Write it with `--lock` first",
+            path.display()
+        )
+    })?;
+    let entries = parse(&text)
+        .with_context(|| format!("{} is not a lock file written by `--lock`", path.display()))?;
+    let mut drift = Vec::new();
+    for (file, hash) in &entries {
+        match fs::read(options.input_folder.join(file)) {
+            Ok(content) if sha256(&content) == *hash => {}
+            Ok(_) => drift.push(format!("changed: {}", file.display())),
+            Err(_) => drift.push(format!("missing: {}", file.display())),
+        }
+    }
+    if !drift.is_empty() {
+        anyhow::bail!(
+            "The files have drifted from {}:\n  - {}\nRun the join with `--lock` to accept the changes.",
+            path.display(),
+            drift.join("\n  - ")
+        );
+    }
+    options.files = Some(entries.into_iter().map(|(file, _)| file).collect());
+    options.max_tokens = None;
+    options.relevant_to = None;
+    options.entry = None;
+    Ok(())
+}
+
+/// Reads the files of a lock file and their hashes.
+fn parse(text: &str) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let lock: Value = serde_json::from_str(text)?;
+    lock["files"]
+        .as_array()
+        .and_then(|files| {
+            files
+                .iter()
+                .map(|file| {
+                    Some((
+                        PathBuf::from(file["path"].as_str()?),
+                        file["sha256"].as_str()?.to_string(),
+                    ))
+                })
+                .collect()
+        })
+        .context("`files` must list the `path` and `sha256` of each file")
+}
+
+// --- Unit Tests for Lock Files ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that a lock file pins the files of a join, in order, and that
+    /// a changed or missing file is reported as drift.
+    #[test]
+    fn test_lock_and_freeze() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("src/b.rs").write_str("fn b() {}\n")?;
+        dir.child("README.md").write_str("# App\n")?;
+        let lock = dir.path().join(LOCK_FILE);
+        let options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        let report = Report {
+            included: vec![
+                (dir.path().join("src/b.rs"), 10),
+                (dir.path().join("README.md"), 6),
+            ],
+            ..Report::default()
+        };
+        write(&lock, &options, &report)?;
+        assert!(fs::read_to_string(&lock)?.contains("\"path\": \"src/b.rs\""));
+
+        let mut frozen = options.clone();
+        frozen.max_tokens = Some(1);
+        freeze(&lock, &mut frozen)?;
+        assert_eq!(
+            frozen.files,
+            Some(vec![PathBuf::from("src/b.rs"), PathBuf::from("README.md")])
+        );
+        assert_eq!(frozen.max_tokens, None);
+
+        dir.child("src/b.rs").write_str("fn b2() {}\n")?;
+        fs::remove_file(dir.path().join("README.md"))?;
+        let error = freeze(&lock, &mut options.clone()).unwrap_err().to_string();
+        assert!(
+            error.contains("  - changed: src/b.rs\n  - missing: README.md\n"),
+            "{error}"
+        );
+
+        fs::write(&lock, "{\"files\": [{\"path\": \"a.rs\"}]}")?;
+        assert!(freeze(&lock, &mut options.clone()).is_err());
+        Ok(())
+    }
+}
@@ -1,10 +1,18 @@
+#[cfg(feature = "color")]
 use anstyle::{AnsiColor, Color, Style};
+#[cfg(feature = "color")]
 use clap::builder::styling::Styles;
 use clap::{CommandFactory, FromArgMatches};
-use join_ai::{cli::Cli, run};
+#[cfg(feature = "color")]
+use join_ai::cli::ColorMode;
+use join_ai::{cli::Cli, exit, run};
+use join_ai_core::events;
+use std::io::IsTerminal;
+use std::process::ExitCode;
 
 /// Creates a custom style for the CLI's help output, mimicking the appearance of `cargo`.
 /// This provides a more professional and familiar feel for Rust developers.
+#[cfg(feature = "color")]
 fn get_styles() -> Styles {
     Styles::styled()
         .header(
@@ -26,19 +34,50 @@ fn get_styles() -> Styles {
 }
 
 /// The main entry point of the application binary.
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
     // 1. Build the command-line interface definition from the `Cli` struct.
-    let mut cmd = Cli::command();
+    let cmd = Cli::command();
 
-    // 2. Apply the custom styles to the command's help message.
-    cmd = cmd.styles(get_styles());
+    // 2. Apply the custom styles to the command's help message, honoring
+    //    `--color` and `NO_COLOR` (colors are only used on terminals by default).
+    #[cfg(feature = "color")]
+    let cmd = {
+        let color = ColorMode::detect(std::env::args_os(), std::env::var_os("NO_COLOR"));
+        cmd.color(color.into()).styles(get_styles())
+    };
 
-    // 3. Parse the actual command-line arguments provided by the user.
-    let matches = cmd.get_matches();
+    // 3. Parse the actual command-line arguments provided by the user. Usage
+    //    errors exit with the generic error code rather than clap's own.
+    let matches = match cmd.try_get_matches() {
+        Ok(matches) => matches,
+        Err(e) => {
+            let _ = e.print();
+            return if e.use_stderr() {
+                ExitCode::from(exit::ERROR)
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
 
-    // 4. Convert the parsed matches back into our strongly-typed `Cli` struct.
-    let cli = Cli::from_arg_matches(&matches)?;
-
-    // 5. Pass the parsed command to the core logic in the `lib.rs` crate.
-    run(cli.command)
+    // 4. Convert the parsed matches back into our strongly-typed `Cli` struct,
+    //    and pass the parsed command to the core logic in the `lib.rs` crate.
+    //    Paths in messages link to their files on terminals that support it.
+    let result = Cli::from_arg_matches(&matches)
+        .map_err(anyhow::Error::from)
+        .and_then(|cli| {
+            let terminal = std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+            events::set_hyperlinks(
+                cli.color
+                    .hyperlinks(|name| std::env::var(name).ok(), terminal),
+            );
+            run(cli.command)
+        });
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            exit::for_error(&e)
+        }
+    }
 }
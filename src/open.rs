@@ -0,0 +1,71 @@
+//! Opening the output file after a join (`--open`), to inspect it before
+//! pasting it into a model.
+
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::process::Command;
+
+/// Opens `path` in the user's editor (`$VISUAL` or `$EDITOR`), or with the
+/// default application of the operating system when no editor is configured.
+///
+/// An editor runs in the foreground, as it usually needs the terminal; the
+/// default application is launched in the background.
+pub fn open(path: &Path) -> anyhow::Result<()> {
+    let editor = std::env::var_os("VISUAL")
+        .filter(|value| !value.is_empty())
+        .or_else(|| std::env::var_os("EDITOR").filter(|value| !value.is_empty()));
+    let Some(mut command) = opener(path, editor.as_deref()) else {
+        anyhow::bail!("The configured editor is blank; check $VISUAL and $EDITOR.");
+    };
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run {program}: {e}"))?;
+    if !status.success() {
+        anyhow::bail!("{program} exited with {status}");
+    }
+    Ok(())
+}
+
+/// Builds the command that opens `path`: `editor` (which may carry arguments,
+/// e.g. `code --wait`) if set, otherwise the opener of the platform.
+fn opener(path: &Path, editor: Option<&OsStr>) -> Option<Command> {
+    if let Some(editor) = editor {
+        let editor = editor.to_string_lossy();
+        let mut words = editor.split_whitespace();
+        let mut command = Command::new(words.next()?);
+        command.args(words).arg(path);
+        return Some(command);
+    }
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        // `start` is a shell builtin; its first quoted argument is a window title.
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(OsString::from(path));
+    Some(command)
+}
+
+// --- Unit Tests for Opening the Output ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that editor commands keep their arguments before the path.
+    #[test]
+    fn test_editor_command() {
+        let path = Path::new("out.txt");
+        let command = opener(path, Some(OsStr::new("code --wait"))).unwrap();
+        assert_eq!(command.get_program(), "code");
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(args, ["--wait", "out.txt"]);
+
+        assert!(opener(path, Some(OsStr::new("  "))).is_none());
+        assert!(opener(path, None).is_some());
+    }
+}
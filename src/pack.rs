@@ -0,0 +1,240 @@
+//! Context packs (`--pack`): one `.joinpack` file that bundles the output of a
+//! join with what it takes to check and reproduce it, so that teammates and CI
+//! share the very same context. `ask` reads a pack in place of an input folder.
+//!
+//! A pack is a gzip-compressed tar archive of:
+//! - `manifest.json`: the version of join-ai, the format and size of the
+//!   output, and the names of its parts in the pack;
+//! - `output/`: the output file, or its parts when the output is split;
+//! - `join-ai.lock`: the files of the join with their hashes, as `--lock`
+//!   writes them, for `--frozen` to join them again;
+//! - `config.json`: the arguments of the join, and the `.join-ai.toml` files
+//!   that applied to it.
+//!
+//! Packs are only written and read when the crate is built with the `archive`
+//! feature.
+
+use std::path::Path;
+
+#[cfg(feature = "archive")]
+use {
+    crate::lock,
+    anyhow::Context,
+    flate2::{Compression, read::GzDecoder, write::GzEncoder},
+    join_ai_core::config::CONFIG_FILE,
+    join_ai_core::format::OutputFormat,
+    join_ai_core::selection::estimate_tokens,
+    join_ai_core::{JoinOptions, processor::Report},
+    serde_json::{Value, json},
+    std::collections::HashMap,
+    std::fs::{self, File},
+    std::io::{BufReader, Read, Write},
+    std::time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The extension of pack files.
+pub const EXTENSION: &str = "joinpack";
+
+/// The version of the layout of packs, which readers check.
+#[cfg(feature = "archive")]
+const VERSION: u64 = 1;
+
+/// Whether `path` is a pack file, to be read rather than joined.
+pub fn is_pack(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == EXTENSION)
+        && path.is_file()
+}
+
+/// Writes a pack of the join of `report`, made with `options`, to `path`.
+#[cfg(feature = "archive")]
+pub fn write(path: &Path, options: &JoinOptions, report: &Report) -> anyhow::Result<()> {
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let outputs: Vec<String> = report
+        .outputs
+        .iter()
+        .map(|output| {
+            let name = output.file_name().unwrap_or_default().to_string_lossy();
+            format!("output/{name}")
+        })
+        .collect();
+    let manifest = json!({
+        "version": VERSION,
+        "join_ai": env!("CARGO_PKG_VERSION"),
+        "created": created,
+        "format": match options.format {
+            OutputFormat::Text => "text",
+            OutputFormat::Markdown => "markdown",
+        },
+        "files": report.files_written,
+        "bytes": report.bytes_written,
+        "tokens": estimate_tokens(report.bytes_written),
+        "outputs": outputs,
+    });
+
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut pack = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mut append = |name: &str, content: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(created);
+        pack.append_data(&mut header, name, content)
+    };
+    append("manifest.json", pretty(&manifest)?.as_bytes())?;
+    for (output, name) in report.outputs.iter().zip(&outputs) {
+        let content =
+            fs::read(output).with_context(|| format!("Failed to read {}", output.display()))?;
+        append(name, &content)?;
+    }
+    append(lock::LOCK_FILE, lock::render(options, report)?.as_bytes())?;
+    append("config.json", pretty(&config(options))?.as_bytes())?;
+    pack.into_inner()?.finish()?.flush()?;
+    Ok(())
+}
+
+/// Reads the output of the pack at `path`, its parts put back together.
+#[cfg(feature = "archive")]
+pub fn read_output(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut entries: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+    for entry in archive
+        .entries()
+        .with_context(|| format!("{} is not a pack written by `--pack`", path.display()))?
+    {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        entries.insert(name, content);
+    }
+    let manifest: Value = entries
+        .get("manifest.json")
+        .map(|manifest| serde_json::from_slice(manifest))
+        .transpose()?
+        .with_context(|| format!("{} has no manifest.json", path.display()))?;
+    if manifest["version"].as_u64() != Some(VERSION) {
+        anyhow::bail!(
+            "{} is a pack of version {}, which this version of join-ai cannot read.",
+            path.display(),
+            manifest["version"]
+        );
+    }
+    let mut output = Vec::new();
+    for name in manifest["outputs"].as_array().into_iter().flatten() {
+        let name = name.as_str().unwrap_or_default();
+        let part = entries
+            .remove(name)
+            .with_context(|| format!("{} lacks {name}, listed in its manifest", path.display()))?;
+        output.extend(part);
+    }
+    Ok(output)
+}
+
+#[cfg(not(feature = "archive"))]
+pub fn write(
+    _path: &Path,
+    _options: &join_ai_core::JoinOptions,
+    _report: &join_ai_core::processor::Report,
+) -> anyhow::Result<()> {
+    anyhow::bail!("`--pack` requires join-ai to be built with the `archive` feature.")
+}
+
+#[cfg(not(feature = "archive"))]
+pub fn read_output(_path: &Path) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("Reading a pack requires join-ai to be built with the `archive` feature.")
+}
+
+/// The configuration of a join: its arguments, and the `.join-ai.toml` files
+/// of its input folder and the folders above it, by their path from it.
+#[cfg(feature = "archive")]
+fn config(options: &JoinOptions) -> Value {
+    let absolute =
+        std::path::absolute(&options.input_folder).unwrap_or_else(|_| options.input_folder.clone());
+    let files: Vec<Value> = absolute
+        .ancestors()
+        .enumerate()
+        .filter_map(|(depth, folder)| {
+            let content = fs::read_to_string(folder.join(CONFIG_FILE)).ok()?;
+            Some(json!({
+                "path": format!("{}{CONFIG_FILE}", "../".repeat(depth)),
+                "content": content,
+            }))
+        })
+        .collect();
+    json!({
+        "arguments": std::env::args().skip(1).collect::<Vec<_>>(),
+        "config_files": files,
+    })
+}
+
+/// Formats `value` as indented JSON, with a final newline.
+#[cfg(feature = "archive")]
+fn pretty(value: &Value) -> anyhow::Result<String> {
+    Ok(format!("{}\n", serde_json::to_string_pretty(value)?))
+}
+
+// --- Unit Tests for Context Packs ---
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that a pack holds the manifest, the lock file and the
+    /// configuration, and gives back the parts of the output in order.
+    #[test]
+    fn test_write_and_read_pack() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("src/a.rs").write_str("fn a() {}\n")?;
+        dir.child(CONFIG_FILE)
+            .write_str("never_include = [\"*.pem\"]\n")?;
+        dir.child("out-1.txt").write_str("// FILE: src/a.rs\n")?;
+        dir.child("out-2.txt").write_str("fn a() {}\n")?;
+        let options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        let report = Report {
+            files_written: 1,
+            bytes_written: 10,
+            included: vec![(dir.path().join("src/a.rs"), 10)],
+            outputs: vec![dir.path().join("out-1.txt"), dir.path().join("out-2.txt")],
+            ..Report::default()
+        };
+        let path = dir.path().join("context.joinpack");
+        write(&path, &options, &report)?;
+        assert!(is_pack(&path));
+        assert_eq!(read_output(&path)?, b"// FILE: src/a.rs\nfn a() {}\n");
+
+        let mut names = Vec::new();
+        let file = File::open(&path)?;
+        for entry in tar::Archive::new(GzDecoder::new(file)).entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            match name.as_str() {
+                "join-ai.lock" => assert!(content.contains("\"path\": \"src/a.rs\"")),
+                "config.json" => assert!(content.contains("never_include")),
+                _ => {}
+            }
+            names.push(name);
+        }
+        assert_eq!(
+            names,
+            [
+                "manifest.json",
+                "output/out-1.txt",
+                "output/out-2.txt",
+                "join-ai.lock",
+                "config.json"
+            ]
+        );
+
+        fs::write(&path, b"not a pack")?;
+        assert!(read_output(&path).is_err());
+        Ok(())
+    }
+}
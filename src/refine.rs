@@ -0,0 +1,244 @@
+//! Budget refinement: when a join is over `--max-tokens` on a terminal, join-ai
+//! offers to fit it by hand rather than leave out whichever files come last.
+//! The heaviest directories are listed, a keystroke leaves one out or keeps
+//! only the files at its top, and the tokens are estimated again until the
+//! join fits. The decisions can then be saved to `.joinignore`, so that later
+//! joins start from them.
+//!
+//! The loop only runs when the crate is built with the `interactive` feature.
+//! Otherwise, and in scripts, the budget leaves out the last files.
+
+use crate::cli::JoinArgs;
+use join_ai_core::{JoinOptions, selection, walker};
+use std::path::PathBuf;
+
+#[cfg(feature = "interactive")]
+use {
+    crate::suggest,
+    crate::tokens::{self, Row},
+    console::Term,
+    join_ai_core::events::LogFormat,
+    join_ai_core::selection::estimate_tokens,
+    join_ai_core::walker::IGNORE_FILE,
+    std::io::{self, IsTerminal},
+};
+
+/// How many directories are offered at a time, one per digit key.
+#[cfg(feature = "interactive")]
+const CHOICES: usize = 9;
+
+/// How a directory is cut down to fit a join in its budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// Leave the directory out.
+    Exclude(String),
+    /// Keep the files at the top of the directory, without its subdirectories.
+    Truncate(String),
+}
+
+impl Decision {
+    /// The exclusion pattern of the decision: `/src/legacy/`, or
+    /// `/src/legacy/*/` for a truncation.
+    pub fn pattern(&self) -> String {
+        match self {
+            Self::Exclude(dir) => format!("/{dir}"),
+            Self::Truncate(dir) => format!("/{dir}*/"),
+        }
+    }
+
+    /// Leaves out the files of `options` that the decision cuts.
+    pub fn apply_to(&self, options: &mut JoinOptions) {
+        options
+            .exclude
+            .get_or_insert_with(Vec::new)
+            .push(self.pattern());
+    }
+}
+
+/// The files that `options` would join without a budget, with the bytes that
+/// count against it.
+pub fn estimate(options: &JoinOptions) -> anyhow::Result<Vec<(PathBuf, u64)>> {
+    let mut unbounded = options.clone();
+    unbounded.max_tokens = None;
+    let paths: Vec<PathBuf> = walker::find_files(&unbounded)?.into_iter().collect();
+    let selection = selection::select(paths, &unbounded);
+    Ok(selection
+        .included
+        .into_iter()
+        .map(|candidate| (candidate.path, candidate.size))
+        .collect())
+}
+
+/// Offers to fit the join of `options` in its budget by hand, when it is over
+/// it and join-ai runs on a terminal. The decisions are applied to `options`.
+#[cfg(feature = "interactive")]
+pub fn refine(args: &JoinArgs, options: &mut JoinOptions) -> anyhow::Result<()> {
+    let Some(budget) = options.max_tokens else {
+        return Ok(());
+    };
+    if args.check
+        || args.frozen
+        || args.watch
+        || args.log_format != LogFormat::Text
+        || options.files.is_some()
+        || !io::stdin().is_terminal()
+        || !io::stderr().is_terminal()
+    {
+        return Ok(());
+    }
+    let total = |files: &[(PathBuf, u64)]| files.iter().map(|(_, bytes)| bytes).sum::<u64>();
+    let mut files = estimate(options)?;
+    if estimate_tokens(total(&files)) <= budget {
+        return Ok(());
+    }
+    let term = Term::stderr();
+    term.write_line(&format!(
+        "The join is ~{} tokens, over the budget of {budget}. Fit it by hand? [y/N]",
+        estimate_tokens(total(&files))
+    ))?;
+    if !matches!(term.read_char()?, 'y' | 'Y') {
+        return Ok(());
+    }
+
+    let mut depth = 1;
+    let mut decisions = Vec::new();
+    loop {
+        let tokens = estimate_tokens(total(&files));
+        if tokens <= budget {
+            term.write_line(&format!("The join fits: ~{tokens} tokens of {budget}."))?;
+            break;
+        }
+        let analysis = tokens::analyze(&files, &options.input_folder, depth, CHOICES + 1);
+        let rows: Vec<&Row> = analysis
+            .directories
+            .iter()
+            .filter(|row| row.name != "./")
+            .take(CHOICES)
+            .collect();
+        term.write_line("")?;
+        term.write_line(&format!(
+            "~{tokens} tokens, {} over the budget of {budget}:",
+            tokens - budget
+        ))?;
+        let width = rows.iter().map(|row| row.name.len()).max().unwrap_or(0);
+        for (number, row) in rows.iter().enumerate() {
+            term.write_line(&format!(
+                "  [{}] {:<width$}  ~{:>8} tokens  {:>5.1}%  {} files",
+                number + 1,
+                row.name,
+                estimate_tokens(row.bytes),
+                tokens::percent(row.bytes, analysis.total),
+                row.files
+            ))?;
+        }
+        term.write_line(
+            "1-9: leave out   t, 1-9: keep only its top files   +/-: deeper/shallower   q: let the budget leave out the last files",
+        )?;
+        let pick = |key: char| {
+            let number = key.to_digit(10)? as usize;
+            rows.get(number.checked_sub(1)?).map(|row| row.name.clone())
+        };
+        let decision = match term.read_char()? {
+            'q' | 'Q' => break,
+            '+' => {
+                depth += 1;
+                continue;
+            }
+            '-' => {
+                depth = depth.saturating_sub(1).max(1);
+                continue;
+            }
+            't' | 'T' => match pick(term.read_char()?) {
+                Some(dir) => Decision::Truncate(dir),
+                None => continue,
+            },
+            key => match pick(key) {
+                Some(dir) => Decision::Exclude(dir),
+                None => continue,
+            },
+        };
+        term.write_line(&match &decision {
+            Decision::Exclude(dir) => format!("Leaving out {dir}"),
+            Decision::Truncate(dir) => format!("Keeping only the top files of {dir}"),
+        })?;
+        decision.apply_to(options);
+        decisions.push(decision);
+        files = estimate(options)?;
+    }
+
+    // Decisions are only saved for a local folder, not a download.
+    let Some(folder) = args.input_folder.as_ref().filter(|path| path.is_dir()) else {
+        return Ok(());
+    };
+    if decisions.is_empty() {
+        return Ok(());
+    }
+    let ignore_file = folder.join(IGNORE_FILE);
+    term.write_line(&format!(
+        "Save these decisions to {}? [y/N]",
+        ignore_file.display()
+    ))?;
+    if matches!(term.read_char()?, 'y' | 'Y') {
+        let entries: Vec<(String, String)> = decisions
+            .iter()
+            .map(|decision| {
+                let reason = match decision {
+                    Decision::Exclude(_) => format!("Left out to fit {budget} tokens"),
+                    Decision::Truncate(_) => {
+                        format!("Subdirectories left out to fit {budget} tokens")
+                    }
+                };
+                (reason, decision.pattern())
+            })
+            .collect();
+        let added = suggest::append_patterns(&ignore_file, "join-ai join --max-tokens", &entries)?;
+        term.write_line(&format!(
+            "Added {added} patterns to {}.",
+            ignore_file.display()
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "interactive"))]
+pub fn refine(_args: &JoinArgs, _options: &mut JoinOptions) -> anyhow::Result<()> {
+    Ok(())
+}
+
+// --- Unit Tests for Budget Refinement ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that leaving out a directory drops all its files from the
+    /// estimate, and that truncating one keeps only the files at its top.
+    #[test]
+    fn test_decisions_cut_the_estimate() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("src/lib.rs").write_str(&"a".repeat(400))?;
+        dir.child("src/legacy/old.rs").write_str(&"b".repeat(800))?;
+        dir.child("src/legacy/deep/older.rs")
+            .write_str(&"c".repeat(1200))?;
+        dir.child("docs/guide.md").write_str(&"d".repeat(2000))?;
+        let mut options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        options.max_tokens = Some(100);
+        let total = |options: &JoinOptions| -> anyhow::Result<u64> {
+            Ok(estimate(options)?.iter().map(|(_, bytes)| bytes).sum())
+        };
+        assert_eq!(total(&options)?, 4400);
+
+        let truncate = Decision::Truncate("src/".to_string());
+        assert_eq!(truncate.pattern(), "/src/*/");
+        truncate.apply_to(&mut options);
+        assert_eq!(total(&options)?, 2400);
+
+        let exclude = Decision::Exclude("docs/".to_string());
+        assert_eq!(exclude.pattern(), "/docs/");
+        exclude.apply_to(&mut options);
+        let files = estimate(&options)?;
+        assert_eq!(files, [(dir.path().join("src/lib.rs"), 400)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+//! Fetches repositories and pull requests through the Bitbucket Cloud REST
+//! API (2.0). Bitbucket Data Center has an API of its own, which is not
+//! supported.
+
+use super::discussion::{Comment, Discussion, Review};
+use super::{Api, Host, PrSpec, RepoSpec, encode, encode_path};
+use crate::cli::Secret;
+use crate::secrets;
+use anyhow::Context;
+use base64::Engine;
+use join_ai_core::{JoinOptions, walker};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// The API endpoint used when `BITBUCKET_API_URL` is not set.
+const DEFAULT_API_URL: &str = "https://api.bitbucket.org/2.0";
+
+/// The most pages read from a list endpoint.
+const MAX_PAGES: usize = 30;
+
+/// The most folders listed while walking a repository.
+const MAX_FOLDERS: usize = 10_000;
+
+/// A page of a list endpoint, with the URL of the next one.
+#[derive(Deserialize)]
+struct Page<T> {
+    values: Vec<T>,
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    mainbranch: Option<Branch>,
+}
+
+#[derive(Deserialize)]
+struct Branch {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    hash: String,
+}
+
+/// A file or folder of a repository at a commit.
+#[derive(Deserialize)]
+struct SrcEntry {
+    path: String,
+    /// `commit_file`, `commit_directory` or `commit_link` (a submodule).
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// The account that wrote a pull request or a comment.
+#[derive(Deserialize)]
+struct User {
+    display_name: String,
+    nickname: Option<String>,
+}
+
+impl User {
+    fn name(self) -> String {
+        self.nickname.unwrap_or(self.display_name)
+    }
+}
+
+#[derive(Deserialize)]
+struct PullRequest {
+    title: String,
+    description: Option<String>,
+    links: Links,
+    author: User,
+    source: Source,
+    #[serde(default)]
+    participants: Vec<Participant>,
+}
+
+#[derive(Deserialize)]
+struct Links {
+    html: Link,
+}
+
+#[derive(Deserialize)]
+struct Link {
+    href: String,
+}
+
+/// The branch a pull request comes from, in the repository or in a fork.
+#[derive(Deserialize)]
+struct Source {
+    commit: Commit,
+    /// `None` once a fork is deleted.
+    repository: Option<SourceRepository>,
+}
+
+#[derive(Deserialize)]
+struct SourceRepository {
+    full_name: String,
+}
+
+/// A reviewer or commenter of a pull request.
+#[derive(Deserialize)]
+struct Participant {
+    user: User,
+    /// `approved`, `changes_requested`, or `None` before a verdict.
+    state: Option<String>,
+}
+
+/// A file changed by a pull request.
+#[derive(Deserialize)]
+struct DiffStat {
+    status: String,
+    /// The file at the head of the pull request; `None` when it is removed.
+    new: Option<DiffFile>,
+}
+
+#[derive(Deserialize)]
+struct DiffFile {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct PullComment {
+    content: Content,
+    user: User,
+    /// The file and line commented on, for comments on the diff.
+    inline: Option<Inline>,
+    #[serde(default)]
+    deleted: bool,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    raw: String,
+}
+
+#[derive(Deserialize)]
+struct Inline {
+    path: String,
+    /// The line in the head of the pull request.
+    to: Option<u64>,
+}
+
+/// A minimal client for the parts of the Bitbucket API needed to download a
+/// repository.
+pub struct Bitbucket {
+    api: Api,
+}
+
+impl Bitbucket {
+    /// Creates a client configured from the environment. An access token (the
+    /// one stored by `config set-secret bitbucket`, or else `BITBUCKET_TOKEN`)
+    /// or `BITBUCKET_USERNAME` and `BITBUCKET_APP_PASSWORD` are used for
+    /// authentication, and `BITBUCKET_API_URL` selects another endpoint of the
+    /// same API.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let authorization = match (
+            secrets::get(Secret::Bitbucket),
+            var("BITBUCKET_USERNAME"),
+            var("BITBUCKET_APP_PASSWORD"),
+        ) {
+            (Some(token), _, _) => Some(format!("Bearer {token}")),
+            (None, Some(username), Some(password)) => Some(format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"))
+            )),
+            _ => None,
+        };
+        let api_url = var("BITBUCKET_API_URL").unwrap_or_else(|| DEFAULT_API_URL.to_string());
+        let missing_token = authorization.is_none().then_some("BITBUCKET_TOKEN");
+        let headers = authorization
+            .into_iter()
+            .map(|authorization| ("Authorization", authorization))
+            .collect();
+        Self {
+            api: Api::new(
+                "Bitbucket",
+                &api_url,
+                "application/json",
+                headers,
+                missing_token,
+            ),
+        }
+    }
+
+    /// Downloads every file of the repository that is selected by `options`
+    /// into `dest`, returning the number of files written. The repository is
+    /// listed one folder at a time.
+    pub fn download(
+        &self,
+        spec: &RepoSpec,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        let repo_path = format!("/repositories/{}/{}", spec.owner, spec.repo);
+        let reference = match &spec.reference {
+            Some(reference) => reference.clone(),
+            None => {
+                let repository: Repository = self.api.get_json(&repo_path)?;
+                repository
+                    .mainbranch
+                    .with_context(|| format!("The repository {spec} has no main branch"))?
+                    .name
+            }
+        };
+        // Branch names may change while the files are read, commits do not.
+        let commit: Commit = self
+            .api
+            .get_json(&format!("{repo_path}/commit/{}", encode(&reference)))?;
+        let src_path = format!("{repo_path}/src/{}", commit.hash);
+
+        let overrides = walker::build_overrides(options)?;
+        let mut folders = vec![String::new()];
+        let mut listed = 0;
+        let mut count = 0;
+        while let Some(folder) = folders.pop() {
+            listed += 1;
+            if listed > MAX_FOLDERS {
+                join_ai_core::events::warning(&format!(
+                    "{spec} has more than {MAX_FOLDERS} folders; some files may be missing."
+                ));
+                break;
+            }
+            let entries: Vec<SrcEntry> =
+                self.pages(&format!("{src_path}/{}?pagelen=100", encode_path(&folder)))?;
+            for entry in entries {
+                match entry.kind.as_str() {
+                    "commit_directory" => folders.push(format!("{}/", entry.path)),
+                    "commit_file" => {
+                        let relative_path = Path::new(&entry.path);
+                        if !walker::is_selected(&overrides, relative_path) {
+                            continue;
+                        }
+                        self.api.download(
+                            &format!("{src_path}/{}", encode_path(&entry.path)),
+                            "*/*",
+                            relative_path,
+                            dest,
+                        )?;
+                        count += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Downloads the files changed by the pull request `spec` that are
+    /// selected by `options`, as they are at its head, into `dest`, returning
+    /// the number of files written. Deleted files are left out.
+    pub fn download_pull_request(
+        &self,
+        spec: &PrSpec,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        let pull_path = format!(
+            "/repositories/{}/{}/pullrequests/{}",
+            spec.owner, spec.repo, spec.number
+        );
+        let pull: PullRequest = self.api.get_json(&pull_path)?;
+        // The head commit is read from the fork the pull request comes from.
+        let source = match &pull.source.repository {
+            Some(repository) => format!("/repositories/{}", repository.full_name),
+            None => format!("/repositories/{}/{}", spec.owner, spec.repo),
+        };
+        let files: Vec<DiffStat> = self.pages(&format!("{pull_path}/diffstat?pagelen=100"))?;
+
+        let overrides = walker::build_overrides(options)?;
+        let mut count = 0;
+        for file in files.iter().filter(|file| file.status != "removed") {
+            let Some(new) = &file.new else {
+                continue;
+            };
+            let relative_path = Path::new(&new.path);
+            if !walker::is_selected(&overrides, relative_path) {
+                continue;
+            }
+            self.api.download(
+                &format!(
+                    "{source}/src/{}/{}",
+                    pull.source.commit.hash,
+                    encode_path(&new.path)
+                ),
+                "*/*",
+                relative_path,
+                dest,
+            )?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Reads the description of the pull request `spec`, the verdicts of its
+    /// participants and its comments.
+    pub fn pull_request_discussion(&self, spec: &PrSpec) -> anyhow::Result<Discussion> {
+        let pull_path = format!(
+            "/repositories/{}/{}/pullrequests/{}",
+            spec.owner, spec.repo, spec.number
+        );
+        let pull: PullRequest = self.api.get_json(&pull_path)?;
+        let comments: Vec<PullComment> =
+            self.pages(&format!("{pull_path}/comments?pagelen=100"))?;
+        Ok(Discussion {
+            title: pull.title,
+            reference: spec.to_string(),
+            url: pull.links.html.href,
+            author: pull.author.name(),
+            body: pull.description,
+            reviews: pull
+                .participants
+                .into_iter()
+                .filter_map(|participant| {
+                    Some(Review {
+                        state: participant.state?.to_uppercase(),
+                        author: participant.user.name(),
+                        body: None,
+                    })
+                })
+                .collect(),
+            comments: comments
+                .into_iter()
+                .filter(|comment| !comment.deleted)
+                .map(|comment| Comment {
+                    author: comment.user.name(),
+                    body: comment.content.raw,
+                    location: comment.inline.map(|inline| (inline.path, inline.to)),
+                })
+                .collect(),
+        })
+    }
+
+    /// Reads every page of the list endpoint at `path`, following the link to
+    /// the next page.
+    fn pages<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut next = Some(path.to_string());
+        for _ in 0..MAX_PAGES {
+            let Some(url) = next else {
+                break;
+            };
+            let page: Page<T> = self.api.get_json(&url)?;
+            items.extend(page.values);
+            next = page.next;
+        }
+        Ok(items)
+    }
+}
+
+impl Host for Bitbucket {
+    fn repository(&self, spec: &str, request: bool) -> anyhow::Result<String> {
+        let (owner, repo) = match request {
+            true => spec.parse::<PrSpec>().map(|spec| (spec.owner, spec.repo))?,
+            false => spec
+                .parse::<RepoSpec>()
+                .map(|spec| (spec.owner, spec.repo))?,
+        };
+        Ok(format!("{owner}/{repo}"))
+    }
+
+    fn fetch(&self, spec: &str, options: &JoinOptions, dest: &Path) -> anyhow::Result<usize> {
+        self.download(&spec.parse()?, options, dest)
+    }
+
+    fn fetch_request(
+        &self,
+        spec: &str,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        self.download_pull_request(&spec.parse()?, options, dest)
+    }
+
+    fn discussion(&self, spec: &str) -> anyhow::Result<Discussion> {
+        self.pull_request_discussion(&spec.parse()?)
+    }
+}
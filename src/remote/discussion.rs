@@ -0,0 +1,152 @@
+//! The discussion of a pull or merge request (`--pr-discussion`): its
+//! description, reviews and comments, read from any host and written as one
+//! Markdown document for the model to read along with the changes.
+
+/// A pull or merge request, as described by its author and discussed by its
+/// reviewers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discussion {
+    pub title: String,
+    /// The request as it was given, e.g. `owner/repo#7`.
+    pub reference: String,
+    pub url: String,
+    pub author: String,
+    pub body: Option<String>,
+    pub reviews: Vec<Review>,
+    pub comments: Vec<Comment>,
+}
+
+/// A review of a request, with its verdict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Review {
+    pub author: String,
+    /// `APPROVED`, `CHANGES_REQUESTED`, `COMMENTED`, ...
+    pub state: String,
+    pub body: Option<String>,
+}
+
+/// A comment on a request, or on a line of one of its files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub author: String,
+    pub body: String,
+    /// The file commented on, and the line in the head of the request (`None`
+    /// when it is outdated). Comments on the whole request have none.
+    pub location: Option<(String, Option<u64>)>,
+}
+
+impl Discussion {
+    /// Renders the discussion as a Markdown document.
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "# {} ({})\n\n{}, opened by @{}.\n\n",
+            self.title, self.reference, self.url, self.author
+        );
+        match self.body.as_deref().map(str::trim) {
+            Some(body) if !body.is_empty() => text.push_str(&format!("{body}\n")),
+            _ => text.push_str("(No description.)\n"),
+        }
+        // Approvals without a comment still tell where the review stands.
+        if !self.reviews.is_empty() {
+            text.push_str("\n## Reviews\n\n");
+            for review in &self.reviews {
+                text.push_str(&format!("- @{} ({})", review.author, review.state));
+                match review.body.as_deref().map(str::trim) {
+                    Some(body) if !body.is_empty() => {
+                        text.push_str(&format!(": {}\n", indent(body)))
+                    }
+                    _ => text.push('\n'),
+                }
+            }
+        }
+        let (on_lines, general): (Vec<_>, Vec<_>) = self
+            .comments
+            .iter()
+            .partition(|comment| comment.location.is_some());
+        if !general.is_empty() {
+            text.push_str("\n## Comments\n\n");
+            for comment in general {
+                text.push_str(&format!(
+                    "- @{}: {}\n",
+                    comment.author,
+                    indent(comment.body.trim())
+                ));
+            }
+        }
+        if !on_lines.is_empty() {
+            text.push_str("\n## Review Comments\n\n");
+            for comment in on_lines {
+                let Some((path, line)) = &comment.location else {
+                    continue;
+                };
+                let line = line.map_or(" (outdated)".to_string(), |line| format!(":{line}"));
+                text.push_str(&format!(
+                    "- `{path}`{line}, @{}: {}\n",
+                    comment.author,
+                    indent(comment.body.trim())
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// Indents the lines after the first of `text`, so that it stays in its list
+/// item.
+fn indent(text: &str) -> String {
+    text.lines().collect::<Vec<_>>().join("\n  ")
+}
+
+// --- Unit Tests for Request Discussions ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that the discussion of a request lists its description,
+    /// reviews, comments and line comments.
+    #[test]
+    fn test_render_discussion() {
+        let mut discussion = Discussion {
+            title: "Retry failed uploads".to_string(),
+            reference: "acme/app#7".to_string(),
+            url: "https://github.com/acme/app/pull/7".to_string(),
+            author: "ana".to_string(),
+            body: Some("Uploads are retried 3 times.\r\n".to_string()),
+            reviews: vec![Review {
+                author: "bo".to_string(),
+                state: "APPROVED".to_string(),
+                body: Some(String::new()),
+            }],
+            comments: vec![Comment {
+                author: "bo".to_string(),
+                body: "Why 3?\nMake it a constant.".to_string(),
+                location: Some(("src/upload.rs".to_string(), Some(42))),
+            }],
+        };
+        assert_eq!(
+            discussion.render(),
+            "# Retry failed uploads (acme/app#7)\n\n\
+             https://github.com/acme/app/pull/7, opened by @ana.\n\n\
+             Uploads are retried 3 times.\n\n\
+             ## Reviews\n\n- @bo (APPROVED)\n\n\
+             ## Review Comments\n\n- `src/upload.rs`:42, @bo: Why 3?\n  Make it a constant.\n"
+        );
+
+        discussion.body = None;
+        discussion.reviews.clear();
+        discussion.comments.push(Comment {
+            author: "cy".to_string(),
+            body: "Rebased.".to_string(),
+            location: None,
+        });
+        discussion.comments[0].location = Some(("src/upload.rs".to_string(), None));
+        assert_eq!(
+            discussion.render(),
+            "# Retry failed uploads (acme/app#7)\n\n\
+             https://github.com/acme/app/pull/7, opened by @ana.\n\n\
+             (No description.)\n\n\
+             ## Comments\n\n- @cy: Rebased.\n\n\
+             ## Review Comments\n\n- `src/upload.rs` (outdated), @bo: Why 3?\n  Make it a constant.\n"
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Fetches repositories through the GitHub REST API, so a join works without
+//! `git` installed and without cloning the full history.
+
+use super::discussion::{Comment, Discussion, Review};
+use super::{Api, Host, PrSpec, RepoSpec};
+use crate::cli::Secret;
+use crate::secrets;
+use anyhow::{Context, bail};
+use join_ai_core::{JoinOptions, walker};
+use serde::Deserialize;
+use std::path::Path;
+
+/// The API endpoint used when `GITHUB_API_URL` is not set.
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// The media type of the JSON responses of the API.
+const JSON: &str = "application/vnd.github+json";
+
+/// The most pages read from a list endpoint; the files of a pull request are
+/// listed up to 3000.
+const MAX_PAGES: usize = 30;
+
+#[derive(Deserialize)]
+struct Repository {
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct Tree {
+    tree: Vec<TreeEntry>,
+    truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+    sha: String,
+}
+
+/// A file changed by a pull request.
+#[derive(Deserialize)]
+struct PullFile {
+    filename: String,
+    status: String,
+    /// The blob of the file at the head of the pull request.
+    sha: Option<String>,
+}
+
+/// The account that wrote a pull request, a review or a comment.
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+/// A pull request, as described by its author.
+#[derive(Deserialize)]
+struct PullRequest {
+    title: String,
+    body: Option<String>,
+    html_url: String,
+    user: User,
+}
+
+/// A review of a pull request, with its verdict.
+#[derive(Deserialize)]
+struct PullReview {
+    body: Option<String>,
+    state: String,
+    user: User,
+}
+
+/// A comment of a review on a line of a changed file.
+#[derive(Deserialize)]
+struct ReviewComment {
+    path: String,
+    /// The line in the head of the pull request; `None` when it is outdated.
+    line: Option<u64>,
+    body: String,
+    user: User,
+}
+
+/// An issue, as described by its author.
+#[derive(Deserialize)]
+struct Issue {
+    title: String,
+    body: Option<String>,
+    html_url: String,
+    user: User,
+}
+
+#[derive(Deserialize)]
+struct IssueComment {
+    #[serde(default)]
+    body: String,
+    user: User,
+}
+
+/// Parses the issue `spec`: a URL such as
+/// `https://github.com/owner/repo/issues/12`, `owner/repo#12`, or a bare
+/// number (`12` or `#12`) in the repository `default`, given as
+/// `owner/repo`.
+pub fn parse_issue(spec: &str, default: Option<&str>) -> anyhow::Result<PrSpec> {
+    if let Some(url) = spec
+        .strip_prefix("https://")
+        .or_else(|| spec.strip_prefix("http://"))
+    {
+        // The host is followed by `owner/repo/issues/number` (or `pull`).
+        let parts: Vec<&str> = url.trim_end_matches('/').split('/').collect();
+        return match parts.as_slice() {
+            [_, owner, repo, "issues" | "pull", number] => {
+                format!("{owner}/{repo}#{number}").parse()
+            }
+            _ => {
+                bail!("Invalid issue URL '{spec}'. Expected https://HOST/owner/repo/issues/number.")
+            }
+        };
+    }
+    if spec.contains('/') {
+        return spec.parse();
+    }
+    let number = spec.trim_start_matches('#');
+    let repository = default.with_context(|| {
+        format!(
+            "The repository of the issue '{spec}' is unknown. Give it as owner/repo#number or as a URL."
+        )
+    })?;
+    format!("{repository}#{number}").parse()
+}
+
+/// Reads `owner/repo` from the URL of a git remote, such as
+/// `git@github.com:owner/repo.git` or `https://github.com/owner/repo`.
+pub fn repository_of_remote(url: &str) -> Option<String> {
+    let path = url.trim_end_matches('/').trim_end_matches(".git");
+    let mut parts = path.rsplit(['/', ':']);
+    let repo = parts.next().filter(|repo| !repo.is_empty())?;
+    let owner = parts.next().filter(|owner| !owner.is_empty())?;
+    Some(format!("{owner}/{repo}"))
+}
+
+/// A minimal client for the parts of the GitHub API needed to download a tree.
+pub struct GitHub {
+    api: Api,
+}
+
+impl GitHub {
+    /// Creates a client configured from the environment. The token stored by
+    /// `config set-secret github`, or else `GITHUB_TOKEN` (or `GH_TOKEN`), is
+    /// used for authentication and `GITHUB_API_URL` selects a GitHub
+    /// Enterprise endpoint.
+    pub fn from_env() -> Self {
+        let token = secrets::get(Secret::GitHub);
+        let api_url =
+            std::env::var("GITHUB_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+        let mut headers = vec![("X-GitHub-Api-Version", "2022-11-28".to_string())];
+        if let Some(token) = &token {
+            headers.push(("Authorization", format!("Bearer {token}")));
+        }
+        let missing_token = token.is_none().then_some("GITHUB_TOKEN");
+        Self {
+            api: Api::new("GitHub", &api_url, JSON, headers, missing_token),
+        }
+    }
+
+    /// Downloads every file of the repository that is selected by `options` into
+    /// `dest`, returning the number of files written.
+    ///
+    /// Filtering happens before any content is requested, so excluded folders
+    /// never count against the API rate limit.
+    pub fn download(
+        &self,
+        spec: &RepoSpec,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        let repo_path = format!("/repos/{}/{}", spec.owner, spec.repo);
+        let reference = match &spec.reference {
+            Some(reference) => reference.clone(),
+            None => {
+                let repository: Repository = self.api.get_json(&repo_path)?;
+                repository.default_branch
+            }
+        };
+
+        let tree: Tree = self
+            .api
+            .get_json(&format!("{repo_path}/git/trees/{reference}?recursive=1"))?;
+        if tree.truncated {
+            join_ai_core::events::warning(&format!(
+                "The tree of {spec} is too large for a single API response; \
+                 some files may be missing."
+            ));
+        }
+
+        let overrides = walker::build_overrides(options)?;
+        let mut count = 0;
+        for entry in tree.tree.iter().filter(|entry| entry.kind == "blob") {
+            let relative_path = Path::new(&entry.path);
+            if !walker::is_selected(&overrides, relative_path) {
+                continue;
+            }
+
+            self.download_blob(&repo_path, &entry.sha, relative_path, dest)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Downloads the files changed by the pull request `spec` that are
+    /// selected by `options`, as they are at its head, into `dest`, returning
+    /// the number of files written. Deleted files are left out.
+    pub fn download_pull_request(
+        &self,
+        spec: &PrSpec,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        let repo_path = format!("/repos/{}/{}", spec.owner, spec.repo);
+        let files: Vec<PullFile> = self.api.get_pages(
+            &format!("{repo_path}/pulls/{}/files", spec.number),
+            MAX_PAGES,
+        )?;
+        let overrides = walker::build_overrides(options)?;
+        let mut count = 0;
+        for file in &files {
+            let Some(sha) = file.sha.as_deref().filter(|_| file.status != "removed") else {
+                continue;
+            };
+            let relative_path = Path::new(&file.filename);
+            if !walker::is_selected(&overrides, relative_path) {
+                continue;
+            }
+            self.download_blob(&repo_path, sha, relative_path, dest)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Reads the description of the pull request `spec`, its reviews and the
+    /// comments of its reviews on changed lines.
+    pub fn pull_request_discussion(&self, spec: &PrSpec) -> anyhow::Result<Discussion> {
+        let pull_path = format!("/repos/{}/{}/pulls/{}", spec.owner, spec.repo, spec.number);
+        let pull: PullRequest = self.api.get_json(&pull_path)?;
+        let reviews: Vec<PullReview> = self
+            .api
+            .get_pages(&format!("{pull_path}/reviews"), MAX_PAGES)?;
+        let comments: Vec<ReviewComment> = self
+            .api
+            .get_pages(&format!("{pull_path}/comments"), MAX_PAGES)?;
+        Ok(Discussion {
+            title: pull.title,
+            reference: spec.to_string(),
+            url: pull.html_url,
+            author: pull.user.login,
+            body: pull.body,
+            reviews: reviews
+                .into_iter()
+                .map(|review| Review {
+                    author: review.user.login,
+                    state: review.state,
+                    body: review.body,
+                })
+                .collect(),
+            comments: comments
+                .into_iter()
+                .map(|comment| Comment {
+                    author: comment.user.login,
+                    body: comment.body,
+                    location: Some((comment.path, comment.line)),
+                })
+                .collect(),
+        })
+    }
+
+    /// Reads the issue `spec`, with its comments.
+    pub fn issue(&self, spec: &PrSpec) -> anyhow::Result<Discussion> {
+        let issue_path = format!("/repos/{}/{}/issues/{}", spec.owner, spec.repo, spec.number);
+        let issue: Issue = self.api.get_json(&issue_path)?;
+        let comments: Vec<IssueComment> = self
+            .api
+            .get_pages(&format!("{issue_path}/comments"), MAX_PAGES)?;
+        Ok(Discussion {
+            title: issue.title,
+            reference: spec.to_string(),
+            url: issue.html_url,
+            author: issue.user.login,
+            body: issue.body,
+            reviews: Vec::new(),
+            comments: comments
+                .into_iter()
+                .map(|comment| Comment {
+                    author: comment.user.login,
+                    body: comment.body,
+                    location: None,
+                })
+                .collect(),
+        })
+    }
+
+    /// Downloads the blob `sha` of the repository at `repo_path` to
+    /// `relative_path` below `dest`.
+    fn download_blob(
+        &self,
+        repo_path: &str,
+        sha: &str,
+        relative_path: &Path,
+        dest: &Path,
+    ) -> anyhow::Result<()> {
+        self.api.download(
+            &format!("{repo_path}/git/blobs/{sha}"),
+            "application/vnd.github.raw",
+            relative_path,
+            dest,
+        )
+    }
+}
+
+impl Host for GitHub {
+    fn repository(&self, spec: &str, request: bool) -> anyhow::Result<String> {
+        let (owner, repo) = match request {
+            true => spec.parse::<PrSpec>().map(|spec| (spec.owner, spec.repo))?,
+            false => spec
+                .parse::<RepoSpec>()
+                .map(|spec| (spec.owner, spec.repo))?,
+        };
+        Ok(format!("{owner}/{repo}"))
+    }
+
+    fn fetch(&self, spec: &str, options: &JoinOptions, dest: &Path) -> anyhow::Result<usize> {
+        self.download(&spec.parse()?, options, dest)
+    }
+
+    fn fetch_request(
+        &self,
+        spec: &str,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        self.download_pull_request(&spec.parse()?, options, dest)
+    }
+
+    fn discussion(&self, spec: &str) -> anyhow::Result<Discussion> {
+        self.pull_request_discussion(&spec.parse()?)
+    }
+}
+
+// --- Unit Tests for Issue Specs ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that issues are parsed from URLs, `owner/repo#number` and bare
+    /// numbers in a known repository.
+    #[test]
+    fn test_parse_issue() {
+        let expected: PrSpec = "acme/app#12".parse().unwrap();
+        for spec in [
+            "https://github.com/acme/app/issues/12",
+            "https://github.example.com/acme/app/pull/12/",
+            "acme/app#12",
+            "12",
+            "#12",
+        ] {
+            assert_eq!(
+                parse_issue(spec, Some("acme/app")).unwrap(),
+                expected,
+                "{spec}"
+            );
+        }
+        assert!(parse_issue("12", None).is_err());
+        assert!(parse_issue("https://github.com/acme/app/wiki", None).is_err());
+    }
+
+    /// Verifies that `owner/repo` is read from SSH and HTTPS remotes.
+    #[test]
+    fn test_repository_of_remote() {
+        for url in [
+            "git@github.com:acme/app.git",
+            "https://github.com/acme/app",
+            "ssh://git@github.com/acme/app.git/",
+        ] {
+            assert_eq!(
+                repository_of_remote(url).as_deref(),
+                Some("acme/app"),
+                "{url}"
+            );
+        }
+        assert_eq!(repository_of_remote("app"), None);
+    }
+}
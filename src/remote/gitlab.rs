@@ -0,0 +1,382 @@
+//! Fetches projects and merge requests through the GitLab REST API (v4), on
+//! gitlab.com or a self-hosted instance.
+
+use super::discussion::{Comment, Discussion, Review};
+use super::{Api, Host, encode};
+use crate::cli::Secret;
+use crate::secrets;
+use anyhow::{Context, bail};
+use join_ai_core::{JoinOptions, walker};
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The API endpoint used when `GITLAB_API_URL` is not set.
+const DEFAULT_API_URL: &str = "https://gitlab.com/api/v4";
+
+/// The most pages read from a list endpoint, other than the tree of a project.
+const MAX_PAGES: usize = 30;
+
+/// The most pages of the tree of a project read, for up to 100,000 entries.
+const MAX_TREE_PAGES: usize = 1000;
+
+/// A project in the `group/project[@ref]` form, where the group may have
+/// subgroups (`group/subgroup/project`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectSpec {
+    /// The full path of the project, e.g. `group/subgroup/project`.
+    pub path: String,
+    /// A branch, tag or commit. `None` means the project's default branch.
+    pub reference: Option<String>,
+}
+
+/// Whether `path` names a project: a group and a project, without empty
+/// parts.
+fn is_project_path(path: &str) -> bool {
+    path.contains('/') && path.split('/').all(|part| !part.is_empty())
+}
+
+impl FromStr for ProjectSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (path, reference) = match spec.split_once('@') {
+            Some((path, reference)) => (path, Some(reference.to_string())),
+            None => (spec, None),
+        };
+        if !is_project_path(path) || reference.as_deref() == Some("") {
+            bail!("Invalid GitLab project '{spec}'. Expected the form group/project[@ref].");
+        }
+        Ok(Self {
+            path: path.trim_end_matches(".git").to_string(),
+            reference,
+        })
+    }
+}
+
+impl fmt::Display for ProjectSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(reference) = &self.reference {
+            write!(f, "@{reference}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A merge request in the `group/project!iid` form GitLab itself uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeRequestSpec {
+    pub path: String,
+    /// The number of the merge request within its project.
+    pub iid: u64,
+}
+
+impl FromStr for MergeRequestSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parsed = spec.split_once('!').and_then(|(path, iid)| {
+            let iid = iid.parse().ok().filter(|&iid| iid > 0)?;
+            is_project_path(path).then(|| Self {
+                path: path.trim_end_matches(".git").to_string(),
+                iid,
+            })
+        });
+        parsed.with_context(|| {
+            format!(
+                "Invalid GitLab merge request '{spec}'. Expected the form group/project!number."
+            )
+        })
+    }
+}
+
+impl fmt::Display for MergeRequestSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}!{}", self.path, self.iid)
+    }
+}
+
+#[derive(Deserialize)]
+struct Project {
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    id: String,
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// The account that wrote a merge request or a note.
+#[derive(Deserialize)]
+struct User {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    title: String,
+    description: Option<String>,
+    web_url: String,
+    author: User,
+    /// The head commit of the merge request.
+    sha: String,
+}
+
+/// A file changed by a merge request.
+#[derive(Deserialize)]
+struct Diff {
+    new_path: String,
+    deleted_file: bool,
+}
+
+#[derive(Deserialize)]
+struct Approvals {
+    #[serde(default)]
+    approved_by: Vec<Approval>,
+}
+
+#[derive(Deserialize)]
+struct Approval {
+    user: User,
+}
+
+/// A comment on a merge request, or on a line of a diff when it has a
+/// position.
+#[derive(Deserialize)]
+struct Note {
+    body: String,
+    author: User,
+    /// Notes written by GitLab itself, such as "added 1 commit".
+    system: bool,
+    position: Option<Position>,
+}
+
+#[derive(Deserialize)]
+struct Position {
+    new_path: String,
+    new_line: Option<u64>,
+}
+
+/// A minimal client for the parts of the GitLab API needed to download a
+/// project.
+pub struct GitLab {
+    api: Api,
+}
+
+impl GitLab {
+    /// Creates a client configured from the environment. The token stored by
+    /// `config set-secret gitlab`, or else `GITLAB_TOKEN` (or `GL_TOKEN`), is
+    /// used for authentication and `GITLAB_API_URL` selects a self-hosted
+    /// instance, e.g. `https://gitlab.example.com/api/v4`.
+    pub fn from_env() -> Self {
+        let token = secrets::get(Secret::GitLab);
+        let api_url =
+            std::env::var("GITLAB_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+        let headers = token
+            .iter()
+            .map(|token| ("PRIVATE-TOKEN", token.clone()))
+            .collect();
+        let missing_token = token.is_none().then_some("GITLAB_TOKEN");
+        Self {
+            api: Api::new(
+                "GitLab",
+                &api_url,
+                "application/json",
+                headers,
+                missing_token,
+            ),
+        }
+    }
+
+    /// Downloads every file of the project that is selected by `options` into
+    /// `dest`, returning the number of files written.
+    pub fn download(
+        &self,
+        spec: &ProjectSpec,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        let project_path = format!("/projects/{}", encode(&spec.path));
+        let reference = match &spec.reference {
+            Some(reference) => reference.clone(),
+            None => {
+                let project: Project = self.api.get_json(&project_path)?;
+                project
+                    .default_branch
+                    .with_context(|| format!("The project {spec} has no default branch"))?
+            }
+        };
+        let tree: Vec<TreeEntry> = self.api.get_pages(
+            &format!(
+                "{project_path}/repository/tree?recursive=true&ref={}",
+                encode(&reference)
+            ),
+            MAX_TREE_PAGES,
+        )?;
+
+        let overrides = walker::build_overrides(options)?;
+        let mut count = 0;
+        for entry in tree.iter().filter(|entry| entry.kind == "blob") {
+            let relative_path = Path::new(&entry.path);
+            if !walker::is_selected(&overrides, relative_path) {
+                continue;
+            }
+            self.api.download(
+                &format!("{project_path}/repository/blobs/{}/raw", entry.id),
+                "*/*",
+                relative_path,
+                dest,
+            )?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Downloads the files changed by the merge request `spec` that are
+    /// selected by `options`, as they are at its head, into `dest`, returning
+    /// the number of files written. Deleted files are left out.
+    pub fn download_merge_request(
+        &self,
+        spec: &MergeRequestSpec,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        let project_path = format!("/projects/{}", encode(&spec.path));
+        let request_path = format!("{project_path}/merge_requests/{}", spec.iid);
+        let request: MergeRequest = self.api.get_json(&request_path)?;
+        let diffs: Vec<Diff> = self
+            .api
+            .get_pages(&format!("{request_path}/diffs"), MAX_PAGES)?;
+
+        let overrides = walker::build_overrides(options)?;
+        let mut count = 0;
+        for diff in diffs.iter().filter(|diff| !diff.deleted_file) {
+            let relative_path = Path::new(&diff.new_path);
+            if !walker::is_selected(&overrides, relative_path) {
+                continue;
+            }
+            // The head of a merge request from a fork is kept in the target
+            // project too.
+            self.api.download(
+                &format!(
+                    "{project_path}/repository/files/{}/raw?ref={}",
+                    encode(&diff.new_path),
+                    request.sha
+                ),
+                "*/*",
+                relative_path,
+                dest,
+            )?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Reads the description of the merge request `spec`, its approvals and
+    /// the comments written on it, leaving out the notes of GitLab itself.
+    pub fn merge_request_discussion(&self, spec: &MergeRequestSpec) -> anyhow::Result<Discussion> {
+        let request_path = format!(
+            "/projects/{}/merge_requests/{}",
+            encode(&spec.path),
+            spec.iid
+        );
+        let request: MergeRequest = self.api.get_json(&request_path)?;
+        let approvals: Approvals = self.api.get_json(&format!("{request_path}/approvals"))?;
+        let notes: Vec<Note> = self.api.get_pages(
+            &format!("{request_path}/notes?sort=asc&order_by=created_at"),
+            MAX_PAGES,
+        )?;
+        Ok(Discussion {
+            title: request.title,
+            reference: spec.to_string(),
+            url: request.web_url,
+            author: request.author.username,
+            body: request.description,
+            reviews: approvals
+                .approved_by
+                .into_iter()
+                .map(|approval| Review {
+                    author: approval.user.username,
+                    state: "APPROVED".to_string(),
+                    body: None,
+                })
+                .collect(),
+            comments: notes
+                .into_iter()
+                .filter(|note| !note.system)
+                .map(|note| Comment {
+                    author: note.author.username,
+                    body: note.body,
+                    location: note
+                        .position
+                        .map(|position| (position.new_path, position.new_line)),
+                })
+                .collect(),
+        })
+    }
+}
+
+impl Host for GitLab {
+    fn repository(&self, spec: &str, request: bool) -> anyhow::Result<String> {
+        match request {
+            true => spec.parse::<MergeRequestSpec>().map(|spec| spec.path),
+            false => spec.parse::<ProjectSpec>().map(|spec| spec.path),
+        }
+    }
+
+    fn fetch(&self, spec: &str, options: &JoinOptions, dest: &Path) -> anyhow::Result<usize> {
+        self.download(&spec.parse()?, options, dest)
+    }
+
+    fn fetch_request(
+        &self,
+        spec: &str,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize> {
+        self.download_merge_request(&spec.parse()?, options, dest)
+    }
+
+    fn discussion(&self, spec: &str) -> anyhow::Result<Discussion> {
+        self.merge_request_discussion(&spec.parse()?)
+    }
+}
+
+// --- Unit Tests for GitLab Specs ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that projects in nested groups and merge requests are parsed,
+    /// and that malformed ones are rejected.
+    #[test]
+    fn test_parse_gitlab_specs() {
+        let spec: ProjectSpec = "acme/platform/api@release/2.0".parse().unwrap();
+        assert_eq!(spec.path, "acme/platform/api");
+        assert_eq!(spec.reference.as_deref(), Some("release/2.0"));
+        assert_eq!(spec.to_string(), "acme/platform/api@release/2.0");
+
+        let spec: MergeRequestSpec = "acme/platform/api!42".parse().unwrap();
+        assert_eq!(spec.path, "acme/platform/api");
+        assert_eq!(spec.iid, 42);
+        assert_eq!(spec.to_string(), "acme/platform/api!42");
+
+        for spec in ["api", "acme//api", "acme/api/", "acme/api@"] {
+            assert!(
+                spec.parse::<ProjectSpec>().is_err(),
+                "{spec} should be invalid"
+            );
+        }
+        for spec in ["acme/api", "api!1", "acme/api!0", "acme/api#1"] {
+            assert!(
+                spec.parse::<MergeRequestSpec>().is_err(),
+                "{spec} should be invalid"
+            );
+        }
+    }
+}
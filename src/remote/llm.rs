@@ -0,0 +1,721 @@
+//! Chat completions from language model providers, for the `ask` command.
+//! Replies can be streamed as they are generated: Ollama sends one JSON object
+//! per line, and OpenAI-compatible servers send server-sent events.
+//!
+//! A local Ollama server needs no API key, so code never has to leave the
+//! machine. OpenAI-compatible servers (hosted or local, such as llama.cpp or
+//! LM Studio) are sent the key stored by `config set-secret openai`, or else
+//! `OPENAI_API_KEY`, when there is one.
+//!
+//! Replies can be cached on disk, keyed by a hash of the whole request (the
+//! prompt, model and parameters), so that repeating a question in a script
+//! neither waits nor pays for the same answer twice.
+
+use crate::cli::{Provider, Secret};
+use crate::secrets;
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use ureq::http::Response;
+use ureq::{Agent, Body};
+
+/// The time allowed for a completion. Local models can take minutes to read a
+/// large context.
+const COMPLETION_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The largest response read, well above a batch of embeddings.
+const MAX_RESPONSE_LEN: u64 = 100 * 1024 * 1024;
+
+/// One message of a chat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub role: &'static str,
+    pub content: String,
+    /// The images shown to a vision model along with the text.
+    pub images: Vec<Image>,
+}
+
+/// An image attached to a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// The media type, such as `image/png`.
+    pub mime: &'static str,
+    /// The image, encoded in base64.
+    pub data: String,
+}
+
+impl Message {
+    /// Instructions that frame the conversation.
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system",
+            content: content.into(),
+            images: Vec::new(),
+        }
+    }
+
+    /// A message from the user.
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user",
+            content: content.into(),
+            images: Vec::new(),
+        }
+    }
+
+    /// Attaches the image `bytes` of type `mime` to the message.
+    pub fn with_image(mut self, mime: &'static str, bytes: &[u8]) -> Self {
+        use base64::Engine;
+        self.images.push(Image {
+            mime,
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        });
+        self
+    }
+
+    /// The message in the chat format of `provider`. Ollama takes images as a
+    /// list beside the text, and OpenAI as parts of the content.
+    fn to_json(&self, provider: Provider) -> serde_json::Value {
+        if self.images.is_empty() {
+            return serde_json::json!({ "role": self.role, "content": self.content });
+        }
+        match provider {
+            Provider::Ollama => serde_json::json!({
+                "role": self.role,
+                "content": self.content,
+                "images": self.images.iter().map(|image| &image.data).collect::<Vec<_>>(),
+            }),
+            Provider::OpenAi => {
+                let mut parts = vec![serde_json::json!({ "type": "text", "text": self.content })];
+                parts.extend(self.images.iter().map(|image| {
+                    serde_json::json!({
+                        "type": "image_url",
+                        "image_url": { "url": format!("data:{};base64,{}", image.mime, image.data) },
+                    })
+                }));
+                serde_json::json!({ "role": self.role, "content": parts })
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReplyMessage {
+    content: String,
+}
+
+/// The reply of Ollama's `/api/chat`.
+#[derive(Deserialize)]
+struct OllamaReply {
+    message: ReplyMessage,
+}
+
+/// The reply of the OpenAI `/chat/completions` endpoint.
+#[derive(Deserialize)]
+struct OpenAiReply {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ReplyMessage,
+}
+
+/// One line of a streamed reply of Ollama's `/api/chat`.
+#[derive(Deserialize)]
+struct OllamaChunk {
+    message: Option<ReplyMessage>,
+    error: Option<String>,
+}
+
+/// One event of a streamed reply of the OpenAI `/chat/completions` endpoint.
+#[derive(Deserialize)]
+struct OpenAiChunk {
+    #[serde(default)]
+    choices: Vec<DeltaChoice>,
+}
+
+#[derive(Deserialize)]
+struct DeltaChoice {
+    delta: Delta,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+/// The reply of Ollama's `/api/embed`.
+#[derive(Deserialize)]
+struct OllamaEmbeddings {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// The reply of the OpenAI `/embeddings` endpoint.
+#[derive(Deserialize)]
+struct OpenAiEmbeddings {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// A client for the chat API of one provider and model.
+pub struct Client {
+    agent: Agent,
+    provider: Provider,
+    model: String,
+    base_url: String,
+    api_key: Option<String>,
+    context_window: Option<u64>,
+    cache: Option<ReplyCache>,
+}
+
+/// Replies stored on disk, one file per request, reused while younger than
+/// `ttl`.
+pub struct ReplyCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ReplyCache {
+    /// Opens the cache in `dir`, which is created when the first reply is
+    /// stored.
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// The name under which the reply to `body`, sent to `url`, is stored.
+    fn key(url: &str, body: &serde_json::Value) -> String {
+        // Object keys are sorted, so equal requests serialize alike.
+        let request = format!("{url}\n{body}");
+        format!("{:016x}", join_ai_core::cache::hash(request.as_bytes()))
+    }
+
+    /// Returns the stored reply for `key`, unless there is none or it expired.
+    fn get(&self, key: &str) -> Option<String> {
+        let path = self.dir.join(key);
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age > self.ttl {
+            return None;
+        }
+        fs::read_to_string(path).ok()
+    }
+
+    /// Stores `reply` under `key`. A cache that cannot be written is ignored.
+    fn put(&self, key: &str, reply: &str) {
+        let _ = fs::create_dir_all(&self.dir).and_then(|_| fs::write(self.dir.join(key), reply));
+    }
+}
+
+impl Client {
+    /// Creates a client for `provider`, using its default model and endpoint
+    /// unless `model` or `base_url` are given.
+    pub fn new(provider: Provider, model: Option<&str>, base_url: Option<&str>) -> Self {
+        let api_key = match provider {
+            Provider::Ollama => None,
+            Provider::OpenAi => secrets::get(Secret::OpenAi),
+        };
+        Self {
+            agent: super::agent_with_timeout(COMPLETION_TIMEOUT),
+            provider,
+            model: model.unwrap_or(provider.default_model()).to_string(),
+            base_url: base_url
+                .unwrap_or(provider.default_base_url())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key,
+            context_window: None,
+            cache: None,
+        }
+    }
+
+    /// Reuses the replies stored in `cache`, and stores new ones there.
+    pub fn with_cache(mut self, cache: ReplyCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Asks the provider to give the model a window of `tokens`, where it can
+    /// be set per request (Ollama otherwise truncates long prompts to its own
+    /// default).
+    pub fn with_context_window(mut self, tokens: u64) -> Self {
+        self.context_window = Some(tokens);
+        self
+    }
+
+    /// The model this client asks.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Sends `messages` and returns the reply of the model.
+    pub fn chat(&self, messages: &[Message]) -> anyhow::Result<String> {
+        let (url, body) = self.chat_request(messages);
+        let key = ReplyCache::key(&url, &body);
+        if let Some(reply) = self.cached(&key) {
+            return Ok(reply);
+        }
+        let text = self.post(&url, &body)?;
+        let reply = parse_reply(self.provider, &text)?;
+        self.store(&key, &reply);
+        Ok(reply)
+    }
+
+    /// Sends `messages` and passes the reply of the model to `on_text` piece by
+    /// piece, as it is generated. Returns the whole reply.
+    pub fn chat_stream(
+        &self,
+        messages: &[Message],
+        on_text: &mut dyn FnMut(&str),
+    ) -> anyhow::Result<String> {
+        let (url, mut body) = self.chat_request(messages);
+        // Keyed like `chat`, as the reply is the same either way.
+        let key = ReplyCache::key(&url, &body);
+        if let Some(reply) = self.cached(&key) {
+            on_text(&reply);
+            return Ok(reply);
+        }
+        body["stream"] = serde_json::Value::Bool(true);
+        let response = self.send(&url, &body)?;
+        let mut reply = String::new();
+        for line in BufReader::new(response.into_body().into_reader()).lines() {
+            let line = line.with_context(|| format!("Reading the reply from {url} failed"))?;
+            if let Some(text) = parse_stream_line(self.provider, &line)? {
+                on_text(&text);
+                reply.push_str(&text);
+            }
+        }
+        self.store(&key, &reply);
+        Ok(reply)
+    }
+
+    /// Returns the cached reply for `key`, if there is one.
+    fn cached(&self, key: &str) -> Option<String> {
+        self.cache.as_ref().and_then(|cache| cache.get(key))
+    }
+
+    /// Caches `reply` under `key`, if replies are cached.
+    fn store(&self, key: &str, reply: &str) {
+        if let Some(cache) = &self.cache {
+            cache.put(key, reply);
+        }
+    }
+
+    /// Returns the embedding of each of `inputs`, in order.
+    pub fn embed(&self, inputs: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let (url, body) = self.embedding_request(inputs);
+        let text = self.post(&url, &body)?;
+        let embeddings = parse_embeddings(self.provider, &text)?;
+        if embeddings.len() != inputs.len() {
+            bail!(
+                "{} returned {} embeddings for {} inputs.",
+                self.provider.name(),
+                embeddings.len(),
+                inputs.len()
+            );
+        }
+        Ok(embeddings)
+    }
+
+    /// Sends `body` to `url` and returns the body of a successful response.
+    fn post(&self, url: &str, body: &serde_json::Value) -> anyhow::Result<String> {
+        let mut response = self.send(url, body)?;
+        Ok(response
+            .body_mut()
+            .with_config()
+            .limit(MAX_RESPONSE_LEN)
+            .read_to_string()?)
+    }
+
+    /// Sends `body` to `url` and returns the response, failing unless it is
+    /// successful. Requests turned away for a while, as when the quota of the
+    /// API is spent, are retried.
+    fn send(&self, url: &str, body: &serde_json::Value) -> anyhow::Result<Response<Body>> {
+        let mut response = super::send_with_retries(self.provider.name(), || {
+            let mut request = self.agent.post(url);
+            if let Some(key) = &self.api_key {
+                request = request.header("Authorization", format!("Bearer {key}"));
+            }
+            request.send_json(body).with_context(|| {
+                let hint = match self.provider {
+                    Provider::Ollama => " (is Ollama running? Start it with `ollama serve`)",
+                    Provider::OpenAi => "",
+                };
+                format!("{}{hint}", super::request_failed(url))
+            })
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let details = super::rate_limit_details(&response);
+            let text = response
+                .body_mut()
+                .with_config()
+                .limit(MAX_RESPONSE_LEN)
+                .read_to_string()?;
+            bail!(
+                "{} returned HTTP {status} for {url}: {}{details}",
+                self.provider.name(),
+                text.trim()
+            );
+        }
+        Ok(response)
+    }
+
+    /// Returns the URL and JSON body of the chat request for `messages`.
+    fn chat_request(&self, messages: &[Message]) -> (String, serde_json::Value) {
+        let messages: Vec<serde_json::Value> = messages
+            .iter()
+            .map(|message| message.to_json(self.provider))
+            .collect();
+        match self.provider {
+            Provider::Ollama => {
+                let mut body = serde_json::json!({
+                    "model": self.model,
+                    "messages": messages,
+                    "stream": false,
+                });
+                if let Some(tokens) = self.context_window {
+                    body["options"] = serde_json::json!({ "num_ctx": tokens });
+                }
+                (format!("{}/api/chat", self.base_url), body)
+            }
+            Provider::OpenAi => (
+                format!("{}/chat/completions", self.base_url),
+                serde_json::json!({
+                    "model": self.model,
+                    "messages": messages,
+                }),
+            ),
+        }
+    }
+
+    /// Returns the URL and JSON body of the embedding request for `inputs`.
+    fn embedding_request(&self, inputs: &[&str]) -> (String, serde_json::Value) {
+        let path = match self.provider {
+            Provider::Ollama => "api/embed",
+            Provider::OpenAi => "embeddings",
+        };
+        let body = serde_json::json!({
+            "model": self.model,
+            "input": inputs,
+        });
+        (format!("{}/{path}", self.base_url), body)
+    }
+}
+
+/// Extracts the text of the reply from the response body of `provider`.
+fn parse_reply(provider: Provider, body: &str) -> anyhow::Result<String> {
+    let invalid = || format!("Unexpected response from {}: {body}", provider.name());
+    match provider {
+        Provider::Ollama => {
+            let reply: OllamaReply = serde_json::from_str(body).with_context(invalid)?;
+            Ok(reply.message.content)
+        }
+        Provider::OpenAi => {
+            let reply: OpenAiReply = serde_json::from_str(body).with_context(invalid)?;
+            match reply.choices.into_iter().next() {
+                Some(choice) => Ok(choice.message.content),
+                None => bail!("{} returned no answer.", provider.name()),
+            }
+        }
+    }
+}
+
+/// Extracts the text of one line of a streamed reply of `provider`. Lines
+/// without text, such as the last one, give `None`.
+fn parse_stream_line(provider: Provider, line: &str) -> anyhow::Result<Option<String>> {
+    let line = line.trim();
+    let invalid = || format!("Unexpected response from {}: {line}", provider.name());
+    let text = match provider {
+        Provider::Ollama => {
+            if line.is_empty() {
+                return Ok(None);
+            }
+            let chunk: OllamaChunk = serde_json::from_str(line).with_context(invalid)?;
+            if let Some(error) = chunk.error {
+                bail!("{} failed: {error}", provider.name());
+            }
+            chunk.message.map(|message| message.content)
+        }
+        Provider::OpenAi => {
+            // Comments, event names and blank lines carry no data.
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                return Ok(None);
+            };
+            if data == "[DONE]" {
+                return Ok(None);
+            }
+            let chunk: OpenAiChunk = serde_json::from_str(data).with_context(invalid)?;
+            chunk
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|choice| choice.delta.content)
+        }
+    };
+    Ok(text.filter(|text| !text.is_empty()))
+}
+
+/// Extracts the embeddings, in input order, from the response body of
+/// `provider`.
+fn parse_embeddings(provider: Provider, body: &str) -> anyhow::Result<Vec<Vec<f32>>> {
+    let invalid = || format!("Unexpected response from {}: {body}", provider.name());
+    match provider {
+        Provider::Ollama => {
+            let reply: OllamaEmbeddings = serde_json::from_str(body).with_context(invalid)?;
+            Ok(reply.embeddings)
+        }
+        Provider::OpenAi => {
+            let mut reply: OpenAiEmbeddings = serde_json::from_str(body).with_context(invalid)?;
+            reply.data.sort_by_key(|item| item.index);
+            Ok(reply.data.into_iter().map(|item| item.embedding).collect())
+        }
+    }
+}
+
+// --- Unit Tests for Language Model Providers ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves one HTTP request with `reply` on a local port, returning the
+    /// base URL and a handle that yields the body of the request.
+    fn serve_once(reply: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{reply}",
+                reply.len()
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            String::from_utf8(body).unwrap()
+        });
+        (url, handle)
+    }
+
+    /// Verifies that each provider gets the request shape it expects, with
+    /// the defaults filled in.
+    #[test]
+    fn test_chat_requests() {
+        let messages = [Message::user("Hi")];
+        let client = Client::new(Provider::Ollama, None, None);
+        let (url, body) = client.chat_request(&messages);
+        assert_eq!(url, "http://localhost:11434/api/chat");
+        assert_eq!(body["model"], "llama3.2");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert!(body.get("options").is_none());
+        let (_, body) = client.with_context_window(8192).chat_request(&messages);
+        assert_eq!(body["options"]["num_ctx"], 8192);
+
+        let client = Client::new(
+            Provider::OpenAi,
+            Some("qwen2.5-coder"),
+            Some("http://localhost:8080/v1/"),
+        );
+        let (url, body) = client.chat_request(&messages);
+        assert_eq!(url, "http://localhost:8080/v1/chat/completions");
+        assert_eq!(body["model"], "qwen2.5-coder");
+        assert!(body.get("stream").is_none());
+    }
+
+    /// Verifies that images are sent beside the text to Ollama, and as data
+    /// URLs among the content parts to OpenAI.
+    #[test]
+    fn test_image_messages() {
+        let message = Message::user("Describe it").with_image("image/png", b"PNG");
+        assert_eq!(
+            message.to_json(Provider::Ollama),
+            serde_json::json!({ "role": "user", "content": "Describe it", "images": ["UE5H"] })
+        );
+        let openai = message.to_json(Provider::OpenAi);
+        assert_eq!(openai["content"][0]["text"], "Describe it");
+        assert_eq!(
+            openai["content"][1]["image_url"]["url"],
+            "data:image/png;base64,UE5H"
+        );
+        assert_eq!(
+            Message::user("Hi").to_json(Provider::OpenAi),
+            serde_json::json!({ "role": "user", "content": "Hi" })
+        );
+    }
+
+    /// Verifies that answers are read from the replies of both providers, and
+    /// that malformed replies are reported.
+    #[test]
+    fn test_parse_replies() {
+        let ollama =
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"42"},"done":true}"#;
+        assert_eq!(parse_reply(Provider::Ollama, ollama).unwrap(), "42");
+        let openai = r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"yes"}}]}"#;
+        assert_eq!(parse_reply(Provider::OpenAi, openai).unwrap(), "yes");
+        assert!(parse_reply(Provider::OpenAi, r#"{"choices":[]}"#).is_err());
+        assert!(parse_reply(Provider::Ollama, "not json").is_err());
+    }
+
+    /// Verifies that embeddings are requested in batches and read back in
+    /// input order from both providers.
+    #[test]
+    fn test_embeddings() {
+        let client = Client::new(Provider::Ollama, Some("nomic-embed-text"), None);
+        let (url, body) = client.embedding_request(&["a", "b"]);
+        assert_eq!(url, "http://localhost:11434/api/embed");
+        assert_eq!(body["input"][1], "b");
+
+        let ollama = r#"{"model":"nomic-embed-text","embeddings":[[0.5,1.0],[0.25,0.0]]}"#;
+        assert_eq!(
+            parse_embeddings(Provider::Ollama, ollama).unwrap(),
+            vec![vec![0.5, 1.0], vec![0.25, 0.0]]
+        );
+        let openai = r#"{"data":[{"index":1,"embedding":[2.0]},{"index":0,"embedding":[1.0]}]}"#;
+        assert_eq!(
+            parse_embeddings(Provider::OpenAi, openai).unwrap(),
+            vec![vec![1.0], vec![2.0]]
+        );
+    }
+
+    /// Verifies a round trip with a local server that needs no API key.
+    #[test]
+    fn test_chat_with_local_server() {
+        let (url, request) =
+            serve_once(r#"{"message":{"role":"assistant","content":"In main.rs."}}"#);
+        let client = Client::new(Provider::Ollama, Some("codellama"), Some(&url));
+        let answer = client
+            .chat(&[Message::user("Where is the entry point?")])
+            .unwrap();
+        assert_eq!(answer, "In main.rs.");
+
+        let body: serde_json::Value = serde_json::from_str(&request.join().unwrap()).unwrap();
+        assert_eq!(body["model"], "codellama");
+        assert_eq!(body["messages"][0]["content"], "Where is the entry point?");
+    }
+
+    /// Verifies that the text of streamed replies is read from both formats,
+    /// and that errors in the stream are reported.
+    #[test]
+    fn test_parse_stream_lines() {
+        let ollama = r#"{"message":{"role":"assistant","content":"In "},"done":false}"#;
+        assert_eq!(
+            parse_stream_line(Provider::Ollama, ollama)
+                .unwrap()
+                .as_deref(),
+            Some("In ")
+        );
+        let done = r#"{"message":{"role":"assistant","content":""},"done":true}"#;
+        assert_eq!(parse_stream_line(Provider::Ollama, done).unwrap(), None);
+        assert!(parse_stream_line(Provider::Ollama, r#"{"error":"model not found"}"#).is_err());
+
+        let openai = r#"data: {"choices":[{"index":0,"delta":{"content":"main"}}]}"#;
+        assert_eq!(
+            parse_stream_line(Provider::OpenAi, openai)
+                .unwrap()
+                .as_deref(),
+            Some("main")
+        );
+        let role = r#"data: {"choices":[{"index":0,"delta":{"role":"assistant"}}]}"#;
+        assert_eq!(parse_stream_line(Provider::OpenAi, role).unwrap(), None);
+        assert_eq!(parse_stream_line(Provider::OpenAi, ": ping").unwrap(), None);
+        assert_eq!(
+            parse_stream_line(Provider::OpenAi, "data: [DONE]").unwrap(),
+            None
+        );
+    }
+
+    /// Verifies that a streamed reply is passed on piece by piece, and that
+    /// streaming is requested.
+    #[test]
+    fn test_chat_stream_with_local_server() {
+        let (url, request) = serve_once(concat!(
+            r#"{"message":{"role":"assistant","content":"In "},"done":false}"#,
+            "\n",
+            r#"{"message":{"role":"assistant","content":"main.rs."},"done":false}"#,
+            "\n",
+            r#"{"message":{"role":"assistant","content":""},"done":true}"#,
+            "\n",
+        ));
+        let client = Client::new(Provider::Ollama, None, Some(&url));
+        let mut pieces = Vec::new();
+        let answer = client
+            .chat_stream(&[Message::user("Where?")], &mut |text| {
+                pieces.push(text.to_string())
+            })
+            .unwrap();
+        assert_eq!(pieces, ["In ", "main.rs."]);
+        assert_eq!(answer, "In main.rs.");
+
+        let body: serde_json::Value = serde_json::from_str(&request.join().unwrap()).unwrap();
+        assert_eq!(body["stream"], true);
+    }
+
+    /// Verifies that a cached reply is reused, streamed or not, without a
+    /// request, for the same prompt and model only, and only until it expires.
+    #[test]
+    fn test_cached_replies() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (url, request) =
+            serve_once(r#"{"message":{"role":"assistant","content":"42"},"done":true}"#);
+        let cached = |model| {
+            Client::new(Provider::Ollama, Some(model), Some(&url))
+                .with_cache(ReplyCache::new(dir.path(), Duration::from_secs(60)))
+        };
+        let question = [Message::user("Why?")];
+        assert_eq!(cached("llama3.2").chat(&question).unwrap(), "42");
+        request.join().unwrap();
+
+        // The server is gone, so these are answered from the cache.
+        assert_eq!(cached("llama3.2").chat(&question).unwrap(), "42");
+        let mut streamed = String::new();
+        cached("llama3.2")
+            .chat_stream(&question, &mut |text| streamed.push_str(text))
+            .unwrap();
+        assert_eq!(streamed, "42");
+        assert!(cached("codellama").chat(&question).is_err());
+        assert!(cached("llama3.2").chat(&[Message::user("How?")]).is_err());
+
+        let expired = Client::new(Provider::Ollama, Some("llama3.2"), Some(&url))
+            .with_cache(ReplyCache::new(dir.path(), Duration::ZERO));
+        let entry = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap();
+        fs::File::options()
+            .write(true)
+            .open(entry.path())
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(1))
+            .unwrap();
+        assert!(expired.chat(&question).is_err());
+    }
+}
@@ -0,0 +1,654 @@
+//! Remote services reached over HTTP: repository hosts and language model
+//! providers. These features are only available when the crate is built with
+//! the `network` feature.
+//!
+//! Requests go through the proxy set in `HTTPS_PROXY` (or `ALL_PROXY`,
+//! `HTTP_PROXY`), except to the hosts listed in `NO_PROXY`. Requests that the
+//! server turns away for a while (HTTP 429 and 502-504) are retried with
+//! exponential backoff, and errors report the rate-limit headers of the
+//! response.
+
+pub mod bitbucket;
+pub mod discussion;
+pub mod github;
+pub mod gitlab;
+pub mod llm;
+
+use anyhow::{Context, bail};
+use discussion::Discussion;
+use join_ai_core::JoinOptions;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use ureq::http::Response;
+use ureq::{Agent, Proxy};
+
+/// The overall time allowed for a single HTTP request, including the body.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The longest rate-limit reset we are willing to wait for before giving up.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// The largest file we download, matching the limit of the GitHub blobs API.
+const MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+
+/// How many items the list endpoints return per page, at most.
+const PAGE_SIZE: usize = 100;
+
+/// How many times a request that the server turned away is sent again.
+const MAX_RETRIES: u32 = 4;
+
+/// The wait before the first retry, doubled for each of the next ones.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The environment variables that set the proxy, in the order they are read.
+const PROXY_VARS: &[&str] = &[
+    "ALL_PROXY",
+    "all_proxy",
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+];
+
+/// Builds the HTTP agent shared by all remote providers.
+///
+/// HTTP error statuses are returned as regular responses (instead of errors) so
+/// providers can inspect headers such as rate-limit information.
+pub fn agent() -> Agent {
+    agent_with_timeout(REQUEST_TIMEOUT)
+}
+
+/// Builds an HTTP agent like [`agent`], allowing `timeout` for each request.
+pub fn agent_with_timeout(timeout: Duration) -> Agent {
+    Agent::config_builder()
+        .http_status_as_error(false)
+        .timeout_global(Some(timeout))
+        .proxy(proxy().cloned())
+        .user_agent(concat!("join-ai/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .into()
+}
+
+/// The proxy that requests go through, read once from the environment. A
+/// proxy variable that is not a proxy URL is reported, rather than silently
+/// connecting without it.
+fn proxy() -> Option<&'static Proxy> {
+    static PROXY: OnceLock<Option<Proxy>> = OnceLock::new();
+    PROXY
+        .get_or_init(|| {
+            let proxy = Proxy::try_from_env();
+            if proxy.is_none()
+                && let Some((name, value)) = PROXY_VARS
+                    .iter()
+                    .find_map(|name| Some((name, std::env::var(name).ok()?)))
+                    .filter(|(_, value)| !value.trim().is_empty())
+            {
+                let error = Proxy::new(&value).err().map(|e| format!(" ({e})"));
+                join_ai_core::events::warning(&format!(
+                    "{name} is not a proxy URL{}; connecting without a proxy.",
+                    error.unwrap_or_default()
+                ));
+            }
+            proxy
+        })
+        .as_ref()
+}
+
+/// The message of a request to `url` that could not be sent, which names the
+/// proxy it went through.
+pub fn request_failed(url: &str) -> String {
+    match proxy() {
+        Some(proxy) => format!(
+            "Request to {url} failed (through the proxy {}:{})",
+            proxy.host(),
+            proxy.port()
+        ),
+        None => format!("Request to {url} failed"),
+    }
+}
+
+/// Sends the request made by `send` to `host`, sending it again with
+/// exponential backoff while the server turns it away for a while (HTTP 429
+/// and 502-504), and waiting out short rate-limit windows. Returns the last
+/// response, whose status the caller checks.
+pub fn send_with_retries(
+    host: &str,
+    send: impl Fn() -> anyhow::Result<Response<ureq::Body>>,
+) -> anyhow::Result<Response<ureq::Body>> {
+    let mut attempt = 0;
+    loop {
+        let response = send()?;
+        let Some(delay) = retry_delay(&response, attempt) else {
+            return Ok(response);
+        };
+        let status = response.status();
+        let reason = match rate_limit_wait(&response) {
+            Some(_) => format!("{host} API rate limit reached"),
+            None => format!("{host} returned HTTP {status}"),
+        };
+        join_ai_core::events::warning(&format!(
+            "{reason}, retrying in {:.1}s ({}/{MAX_RETRIES})...",
+            delay.as_secs_f32(),
+            attempt + 1
+        ));
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// Returns how long to wait before sending again a request that got
+/// `response`, after `attempt` retries, or `None` to give up on it: the
+/// status is not transient, the retries are spent, or the rate limit resets
+/// too late to wait for.
+fn retry_delay<B>(response: &Response<B>, attempt: u32) -> Option<Duration> {
+    if attempt >= MAX_RETRIES {
+        return None;
+    }
+    if let Some(wait) = rate_limit_wait(response) {
+        return (wait <= MAX_RATE_LIMIT_WAIT).then_some(wait);
+    }
+    matches!(response.status().as_u16(), 429 | 502 | 503 | 504)
+        .then(|| RETRY_DELAY * 2u32.pow(attempt))
+}
+
+/// A repository host whose files are fetched through its API.
+pub trait Host {
+    /// Checks the form of `spec`, a repository or (if `request` is set) a pull
+    /// or merge request, and returns the name of its repository.
+    fn repository(&self, spec: &str, request: bool) -> anyhow::Result<String>;
+
+    /// Downloads every file of the repository `spec` that is selected by
+    /// `options` into `dest`, returning the number of files written.
+    fn fetch(&self, spec: &str, options: &JoinOptions, dest: &Path) -> anyhow::Result<usize>;
+
+    /// Downloads the files changed by the pull or merge request `spec` that
+    /// are selected by `options`, as they are at its head, into `dest`,
+    /// returning the number of files written. Deleted files are left out.
+    fn fetch_request(
+        &self,
+        spec: &str,
+        options: &JoinOptions,
+        dest: &Path,
+    ) -> anyhow::Result<usize>;
+
+    /// Reads the description of the pull or merge request `spec`, its reviews
+    /// and its comments.
+    fn discussion(&self, spec: &str) -> anyhow::Result<Discussion>;
+}
+
+/// A repository reference in the `owner/repo[@ref]` form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoSpec {
+    pub owner: String,
+    pub repo: String,
+    /// A branch, tag or commit. `None` means the repository's default branch.
+    pub reference: Option<String>,
+}
+
+impl FromStr for RepoSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (name, reference) = match spec.split_once('@') {
+            Some((name, reference)) => (name, Some(reference.to_string())),
+            None => (spec, None),
+        };
+        match name.split_once('/') {
+            Some((owner, repo))
+                if !owner.is_empty()
+                    && !repo.is_empty()
+                    && !repo.contains('/')
+                    && reference.as_deref() != Some("") =>
+            {
+                Ok(Self {
+                    owner: owner.to_string(),
+                    repo: repo.trim_end_matches(".git").to_string(),
+                    reference,
+                })
+            }
+            _ => bail!("Invalid repository '{spec}'. Expected the form owner/repo[@ref]."),
+        }
+    }
+}
+
+impl fmt::Display for RepoSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.repo)?;
+        if let Some(reference) = &self.reference {
+            write!(f, "@{reference}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A pull request or an issue in the `owner/repo#number` form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrSpec {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl FromStr for PrSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parsed = spec.split_once('#').and_then(|(name, number)| {
+            let (owner, repo) = name.split_once('/')?;
+            let number = number.parse().ok().filter(|&number| number > 0)?;
+            (!owner.is_empty() && !repo.is_empty() && !repo.contains('/')).then(|| Self {
+                owner: owner.to_string(),
+                repo: repo.trim_end_matches(".git").to_string(),
+                number,
+            })
+        });
+        parsed.with_context(|| {
+            format!("Invalid pull request '{spec}'. Expected the form owner/repo#number.")
+        })
+    }
+}
+
+impl fmt::Display for PrSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}#{}", self.owner, self.repo, self.number)
+    }
+}
+
+/// Downloads the document at `url`, such as a ticket or a design document.
+pub fn fetch_url(url: &str) -> anyhow::Result<Vec<u8>> {
+    let agent = agent();
+    let host = url
+        .split("://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next());
+    let mut response = send_with_retries(host.unwrap_or(url), || {
+        agent.get(url).call().with_context(|| request_failed(url))
+    })?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!(
+            "{url} returned HTTP {status}{}",
+            rate_limit_details(&response)
+        );
+    }
+    response
+        .body_mut()
+        .with_config()
+        .limit(MAX_FILE_SIZE)
+        .read_to_vec()
+        .with_context(|| format!("Failed to download {url}"))
+}
+
+/// The REST API of a repository host, with the headers that authenticate
+/// each request.
+pub struct Api {
+    agent: Agent,
+    /// The name of the host, as shown in messages.
+    host: &'static str,
+    base_url: String,
+    /// The media type asked for JSON responses.
+    accept: &'static str,
+    headers: Vec<(&'static str, String)>,
+    /// The variable that holds the token, named in hints while none is set.
+    missing_token: Option<&'static str>,
+}
+
+impl Api {
+    /// Creates a client for the API at `base_url`. `missing_token` is the
+    /// variable to set when the request is not authenticated.
+    pub fn new(
+        host: &'static str,
+        base_url: &str,
+        accept: &'static str,
+        headers: Vec<(&'static str, String)>,
+        missing_token: Option<&'static str>,
+    ) -> Self {
+        Self {
+            agent: agent(),
+            host,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            accept,
+            headers,
+            missing_token,
+        }
+    }
+
+    /// Reads the JSON response of the endpoint at `path`.
+    pub fn get_json<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        Ok(self.get(path, self.accept)?.body_mut().read_json()?)
+    }
+
+    /// Reads every page of the list endpoint at `path`, numbered from 1, up
+    /// to `max_pages`.
+    pub fn get_pages<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        max_pages: usize,
+    ) -> anyhow::Result<Vec<T>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        for page in 1..=max_pages {
+            let batch: Vec<T> = self.get_json(&format!(
+                "{path}{separator}per_page={PAGE_SIZE}&page={page}"
+            ))?;
+            let last = batch.len() < PAGE_SIZE;
+            items.extend(batch);
+            if last {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Downloads the content at `path`, asked for as `accept`, to
+    /// `relative_path` below `dest`. The path comes from the host, so one that
+    /// would leave `dest`, such as `../x` or `/x`, is refused.
+    pub fn download(
+        &self,
+        path: &str,
+        accept: &str,
+        relative_path: &Path,
+        dest: &Path,
+    ) -> anyhow::Result<()> {
+        let relative = relative_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if relative_path.as_os_str().is_empty() || !relative {
+            bail!(
+                "Refusing to download {} outside of the destination folder",
+                relative_path.display()
+            );
+        }
+        let content = self
+            .get(path, accept)?
+            .body_mut()
+            .with_config()
+            .limit(MAX_FILE_SIZE)
+            .read_to_vec()
+            .with_context(|| format!("Failed to download {}", relative_path.display()))?;
+
+        let target = dest.join(relative_path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, content)?;
+        Ok(())
+    }
+
+    /// Sends a GET request to `path` of the API, or to a full URL given by
+    /// the API itself, waiting out short rate-limit windows.
+    pub fn get(&self, path: &str, accept: &str) -> anyhow::Result<Response<ureq::Body>> {
+        let url = match path.starts_with("http://") || path.starts_with("https://") {
+            true => path.to_string(),
+            false => format!("{}{path}", self.base_url),
+        };
+        let host = self.host;
+        let mut response = send_with_retries(host, || {
+            let mut request = self.agent.get(&url).header("Accept", accept);
+            for (name, value) in &self.headers {
+                request = request.header(*name, value);
+            }
+            request.call().with_context(|| request_failed(&url))
+        })?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let details = rate_limit_details(&response);
+        if let Some(wait) = rate_limit_wait(&response) {
+            let hint = self
+                .missing_token
+                .map(|token| format!(" Set {token} to raise the limit."))
+                .unwrap_or_default();
+            bail!(
+                "{host} API rate limit exceeded; it resets in {}s.{hint}{details}",
+                wait.as_secs()
+            );
+        }
+
+        let message = response.body_mut().read_to_string().unwrap_or_default();
+        if let Some(token) = self.missing_token
+            && matches!(status.as_u16(), 401 | 404)
+        {
+            bail!(
+                "{host} returned {} for {url}. Private repositories require {token}.",
+                status.as_u16()
+            );
+        }
+        bail!(
+            "{host} returned HTTP {status} for {url}: {}{details}",
+            message.trim()
+        );
+    }
+}
+
+/// Returns how long to wait if `response` reports an exhausted rate limit,
+/// from the `Retry-After` header or the `X-RateLimit-*` (GitHub) and
+/// `RateLimit-*` (GitLab) headers.
+fn rate_limit_wait<B>(response: &Response<B>) -> Option<Duration> {
+    if !matches!(response.status().as_u16(), 403 | 429) {
+        return None;
+    }
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+
+    if let Some(seconds) = header("retry-after") {
+        return Some(Duration::from_secs(seconds));
+    }
+    for prefix in ["x-ratelimit", "ratelimit"] {
+        if header(&format!("{prefix}-remaining")) == Some(0) {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            let reset = header(&format!("{prefix}-reset")).unwrap_or(now);
+            return Some(Duration::from_secs(reset.saturating_sub(now) + 1));
+        }
+    }
+    None
+}
+
+/// Lists the rate-limit headers of `response` for an error message, as
+/// ` (x-ratelimit-remaining: 0, x-ratelimit-reset: 1700000000)`, or nothing
+/// when it has none. These include the `X-RateLimit-*-Requests` and
+/// `-Tokens` headers of OpenAI.
+pub fn rate_limit_details<B>(response: &Response<B>) -> String {
+    let headers: Vec<String> = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| {
+            let name = name.as_str();
+            name == "retry-after"
+                || name.starts_with("x-ratelimit-")
+                || name.starts_with("ratelimit-")
+        })
+        .filter_map(|(name, value)| Some(format!("{name}: {}", value.to_str().ok()?)))
+        .collect();
+    match headers.is_empty() {
+        true => String::new(),
+        false => format!(" ({})", headers.join(", ")),
+    }
+}
+
+/// Percent-encodes `text` as one segment of a URL path, or as the value of a
+/// query parameter.
+pub fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Percent-encodes each segment of the file path `path`, keeping the slashes
+/// between them.
+pub fn encode_path(path: &str) -> String {
+    path.split('/').map(encode).collect::<Vec<_>>().join("/")
+}
+
+// --- Unit Tests for Repository Specs ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that paths that would leave the destination folder are refused
+    /// before anything is requested.
+    #[test]
+    fn test_download_refuses_escaping_paths() {
+        let api = Api::new("Test", "http://127.0.0.1:9", "*/*", Vec::new(), None);
+        let dest = Path::new("dest");
+        for path in ["../outside.txt", "/etc/passwd", "a/../../b", "./a", ""] {
+            let error = api
+                .download("/file", "*/*", Path::new(path), dest)
+                .unwrap_err();
+            assert!(error.to_string().starts_with("Refusing"), "{path}: {error}");
+        }
+    }
+
+    /// Verifies that `owner/repo` and `owner/repo@ref` are parsed correctly.
+    #[test]
+    fn test_parse_repo_spec() {
+        let spec: RepoSpec = "luizvbo/join-ai".parse().unwrap();
+        assert_eq!(spec.owner, "luizvbo");
+        assert_eq!(spec.repo, "join-ai");
+        assert_eq!(spec.reference, None);
+
+        let spec: RepoSpec = "luizvbo/join-ai@v0.3.0".parse().unwrap();
+        assert_eq!(spec.reference.as_deref(), Some("v0.3.0"));
+        assert_eq!(spec.to_string(), "luizvbo/join-ai@v0.3.0");
+    }
+
+    /// Verifies that pull requests are parsed as `owner/repo#number`, and that
+    /// malformed ones are rejected.
+    #[test]
+    fn test_parse_pr_spec() {
+        let spec: PrSpec = "luizvbo/join-ai#123".parse().unwrap();
+        assert_eq!(
+            spec,
+            PrSpec {
+                owner: "luizvbo".to_string(),
+                repo: "join-ai".to_string(),
+                number: 123,
+            }
+        );
+        assert_eq!(spec.to_string(), "luizvbo/join-ai#123");
+        for spec in [
+            "luizvbo/join-ai",
+            "join-ai#1",
+            "a/b#",
+            "a/b#0",
+            "a/b#x",
+            "a/b/c#1",
+        ] {
+            assert!(spec.parse::<PrSpec>().is_err(), "{spec} should be invalid");
+        }
+    }
+
+    /// Verifies that malformed specs are rejected.
+    #[test]
+    fn test_invalid_repo_spec() {
+        for spec in ["join-ai", "/join-ai", "luizvbo/", "a/b/c", "a/b@"] {
+            assert!(
+                spec.parse::<RepoSpec>().is_err(),
+                "{spec} should be invalid"
+            );
+        }
+    }
+
+    /// Verifies that paths and refs are percent-encoded, keeping the slashes
+    /// of file paths.
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("group/sub/app"), "group%2Fsub%2Fapp");
+        assert_eq!(encode("feature/ü x"), "feature%2F%C3%BC%20x");
+        assert_eq!(encode_path("src/my file.rs"), "src/my%20file.rs");
+    }
+
+    /// Verifies that overloaded and unavailable servers are retried with a
+    /// growing delay, that rate limits are waited out only when they reset
+    /// soon, and that other errors are not retried.
+    #[test]
+    fn test_retry_delay() {
+        let response = |status: u16, headers: &[(&str, &str)]| {
+            let mut builder = Response::builder().status(status);
+            for (name, value) in headers {
+                builder = builder.header(*name, *value);
+            }
+            builder.body(()).unwrap()
+        };
+        let unavailable = response(503, &[]);
+        assert_eq!(retry_delay(&unavailable, 0), Some(RETRY_DELAY));
+        assert_eq!(retry_delay(&unavailable, 2), Some(RETRY_DELAY * 4));
+        assert_eq!(retry_delay(&unavailable, MAX_RETRIES), None);
+        assert_eq!(
+            retry_delay(&response(429, &[("retry-after", "3")]), 0),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            retry_delay(&response(429, &[("retry-after", "3600")]), 0),
+            None
+        );
+        for status in [200, 400, 401, 404, 500, 501] {
+            assert_eq!(retry_delay(&response(status, &[]), 0), None);
+        }
+
+        let limited = response(
+            429,
+            &[
+                ("x-ratelimit-remaining-tokens", "0"),
+                ("x-ratelimit-reset-tokens", "6m0s"),
+                ("content-type", "application/json"),
+            ],
+        );
+        assert_eq!(
+            rate_limit_details(&limited),
+            " (x-ratelimit-remaining-tokens: 0, x-ratelimit-reset-tokens: 6m0s)"
+        );
+        assert_eq!(rate_limit_details(&unavailable), "");
+    }
+
+    /// Verifies that a download is sent again after the server was briefly
+    /// unavailable.
+    #[test]
+    fn test_fetch_url_retries() -> anyhow::Result<()> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/doc.md", listener.local_addr()?);
+        let server = thread::spawn(move || {
+            for reply in [
+                "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nConnection: close\r\n\r\n# Doc\n",
+            ] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                reader.get_mut().write_all(reply.as_bytes()).unwrap();
+            }
+        });
+        assert_eq!(fetch_url(&url)?, b"# Doc\n");
+        server.join().unwrap();
+        Ok(())
+    }
+}
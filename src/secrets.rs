@@ -0,0 +1,162 @@
+//! Tokens kept in the OS keyring (`join-ai config set-secret`): the API keys
+//! of language models and the tokens of code hosts are stored by the keychain
+//! of the system (the macOS Keychain, the Windows Credential Manager, or the
+//! Secret Service of Linux desktops) instead of in plaintext configuration
+//! files or the shell history. A token that is not in the keyring is read from
+//! its environment variable, so CI jobs and servers without a keyring keep
+//! working as before.
+//!
+//! The keyring is only used when the crate is built with the `keyring`
+//! feature.
+
+use crate::cli::{ConfigArgs, Secret};
+use std::process::ExitCode;
+
+#[cfg(feature = "keyring")]
+use {
+    crate::cli::ConfigAction,
+    anyhow::Context,
+    join_ai_core::events,
+    std::io::{self, IsTerminal},
+};
+
+/// The service under which the tokens are stored in the keyring.
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "join-ai";
+
+/// Handles the logic for the 'config' command.
+#[cfg(feature = "keyring")]
+pub fn config(args: &ConfigArgs) -> anyhow::Result<ExitCode> {
+    match args.action {
+        ConfigAction::SetSecret { name } => {
+            let token = read_token(name)?;
+            entry(name)
+                .and_then(|entry| entry.set_password(&token))
+                .with_context(|| {
+                    format!(
+                        "Failed to store the {} token in the OS keyring",
+                        name.name()
+                    )
+                })?;
+            events::progress(&format!(
+                "Stored the {} token in the OS keyring.",
+                name.name()
+            ));
+        }
+        ConfigAction::DeleteSecret { name } => {
+            match entry(name).and_then(|entry| entry.delete_credential()) {
+                Ok(()) => events::progress(&format!(
+                    "Removed the {} token from the OS keyring.",
+                    name.name()
+                )),
+                Err(keyring::Error::NoEntry) => {
+                    events::progress(&format!("The OS keyring holds no {} token.", name.name()))
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!(
+                            "Failed to remove the {} token from the OS keyring",
+                            name.name()
+                        )
+                    });
+                }
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn config(_args: &ConfigArgs) -> anyhow::Result<ExitCode> {
+    anyhow::bail!("`config` requires join-ai to be built with the `keyring` feature.")
+}
+
+/// Returns the token for `secret`: the one in the keyring, or else the value
+/// of the first of its environment variables that is set.
+pub fn get(secret: Secret) -> Option<String> {
+    resolve(secret, from_keyring(secret), |name| {
+        std::env::var(name).ok()
+    })
+}
+
+/// Picks the token for `secret` among the one `stored` in the keyring and
+/// the environment variables read by `var`. Empty values are not tokens.
+fn resolve(
+    secret: Secret,
+    stored: Option<String>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    stored
+        .into_iter()
+        .chain(secret.env_vars().iter().filter_map(|name| var(name)))
+        .find(|token| !token.is_empty())
+}
+
+/// The entry of the keyring that holds the token for `secret`.
+#[cfg(feature = "keyring")]
+fn entry(secret: Secret) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, secret.name())
+}
+
+/// Reads the token for `secret` from the keyring. A keyring that cannot be
+/// reached, as on a server without a desktop session, holds no token.
+#[cfg(feature = "keyring")]
+fn from_keyring(secret: Secret) -> Option<String> {
+    entry(secret).and_then(|entry| entry.get_password()).ok()
+}
+
+#[cfg(not(feature = "keyring"))]
+fn from_keyring(_secret: Secret) -> Option<String> {
+    None
+}
+
+/// Reads the token to store for `secret`: prompted for without echoing it on
+/// a terminal, or the first line of stdin otherwise (e.g., from a password
+/// manager).
+#[cfg(feature = "keyring")]
+fn read_token(secret: Secret) -> anyhow::Result<String> {
+    let token = match io::stdin().is_terminal() {
+        true => rpassword::prompt_password(format!("Token for {}: ", secret.name()))?,
+        false => {
+            let mut line = String::new();
+            io::stdin().read_line(&mut line)?;
+            line
+        }
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        anyhow::bail!("No token was given.");
+    }
+    Ok(token.to_string())
+}
+
+// --- Unit Tests for Keyring Secrets ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that a token in the keyring comes before the environment,
+    /// whose variables are read in order, and that empty values are skipped.
+    #[test]
+    fn test_resolve_token() {
+        let env = |name: &str| match name {
+            "GITHUB_TOKEN" => Some(String::new()),
+            "GH_TOKEN" => Some("gh-env".to_string()),
+            "OPENAI_API_KEY" => Some("sk-env".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            resolve(Secret::OpenAi, Some("sk-keyring".to_string()), env),
+            Some("sk-keyring".to_string())
+        );
+        assert_eq!(
+            resolve(Secret::OpenAi, None, env),
+            Some("sk-env".to_string())
+        );
+        assert_eq!(
+            resolve(Secret::GitHub, Some(String::new()), env),
+            Some("gh-env".to_string())
+        );
+        assert_eq!(resolve(Secret::GitLab, None, env), None);
+    }
+}
@@ -0,0 +1,763 @@
+//! The HTTP server (`join-ai serve`), for platforms that offer repository
+//! packing as a service without starting a process per request.
+//!
+//! - `POST /join` joins a folder below the root. The JSON body holds the long
+//!   options of `join` (`{"path": "app", "patterns": ["*.rs"], "max_tokens":
+//!   50000}`) and the response is the joined text, or a manifest of the files
+//!   with the content when the request accepts `application/json`.
+//! - `GET /tree?path=app` lists the files a join of a folder would consider.
+//!
+//! With `--stdio`, the same requests are read as JSON-RPC 2.0 messages, one
+//! per line on stdin, and answered one per line on stdout, for editor
+//! extensions that keep join-ai running as a child process. The methods are
+//! `join` and `tree`, as above, `tokens` (the files of a join and their
+//! estimated tokens, without the content) and `explain` (the files a join
+//! leaves out, by the rule that excludes them).
+//!
+//! Requests are not authenticated, and cannot reach outside the root folder:
+//! a join that would read a file resolving outside of it, such as through a
+//! symbolic link, is refused.
+
+use crate::cli::{Cli, Commands, JoinArgs, ServeArgs};
+use crate::{input, pipeline_for};
+use clap::Parser;
+use join_ai_core::JoinOptions;
+use join_ai_core::events;
+use join_ai_core::format::OutputFormat;
+use join_ai_core::licenses::LicenseDenied;
+use join_ai_core::secrets::SecretsFound;
+use join_ai_core::selection::estimate_tokens;
+use join_ai_core::walker;
+use serde_json::{Map, Value, json};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+
+/// The largest request body that is read.
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// Options of `join` that requests can set: they select, order and transform
+/// the files of the folder joined. Any other option is refused, since it may
+/// run commands or write files on the server, read files outside the folder,
+/// reach other hosts (with the server's credentials), read its stdin, or keep
+/// the request from completing. Options added later are refused until they are
+/// listed here.
+const ALLOWED_OPTIONS: &[&str] = &[
+    "format",
+    "target",
+    "patterns",
+    "exclude",
+    "no_detect",
+    "max_depth",
+    "max_file_size",
+    "warn_size",
+    "max_tokens",
+    "relevant_to",
+    "top_k",
+    "entry",
+    "follow_imports",
+    "sort",
+    "churn_days",
+    "near_duplicates",
+    "dry_run",
+    "list_only",
+    "verify",
+    "hidden",
+    "no_follow",
+    "mmap",
+    "normalize",
+    "strip_comments",
+    "keep_comments",
+    "strip_disabled",
+    "preserve_lines",
+    "keep_header",
+    "redact",
+    "redact_pii",
+    "secrets_policy",
+    "git_info",
+    "dependencies",
+    "include_agent_rules",
+    "module_graph",
+    "group_by_dir",
+    "symbols",
+    "licenses",
+    "deny_license",
+    "blame",
+    "git_meta",
+    "rev",
+    "submodules",
+    "packages",
+    "owners",
+    "show_owners",
+    "read_timeout",
+    "error_policy",
+];
+
+/// Handles the logic for the 'serve' command: answers requests until the
+/// process is stopped.
+pub fn serve(args: &ServeArgs) -> anyhow::Result<ExitCode> {
+    let root = args.root.canonicalize().map_err(|e| {
+        anyhow::anyhow!(
+            "The root folder {} cannot be read: {e}",
+            args.root.display()
+        )
+    })?;
+    if args.stdio {
+        return stdio(&root);
+    }
+    let server = tiny_http::Server::http(&args.http)
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {e}", args.http))?;
+    events::progress(&format!(
+        "Serving {} on http://{} (POST /join, GET /tree)",
+        root.display(),
+        args.http
+    ));
+    for mut request in server.incoming_requests() {
+        let root = root.clone();
+        thread::spawn(move || {
+            let mut body = Vec::new();
+            let read = request
+                .as_reader()
+                .take(MAX_BODY + 1)
+                .read_to_end(&mut body);
+            let wants_json = request.headers().iter().any(|header| {
+                header.field.equiv("Accept") && header.value.as_str().contains("application/json")
+            });
+            let reply = match read {
+                Ok(_) if body.len() as u64 > MAX_BODY => {
+                    Reply::error(413, "The request body is larger than 1 MB.")
+                }
+                Ok(_) => route(
+                    request.method().as_str(),
+                    request.url(),
+                    &body,
+                    wants_json,
+                    &root,
+                ),
+                Err(e) => Reply::error(400, &format!("Failed to read the request: {e}")),
+            };
+            events::progress(&format!(
+                "{} {} {}",
+                request.method(),
+                request.url(),
+                reply.status
+            ));
+            let mut response =
+                tiny_http::Response::from_data(reply.body).with_status_code(reply.status);
+            let headers = [("Content-Type", reply.content_type.to_string())]
+                .into_iter()
+                .chain(reply.headers);
+            for (name, value) in headers {
+                if let Ok(header) = tiny_http::Header::from_bytes(name, value) {
+                    response.add_header(header);
+                }
+            }
+            if let Err(e) = request.respond(response) {
+                events::warning(&format!("Failed to send a response: {e}"));
+            }
+        });
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A response to a request.
+#[derive(Debug)]
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(status: u16, value: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({ "error": message }))
+    }
+}
+
+/// Answers a request for `url` under the folder `root`.
+fn route(method: &str, url: &str, body: &[u8], wants_json: bool, root: &Path) -> Reply {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    match (method, path) {
+        ("POST", "/join") => join(body, wants_json, root),
+        ("GET", "/tree") => tree(query_param(query, "path").as_deref(), root),
+        (_, "/join" | "/tree") => Reply::error(405, &format!("{path} does not accept {method}.")),
+        _ => Reply::error(404, &format!("There is nothing at {path}.")),
+    }
+}
+
+/// Joins a folder below `root` with the options of the JSON `body`.
+fn join(body: &[u8], wants_json: bool, root: &Path) -> Reply {
+    let fields = match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Object(fields)) => fields,
+        _ if body.is_empty() => Map::new(),
+        _ => return Reply::error(400, "The body must be a JSON object of `join` options."),
+    };
+    join_fields(&fields, wants_json, root)
+}
+
+/// Joins a folder below `root` with the options of a request, `path` naming
+/// the folder.
+fn join_fields(fields: &Map<String, Value>, wants_json: bool, root: &Path) -> Reply {
+    let (label, mut args) = match request_args(fields, root) {
+        Ok(request) => request,
+        Err(reply) => return reply,
+    };
+    let Ok(temp_dir) = tempfile::TempDir::new() else {
+        return Reply::error(500, "Failed to create a temporary folder.");
+    };
+    args.output_file = temp_dir.path().join("joined");
+    let pipeline = match pipeline_for(&args) {
+        Ok(pipeline) => pipeline,
+        Err(e) => return Reply::error(400, &format!("{e:#}")),
+    };
+    let result = input::prepare(&args).and_then(|input| {
+        let mut options = JoinOptions::from(&args);
+        input.apply_to(&mut options);
+        // Headers show the requested path, not where the root is on the server.
+        options.display_root = Some(PathBuf::from(&label));
+        let files = walker::find_files(&options)?;
+        if let Some(file) = files.into_iter().find(|file| is_outside(file, root)) {
+            let relative = file.strip_prefix(root).unwrap_or(&file).to_path_buf();
+            return Err(OutsideRoot(relative).into());
+        }
+        join_ai_core::join(&options, &pipeline)
+    });
+    let report = match result {
+        Ok(report) => report,
+        Err(e) if e.is::<SecretsFound>() || e.is::<LicenseDenied>() => {
+            return Reply::error(422, &format!("{e:#}"));
+        }
+        Err(e) if e.is::<OutsideRoot>() => return Reply::error(403, &format!("{e:#}")),
+        Err(e) => return Reply::error(500, &format!("{e:#}")),
+    };
+    let content = match args.dry_run {
+        true => None,
+        false => Some(fs::read(&args.output_file).unwrap_or_default()),
+    };
+    let tokens = match &content {
+        Some(content) => estimate_tokens(content.len() as u64),
+        None => estimate_tokens(report.included.iter().map(|(_, bytes)| bytes).sum()),
+    };
+    match content {
+        Some(content) if !wants_json => Reply {
+            status: 200,
+            content_type: match JoinOptions::from(&args).format {
+                OutputFormat::Markdown => "text/markdown; charset=utf-8",
+                OutputFormat::Text => "text/plain; charset=utf-8",
+            },
+            headers: vec![
+                ("X-Join-AI-Files", report.included.len().to_string()),
+                ("X-Join-AI-Tokens", tokens.to_string()),
+            ],
+            body: content,
+        },
+        content => Reply::json(
+            200,
+            &json!({
+                "files": report
+                    .included
+                    .iter()
+                    .map(|(path, bytes)| json!({ "path": path.display().to_string(), "bytes": bytes }))
+                    .collect::<Vec<_>>(),
+                "skipped": report
+                    .skipped
+                    .iter()
+                    .map(|(path, reason)| json!({ "path": path.display().to_string(), "reason": reason.to_string() }))
+                    .collect::<Vec<_>>(),
+                "tokens": tokens,
+                "content": content.map(|content| String::from_utf8_lossy(&content).into_owned()),
+            }),
+        ),
+    }
+}
+
+/// Parses the options of a request as the arguments of `join` on the folder
+/// it names below `root`, returning the folder as requested with them.
+fn request_args(fields: &Map<String, Value>, root: &Path) -> Result<(String, JoinArgs), Reply> {
+    let label = match fields.get("path") {
+        None => ".".to_string(),
+        Some(Value::String(path)) => path.clone(),
+        Some(_) => return Err(Reply::error(400, "`path` must be a string.")),
+    };
+    let folder = resolve(root, &label)?;
+    let mut args = join_args(fields).map_err(|message| Reply::error(400, &message))?;
+    args.input_folder = Some(folder);
+    input::validate(&args).map_err(|e| Reply::error(400, &format!("{e:#}")))?;
+    Ok((label, args))
+}
+
+/// Lists the files a join of the folder at `path` would consider, with their
+/// sizes.
+fn tree(path: Option<&str>, root: &Path) -> Reply {
+    let folder = match resolve(root, path.unwrap_or(".")) {
+        Ok(folder) => folder,
+        Err(reply) => return reply,
+    };
+    let options = JoinOptions::new(&folder, folder.join(".join-ai-no-output"));
+    let files = match join_ai_core::walker::find_files(&options) {
+        Ok(files) => files,
+        Err(e) => return Reply::error(500, &format!("{e:#}")),
+    };
+    let mut files: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|file| !is_outside(file, root))
+        .collect();
+    files.sort();
+    let files: Vec<Value> = files
+        .iter()
+        .map(|file| {
+            let bytes = fs::metadata(file).map_or(0, |metadata| metadata.len());
+            let relative = file.strip_prefix(&folder).unwrap_or(file);
+            json!({ "path": relative.display().to_string(), "bytes": bytes })
+        })
+        .collect();
+    Reply::json(200, &json!({ "files": files }))
+}
+
+/// The error codes of JSON-RPC 2.0.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Any other failure, with the HTTP status it would have in `data`.
+const SERVER_ERROR: i64 = -32000;
+
+/// Answers the JSON-RPC messages read from stdin, one per line, until stdin
+/// is closed.
+fn stdio(root: &Path) -> anyhow::Result<ExitCode> {
+    // Stdout carries nothing but the responses.
+    events::set_progress_to_stderr(true);
+    events::progress(&format!(
+        "Serving {} on stdin (join, tree, tokens, explain)",
+        root.display()
+    ));
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = rpc(&line, root) {
+            writeln!(stdout, "{response}")?;
+            stdout.flush()?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Answers the JSON-RPC message `line` for the folder `root`. Notifications,
+/// which have no `id`, are run but not answered.
+fn rpc(line: &str, root: &Path) -> Option<Value> {
+    let Ok(message) = serde_json::from_str::<Value>(line) else {
+        return Some(rpc_error(
+            Value::Null,
+            PARSE_ERROR,
+            "The message is not valid JSON.",
+            None,
+        ));
+    };
+    let id = message.get("id").cloned();
+    let empty = Map::new();
+    let params = match message.get("params") {
+        None => Some(&empty),
+        Some(Value::Object(params)) => Some(params),
+        Some(_) => None,
+    };
+    let (Some(method), Some(params)) = (message.get("method").and_then(Value::as_str), params)
+    else {
+        return Some(rpc_error(
+            id.unwrap_or(Value::Null),
+            INVALID_REQUEST,
+            "A request needs a `method`, and `params` must be an object.",
+            None,
+        ));
+    };
+    let reply = match method {
+        "join" => join_fields(params, true, root),
+        "tree" => match params.get("path") {
+            None => tree(None, root),
+            Some(Value::String(path)) => tree(Some(path), root),
+            Some(_) => Reply::error(400, "`path` must be a string."),
+        },
+        "tokens" => tokens(params, root),
+        "explain" => explain(params, root),
+        _ => {
+            return id.map(|id| {
+                rpc_error(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("There is no method `{method}`."),
+                    None,
+                )
+            });
+        }
+    };
+    let id = id?;
+    let body: Value = serde_json::from_slice(&reply.body).unwrap_or_default();
+    Some(match reply.status {
+        200 => json!({ "jsonrpc": "2.0", "id": id, "result": body }),
+        status => rpc_error(
+            id,
+            if status == 400 {
+                INVALID_PARAMS
+            } else {
+                SERVER_ERROR
+            },
+            body["error"].as_str().unwrap_or_default(),
+            Some(json!({ "status": status })),
+        ),
+    })
+}
+
+fn rpc_error(id: Value, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+/// Lists the files a join with the options of a request would include, with
+/// the estimated tokens of each and of the whole output, without joining them.
+fn tokens(fields: &Map<String, Value>, root: &Path) -> Reply {
+    let mut fields = fields.clone();
+    fields.insert("dry_run".to_string(), Value::Bool(true));
+    let reply = join_fields(&fields, true, root);
+    if reply.status != 200 {
+        return reply;
+    }
+    let mut manifest: Value = serde_json::from_slice(&reply.body).unwrap_or_default();
+    if let Some(files) = manifest["files"].as_array_mut() {
+        for file in files {
+            file["tokens"] = json!(estimate_tokens(file["bytes"].as_u64().unwrap_or(0)));
+        }
+    }
+    if let Some(manifest) = manifest.as_object_mut() {
+        manifest.remove("content");
+    }
+    Reply::json(200, &manifest)
+}
+
+/// Lists the files a join with the options of a request leaves out, grouped
+/// by the pattern or ignore rule that excludes them.
+fn explain(fields: &Map<String, Value>, root: &Path) -> Reply {
+    let (_, args) = match request_args(fields, root) {
+        Ok(request) => request,
+        Err(reply) => return reply,
+    };
+    let options = JoinOptions::from(&args);
+    let groups = match walker::explain_exclusions(&options) {
+        Ok(groups) => groups,
+        Err(e) => return Reply::error(500, &format!("{e:#}")),
+    };
+    let excluded: Vec<Value> = groups
+        .iter()
+        .map(|(rule, paths)| {
+            let files: Vec<String> = paths
+                .iter()
+                .map(|path| {
+                    let relative = path.strip_prefix(&options.input_folder).unwrap_or(path);
+                    relative.display().to_string()
+                })
+                .collect();
+            json!({ "rule": rule, "files": files })
+        })
+        .collect();
+    Reply::json(200, &json!({ "excluded": excluded }))
+}
+
+/// Resolves `path`, relative to `root`, failing for paths outside of it.
+fn resolve(root: &Path, path: &str) -> Result<PathBuf, Reply> {
+    let resolved = root
+        .join(path)
+        .canonicalize()
+        .map_err(|_| Reply::error(404, &format!("{path} does not exist.")))?;
+    if !resolved.starts_with(root) {
+        return Err(Reply::error(
+            403,
+            &format!("{path} is outside the served folder."),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// Whether the file at `path` resolves outside of `root`, such as through a
+/// symbolic link. Broken links resolve nowhere, and are read as errors.
+fn is_outside(path: &Path, root: &Path) -> bool {
+    path.canonicalize()
+        .is_ok_and(|resolved| !resolved.starts_with(root))
+}
+
+/// A file that a join of a request would read from outside the root folder.
+#[derive(Debug)]
+struct OutsideRoot(PathBuf);
+
+impl fmt::Display for OutsideRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} resolves outside the served folder. Leave it out with `exclude`.",
+            self.0.display()
+        )
+    }
+}
+
+impl std::error::Error for OutsideRoot {}
+
+/// Parses the options of a `/join` request as the arguments of `join`: each
+/// field is a long option, with `true` for flags and a list for repeated
+/// options.
+fn join_args(fields: &Map<String, Value>) -> Result<JoinArgs, String> {
+    let mut argv = vec!["join-ai".to_string(), "join".to_string()];
+    for (name, value) in fields {
+        if name == "path" {
+            continue;
+        }
+        if !ALLOWED_OPTIONS.contains(&name.as_str()) {
+            return Err(format!("`{name}` is not an option of /join."));
+        }
+        let option = format!("--{}", name.replace('_', "-"));
+        let values = match value {
+            Value::Array(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        for value in values {
+            match value {
+                Value::Bool(true) => argv.push(option.clone()),
+                Value::Bool(false) | Value::Null => {}
+                Value::String(value) => argv.push(format!("{option}={value}")),
+                Value::Number(value) => argv.push(format!("{option}={value}")),
+                _ => return Err(format!("`{name}` must be a string, number or boolean.")),
+            }
+        }
+    }
+    argv.extend(["--".to_string(), ".".to_string()]);
+    match Cli::try_parse_from(argv) {
+        Ok(Cli {
+            command: Commands::Join(args),
+            ..
+        }) => Ok(args),
+        Ok(_) => unreachable!("the command is always `join`"),
+        Err(e) => Err(e.render().to_string().trim().to_string()),
+    }
+}
+
+/// Reads the parameter `name` of a query string, decoding `%XX` and `+`.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    let value = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))?;
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.bytes();
+    while let Some(byte) = rest.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex: Vec<u8> = rest.by_ref().take(2).collect();
+                let decoded = std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match decoded {
+                    Some(decoded) => bytes.push(decoded),
+                    None => bytes.extend([b'%'].iter().chain(&hex)),
+                }
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// --- Unit Tests for the HTTP Server ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn body(reply: &Reply) -> Value {
+        serde_json::from_slice(&reply.body).unwrap()
+    }
+
+    /// Verifies that `/join` joins a folder with the options of the request,
+    /// as text or as a manifest, and rejects denied options and bad paths.
+    #[test]
+    fn test_join_endpoint() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("app/src/main.rs").write_str("fn main() {}\n")?;
+        dir.child("app/notes.txt").write_str("notes\n")?;
+        let root = dir.path().canonicalize()?;
+
+        let request = br#"{"path": "app", "patterns": ["*.rs"], "format": "markdown"}"#;
+        let reply = route("POST", "/join", request, false, &root);
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.content_type, "text/markdown; charset=utf-8");
+        let text = String::from_utf8(reply.body)?;
+        assert!(text.starts_with("## app/src/main.rs\n"), "{text}");
+        assert!(!text.contains("notes"));
+
+        let reply = route("POST", "/join", br#"{"path": "app"}"#, true, &root);
+        let manifest = body(&reply);
+        assert_eq!(manifest["files"].as_array().unwrap().len(), 2);
+        assert!(manifest["content"].as_str().unwrap().contains("notes"));
+
+        let reply = route("POST", "/join", br#"{"dry_run": true}"#, true, &root);
+        assert!(body(&reply)["content"].is_null());
+
+        let denied = route(
+            "POST",
+            "/join",
+            br#"{"output_file": "/tmp/x"}"#,
+            false,
+            &root,
+        );
+        assert_eq!(denied.status, 400);
+        for request in [
+            &br#"{"describe_images": true}"#[..],
+            br#"{"vision_provider": "openai"}"#,
+            br#"{"vision_base_url": "http://attacker.example"}"#,
+            br#"{"vision_model": "gpt-4o"}"#,
+            br#"{"cache": true}"#,
+        ] {
+            assert_eq!(route("POST", "/join", request, false, &root).status, 400);
+        }
+        let invalid = route("POST", "/join", br#"{"max_tokens": "many"}"#, false, &root);
+        assert_eq!(invalid.status, 400);
+        let outside = route("POST", "/join", br#"{"path": "../"}"#, false, &root);
+        assert_eq!(outside.status, 403);
+        assert_eq!(route("GET", "/join", b"", false, &root).status, 405);
+        assert_eq!(route("GET", "/", b"", false, &root).status, 404);
+        Ok(())
+    }
+
+    /// Verifies that JSON-RPC messages are answered with the result of each
+    /// method, or an error, and that notifications are not answered.
+    #[test]
+    fn test_stdio_requests() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("app/src/main.rs").write_str("fn main() {}\n")?;
+        dir.child("app/notes.txt").write_str("notes\n")?;
+        let root = dir.path().canonicalize()?;
+        let call = |line: &str| rpc(line, &root).unwrap();
+
+        let joined = call(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "join", "params": {"path": "app", "patterns": ["*.rs"]}}"#,
+        );
+        assert_eq!(joined["id"], 1);
+        assert!(
+            joined["result"]["content"]
+                .as_str()
+                .unwrap()
+                .contains("fn main() {}")
+        );
+
+        let tokens =
+            call(r#"{"jsonrpc": "2.0", "id": "t", "method": "tokens", "params": {"path": "app"}}"#);
+        assert_eq!(tokens["result"]["files"].as_array().unwrap().len(), 2);
+        assert_eq!(tokens["result"]["files"][0]["tokens"], 2);
+        assert!(tokens["result"].get("content").is_none());
+
+        let tree =
+            call(r#"{"jsonrpc": "2.0", "id": 2, "method": "tree", "params": {"path": "app"}}"#);
+        assert_eq!(tree["result"]["files"].as_array().unwrap().len(), 2);
+
+        let explain = call(
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "explain", "params": {"path": "app", "exclude": ["*.txt"]}}"#,
+        );
+        assert_eq!(
+            explain["result"]["excluded"][0]["files"],
+            json!(["notes.txt"])
+        );
+
+        let denied = call(
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "join", "params": {"output_file": "/tmp/x"}}"#,
+        );
+        assert_eq!(denied["error"]["code"], INVALID_PARAMS);
+        let outside =
+            call(r#"{"jsonrpc": "2.0", "id": 5, "method": "tree", "params": {"path": ".."}}"#);
+        assert_eq!(outside["error"]["data"]["status"], 403);
+        let unknown = call(r#"{"jsonrpc": "2.0", "id": 6, "method": "unjoin"}"#);
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(call("{not json")["error"]["code"], PARSE_ERROR);
+        assert_eq!(call(r#"{"id": 7}"#)["error"]["code"], INVALID_REQUEST);
+        assert!(rpc(r#"{"jsonrpc": "2.0", "method": "tree"}"#, &root).is_none());
+        Ok(())
+    }
+
+    /// Verifies that `/tree` lists the files of a folder below the root.
+    #[test]
+    fn test_tree_endpoint() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("my app/src/main.rs")
+            .write_str("fn main() {}\n")?;
+        let root = dir.path().canonicalize()?;
+
+        let reply = route("GET", "/tree?path=my%20app", b"", false, &root);
+        assert_eq!(reply.status, 200);
+        assert_eq!(
+            body(&reply)["files"],
+            json!([{ "path": "src/main.rs", "bytes": 13 }])
+        );
+        assert_eq!(route("GET", "/tree?path=..", b"", false, &root).status, 403);
+        assert_eq!(query_param("a=1&path=x+y%2Fz", "path").unwrap(), "x y/z");
+        Ok(())
+    }
+
+    /// Verifies that a symbolic link below the root to a file outside of it
+    /// is neither joined nor listed.
+    #[cfg(unix)]
+    #[test]
+    fn test_links_outside_root() -> anyhow::Result<()> {
+        let outside = TempDir::new()?;
+        outside.child("credentials").write_str("TOPSECRET\n")?;
+        let dir = TempDir::new()?;
+        dir.child("app/main.rs").write_str("fn main() {}\n")?;
+        dir.child("app/lib.rs").write_str("pub fn lib() {}\n")?;
+        std::os::unix::fs::symlink(
+            outside.path().join("credentials"),
+            dir.path().join("app/link.txt"),
+        )?;
+        std::os::unix::fs::symlink(
+            dir.path().join("app/lib.rs"),
+            dir.path().join("app/alias.rs"),
+        )?;
+        let root = dir.path().canonicalize()?;
+
+        let reply = route("POST", "/join", br#"{"path": "app"}"#, false, &root);
+        assert_eq!(reply.status, 403);
+        let error = body(&reply)["error"].as_str().unwrap().to_string();
+        assert!(
+            error.starts_with("app/link.txt resolves outside"),
+            "{error}"
+        );
+        assert!(!error.contains("TOPSECRET"));
+
+        let request = br#"{"path": "app", "exclude": ["link.txt"]}"#;
+        let reply = route("POST", "/join", request, false, &root);
+        assert_eq!(reply.status, 200);
+        let text = String::from_utf8(reply.body)?;
+        assert!(text.contains("pub fn lib() {}"), "{text}");
+        assert!(!text.contains("TOPSECRET"));
+
+        let reply = route("GET", "/tree?path=app", b"", false, &root);
+        let listed = body(&reply)["files"].to_string();
+        assert!(
+            listed.contains("alias.rs") && !listed.contains("link.txt"),
+            "{listed}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! The `strip` command: removes comments from a single file or stdin and
+//! writes the result to stdout, with the same decommenter as
+//! `join --strip-comments`.
+
+use crate::cli::StripArgs;
+use anyhow::Context;
+use join_ai_core::decommenter::{LanguageDB, StripOptions, remove_comments_with};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Strips the comments of the input of `args` and writes it to stdout.
+pub fn strip(args: &StripArgs) -> anyhow::Result<ExitCode> {
+    let content = if args.file == Path::new("-") {
+        let mut content = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut content)
+            .context("Failed to read stdin")?;
+        content
+    } else {
+        std::fs::read(&args.file)
+            .with_context(|| format!("Failed to read {}", args.file.display()))?
+    };
+    let stripped = strip_content(args, &content)?;
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(stripped.as_bytes())?;
+    stdout.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Removes the comments of `content`, in the language given by `args` or
+/// detected from the file name and shebang line.
+fn strip_content(args: &StripArgs, content: &[u8]) -> anyhow::Result<String> {
+    let languages = LanguageDB::builtin();
+    let language = match &args.lang {
+        Some(name) => languages.find(name).with_context(|| {
+            format!("Unknown language '{name}'. Pass a language name or extension, such as 'rs' or 'python'.")
+        })?,
+        None => languages.detect(&args.file, content).with_context(|| {
+            format!(
+                "Cannot tell the language of {}. Pass it with --lang.",
+                args.file.display()
+            )
+        })?,
+    };
+    let text = std::str::from_utf8(content)
+        .with_context(|| format!("{} is not valid UTF-8", args.file.display()))?;
+    let options = StripOptions {
+        keep_doc: args.keep_doc,
+        keep_markers: args
+            .keep_comments
+            .iter()
+            .filter(|marker| !marker.is_empty())
+            .cloned()
+            .collect(),
+        strip_disabled: args.strip_disabled,
+        preserve_lines: args.preserve_lines,
+        keep_header: args.keep_header,
+    };
+    Ok(remove_comments_with(text, language, &options))
+}
+
+// --- Unit Tests for the Strip Command ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn args(file: &str, lang: Option<&str>) -> StripArgs {
+        StripArgs {
+            file: PathBuf::from(file),
+            lang: lang.map(str::to_string),
+            keep_doc: false,
+            keep_comments: vec!["TODO".to_string()],
+            strip_disabled: false,
+            preserve_lines: false,
+            keep_header: None,
+        }
+    }
+
+    /// Verifies that the language comes from `--lang`, the file name or the
+    /// shebang line, and that unknown languages are reported.
+    #[test]
+    fn test_strip_content() -> anyhow::Result<()> {
+        let code = b"x = 1  # one\n# TODO: two\n";
+        assert_eq!(
+            strip_content(&args("-", Some("py")), code)?,
+            "x = 1\n# TODO: two\n"
+        );
+        assert_eq!(
+            strip_content(&args("a.py", None), code)?,
+            "x = 1\n# TODO: two\n"
+        );
+        assert_eq!(
+            strip_content(&args("-", None), b"#!/bin/sh\necho # hi\n")?,
+            "#!/bin/sh\necho\n"
+        );
+
+        let error = strip_content(&args("-", None), code).unwrap_err();
+        assert!(error.to_string().contains("--lang"), "{error}");
+        let error = strip_content(&args("-", Some("cobol")), code).unwrap_err();
+        assert!(
+            error.to_string().contains("Unknown language 'cobol'"),
+            "{error}"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+//! The `suggest` command: scans the files a join would include, and proposes
+//! patterns that leave out the ones that rarely belong in a prompt (vendored
+//! dependencies, generated code, data dumps and huge files), each with the
+//! tokens it saves. `--apply` adds them to the `.joinignore` file of the input
+//! folder, which every later join reads.
+
+use crate::cli::SuggestArgs;
+use anyhow::Context;
+use join_ai_core::JoinOptions;
+use join_ai_core::dependencies::LOCKFILES;
+use join_ai_core::events;
+use join_ai_core::selection::{estimate_tokens, format_size};
+use join_ai_core::walker::{self, IGNORE_FILE};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+
+/// Folders of dependencies copied into the repository.
+const VENDOR_DIRS: &[&str] = &[
+    "node_modules",
+    "vendor",
+    "third_party",
+    "third-party",
+    "bower_components",
+    "Pods",
+    ".venv",
+    "venv",
+    "site-packages",
+];
+
+/// Folders of build outputs and generated sources.
+const BUILD_DIRS: &[&str] = &["dist", "build", "target", "generated", "__generated__"];
+
+/// Endings of the names of generated and minified files.
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".min.js",
+    ".min.css",
+    ".js.map",
+    ".css.map",
+    ".pb.go",
+    "_pb2.py",
+    "_pb2_grpc.py",
+    ".g.dart",
+    ".freezed.dart",
+    ".Designer.cs",
+];
+
+/// Extensions of data files and dumps.
+const DATA_EXTENSIONS: &[&str] = &["csv", "tsv", "jsonl", "ndjson", "sql", "log", "dump"];
+
+/// Comments that code generators put at the top of the files they write,
+/// compared in lowercase.
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "code generated by",
+    "autogenerated",
+    "auto-generated",
+];
+
+/// How many bytes at the start of a file are searched for generated markers.
+const MARKER_LEN: usize = 1024;
+
+/// Suggestions that save fewer tokens than this are not worth a pattern.
+const MIN_TOKENS: u64 = 500;
+
+/// Why a file is suggested to be left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Vendored,
+    Generated,
+    Data,
+    Huge,
+}
+
+impl Kind {
+    /// The reason, as shown in the table and in `.joinignore`.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Kind::Vendored => "vendored dependencies",
+            Kind::Generated => "generated code",
+            Kind::Data => "data",
+            Kind::Huge => "huge file",
+        }
+    }
+}
+
+/// A pattern that leaves out files, in gitignore syntax, with the files it
+/// matches among those scanned and their size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub pattern: String,
+    pub kind: Kind,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Scans the folder of `args`, prints the suggested patterns with their
+/// savings, and adds them to `.joinignore` with `--apply`.
+pub fn suggest(args: &SuggestArgs) -> anyhow::Result<ExitCode> {
+    let Some(folder) = args.join.input_folder.as_ref().filter(|path| path.is_dir()) else {
+        anyhow::bail!("`suggest` scans a local folder; give the path of one.");
+    };
+    let mut options = JoinOptions::from(&args.join);
+    if let Some(defaults) = crate::detect_project(&args.join, &mut options) {
+        events::progress(&defaults.describe());
+    }
+    let mut files: Vec<(PathBuf, u64)> = walker::find_files(&options)?
+        .into_iter()
+        .map(|path| {
+            let size = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+            (path, size)
+        })
+        .collect();
+    files.sort();
+    let total: u64 = files.iter().map(|(_, size)| size).sum();
+    let suggestions = suggestions(&files, &options.input_folder, args.huge);
+    println!(
+        "Scanned {} files ({}, ~{} tokens).",
+        files.len(),
+        format_size(total),
+        estimate_tokens(total)
+    );
+    if suggestions.is_empty() {
+        println!("Nothing to suggest: no vendored, generated, data or huge files were found.");
+        return Ok(ExitCode::SUCCESS);
+    }
+    for line in render(&suggestions, total) {
+        println!("{line}");
+    }
+
+    let ignore_file = folder.join(IGNORE_FILE);
+    match args.apply {
+        true => {
+            let added = apply(&ignore_file, &suggestions)?;
+            events::progress(&format!(
+                "Added {added} patterns to {}.",
+                ignore_file.display()
+            ));
+        }
+        false => println!(
+            "Run again with --apply to add them to {}.",
+            ignore_file.display()
+        ),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Groups the `files` under `root`, with their sizes, into suggestions,
+/// largest savings first. Files of at least `huge` bytes that are not
+/// otherwise left out are suggested one by one.
+pub fn suggestions(files: &[(PathBuf, u64)], root: &Path, huge: u64) -> Vec<Suggestion> {
+    let mut found: HashMap<String, Suggestion> = HashMap::new();
+    let mut add = |pattern: String, kind: Kind, bytes: u64| {
+        let suggestion = found.entry(pattern.clone()).or_insert(Suggestion {
+            pattern,
+            kind,
+            files: 0,
+            bytes: 0,
+        });
+        suggestion.files += 1;
+        suggestion.bytes += bytes;
+    };
+    // Files marked as generated, by folder, and how many files each folder has.
+    let mut marked: HashMap<PathBuf, Vec<(&Path, u64)>> = HashMap::new();
+    let mut counts: HashMap<&Path, usize> = HashMap::new();
+    for (path, bytes) in files {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        *counts.entry(parent).or_default() += 1;
+        let folders: Vec<&str> = parent
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        let name = relative
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let extension = relative
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        if let Some(folder) = folders.iter().find(|folder| VENDOR_DIRS.contains(folder)) {
+            add(format!("{folder}/"), Kind::Vendored, *bytes);
+        } else if let Some(folder) = folders.iter().find(|folder| BUILD_DIRS.contains(folder)) {
+            add(format!("{folder}/"), Kind::Generated, *bytes);
+        } else if LOCKFILES.contains(&name.as_ref()) {
+            add(name.into_owned(), Kind::Generated, *bytes);
+        } else if let Some(suffix) = GENERATED_SUFFIXES
+            .iter()
+            .find(|suffix| name.ends_with(*suffix))
+        {
+            add(format!("*{suffix}"), Kind::Generated, *bytes);
+        } else if let Some(extension) =
+            extension.filter(|extension| DATA_EXTENSIONS.contains(&extension.as_str()))
+        {
+            add(format!("*.{extension}"), Kind::Data, *bytes);
+        } else if is_marked_generated(path) {
+            marked
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push((relative, *bytes));
+        } else if *bytes >= huge {
+            add(anchored(relative), Kind::Huge, *bytes);
+        }
+    }
+    // A folder of generated files only is left out as a whole.
+    for (folder, generated) in marked {
+        let whole = !folder.as_os_str().is_empty()
+            && counts.get(folder.as_path()) == Some(&generated.len());
+        for (relative, bytes) in generated {
+            let pattern = match whole {
+                true => format!("{}/", anchored(&folder)),
+                false => anchored(relative),
+            };
+            add(pattern, Kind::Generated, bytes);
+        }
+    }
+
+    let mut suggestions: Vec<Suggestion> = found
+        .into_values()
+        .filter(|suggestion| estimate_tokens(suggestion.bytes) >= MIN_TOKENS)
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then_with(|| a.pattern.cmp(&b.pattern))
+    });
+    suggestions
+}
+
+/// The pattern that matches `relative` only: `/src/big.json`.
+fn anchored(relative: &Path) -> String {
+    format!("/{}", relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Whether the file at `path` starts with a comment of a code generator.
+fn is_marked_generated(path: &Path) -> bool {
+    let mut head = Vec::with_capacity(MARKER_LEN);
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    if file.take(MARKER_LEN as u64).read_to_end(&mut head).is_err() {
+        return false;
+    }
+    let head = String::from_utf8_lossy(&head).to_lowercase();
+    GENERATED_MARKERS.iter().any(|marker| head.contains(marker))
+}
+
+/// Renders the table of `suggestions`, with their share of the `total` bytes
+/// scanned, and the savings of all of them.
+fn render(suggestions: &[Suggestion], total: u64) -> Vec<String> {
+    let share = |bytes: u64| crate::tokens::percent(bytes, total);
+    let width = suggestions
+        .iter()
+        .map(|suggestion| suggestion.pattern.len())
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![String::new(), "Suggested exclusions:".to_string()];
+    for suggestion in suggestions {
+        lines.push(format!(
+            "  {:<width$}  {:<21}  {:>5} files  ~{:>8} tokens  {:>5.1}%",
+            suggestion.pattern,
+            suggestion.kind.describe(),
+            suggestion.files,
+            estimate_tokens(suggestion.bytes),
+            share(suggestion.bytes)
+        ));
+    }
+    let saved: u64 = suggestions.iter().map(|suggestion| suggestion.bytes).sum();
+    lines.push(String::new());
+    lines.push(format!(
+        "Together they save ~{} tokens ({:.1}% of the join).",
+        estimate_tokens(saved),
+        share(saved)
+    ));
+    lines
+}
+
+/// Adds the patterns of `suggestions` that it lacks to the ignore file at
+/// `path`, each under a comment with its reason. Returns how many were added.
+fn apply(path: &Path, suggestions: &[Suggestion]) -> anyhow::Result<usize> {
+    let entries: Vec<(String, String)> = suggestions
+        .iter()
+        .map(|suggestion| {
+            let reason = format!(
+                "{}, ~{} tokens",
+                suggestion.kind.describe(),
+                estimate_tokens(suggestion.bytes)
+            );
+            (reason, suggestion.pattern.clone())
+        })
+        .collect();
+    append_patterns(path, "join-ai suggest", &entries)
+}
+
+/// Adds the `(reason, pattern)` entries whose pattern it lacks to the ignore
+/// file at `path`, under a comment naming the `command` that added them.
+/// Returns how many were added.
+pub fn append_patterns(
+    path: &Path,
+    command: &str,
+    entries: &[(String, String)],
+) -> anyhow::Result<usize> {
+    let mut text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let present: HashSet<String> = text.lines().map(|line| line.trim().to_string()).collect();
+    let added: Vec<&(String, String)> = entries
+        .iter()
+        .filter(|(_, pattern)| !present.contains(pattern))
+        .collect();
+    if added.is_empty() {
+        return Ok(0);
+    }
+    if !text.is_empty() {
+        text.push_str(if text.ends_with('\n') { "\n" } else { "\n\n" });
+    }
+    text.push_str(&format!("# Added by `{command}`.\n"));
+    for (reason, pattern) in &added {
+        text.push_str(&format!("# {reason}\n{pattern}\n"));
+    }
+    fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(added.len())
+}
+
+// --- Unit Tests for Exclusion Suggestions ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Verifies that vendored, generated, data and huge files are grouped
+    /// into patterns, largest savings first, and that small savings and
+    /// regular sources are not suggested.
+    #[test]
+    fn test_suggestions() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let kb = |n: usize| "x".repeat(n * 1024);
+        dir.child("src/main.rs").write_str(&kb(40))?;
+        dir.child("vendor/lib/a.go").write_str(&kb(30))?;
+        dir.child("vendor/b.go").write_str(&kb(10))?;
+        dir.child("web/app.min.js").write_str(&kb(12))?;
+        dir.child("Cargo.lock").write_str(&kb(8))?;
+        dir.child("data/rows.csv").write_str(&kb(20))?;
+        dir.child("api/client.rs").write_str(&format!(
+            "// Code generated by openapi. DO NOT EDIT.\n{}",
+            kb(16)
+        ))?;
+        dir.child("api/types.rs")
+            .write_str(&format!("// @generated\n{}", kb(4)))?;
+        dir.child("src/schema.rs")
+            .write_str(&format!("// @generated\n{}", kb(6)))?;
+        dir.child("assets/big.json").write_str(&kb(150))?;
+        dir.child("notes.log").write_str("tiny")?;
+
+        let options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        let files: Vec<(PathBuf, u64)> = walker::find_files(&options)?
+            .into_iter()
+            .map(|path| {
+                let size = fs::metadata(&path).unwrap().len();
+                (path, size)
+            })
+            .collect();
+        let suggestions = suggestions(&files, dir.path(), 100 * 1024);
+        let found: Vec<(&str, Kind, usize)> = suggestions
+            .iter()
+            .map(|s| (s.pattern.as_str(), s.kind, s.files))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("/assets/big.json", Kind::Huge, 1),
+                ("vendor/", Kind::Vendored, 2),
+                ("/api/", Kind::Generated, 2),
+                ("*.csv", Kind::Data, 1),
+                ("*.min.js", Kind::Generated, 1),
+                ("Cargo.lock", Kind::Generated, 1),
+                ("/src/schema.rs", Kind::Generated, 1),
+            ]
+        );
+
+        // Applied patterns leave the files out of later scans and joins.
+        let ignore_file = dir.path().join(IGNORE_FILE);
+        fs::write(&ignore_file, "*.tmp")?;
+        assert_eq!(apply(&ignore_file, &suggestions)?, 7);
+        assert_eq!(apply(&ignore_file, &suggestions)?, 0);
+        let text = fs::read_to_string(&ignore_file)?;
+        assert!(text.starts_with("*.tmp\n\n# Added by `join-ai suggest`.\n"));
+        assert!(text.contains("# vendored dependencies, ~10240 tokens\nvendor/\n"));
+        let left: Vec<PathBuf> = walker::find_files(&options)?.into_iter().collect();
+        let mut left: Vec<&Path> = left
+            .iter()
+            .map(|path| path.strip_prefix(dir.path()).unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, [Path::new("notes.log"), Path::new("src/main.rs")]);
+        Ok(())
+    }
+}
@@ -0,0 +1,418 @@
+//! The `tokens` command: estimates the tokens of the files selected like
+//! `join`, and with `--analyze` shows where they come from, so that "the
+//! prompt is too big" turns into which directories, extensions and files to
+//! leave out. `--export` writes the same tables as CSV, JSON or HTML.
+
+use crate::cli::TokensArgs;
+use crate::join_to_memory;
+use anyhow::Context;
+use join_ai_core::JoinOptions;
+use join_ai_core::events;
+use join_ai_core::selection::{estimate_tokens, format_size};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+
+/// The width of the longest bar of a histogram, in characters.
+const BAR_WIDTH: usize = 30;
+
+/// A line of a table of `--analyze`: a directory, an extension or a file, and
+/// the content bytes of the files it stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub name: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// The breakdown of a join by `--analyze`, each table heaviest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    /// The content bytes of all the files, and how many there are.
+    pub total: u64,
+    pub count: usize,
+    pub directories: Vec<Row>,
+    pub extensions: Vec<Row>,
+    pub files: Vec<Row>,
+}
+
+/// Joins the files selected by `args` without writing them, and prints the
+/// estimate of their tokens, broken down with `--analyze`.
+pub fn tokens(args: &TokensArgs) -> anyhow::Result<ExitCode> {
+    let mut join = args.join.clone();
+    // Nothing is sent anywhere, so there is nothing to audit.
+    join.audit_log = None;
+    let (report, code, _) = join_to_memory(&join, "tokens", "")?;
+    let total: u64 = report.included.iter().map(|(_, bytes)| bytes).sum();
+    println!(
+        "~{} tokens in {} files ({})",
+        estimate_tokens(total),
+        report.included.len(),
+        format_size(total)
+    );
+    if args.analyze || args.export.is_some() {
+        let options = JoinOptions::from(&args.join);
+        let root = options.display_path(&options.input_folder);
+        let analysis = analyze(
+            &report.included,
+            &root,
+            args.depth as usize,
+            args.top as usize,
+        );
+        if args.analyze {
+            for line in render(&analysis) {
+                println!("{line}");
+            }
+        }
+        if let Some(path) = &args.export {
+            export(path, &analysis)?;
+            events::progress(&format!("Wrote the token tables to {}.", path.display()));
+        }
+    }
+    Ok(code)
+}
+
+/// Writes the tables of `analysis` to `path`, in the format of its extension:
+/// CSV, JSON, or an HTML report.
+pub fn export(path: &Path, analysis: &Analysis) -> anyhow::Result<()> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let content = match extension.as_deref() {
+        Some("csv") => to_csv(analysis),
+        Some("json") => format!("{}\n", serde_json::to_string_pretty(&to_json(analysis))?),
+        Some("html" | "htm") => to_html(analysis),
+        _ => anyhow::bail!(
+            "`--export` writes .csv, .json or .html files, and was given {}.",
+            path.display()
+        ),
+    };
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The tables of `analysis` as exported: their name, their heading, and
+/// their rows.
+fn tables(analysis: &Analysis) -> [(&'static str, &'static str, &[Row]); 3] {
+    [
+        ("directory", "By directory", &analysis.directories),
+        ("extension", "By extension", &analysis.extensions),
+        ("file", "Top files", &analysis.files),
+    ]
+}
+
+/// Renders `analysis` as one CSV table, whose first column tells the table
+/// each row belongs to.
+fn to_csv(analysis: &Analysis) -> String {
+    let mut csv = String::from("table,name,files,bytes,tokens,percent\n");
+    for (table, _, rows) in tables(analysis) {
+        for row in rows {
+            csv.push_str(&format!(
+                "{table},{},{},{},{},{:.1}\n",
+                csv_field(&row.name),
+                row.files,
+                row.bytes,
+                estimate_tokens(row.bytes),
+                percent(row.bytes, analysis.total)
+            ));
+        }
+    }
+    csv
+}
+
+/// Quotes a CSV field that holds a comma, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Renders `analysis` as JSON, with its totals and a list per table.
+fn to_json(analysis: &Analysis) -> Value {
+    let rows = |rows: &[Row]| -> Vec<Value> {
+        rows.iter()
+            .map(|row| {
+                json!({
+                    "name": row.name,
+                    "files": row.files,
+                    "bytes": row.bytes,
+                    "tokens": estimate_tokens(row.bytes),
+                    "percent": (percent(row.bytes, analysis.total) * 10.0).round() / 10.0,
+                })
+            })
+            .collect()
+    };
+    json!({
+        "join_ai": env!("CARGO_PKG_VERSION"),
+        "files": analysis.count,
+        "bytes": analysis.total,
+        "tokens": estimate_tokens(analysis.total),
+        "directories": rows(&analysis.directories),
+        "extensions": rows(&analysis.extensions),
+        "top_files": rows(&analysis.files),
+    })
+}
+
+/// Renders `analysis` as a static HTML page, with a table per breakdown and
+/// the share of each row drawn as a bar.
+fn to_html(analysis: &Analysis) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>join-ai tokens</title>\n<style>\n\
+         body {{ font-family: system-ui, sans-serif; margin: 2em; color: #222; }}\n\
+         table {{ border-collapse: collapse; margin-bottom: 2em; }}\n\
+         th, td {{ padding: 0.2em 0.8em; text-align: right; }}\n\
+         th:first-child, td:first-child {{ text-align: left; font-family: monospace; }}\n\
+         .bar {{ width: 12em; text-align: left; }}\n\
+         .bar div {{ background: #4a7bd0; height: 0.8em; }}\n\
+         </style>\n</head>\n<body>\n<h1>~{} tokens in {} files ({})</h1>\n",
+        estimate_tokens(analysis.total),
+        analysis.count,
+        format_size(analysis.total)
+    );
+    for (_, title, rows) in tables(analysis) {
+        html.push_str(&format!(
+            "<h2>{title}</h2>\n<table>\n<tr><th>Name</th><th>Files</th><th>Tokens</th><th>Share</th><th></th></tr>\n"
+        ));
+        for row in rows {
+            let share = percent(row.bytes, analysis.total);
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{share:.1}%</td>\
+                 <td class=\"bar\"><div style=\"width: {share:.1}%\"></div></td></tr>\n",
+                escape_html(&row.name),
+                row.files,
+                estimate_tokens(row.bytes)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Escapes the characters of `text` that HTML reads as markup.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Breaks `files` down by their directories below `root`, at most `depth`
+/// levels deep, and by extension, keeping the `top` heaviest rows of each
+/// table and the `top` heaviest files.
+pub fn analyze(files: &[(PathBuf, u64)], root: &Path, depth: usize, top: usize) -> Analysis {
+    let mut directories: HashMap<String, Row> = HashMap::new();
+    let mut extensions: HashMap<String, Row> = HashMap::new();
+    let mut rows = Vec::new();
+    for (path, bytes) in files {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let name = relative.to_string_lossy().replace('\\', "/");
+        let folders: Vec<String> = relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .take(depth)
+            .collect();
+        let directory = match folders.is_empty() {
+            true => "./".to_string(),
+            false => format!("{}/", folders.join("/")),
+        };
+        let extension = match relative.extension() {
+            Some(extension) => format!(".{}", extension.to_string_lossy()),
+            None => "(none)".to_string(),
+        };
+        for (table, key) in [(&mut directories, directory), (&mut extensions, extension)] {
+            let row = table.entry(key.clone()).or_insert(Row {
+                name: key,
+                files: 0,
+                bytes: 0,
+            });
+            row.files += 1;
+            row.bytes += bytes;
+        }
+        rows.push(Row {
+            name,
+            files: 1,
+            bytes: *bytes,
+        });
+    }
+    Analysis {
+        total: files.iter().map(|(_, bytes)| bytes).sum(),
+        count: files.len(),
+        directories: heaviest(directories.into_values().collect(), top),
+        extensions: heaviest(extensions.into_values().collect(), top),
+        files: heaviest(rows, top),
+    }
+}
+
+/// Sorts `rows` heaviest first, by name among equals, and keeps `top` of them.
+fn heaviest(mut rows: Vec<Row>, top: usize) -> Vec<Row> {
+    rows.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    rows.truncate(top);
+    rows
+}
+
+/// The share of `total` that `bytes` are, in percent.
+pub fn percent(bytes: u64, total: u64) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => bytes as f64 * 100.0 / total as f64,
+    }
+}
+
+/// Renders `analysis` as the tables printed by `--analyze`: histograms of the
+/// directories and extensions, and a ranking of the files.
+pub fn render(analysis: &Analysis) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (title, rows) in [
+        ("By directory", &analysis.directories),
+        ("By extension", &analysis.extensions),
+    ] {
+        lines.push(String::new());
+        lines.push(format!("{title}:"));
+        let width = rows.iter().map(|row| row.name.len()).max().unwrap_or(0);
+        let heaviest = rows.first().map_or(0, |row| row.bytes);
+        for row in rows {
+            let share = percent(row.bytes, analysis.total);
+            let bar = match heaviest {
+                0 => 0,
+                _ => (row.bytes as f64 * BAR_WIDTH as f64 / heaviest as f64).round() as usize,
+            };
+            lines.push(format!(
+                "  {:<width$}  {:>9} tokens  {share:>5.1}%  {:<BAR_WIDTH$}  {} files",
+                row.name,
+                estimate_tokens(row.bytes),
+                "█".repeat(bar.max(1)),
+                row.files
+            ));
+        }
+    }
+    lines.push(String::new());
+    lines.push(format!("Top {} files:", analysis.files.len()));
+    let width = analysis
+        .files
+        .iter()
+        .map(|row| row.name.len())
+        .max()
+        .unwrap_or(0);
+    for (rank, row) in analysis.files.iter().enumerate() {
+        lines.push(format!(
+            "  {:>3}. {:<width$}  {:>9} tokens  {:>5.1}%",
+            rank + 1,
+            row.name,
+            estimate_tokens(row.bytes),
+            percent(row.bytes, analysis.total)
+        ));
+    }
+    lines
+}
+
+// --- Unit Tests for Token Analysis ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies that files are grouped by their directories, down to the
+    /// depth asked for, and by extension, heaviest first with their shares.
+    #[test]
+    fn test_analyze() {
+        let root = Path::new("repo");
+        let files: Vec<(PathBuf, u64)> = [
+            ("repo/src/remote/llm.rs", 4000),
+            ("repo/src/lib.rs", 2000),
+            ("repo/docs/guide.md", 3000),
+            ("repo/Makefile", 1000),
+        ]
+        .into_iter()
+        .map(|(path, bytes)| (PathBuf::from(path), bytes))
+        .collect();
+
+        let analysis = analyze(&files, root, 1, 20);
+        assert_eq!(analysis.total, 10000);
+        let names = |rows: &[Row]| -> Vec<(String, usize, u64)> {
+            rows.iter()
+                .map(|row| (row.name.clone(), row.files, row.bytes))
+                .collect()
+        };
+        assert_eq!(
+            names(&analysis.directories),
+            [
+                ("src/".to_string(), 2, 6000),
+                ("docs/".to_string(), 1, 3000),
+                ("./".to_string(), 1, 1000)
+            ]
+        );
+        assert_eq!(
+            names(&analysis.extensions),
+            [
+                (".rs".to_string(), 2, 6000),
+                (".md".to_string(), 1, 3000),
+                ("(none)".to_string(), 1, 1000)
+            ]
+        );
+        assert_eq!(analysis.files[0].name, "src/remote/llm.rs");
+
+        let analysis = analyze(&files, root, 2, 2);
+        assert_eq!(
+            names(&analysis.directories),
+            [
+                ("src/remote/".to_string(), 1, 4000),
+                ("docs/".to_string(), 1, 3000)
+            ]
+        );
+        assert_eq!(analysis.files.len(), 2);
+
+        let lines = render(&analysis);
+        assert!(lines.contains(&"By directory:".to_string()));
+        assert!(lines.iter().any(|line| line.contains("src/remote/")
+            && line.contains("40.0%")
+            && line.contains(&"█".repeat(BAR_WIDTH))));
+        assert!(lines.iter().any(
+            |line| line.starts_with("    1. src/remote/llm.rs") && line.contains("1000 tokens")
+        ));
+    }
+
+    /// Verifies that the tables are exported as CSV, JSON and HTML by the
+    /// extension of the path, with names quoted or escaped as needed.
+    #[test]
+    fn test_export() -> anyhow::Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let files: Vec<(PathBuf, u64)> = [("repo/src/a,b.rs", 3000), ("repo/<x>.md", 1000)]
+            .into_iter()
+            .map(|(path, bytes)| (PathBuf::from(path), bytes))
+            .collect();
+        let analysis = analyze(&files, Path::new("repo"), 1, 20);
+
+        let csv = dir.path().join("stats.csv");
+        export(&csv, &analysis)?;
+        let csv = fs::read_to_string(csv)?;
+        assert!(csv.starts_with("table,name,files,bytes,tokens,percent\n"));
+        assert!(csv.contains("directory,src/,1,3000,750,75.0\n"));
+        assert!(csv.contains("file,\"src/a,b.rs\",1,3000,750,75.0\n"));
+
+        let json = dir.path().join("stats.json");
+        export(&json, &analysis)?;
+        let json: Value = serde_json::from_str(&fs::read_to_string(json)?)?;
+        assert_eq!(json["files"], 2);
+        assert_eq!(json["tokens"], 1000);
+        assert_eq!(json["extensions"][0]["name"], ".rs");
+        assert_eq!(json["top_files"][1]["percent"], 25.0);
+
+        let html = dir.path().join("stats.html");
+        export(&html, &analysis)?;
+        let html = fs::read_to_string(html)?;
+        assert!(html.contains("<h1>~1000 tokens in 2 files"));
+        assert!(html.contains("<td>&lt;x&gt;.md</td>"));
+        assert!(!html.contains("<x>"));
+
+        assert!(export(&dir.path().join("stats.txt"), &analysis).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! Watch mode (`--watch`): joins the input folder again whenever one of the
+//! selected files changes, optionally with a desktop notification (`--notify`).
+//!
+//! Changes are detected by polling the sizes and modification times of the
+//! files the walker selects, which works the same on every platform and
+//! filesystem (including network mounts, where change events are unreliable).
+
+use crate::cli::JoinArgs;
+use crate::{input, processor};
+use join_ai_core::selection::{SkipReason, estimate_tokens};
+use join_ai_core::{JoinOptions, events, walker};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+/// How often the input folder is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Joins the input of `args`, then joins it again after every change until the
+/// process is interrupted. A failed join is reported and the watch goes on.
+pub fn watch(mut args: JoinArgs) -> anyhow::Result<ExitCode> {
+    if input::remote(&args).is_some() || args.stdin_tar || args.rev.is_some() {
+        anyhow::bail!("`--watch` only works with a local folder as input.");
+    }
+    if args.notify && !cfg!(feature = "notify") {
+        anyhow::bail!("`--notify` requires join-ai to be built with the `notify` feature.");
+    }
+    input::validate(&args)?;
+
+    let options = JoinOptions::from(&args);
+    let mut last_fingerprint = None;
+    loop {
+        let fingerprint = fingerprint(&options)?;
+        if last_fingerprint != Some(fingerprint) {
+            let rejoin = last_fingerprint.is_some();
+            if rejoin {
+                events::progress("\nChanges detected, joining again...");
+            }
+            match crate::pipeline_for(&args)
+                .and_then(|pipeline| crate::join_and_report(&args, &pipeline))
+            {
+                Ok((report, _)) if rejoin && args.notify => notify(&report, args.max_tokens),
+                Ok(_) => {}
+                Err(e) => events::warning(&format!("The join failed: {e:#}")),
+            }
+            // The output is opened once; editors usually reload it on their own.
+            args.open = false;
+            last_fingerprint = Some(fingerprint);
+            events::progress("Watching for changes (press Ctrl+C to stop)...");
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Hashes the paths, sizes and modification times of the selected files.
+fn fingerprint(options: &JoinOptions) -> anyhow::Result<u64> {
+    let mut paths: Vec<_> = walker::find_files(options)?.into_iter().collect();
+    paths.sort();
+    let mut hasher = DefaultHasher::new();
+    for path in paths {
+        path.hash(&mut hasher);
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok().hash(&mut hasher);
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// Describes a re-join for a notification: its estimated size in tokens, and
+/// how many files did not fit in the `max_tokens` budget.
+#[cfg_attr(not(feature = "notify"), allow(dead_code))]
+fn notification_body(report: &processor::Report, max_tokens: Option<u64>) -> String {
+    let tokens = estimate_tokens(report.bytes_written);
+    let mut body = format!("{} files, ~{tokens} tokens", report.files_written);
+    let over_budget = report
+        .skipped
+        .iter()
+        .filter(|(_, reason)| *reason == SkipReason::Budget)
+        .count();
+    if let Some(max_tokens) = max_tokens {
+        if over_budget > 0 {
+            body.push_str(&format!(
+                "\nOver the budget of {max_tokens} tokens: {over_budget} files left out"
+            ));
+        } else {
+            body.push_str(&format!("\nWithin the budget of {max_tokens} tokens"));
+        }
+    }
+    body
+}
+
+/// Shows a desktop notification for a re-join. Failures (e.g., no notification
+/// daemon) are reported as warnings.
+#[cfg(feature = "notify")]
+fn notify(report: &processor::Report, max_tokens: Option<u64>) {
+    let result = notify_rust::Notification::new()
+        .summary("join-ai: output updated")
+        .body(&notification_body(report, max_tokens))
+        .show();
+    if let Err(e) = result {
+        events::warning(&format!("Could not show a notification: {e}"));
+    }
+}
+
+#[cfg(not(feature = "notify"))]
+fn notify(_report: &processor::Report, _max_tokens: Option<u64>) {}
+
+// --- Unit Tests for Watch Mode ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::path::PathBuf;
+
+    /// Verifies that editing a selected file changes the fingerprint, and that
+    /// writing the output file does not.
+    #[test]
+    fn test_fingerprint_tracks_selected_files() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        dir.child("a.rs").write_str("fn a() {}")?;
+        dir.child("notes.md").write_str("notes")?;
+        let mut options = JoinOptions::new(dir.path(), dir.path().join("out.txt"));
+        options.patterns = Some(vec!["*.rs".to_string()]);
+
+        let before = fingerprint(&options)?;
+        dir.child("out.txt").write_str("output")?;
+        dir.child("notes.md").write_str("more notes")?;
+        assert_eq!(fingerprint(&options)?, before);
+
+        dir.child("a.rs").write_str("fn a() { todo!() }")?;
+        assert_ne!(fingerprint(&options)?, before);
+
+        Ok(())
+    }
+
+    /// Verifies that notifications say whether the budget was exceeded.
+    #[test]
+    fn test_notification_body() {
+        let mut report = processor::Report {
+            files_written: 3,
+            bytes_written: 4000,
+            ..Default::default()
+        };
+        assert_eq!(notification_body(&report, None), "3 files, ~1000 tokens");
+        assert!(
+            notification_body(&report, Some(2000)).ends_with("Within the budget of 2000 tokens")
+        );
+
+        report
+            .skipped
+            .push((PathBuf::from("big.rs"), SkipReason::Budget));
+        assert!(
+            notification_body(&report, Some(1000))
+                .ends_with("Over the budget of 1000 tokens: 1 files left out")
+        );
+    }
+}